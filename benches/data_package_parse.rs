@@ -0,0 +1,68 @@
+//! Compares parsing a large multi-game DataPackage one game at a time against
+//! parsing games concurrently. Run with `cargo bench --bench
+//! data_package_parse`.
+
+use std::time::{Duration, Instant};
+
+use archipelago::data_package::{default_parse_concurrency, parse_games};
+
+const GAMES: usize = 40;
+const NAMES_PER_GAME: usize = 5_000;
+const RUNS: u32 = 5;
+
+fn games() -> serde_json::Map<String, serde_json::Value> {
+    (0..GAMES)
+        .map(|game| {
+            let names = |kind: &str| -> serde_json::Map<String, serde_json::Value> {
+                (0..NAMES_PER_GAME)
+                    .map(|i| {
+                        let name = format!("Game {} {} number {}", game, kind, i);
+                        (name, (game * 100_000 + i).into())
+                    })
+                    .collect()
+            };
+
+            let data = serde_json::json!({
+                "item_name_to_id": names("Item"),
+                "location_name_to_id": names("Location"),
+                "version": 0,
+                "checksum": format!("{:040x}", game),
+            });
+            (format!("Game {}", game), data)
+        })
+        .collect()
+}
+
+fn time(runtime: &tokio::runtime::Runtime, concurrency: usize) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let games = games();
+        let start = Instant::now();
+        let parsed = runtime
+            .block_on(parse_games(games, concurrency))
+            .expect("generated games should parse");
+        total += start.elapsed();
+        assert_eq!(parsed.len(), GAMES);
+    }
+    total / RUNS
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build runtime");
+
+    println!(
+        "{} games with {} items and locations each, average of {} runs",
+        GAMES, NAMES_PER_GAME, RUNS
+    );
+
+    for concurrency in [1, 2, 4, default_parse_concurrency()] {
+        println!(
+            "concurrency {:>3}: {:?}",
+            concurrency,
+            time(&runtime, concurrency)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Measures how long a client takes to handle a large LocationInfo with 1
+//! and 10 subscribers listening. Events are shared between subscribers rather
+//! than copied, so the two should take about as long. Run with
+//! `cargo bench --features fixtures --bench event_fanout`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::time::{Duration, Instant};
+
+use archipelago::bus::{SubscribeOptions, Subscription};
+use archipelago::fixtures;
+use archipelago::protocol::{LocationInfo, NetworkItem, NetworkItemFlags, ServerMessage};
+use futures::StreamExt;
+
+const LOCATIONS: i64 = 50_000;
+const RUNS: u32 = 20;
+
+fn location_info() -> ServerMessage {
+    ServerMessage::LocationInfo(LocationInfo {
+        locations: (0..LOCATIONS)
+            .map(|i| NetworkItem {
+                item: fixtures::FIRST_ITEM_ID + i,
+                location: fixtures::FIRST_LOCATION_ID + i,
+                player: fixtures::OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    })
+}
+
+async fn time(subscribers: usize) -> Duration {
+    let (mut client, mut server) = common::connect().await;
+    let mut subscriptions: Vec<Subscription> = (0..subscribers)
+        .map(|_| client.subscribe(SubscribeOptions::new()))
+        .collect();
+
+    let message = location_info();
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        server.send_messages(vec![message.clone()]).await;
+
+        let start = Instant::now();
+        common::next_message(&mut client).await;
+        for subscription in &mut subscriptions {
+            subscription.next().await.expect("subscription closed");
+        }
+        total += start.elapsed();
+    }
+
+    total / RUNS
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    println!("LocationInfo of {} locations, {} runs", LOCATIONS, RUNS);
+    for subscribers in [1, 10] {
+        println!(
+            "{:>2} subscribers: {:?} per message",
+            subscribers,
+            time(subscribers).await
+        );
+    }
+}
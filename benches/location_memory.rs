@@ -0,0 +1,48 @@
+//! Compares the memory used by `HashSet<i64>` and [`LocationSet`] for large
+//! location lists. Run with `cargo bench --bench location_memory`.
+
+use std::collections::HashSet;
+
+use archipelago::location_set::LocationSet;
+
+/// A lower bound on the heap used by a `HashSet<i64>`: every bucket holds an
+/// 8 byte value plus a control byte.
+fn hash_set_size(set: &HashSet<i64>) -> usize {
+    set.capacity() * (std::mem::size_of::<i64>() + 1)
+}
+
+fn report(name: &str, locations: impl Iterator<Item = i64> + Clone) {
+    let hash_set: HashSet<i64> = locations.clone().collect();
+    let location_set: LocationSet = locations.collect();
+
+    let hash_size = hash_set_size(&hash_set);
+    let bitmap_size = location_set.compressed_size();
+    println!(
+        "{:<40} {:>8} locations: HashSet >= {:>9} bytes, LocationSet {:>8} bytes ({:.1}x smaller)",
+        name,
+        location_set.len(),
+        hash_size,
+        bitmap_size,
+        hash_size as f64 / bitmap_size as f64,
+    );
+}
+
+fn main() {
+    // A typical world: one contiguous block of IDs.
+    report("contiguous", 1_000_000..1_100_000);
+
+    // Half of a world checked, as in checked_locations mid-game.
+    report("every other location", (1_000_000..1_200_000).step_by(2));
+
+    // Several worlds' worth of ranges far apart from each other.
+    report(
+        "10 worlds of 10,000",
+        (0..10).flat_map(|world| {
+            let base = 7_000_000_000 + world * 1_000_000;
+            base..base + 10_000
+        }),
+    );
+
+    // The worst case for a bitmap: IDs spread far apart.
+    report("sparse", (0..100_000).map(|i| i * 70_000));
+}
@@ -0,0 +1,462 @@
+//! Exercises every packet type the crate supports against a server, reporting
+//! which ones were serialized and deserialized cleanly.
+//!
+//! ```text
+//! cargo run --example ap-conformance -- <address> <slot> [game] [--destructive]
+//! ```
+//!
+//! Set `ARCHIPELAGO_PASS` if the room has a password. Packets which change
+//! the state of the slot (LocationChecks, StatusUpdate and Say) are only sent
+//! with `--destructive`, so by default this is safe to point at a live room.
+//! Server packets which can't be provoked, such as RoomUpdate, are reported as
+//! not observed unless the server happens to send one. The exit code is
+//! non-zero if any packet failed.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use archipelago::client::{AnonymousClient, Client, ConnectOptions, MalformedPacketPolicy};
+use archipelago::protocol::{
+    Bounce, ClientMessage, ClientStatus, Cmd, ConnectUpdate, DataStorageOperation, Get,
+    GetDataPackage, ItemsHandlingFlags, LocationChecks, LocationScouts, Say, ServerMessage, Set,
+    SetNotify, StatusUpdate,
+};
+use futures::StreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to watch for an InvalidPacket after sending a packet the server
+/// doesn't answer.
+const SETTLE: Duration = Duration::from_secs(1);
+
+const ALL_CMDS: [Cmd; 24] = [
+    Cmd::RoomInfo,
+    Cmd::ConnectionRefused,
+    Cmd::Connected,
+    Cmd::ReceivedItems,
+    Cmd::LocationInfo,
+    Cmd::RoomUpdate,
+    Cmd::PrintJSON,
+    Cmd::DataPackage,
+    Cmd::Bounced,
+    Cmd::InvalidPacket,
+    Cmd::Retrieved,
+    Cmd::SetReply,
+    Cmd::Connect,
+    Cmd::ConnectUpdate,
+    Cmd::Sync,
+    Cmd::LocationChecks,
+    Cmd::LocationScouts,
+    Cmd::StatusUpdate,
+    Cmd::Say,
+    Cmd::GetDataPackage,
+    Cmd::Bounce,
+    Cmd::Get,
+    Cmd::Set,
+    Cmd::SetNotify,
+];
+
+#[derive(Debug, Clone)]
+enum Outcome {
+    NotObserved,
+    Skipped,
+    Passed,
+    Failed(String),
+}
+
+struct Report {
+    outcomes: Vec<(Cmd, Outcome)>,
+}
+
+impl Report {
+    fn new() -> Self {
+        Self {
+            outcomes: ALL_CMDS
+                .iter()
+                .map(|&cmd| (cmd, Outcome::NotObserved))
+                .collect(),
+        }
+    }
+
+    /// Records the outcome of a packet. A failure is never overwritten by a
+    /// later success.
+    fn record(&mut self, cmd: Cmd, outcome: Outcome) {
+        let (_, current) = self
+            .outcomes
+            .iter_mut()
+            .find(|(c, _)| *c == cmd)
+            .expect("every cmd is in the report");
+
+        if !matches!(current, Outcome::Failed(_)) {
+            *current = outcome;
+        }
+    }
+
+    fn record_result(&mut self, cmd: Cmd, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.record(cmd, Outcome::Passed),
+            Err(error) => self.record(cmd, Outcome::Failed(error)),
+        }
+    }
+
+    /// Prints the report, returning true if nothing failed.
+    fn print(&self) -> bool {
+        let mut ok = true;
+        for (cmd, outcome) in &self.outcomes {
+            let outcome = match outcome {
+                Outcome::NotObserved => "not observed".to_string(),
+                Outcome::Skipped => "skipped".to_string(),
+                Outcome::Passed => "ok".to_string(),
+                Outcome::Failed(error) => {
+                    ok = false;
+                    format!("FAILED: {}", error)
+                }
+            };
+            println!("{:<18} {}", cmd.as_str(), outcome);
+        }
+        ok
+    }
+}
+
+/// Checks a packet survives being serialized and deserialized again.
+fn round_trip<T: Serialize + DeserializeOwned>(packet: &T) -> Result<(), String> {
+    let value = serde_json::to_value(packet).map_err(|e| format!("serializing: {}", e))?;
+    let again: T =
+        serde_json::from_value(value.clone()).map_err(|e| format!("deserializing: {}", e))?;
+    let again = serde_json::to_value(&again).map_err(|e| format!("reserializing: {}", e))?;
+
+    if again != value {
+        return Err(format!("changed in round trip: {} != {}", value, again));
+    }
+
+    Ok(())
+}
+
+struct Conformance {
+    client: Client,
+    report: Report,
+    malformed_seen: u64,
+}
+
+impl Conformance {
+    /// Records a received message, and any malformed packets the client
+    /// skipped while receiving it.
+    fn observe(&mut self, message: &ServerMessage) {
+        if self.client.malformed_packet_count() > self.malformed_seen {
+            self.malformed_seen = self.client.malformed_packet_count();
+            if let Some(malformed) = self.client.last_malformed_packet() {
+                let cmd = serde_json::from_str::<serde_json::Value>(&malformed.packet)
+                    .ok()
+                    .and_then(|packet| packet.get("cmd")?.as_str().map(str::to_string))
+                    .and_then(|name| ALL_CMDS.into_iter().find(|cmd| cmd.as_str() == name));
+                match cmd {
+                    Some(cmd) => self
+                        .report
+                        .record(cmd, Outcome::Failed(malformed.error.clone())),
+                    None => eprintln!("malformed packet without a known cmd: {}", malformed.packet),
+                }
+            }
+        }
+
+        let cmd = message.cmd();
+        self.report.record_result(cmd, round_trip(message));
+    }
+
+    /// Reads messages until one with the given cmd arrives.
+    async fn wait_for(&mut self, cmd: Cmd, timeout: Duration) -> Result<ServerMessage, String> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let message = match tokio::time::timeout_at(deadline, self.client.next()).await {
+                Ok(Some(Ok(message))) => message,
+                Ok(Some(Err(e))) => return Err(format!("receiving: {}", e)),
+                Ok(None) => return Err("connection closed".to_string()),
+                Err(_) => return Err(format!("no {} within {:?}", cmd, timeout)),
+            };
+
+            self.observe(&message);
+            match message {
+                message if message.cmd() == cmd => return Ok(message),
+                ServerMessage::InvalidPacket(invalid) => {
+                    return Err(format!(
+                        "server answered with InvalidPacket: {}",
+                        invalid.text
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends a packet, waiting for the given reply or, for packets which the
+    /// server doesn't answer, for any InvalidPacket.
+    async fn exchange(
+        &mut self,
+        message: ClientMessage,
+        reply: Option<Cmd>,
+    ) -> Option<ServerMessage> {
+        let cmd = message.cmd();
+        if let Err(error) = round_trip(&message) {
+            self.report.record(cmd, Outcome::Failed(error));
+            return None;
+        }
+
+        if let Err(e) = self.client.send(message).await {
+            self.report
+                .record(cmd, Outcome::Failed(format!("sending: {}", e)));
+            return None;
+        }
+
+        match reply {
+            Some(reply) => match self.wait_for(reply, TIMEOUT).await {
+                Ok(message) => {
+                    self.report.record(cmd, Outcome::Passed);
+                    Some(message)
+                }
+                Err(error) => {
+                    self.report.record(cmd, Outcome::Failed(error));
+                    None
+                }
+            },
+            None => {
+                match self.wait_for(Cmd::InvalidPacket, SETTLE).await {
+                    Ok(ServerMessage::InvalidPacket(invalid)) => self.report.record(
+                        cmd,
+                        Outcome::Failed(format!(
+                            "server answered with InvalidPacket: {}",
+                            invalid.text
+                        )),
+                    ),
+                    _ => self.report.record(cmd, Outcome::Passed),
+                }
+                None
+            }
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let destructive = args.iter().any(|arg| arg == "--destructive");
+    args.retain(|arg| arg != "--destructive");
+
+    let usage = "usage: ap-conformance <address> <slot> [game] [--destructive]";
+    let address = args.first().context(usage)?.clone();
+    let slot = args.get(1).context(usage)?.clone();
+    let game = args.get(2).cloned();
+    let password = std::env::var("ARCHIPELAGO_PASS").ok();
+
+    let mut report = Report::new();
+
+    // The anonymous half of the protocol: RoomInfo, the data package, and a
+    // refused Connect on a throwaway connection.
+    let mut anonymous = AnonymousClient::new(&address).await?;
+    report.record_result(Cmd::RoomInfo, round_trip(anonymous.get_room_info()));
+
+    report.record_result(
+        Cmd::GetDataPackage,
+        round_trip(&ClientMessage::GetDataPackage(GetDataPackage {
+            games: anonymous.get_room_info().games.clone(),
+        })),
+    );
+    match anonymous.get_data_package().await {
+        Ok(data_package) => report.record_result(Cmd::DataPackage, round_trip(&data_package)),
+        Err(e) => {
+            report.record(Cmd::GetDataPackage, Outcome::Failed(e.to_string()));
+            report.record(Cmd::DataPackage, Outcome::Failed(e.to_string()));
+        }
+    }
+
+    let refused = AnonymousClient::new(&address)
+        .await?
+        .connect_with(ConnectOptions::slim(format!(
+            "{}-conformance-nonexistent",
+            slot
+        )))
+        .await;
+    match refused {
+        Ok(_) => report.record(
+            Cmd::ConnectionRefused,
+            Outcome::Failed("connecting to a nonexistent slot succeeded".to_string()),
+        ),
+        Err(e) if e.to_string().contains("ConnectionRefused") => {
+            report.record(Cmd::ConnectionRefused, Outcome::Passed)
+        }
+        Err(e) => report.record(Cmd::ConnectionRefused, Outcome::Failed(e.to_string())),
+    }
+
+    let mut options = match &game {
+        Some(game) => ConnectOptions::new(slot).game(game),
+        None => ConnectOptions::new(slot).tags(["Tracker"]),
+    }
+    .items_handling(ItemsHandlingFlags::CAN_RECEIVE_ITEMS)
+    .malformed_packets(MalformedPacketPolicy::Skip);
+    if let Some(password) = password {
+        options = options.password(password);
+    }
+
+    let client = match anonymous.connect_with(options).await {
+        Ok(client) => {
+            report.record(Cmd::Connect, Outcome::Passed);
+            client
+        }
+        Err(e) => {
+            report.record(Cmd::Connect, Outcome::Failed(e.to_string()));
+            report.print();
+            std::process::exit(1);
+        }
+    };
+    report.record_result(Cmd::Connected, round_trip(client.get_connected()));
+
+    let team = client.get_connected().team;
+    let our_slot = client.get_connected().slot;
+    let tags = client.tags().to_vec();
+    let missing = client.get_connected().missing_locations.first().copied();
+
+    let mut conformance = Conformance {
+        client,
+        report,
+        malformed_seen: 0,
+    };
+
+    conformance
+        .exchange(ClientMessage::Sync(()), Some(Cmd::ReceivedItems))
+        .await;
+
+    conformance
+        .exchange(
+            ClientMessage::ConnectUpdate(ConnectUpdate {
+                items_handling: ItemsHandlingFlags::CAN_RECEIVE_ITEMS,
+                tags,
+            }),
+            None,
+        )
+        .await;
+
+    let key = format!("ap_conformance_{}_{}", team, our_slot);
+    conformance
+        .exchange(
+            ClientMessage::Set(Set {
+                key: key.clone(),
+                default: 0.into(),
+                want_reply: true,
+                operations: vec![DataStorageOperation::Add(1.into())],
+            }),
+            Some(Cmd::SetReply),
+        )
+        .await;
+
+    conformance
+        .exchange(
+            ClientMessage::Get(Get {
+                keys: vec![key.clone()],
+            }),
+            Some(Cmd::Retrieved),
+        )
+        .await;
+
+    // SetNotify has no reply of its own, but makes the server send a SetReply
+    // for the next Set even though it doesn't ask for one.
+    conformance
+        .exchange(
+            ClientMessage::SetNotify(SetNotify {
+                keys: vec![key.clone()],
+            }),
+            None,
+        )
+        .await;
+    if conformance
+        .exchange(
+            ClientMessage::Set(Set {
+                key,
+                default: 0.into(),
+                want_reply: false,
+                operations: vec![DataStorageOperation::Add(1.into())],
+            }),
+            Some(Cmd::SetReply),
+        )
+        .await
+        .is_none()
+    {
+        conformance.report.record(
+            Cmd::SetNotify,
+            Outcome::Failed("no SetReply for a notified key".to_string()),
+        );
+    }
+
+    conformance
+        .exchange(
+            ClientMessage::Bounce(Bounce {
+                games: Vec::new(),
+                slots: vec![our_slot],
+                tags: Vec::new(),
+                data: serde_json::json!({ "ap_conformance": true }),
+            }),
+            Some(Cmd::Bounced),
+        )
+        .await;
+
+    match missing {
+        Some(location) => {
+            conformance
+                .exchange(
+                    ClientMessage::LocationScouts(LocationScouts {
+                        locations: vec![location],
+                        create_as_hint: 0,
+                    }),
+                    Some(Cmd::LocationInfo),
+                )
+                .await;
+        }
+        None => conformance
+            .report
+            .record(Cmd::LocationScouts, Outcome::Skipped),
+    }
+
+    if destructive {
+        conformance
+            .exchange(
+                ClientMessage::Say(Say {
+                    text: "ap-conformance".to_string(),
+                }),
+                Some(Cmd::PrintJSON),
+            )
+            .await;
+
+        conformance
+            .exchange(
+                ClientMessage::StatusUpdate(StatusUpdate {
+                    status: ClientStatus::Ready,
+                }),
+                None,
+            )
+            .await;
+
+        match missing {
+            Some(location) => {
+                conformance
+                    .exchange(
+                        ClientMessage::LocationChecks(LocationChecks {
+                            locations: vec![location],
+                        }),
+                        Some(Cmd::RoomUpdate),
+                    )
+                    .await;
+            }
+            None => conformance
+                .report
+                .record(Cmd::LocationChecks, Outcome::Skipped),
+        }
+    } else {
+        for cmd in [Cmd::Say, Cmd::StatusUpdate, Cmd::LocationChecks] {
+            conformance.report.record(cmd, Outcome::Skipped);
+        }
+    }
+
+    if !conformance.report.print() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
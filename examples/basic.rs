@@ -1,35 +1,31 @@
 use anyhow::Context;
-use archipelago::client::AnonymousClient;
-use futures::StreamExt;
+use archipelago::data_package::{FetchPolicy, FileCache};
+use archipelago::smoke;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
-    let mut client = AnonymousClient::new(
-        std::env::var("ARCHIPELAGO_HOST").context("missing ARCHIPELAGO_HOST")?,
-    )
-    .await?;
-
-    println!("Connected!");
+    let host = std::env::var("ARCHIPELAGO_HOST").context("missing ARCHIPELAGO_HOST")?;
 
-    println!("Starting Handshake");
+    let mut options = smoke::text_client(
+        std::env::var("ARCHIPELAGO_NAME").context("missing ARCHIPELAGO_NAME")?,
+        std::env::var("ARCHIPELAGO_GAME").unwrap_or_default(),
+    )
+    .fetch_data_package(FetchPolicy::Auto);
 
-    let mut client = client
-        .connect(
-            std::env::var("ARCHIPELAGO_PASS").ok(),
-            std::env::var("ARCHIPELAGO_GAME").unwrap_or_default(),
-            std::env::var("ARCHIPELAGO_NAME").context("missing ARCHIPELAGO_GAME")?,
-            vec!["AP", "TextClient"],
-            archipelago::protocol::ItemsHandlingFlags::CAN_RECEIVE_ITEMS
-                | archipelago::protocol::ItemsHandlingFlags::HAS_LOCAL_ITEMS
-                | archipelago::protocol::ItemsHandlingFlags::REQUEST_STARTING_INVENTORY,
-        )
-        .await?;
+    if let Ok(password) = std::env::var("ARCHIPELAGO_PASS") {
+        options = options.password(password);
+    }
 
-    println!("Successful Handshake");
+    if let Some(cache) = FileCache::default_location() {
+        options = options.data_package_cache(cache);
+    }
 
-    while let Some(message) = client.next().await.transpose()? {
+    let received = smoke::run(host, options, |message| {
         println!("Message: {:#?}", message);
-    }
+    })
+    .await?;
+
+    println!("Connection closed after {} messages", received);
 
     Ok(())
 }
@@ -0,0 +1,337 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+use futures::Stream;
+use web_time::Instant;
+
+use crate::client::Client;
+use crate::events::{self, Event, EventFilter, EventType, StampedEvent};
+use crate::protocol::ServerMessage;
+
+/// What happens to a subscriber which has fallen `capacity` events behind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Discard the oldest queued event to make room for the new one.
+    #[default]
+    DropOldest,
+
+    /// Discard the new event, keeping everything already queued.
+    DropNewest,
+
+    /// End the subscription.
+    Disconnect,
+}
+
+/// Options for [`Client::subscribe`].
+#[derive(Debug, Clone)]
+pub struct SubscribeOptions {
+    filter: EventFilter,
+    capacity: usize,
+    lag_policy: LagPolicy,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        Self {
+            filter: EventFilter::new(),
+            capacity: 256,
+            lag_policy: LagPolicy::default(),
+        }
+    }
+}
+
+impl SubscribeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only delivers events accepted by `filter`.
+    pub fn filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// How many events may be queued before the lag policy applies. Defaults
+    /// to 256.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Defaults to [`LagPolicy::DropOldest`].
+    pub fn lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.lag_policy = lag_policy;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Queue {
+    events: VecDeque<StampedEvent>,
+    capacity: usize,
+    lag_policy: LagPolicy,
+    lagged: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl Queue {
+    fn push(&mut self, event: StampedEvent) {
+        if self.closed {
+            return;
+        }
+
+        if self.events.len() >= self.capacity {
+            self.lagged += 1;
+            match self.lag_policy {
+                LagPolicy::DropOldest => {
+                    self.events.pop_front();
+                }
+                LagPolicy::DropNewest => return,
+                LagPolicy::Disconnect => {
+                    self.close();
+                    return;
+                }
+            }
+        }
+
+        self.events.push_back(event);
+        self.wake();
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// An independent stream of events from a [`Client`], created by
+/// [`Client::subscribe`].
+///
+/// Events are delivered as the client is driven by its owner, whether
+/// through its own `Stream` implementation, [`Client::events`] or a spawned
+/// [`crate::handle::ClientHandle`]. The subscription ends once the client's
+/// stream ends or the client is dropped, after any queued events have been
+/// yielded.
+#[derive(Debug)]
+pub struct Subscription {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Subscription {
+    /// The number of events discarded or refused because this subscriber
+    /// fell behind, since the last call.
+    pub fn take_lagged(&self) -> u64 {
+        std::mem::take(&mut self.queue.lock().unwrap().lagged)
+    }
+
+    /// Yields [`StampedEvent`]s instead, with the sequence number and receive
+    /// time of each event.
+    pub fn stamped(self) -> StampedSubscription {
+        StampedSubscription(self)
+    }
+
+    fn poll_stamped(&self, cx: &mut Context<'_>) -> Poll<Option<StampedEvent>> {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.poll_stamped(cx)
+            .map(|event| event.map(|stamped| stamped.event))
+    }
+}
+
+/// A [`Subscription`] which yields [`StampedEvent`]s, created by
+/// [`Subscription::stamped`].
+#[derive(Debug)]
+pub struct StampedSubscription(Subscription);
+
+impl StampedSubscription {
+    /// See [`Subscription::take_lagged`].
+    pub fn take_lagged(&self) -> u64 {
+        self.0.take_lagged()
+    }
+}
+
+impl Stream for StampedSubscription {
+    type Item = StampedEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_stamped(cx)
+    }
+}
+
+type Subscribers = Vec<(EventFilter, Arc<Mutex<Queue>>)>;
+
+/// The subscribers of a client, and the sequence numbers of its events.
+#[derive(Debug, Default)]
+pub(crate) struct Bus {
+    subscribers: Arc<Mutex<Subscribers>>,
+    seq: Arc<AtomicU64>,
+}
+
+impl Bus {
+    /// Subscribes with the given options. `team` is the client's own team,
+    /// for [`EventFilter::my_team_only`].
+    pub(crate) fn subscribe(&self, options: SubscribeOptions, team: i64) -> Subscription {
+        let mut subscribers = self.subscribers.lock().unwrap();
+
+        // Drop subscribers which have gone away before adding another.
+        subscribers.retain(|(_, queue)| Arc::strong_count(queue) > 1);
+
+        let queue = Arc::new(Mutex::new(Queue {
+            events: VecDeque::new(),
+            capacity: options.capacity,
+            lag_policy: options.lag_policy,
+            lagged: 0,
+            closed: false,
+            waker: None,
+        }));
+        subscribers.push((options.filter.for_team(team), queue.clone()));
+
+        Subscription { queue }
+    }
+
+    /// Publishes the events for a message, returning them if `keep` is set
+    /// so they can also be passed to [`crate::events::Events`].
+    pub(crate) fn publish(
+        &self,
+        client: &Client,
+        message: &ServerMessage,
+        keep: bool,
+    ) -> Vec<StampedEvent> {
+        publish(
+            &self.subscribers.lock().unwrap(),
+            &self.seq,
+            client,
+            message,
+            keep,
+        )
+    }
+
+    /// Stamps and publishes an event which doesn't come from a server
+    /// message, returning the stamped event.
+    pub(crate) fn publish_event(&self, event: Event) -> StampedEvent {
+        let event = StampedEvent::new(&self.seq, event, Instant::now());
+        for (filter, queue) in self.subscribers.lock().unwrap().iter() {
+            if Arc::strong_count(queue) > 1 && filter.matches(&event.event) {
+                queue.lock().unwrap().push(event.clone());
+            }
+        }
+        event
+    }
+
+    /// Creates a publisher for messages received by another connection, which
+    /// stops working once this bus is dropped.
+    pub(crate) fn publisher(&self) -> BusPublisher {
+        BusPublisher {
+            subscribers: Arc::downgrade(&self.subscribers),
+            seq: self.seq.clone(),
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        for (_, queue) in self.subscribers.lock().unwrap().iter() {
+            queue.lock().unwrap().close();
+        }
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Turns a message into events, which is only done if `keep` is set or a
+/// subscriber accepts the type of event the message becomes. Every event is
+/// stamped once, so it has the same sequence number in every subscription.
+fn publish(
+    subscribers: &Subscribers,
+    seq: &AtomicU64,
+    client: &Client,
+    message: &ServerMessage,
+    keep: bool,
+) -> Vec<StampedEvent> {
+    // Nobody is listening if the subscription was dropped.
+    let listening = |queue: &Arc<Mutex<Queue>>| Arc::strong_count(queue) > 1;
+    let event_type = EventType::of_message(message);
+    if !keep
+        && !subscribers
+            .iter()
+            .any(|(filter, queue)| listening(queue) && filter.accepts_type(event_type))
+    {
+        return Vec::new();
+    }
+
+    let received_at = Instant::now();
+    let mut events = Vec::new();
+    events::dispatch(client, message, &mut |event| {
+        events.push(StampedEvent::new(seq, event, received_at))
+    });
+
+    for (filter, queue) in subscribers {
+        if !listening(queue) {
+            continue;
+        }
+
+        let mut queue = queue.lock().unwrap();
+        for event in &events {
+            if filter.matches(&event.event) {
+                queue.push(event.clone());
+            }
+        }
+    }
+
+    events
+}
+
+/// Publishes messages from another client to a [`Bus`].
+#[derive(Debug, Clone)]
+pub(crate) struct BusPublisher {
+    subscribers: Weak<Mutex<Subscribers>>,
+    seq: Arc<AtomicU64>,
+}
+
+impl BusPublisher {
+    /// Publishes a message received by `client`, returning false if the bus
+    /// has been dropped.
+    pub(crate) fn publish(&self, client: &Client, message: &ServerMessage) -> bool {
+        match self.subscribers.upgrade() {
+            Some(subscribers) => {
+                publish(
+                    &subscribers.lock().unwrap(),
+                    &self.seq,
+                    client,
+                    message,
+                    false,
+                );
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,15 +1,48 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Poll;
-use std::{collections::VecDeque, pin::Pin, result::Result};
+use std::time::Duration;
 
-use anyhow::Context;
-use futures::stream::{SplitSink, SplitStream};
+use futures::stream::SplitSink;
 use futures::{Sink, SinkExt, Stream, StreamExt};
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tungstenite::protocol::frame::coding::{Data, OpCode};
+use tungstenite::protocol::frame::Frame;
+use tungstenite::protocol::CloseFrame;
 use tungstenite::Message;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
 
-use crate::protocol;
+use crate::bus::{Bus, BusPublisher, SubscribeOptions, Subscription};
+use crate::clock::{self, ServerClock, SharedClock};
+use crate::cooldown::{CommandCooldown, CooldownPolicy};
+use crate::data_package::{self, DataPackageCache, DataPackageFetch, FetchPolicy};
+use crate::deathlink::DEATH_LINK_TAG;
+use crate::diagnostics::ConnectStage;
+use crate::error::{Error, Result};
+use crate::events::{Event, StampedEvent};
+use crate::extensions::ExtensionRegistry;
+use crate::hints::{self, HintChange, HintTracker};
+use crate::id_map::{IdMapper, UnmappedId};
+use crate::items::{ItemQueue, ItemSendEvent};
+use crate::location_set::LocationSet;
+use crate::progress::{Progress, ProgressTracker};
+use crate::protocol::{self, ClientTag, DeathLink};
+use crate::reconnect::ReconnectPolicy;
+use crate::resolver::{ResolveError, Resolver};
+use crate::room;
+use crate::slot_data::CommonSlotData;
+use crate::timer;
+#[cfg(any(
+    feature = "tokio-transport",
+    all(feature = "browser-transport", target_arch = "wasm32")
+))]
+use crate::transport::DefaultConnector;
+use crate::transport::{BoxTransport, Connector};
+#[cfg(feature = "tokio-transport")]
+use crate::webhost::WebHostRoom;
 
-const SUPPORTED_VERSION: protocol::NetworkVersion = protocol::NetworkVersion {
+pub(crate) const SUPPORTED_VERSION: protocol::NetworkVersion = protocol::NetworkVersion {
     major: 0,
     minor: 4,
     build: 5,
@@ -19,143 +52,3067 @@ pub struct AnonymousClient {
     ws_reader: MessageStream<protocol::AnonymousServerMessage>,
     ws_writer: MessageSink<protocol::ClientMessage>,
     room_info: protocol::RoomInfo,
+    clock: ServerClock,
+    parse_concurrency: usize,
+    address: String,
+    connector: Arc<dyn Connector>,
 }
 
-type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>, Message>;
-type WsStream = SplitStream<WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>>;
+type WsSink = SplitSink<BoxTransport, Message>;
+// Boxed so the read half can be wrapped, such as by
+// [`crate::faults::FaultyTransport`].
+type WsStream = Pin<Box<dyn Stream<Item = Result<Message, tungstenite::Error>> + Send>>;
 
 impl AnonymousClient {
-    pub async fn new(url: impl AsRef<str>) -> anyhow::Result<Self> {
-        let url = url.as_ref();
-        let (host, port) = url
-            .rsplit_once(':')
-            .map_or_else(|| (url, None), |(url, port)| (url, Some(port)));
-        let port = port.unwrap_or("38281");
+    /// Connects to a server with the [`DefaultConnector`] and waits for its
+    /// RoomInfo. On failure, the error is an [`Error::Connect`] describing
+    /// which stage failed and every address which was tried.
+    ///
+    /// The url may start with `ws://` or `wss://` to choose whether TLS is
+    /// used. Without a scheme, `wss://` is tried first, falling back to
+    /// `ws://` if the TLS or websocket handshake fails, as the official
+    /// clients do. In the browser there's no fallback, see
+    /// `BrowserConnector` in [`crate::transport`]. The port defaults to 38281.
+    #[cfg(any(
+        feature = "tokio-transport",
+        all(feature = "browser-transport", target_arch = "wasm32")
+    ))]
+    pub async fn new(url: impl AsRef<str>) -> Result<Self> {
+        Self::connect_via(Arc::new(DefaultConnector::default()), url).await
+    }
+
+    /// Connects to a server through `connector` and waits for its RoomInfo.
+    /// The connector is kept for reconnecting, see [`crate::transport`].
+    pub async fn connect_via(connector: Arc<dyn Connector>, url: impl AsRef<str>) -> Result<Self> {
+        let connection = connector.connect(url.as_ref()).await?;
+        let diagnostics = connection.diagnostics;
+
+        let (ws_writer, ws_reader) = connection.transport.split();
+        let mut ws_reader = MessageStream::new(Box::pin(ws_reader), VecDeque::new());
+        let ws_writer = MessageSink::new(ws_writer);
+
+        let room_info = match ws_reader.next().await {
+            Some(Ok(protocol::AnonymousServerMessage::RoomInfo(room_info))) => room_info,
+            Some(Ok(_)) => {
+                return Err(diagnostics
+                    .fail(ConnectStage::RoomInfo, "expected RoomInfo message")
+                    .into())
+            }
+            Some(Err(e)) => return Err(diagnostics.fail(ConnectStage::RoomInfo, e).into()),
+            None => {
+                return Err(diagnostics
+                    .fail(ConnectStage::RoomInfo, "stream unexpectedly ended")
+                    .into())
+            }
+        };
+
+        let clock = ServerClock::from_sample(room_info.time, connection.upgrade_started.elapsed());
+
+        Ok(Self {
+            ws_reader,
+            ws_writer,
+            room_info,
+            clock,
+            parse_concurrency: data_package::default_parse_concurrency(),
+            address: diagnostics.url,
+            connector,
+        })
+    }
+
+    /// Injects faults into the messages read from the server from now on,
+    /// for testing how a client copes with a bad connection. Faults aren't
+    /// carried over when the client reconnects.
+    #[cfg(feature = "fixtures")]
+    pub fn inject_faults(mut self, policy: crate::faults::FaultPolicy) -> Self {
+        let inner = std::mem::replace(
+            &mut self.ws_reader.inner,
+            Box::pin(futures::stream::empty()),
+        );
+        self.ws_reader.inner = Box::pin(crate::faults::FaultyTransport::new(inner, policy));
+        self
+    }
+
+    pub fn get_room_info(&self) -> &protocol::RoomInfo {
+        &self.room_info
+    }
+
+    /// Whether the room needs a password to connect, as announced in the
+    /// RoomInfo.
+    pub fn requires_password(&self) -> bool {
+        self.room_info.password_required
+    }
+
+    pub async fn get_data_package(&mut self) -> Result<protocol::DataPackage> {
+        self.ws_writer
+            .send(protocol::ClientMessage::GetDataPackage(
+                protocol::GetDataPackage {
+                    games: self.room_info.games.clone(),
+                },
+            ))
+            .await?;
+
+        self.read_data_package().await
+    }
+
+    /// Sets how many games of a DataPackage are parsed at once. Defaults to
+    /// the number of CPUs, see [`data_package::parse_games`].
+    pub fn set_parse_concurrency(&mut self, concurrency: usize) {
+        self.parse_concurrency = concurrency;
+    }
+
+    /// Reads a DataPackage, parsing each game's data concurrently.
+    async fn read_data_package(&mut self) -> Result<protocol::DataPackage> {
+        let mut message = self
+            .ws_reader
+            .next_value()
+            .await
+            .ok_or(Error::ConnectionClosed)??;
+
+        if message.get("cmd").and_then(serde_json::Value::as_str)
+            != Some(protocol::Cmd::DataPackage.as_str())
+        {
+            return Err(Error::Protocol("expected DataPackage message".to_string()));
+        }
+
+        let games = match message
+            .pointer_mut("/data/games")
+            .map(serde_json::Value::take)
+        {
+            Some(serde_json::Value::Object(games)) => games,
+            _ => {
+                return Err(Error::Protocol(
+                    "DataPackage message is missing its games".to_string(),
+                ))
+            }
+        };
+
+        Ok(protocol::DataPackage {
+            data: protocol::DataPackageObject {
+                games: data_package::parse_games(games, self.parse_concurrency).await?,
+            },
+        })
+    }
+
+    /// Fetches the data package one game at a time, recording each game in
+    /// `fetch` as it arrives. If this fails partway through, the same `fetch`
+    /// can be passed to a new connection to only request the remaining games.
+    pub async fn fetch_data_package(&mut self, fetch: &mut DataPackageFetch) -> Result<()> {
+        while let Some(game) = fetch.pending().first().cloned() {
+            self.ws_writer
+                .send(protocol::ClientMessage::GetDataPackage(
+                    protocol::GetDataPackage {
+                        games: vec![game.clone()],
+                    },
+                ))
+                .await?;
+
+            let data_package = self.read_data_package().await?;
+
+            let mut games = data_package.data.games;
+            match games.remove(&game) {
+                Some(data) => fetch.insert(game, data),
+                None => {
+                    return Err(Error::Protocol(format!(
+                        "server did not send data for {}",
+                        game
+                    )))
+                }
+            }
+
+            for (game, data) in games {
+                fetch.insert(game, data);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn connect(
+        self,
+        password: Option<String>,
+        game: impl Into<String>,
+        name: impl Into<String>,
+        tags: Vec<impl Into<ClientTag>>,
+        items_handling: protocol::ItemsHandlingFlags,
+    ) -> Result<Client> {
+        let mut options = ConnectOptions::new(name)
+            .game(game)
+            .tags(tags)
+            .items_handling(items_handling);
+        options.password = password;
+
+        self.connect_with(options).await
+    }
+
+    /// Connects with [`ConnectOptions::slim`], returning a [`SlimClient`].
+    pub async fn connect_slim(
+        self,
+        name: impl Into<String>,
+        password: Option<String>,
+    ) -> Result<SlimClient> {
+        let mut options = ConnectOptions::slim(name);
+        options.password = password;
+
+        Ok(self.connect_with(options).await?.into_slim())
+    }
+
+    /// Performs the Connect handshake using the given options. The options are
+    /// validated before anything is sent to the server, and if the room
+    /// requires a password but none was given, this fails with
+    /// [`Error::PasswordRequired`] without sending anything.
+    ///
+    /// If the server refuses the password and a password provider was set, the
+    /// Connect is retried on the same socket with the next password it
+    /// supplies, up to the configured attempt limit.
+    pub async fn connect_with(mut self, mut options: ConnectOptions) -> Result<Client> {
+        options.validate()?;
+
+        // Fail before downloading the data package rather than waiting for
+        // the server to refuse the Connect.
+        if self.requires_password()
+            && options.password.is_none()
+            && options.password_provider.is_none()
+        {
+            return Err(Error::PasswordRequired);
+        }
+
+        let data_package = match options.fetch_data_package {
+            FetchPolicy::Skip => None,
+            policy => {
+                let mut fetch = DataPackageFetch::for_room(&self.room_info);
+                if let Some(cache) = &options.data_package_cache {
+                    fetch.load_cached(cache.as_ref());
+                }
+
+                if policy == FetchPolicy::Auto {
+                    let cached: Vec<String> = fetch.received_games().cloned().collect();
+                    self.fetch_data_package(&mut fetch).await?;
+
+                    if let Some(cache) = &options.data_package_cache {
+                        for (game, data) in fetch.received() {
+                            if !cached.contains(game) {
+                                cache.store(game, data)?;
+                            }
+                        }
+                    }
+                }
+
+                Some(fetch.into_data_package())
+            }
+        };
+
+        let mut attempt = 0;
+        let mut password = match options.password.take() {
+            Some(password) => Some(password),
+            None => options
+                .password_provider
+                .as_mut()
+                .and_then(|provider| provider.password(attempt)),
+        };
+
+        let connected = loop {
+            let connect = protocol::Connect {
+                password: password.clone(),
+                game: options.game.clone(),
+                name: options.name.clone(),
+                uuid: options.uuid.clone(),
+                version: SUPPORTED_VERSION,
+                items_handling: options.items_handling,
+                tags: options.connect_tags(),
+                slot_data: options.slot_data,
+            };
+            self.ws_writer
+                .send(protocol::ClientMessage::Connect(connect.clone()))
+                .await?;
+
+            self.ws_writer.flush().await?;
+
+            match self
+                .ws_reader
+                .next()
+                .await
+                .ok_or(Error::ConnectionClosed)??
+            {
+                protocol::AnonymousServerMessage::Connected(connected) => break connected,
+                protocol::AnonymousServerMessage::InvalidPacket(invalid) => {
+                    return Err(HandshakeError::new(invalid, connect).into())
+                }
+                protocol::AnonymousServerMessage::ConnectionRefused(refused) => {
+                    let wrong_password = refused.errors.iter().any(|error| {
+                        matches!(error, protocol::ConnectionRefusedError::InvalidPassword)
+                    });
+
+                    attempt += 1;
+                    let retry = match options.password_provider.as_mut() {
+                        Some(provider)
+                            if wrong_password && attempt < options.max_password_attempts =>
+                        {
+                            provider.password(attempt)
+                        }
+                        _ => None,
+                    };
+
+                    match retry {
+                        Some(next) => password = Some(next),
+                        None => return Err(Error::ConnectionRefused(refused.errors)),
+                    }
+                }
+                msg => {
+                    return Err(Error::Protocol(format!(
+                        "expected Connected message, got {:?}",
+                        msg
+                    )))
+                }
+            }
+        };
+
+        let (ws_reader, message_buffer) = self.ws_reader.into_inner();
+        let ws_writer = self.ws_writer.into_inner();
+        let room_info = self.room_info;
+
+        let mut ws_reader = MessageStream::new(ws_reader, message_buffer);
+        ws_reader.malformed_packets = options.malformed_packets;
+
+        let mut client = Client {
+            ws_reader,
+            ws_writer: MessageSink::new(ws_writer),
+            room_info,
+            clock: SharedClock::new(self.clock),
+            resolver: data_package.as_ref().map(Resolver::new),
+            reported_missing_games: HashSet::new(),
+            late_games: (options.fetch_data_package == FetchPolicy::Auto).then(|| LateGames {
+                cache: options.data_package_cache.take(),
+                requested: BTreeSet::new(),
+            }),
+            outbox: VecDeque::new(),
+            outbox_flushing: false,
+            data_package,
+            sent_checks: connected.checked_locations.iter().copied().collect(),
+            dedup_checks: options.dedup_checks,
+            optimistic_checks: options.optimistic_checks,
+            optimistic_batches: VecDeque::new(),
+            unconfirmed_checks: LocationSet::new(),
+            items_received: 0,
+            replayed_item_lists: 0,
+            awaiting_item_sync: false,
+            item_queue: options.item_queue.then(|| ItemQueue::new(connected.slot)),
+            progress: ProgressTracker::new(std::mem::take(&mut options.progress_thresholds)),
+            reconnect: options.reconnect,
+            command_cooldown: CommandCooldown::new(options.command_cooldown),
+            id_mapper: options.id_mapper.take(),
+            extensions: options.extensions.take(),
+            #[cfg(feature = "tokio-transport")]
+            webhost_room: options.webhost_room.take(),
+            slot_conflict: options.slot_conflict,
+            own_join_seen: false,
+            taken_over: false,
+            hints: HintTracker::new(connected.team, connected.slot),
+            connected,
+            address: self.address,
+            connector: self.connector,
+            name: options.name.clone(),
+            password,
+            uuid: options.uuid.clone(),
+            notify_keys: BTreeSet::new(),
+            notify_values: HashMap::new(),
+            tags: options.connect_tags(),
+            version_tag: options.version_tag.clone(),
+            items_handling: options.items_handling,
+            suppress_say_echo: false,
+            pending_says: VecDeque::new(),
+            say_echo: None,
+            backlog: VecDeque::new(),
+            recent_chat: VecDeque::new(),
+            sent_deaths: VecDeque::new(),
+            session_ended: None,
+            bus: Bus::default(),
+            pending_events: VecDeque::new(),
+            collect_events: false,
+        };
+
+        client.queue_late_games();
+        let progress = client.progress();
+        client.progress.pass_thresholds(&progress);
+
+        if options.auto_death_link
+            && client.common_slot_data().death_link == Some(true)
+            && !client.tags.contains(&ClientTag::DeathLink)
+        {
+            client.tags.push(ClientTag::DeathLink);
+            client.send_connect_update().await?;
+        }
+
+        Ok(client)
+    }
+}
+
+/// How many of our own deaths are remembered to recognise their echoes.
+const SENT_DEATHS_KEPT: usize = 16;
+
+/// Supplies passwords for the Connect handshake, allowing the user to be asked
+/// again when the server rejects a password.
+pub trait PasswordProvider {
+    /// Returns the password to use for the given attempt, starting at 0, or
+    /// None to stop trying.
+    fn password(&mut self, attempt: usize) -> Option<String>;
+}
+
+impl<F> PasswordProvider for F
+where
+    F: FnMut(usize) -> Option<String>,
+{
+    fn password(&mut self, attempt: usize) -> Option<String> {
+        self(attempt)
+    }
+}
+
+/// Options for the Connect handshake.
+pub struct ConnectOptions {
+    password: Option<String>,
+    password_provider: Option<Box<dyn PasswordProvider + Send>>,
+    max_password_attempts: usize,
+    game: String,
+    name: String,
+    uuid: String,
+    tags: Vec<ClientTag>,
+    version_tag: Option<ClientTag>,
+    items_handling: protocol::ItemsHandlingFlags,
+    slot_data: bool,
+    fetch_data_package: FetchPolicy,
+    data_package_cache: Option<Box<dyn DataPackageCache + Send + Sync>>,
+    auto_death_link: bool,
+    malformed_packets: MalformedPacketPolicy,
+    dedup_checks: bool,
+    optimistic_checks: bool,
+    reconnect: Option<ReconnectPolicy>,
+    command_cooldown: CooldownPolicy,
+    id_mapper: Option<Arc<dyn IdMapper>>,
+    extensions: Option<Arc<ExtensionRegistry>>,
+    slot_conflict: SlotConflictPolicy,
+    item_queue: bool,
+    progress_thresholds: Vec<u8>,
+    #[cfg(feature = "tokio-transport")]
+    webhost_room: Option<WebHostRoom>,
+}
+
+impl ConnectOptions {
+    /// Creates options for connecting to the given slot name. By default no
+    /// game is set, which requires one of the Tracker, TextOnly or HintGame
+    /// tags.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            password: None,
+            password_provider: None,
+            max_password_attempts: 3,
+            game: String::new(),
+            name: name.into(),
+            uuid: uuid::Uuid::new_v4().to_string(),
+            tags: Vec::new(),
+            version_tag: None,
+            items_handling: protocol::ItemsHandlingFlags::CAN_RECEIVE_ITEMS,
+            slot_data: true,
+            fetch_data_package: FetchPolicy::default(),
+            data_package_cache: None,
+            auto_death_link: false,
+            malformed_packets: MalformedPacketPolicy::default(),
+            dedup_checks: true,
+            optimistic_checks: false,
+            reconnect: None,
+            command_cooldown: CooldownPolicy::default(),
+            id_mapper: None,
+            extensions: None,
+            slot_conflict: SlotConflictPolicy::default(),
+            item_queue: false,
+            progress_thresholds: Vec::new(),
+            #[cfg(feature = "tokio-transport")]
+            webhost_room: None,
+        }
+    }
+
+    /// A preset for lightweight observers such as stream overlays: connects
+    /// with the Tracker tag, receives no items or slot_data and skips the
+    /// data package. Pair with [`AnonymousClient::connect_slim`] to also drop
+    /// the state a full [`Client`] keeps.
+    pub fn slim(name: impl Into<String>) -> Self {
+        Self::new(name)
+            .tag("Tracker")
+            .items_handling(protocol::ItemsHandlingFlags::NONE)
+            .slot_data(false)
+            .fetch_data_package(FetchPolicy::Skip)
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Sets a provider which is asked for a password if none was set, and
+    /// again every time the server rejects the password.
+    pub fn password_provider(mut self, provider: impl PasswordProvider + Send + 'static) -> Self {
+        self.password_provider = Some(Box::new(provider));
+        self
+    }
+
+    /// The maximum number of Connect attempts made when passwords are
+    /// rejected. Defaults to 3.
+    pub fn max_password_attempts(mut self, attempts: usize) -> Self {
+        self.max_password_attempts = attempts;
+        self
+    }
+
+    pub fn game(mut self, game: impl Into<String>) -> Self {
+        self.game = game.into();
+        self
+    }
+
+    /// Sets the unique identifier of this client. A random one is generated
+    /// if this isn't set.
+    pub fn uuid(mut self, uuid: impl Into<String>) -> Self {
+        self.uuid = uuid.into();
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<ClientTag>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn tags(mut self, tags: impl IntoIterator<Item = impl Into<ClientTag>>) -> Self {
+        self.tags.extend(tags.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets a tag identifying the client and its version, such as
+    /// `"MyGame 2.1.0"`, which some servers collect for telemetry. Unlike
+    /// other tags it is kept by [`Client::set_tags`], so it survives every
+    /// ConnectUpdate and reconnect.
+    pub fn version_tag(mut self, tag: impl Into<String>) -> Self {
+        self.version_tag = Some(ClientTag::from(tag.into()));
+        self
+    }
+
+    pub fn items_handling(mut self, items_handling: protocol::ItemsHandlingFlags) -> Self {
+        self.items_handling = items_handling;
+        self
+    }
+
+    /// Whether the server should include slot_data in the Connected response.
+    pub fn slot_data(mut self, slot_data: bool) -> Self {
+        self.slot_data = slot_data;
+        self
+    }
+
+    /// Sets how the data package is obtained before connecting. Defaults to
+    /// [`FetchPolicy::Skip`].
+    pub fn fetch_data_package(mut self, policy: FetchPolicy) -> Self {
+        self.fetch_data_package = policy;
+        self
+    }
+
+    /// Translates between in-game and Archipelago IDs, see [`crate::id_map`].
+    pub fn id_mapper(mut self, mapper: impl IdMapper + 'static) -> Self {
+        self.id_mapper = Some(Arc::new(mapper));
+        self
+    }
+
+    /// Decodes game-specific Bounce and data storage messages with the
+    /// decoders in `registry`, emitting each result as an
+    /// [`Event::Extension`].
+    pub fn extensions(mut self, registry: ExtensionRegistry) -> Self {
+        self.extensions = Some(Arc::new(registry));
+        self
+    }
+
+    /// Sets the cache used when obtaining the data package.
+    pub fn data_package_cache(
+        mut self,
+        cache: impl DataPackageCache + Send + Sync + 'static,
+    ) -> Self {
+        self.data_package_cache = Some(Box::new(cache));
+        self
+    }
+
+    /// If set, and the slot_data received on connection enables DeathLink
+    /// (see [`CommonSlotData`]), the DeathLink tag is added with a
+    /// ConnectUpdate right after connecting.
+    pub fn auto_death_link(mut self, auto_death_link: bool) -> Self {
+        self.auto_death_link = auto_death_link;
+        self
+    }
+
+    /// Sets what happens when the server sends a packet which can't be
+    /// parsed. Defaults to [`MalformedPacketPolicy::Skip`].
+    pub fn malformed_packets(mut self, policy: MalformedPacketPolicy) -> Self {
+        self.malformed_packets = policy;
+        self
+    }
+
+    /// Whether [`Client::check_locations`] leaves out locations which were
+    /// already sent or checked, so game integrations which resend their whole
+    /// list of checks on every save don't flood the server. Defaults to true.
+    pub fn dedup_checks(mut self, dedup_checks: bool) -> Self {
+        self.dedup_checks = dedup_checks;
+        self
+    }
+
+    /// Whether [`Client::check_locations`] marks locations as checked in
+    /// [`Client::get_connected`] right away, rather than waiting for the
+    /// server to confirm them with a RoomUpdate. This hides latency for
+    /// players far from the server. Checks the server doesn't confirm are
+    /// rolled back, see [`Event::ChecksRolledBack`]. Defaults to false.
+    pub fn optimistic_checks(mut self, optimistic_checks: bool) -> Self {
+        self.optimistic_checks = optimistic_checks;
+        self
+    }
+
+    /// Whether the client feeds the items it receives into an [`ItemQueue`],
+    /// available from [`Client::item_queue`]. Off by default, as the queue
+    /// keeps every item until it's popped.
+    pub fn item_queue(mut self, item_queue: bool) -> Self {
+        self.item_queue = item_queue;
+        self
+    }
+
+    /// Emits [`Event::ProgressThreshold`] as [`Client::progress`] passes each
+    /// of the given percentages, such as `[25, 50, 75, 100]`. Thresholds
+    /// already passed when connecting are left out.
+    pub fn progress_thresholds(mut self, percents: impl IntoIterator<Item = u8>) -> Self {
+        self.progress_thresholds = percents.into_iter().collect();
+        self
+    }
+
+    /// Sets what happens when another game client connects to our slot.
+    /// Defaults to [`SlotConflictPolicy::Notify`].
+    pub fn slot_conflict(mut self, policy: SlotConflictPolicy) -> Self {
+        self.slot_conflict = policy;
+        self
+    }
+
+    /// Reconnects automatically when the connection drops, see
+    /// [`Client::recv`]. Off by default.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// The WebHost room we're connecting to. [`Client::reconnect`] then looks
+    /// up the room's current address rather than reusing the old one, as
+    /// rooms can come back up on a different port after shutting down, and
+    /// emits [`Event::AddressChanged`] when it moved.
+    #[cfg(feature = "tokio-transport")]
+    pub fn webhost_room(mut self, room: WebHostRoom) -> Self {
+        self.webhost_room = Some(room);
+        self
+    }
+
+    /// Sets what the command helpers do with commands sent during the
+    /// server's command cooldown, see [`CommandCooldown`]. Defaults to
+    /// [`CooldownPolicy::Reject`].
+    pub fn command_cooldown(mut self, policy: CooldownPolicy) -> Self {
+        self.command_cooldown = policy;
+        self
+    }
+
+    /// The tags sent in the Connect, including the version tag.
+    fn connect_tags(&self) -> Vec<ClientTag> {
+        let mut tags = self.tags.clone();
+        if let Some(version_tag) = &self.version_tag {
+            if !tags.contains(version_tag) {
+                tags.push(version_tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Returns true if these options connect as a tracker, which only observes
+    /// the slot rather than playing it.
+    pub fn is_tracker(&self) -> bool {
+        self.tags.contains(&ClientTag::Tracker)
+    }
+
+    /// Checks for option combinations the server would refuse, as its refusal
+    /// messages for these cases don't explain what was wrong.
+    pub fn validate(&self) -> Result<(), ConnectOptionsError> {
+        if self.name.is_empty() {
+            return Err(ConnectOptionsError::EmptyName);
+        }
+
+        if self.game.is_empty() && !self.tags.iter().any(ClientTag::is_gameless) {
+            return Err(ConnectOptionsError::GameRequired);
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ConnectOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Passwords are deliberately left out so they don't end up in logs.
+        let mut debug = f.debug_struct("ConnectOptions");
+        debug
+            .field("has_password", &self.password.is_some())
+            .field("has_password_provider", &self.password_provider.is_some())
+            .field("max_password_attempts", &self.max_password_attempts)
+            .field("game", &self.game)
+            .field("name", &self.name)
+            .field("uuid", &self.uuid)
+            .field("tags", &self.tags)
+            .field("version_tag", &self.version_tag)
+            .field("items_handling", &self.items_handling)
+            .field("slot_data", &self.slot_data)
+            .field("fetch_data_package", &self.fetch_data_package)
+            .field("has_data_package_cache", &self.data_package_cache.is_some())
+            .field("auto_death_link", &self.auto_death_link)
+            .field("malformed_packets", &self.malformed_packets)
+            .field("dedup_checks", &self.dedup_checks)
+            .field("optimistic_checks", &self.optimistic_checks)
+            .field("reconnect", &self.reconnect)
+            .field("command_cooldown", &self.command_cooldown)
+            .field("has_id_mapper", &self.id_mapper.is_some())
+            .field("has_extensions", &self.extensions.is_some())
+            .field("slot_conflict", &self.slot_conflict)
+            .field("item_queue", &self.item_queue)
+            .field("progress_thresholds", &self.progress_thresholds);
+        #[cfg(feature = "tokio-transport")]
+        debug.field("webhost_room", &self.webhost_room);
+        debug.finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectOptionsError {
+    #[error("a slot name is required to connect")]
+    EmptyName,
+    #[error("a game is required unless connecting with the Tracker, TextOnly or HintGame tag")]
+    GameRequired,
+}
+
+/// A connection to a slot, streaming the [`protocol::ServerMessage`]s the
+/// server sends.
+///
+/// # Ordering
+///
+/// Messages are returned in the order the server sent them, whether they
+/// arrived in the same frame or in separate ones. Messages which arrive while
+/// a method waits for a particular reply (such as
+/// [`Client::get_location_name_groups`]) are buffered and returned afterwards,
+/// still in their original order. In particular:
+///
+/// - items are delivered in index order, as the server sends them;
+/// - a SetReply is never returned before the Set which caused it was sent, and
+///   never before messages the server sent ahead of it;
+/// - chat messages are delivered in arrival order.
+///
+/// [`Client::events`] and [`Client::subscribe`] see messages in the same
+/// order. These guarantees are checked by `tests/ordering.rs`.
+pub struct Client {
+    ws_reader: MessageStream<protocol::ServerMessage>,
+    ws_writer: MessageSink<protocol::ClientMessage>,
+
+    room_info: protocol::RoomInfo,
+    clock: SharedClock,
+    data_package: Option<protocol::DataPackageObject>,
+    resolver: Option<Resolver>,
+
+    // Games missing from the data package which Event::MissingGameData was
+    // sent for.
+    reported_missing_games: HashSet<String>,
+
+    // Games fetched in the background after the handshake, with
+    // FetchPolicy::Auto.
+    late_games: Option<LateGames>,
+
+    // Messages queued from within poll_next, which can't wait for them to be
+    // sent, and whether any were sent but not yet flushed. They go out as
+    // the stream is polled.
+    outbox: VecDeque<protocol::ClientMessage>,
+    outbox_flushing: bool,
+    connected: protocol::Connected,
+
+    // How we connected, so more connections to the same slot can be made.
+    address: String,
+    connector: Arc<dyn Connector>,
+    #[cfg(feature = "tokio-transport")]
+    webhost_room: Option<WebHostRoom>,
+    name: String,
+    password: Option<String>,
+    uuid: String,
+
+    // Data storage keys registered with SetNotify, and their last known
+    // values, so both can be restored after reconnecting.
+    notify_keys: BTreeSet<String>,
+    notify_values: HashMap<String, serde_json::Value>,
+
+    hints: HintTracker,
+
+    // Locations which were checked when we connected or have been sent
+    // since, used to leave duplicates out of LocationChecks.
+    sent_checks: LocationSet,
+    dedup_checks: bool,
+
+    // With optimistic checks, the locations marked as checked by each
+    // LocationChecks still waiting for the server's RoomUpdate, oldest
+    // first.
+    optimistic_checks: bool,
+    optimistic_batches: VecDeque<Vec<i64>>,
+
+    // Locations sent in LocationChecks which the server hasn't yet confirmed
+    // in a RoomUpdate, resent after reconnecting in case they were lost.
+    unconfirmed_checks: LocationSet,
+
+    // The index after the last item returned from the stream, and how many
+    // full lists of items the server is still expected to resend after a
+    // reconnect, so items already returned can be left out of them.
+    items_received: i64,
+    replayed_item_lists: u8,
+
+    // Whether a Sync was sent after the item indexes skipped ahead or went
+    // back, so ReceivedItems are dropped until the full list arrives.
+    awaiting_item_sync: bool,
+    item_queue: Option<ItemQueue>,
+
+    progress: ProgressTracker,
+
+    reconnect: Option<ReconnectPolicy>,
+    command_cooldown: CommandCooldown,
+    id_mapper: Option<Arc<dyn IdMapper>>,
+    extensions: Option<Arc<ExtensionRegistry>>,
+
+    // Whether the server has announced our own Join since connecting, so
+    // later Joins to our slot are other clients, and whether one of them
+    // took over the slot with a SlotConflictPolicy still to carry out.
+    slot_conflict: SlotConflictPolicy,
+    own_join_seen: bool,
+    taken_over: bool,
+
+    // The tags and items handling currently in effect, which are sent in full
+    // with every ConnectUpdate. The version tag is always among the tags.
+    tags: Vec<ClientTag>,
+    version_tag: Option<ClientTag>,
+    items_handling: protocol::ItemsHandlingFlags,
+
+    // Texts sent with Client::say whose echo hasn't come back yet, oldest
+    // first, and the text of the latest message from the stream if it was
+    // one of those echoes, for Client::chat_message to leave out.
+    suppress_say_echo: bool,
+    pending_says: VecDeque<(Instant, String)>,
+    say_echo: Option<String>,
+
+    // Times of the deaths we sent most recently, so their echoes can be told
+    // apart from other players' deaths.
+    sent_deaths: VecDeque<f64>,
+
+    // Messages which arrived while waiting for a reply to a request, which
+    // still need to be returned from the stream.
+    backlog: VecDeque<protocol::ServerMessage>,
+
+    // Chat received shortly before the connection closed often explains why,
+    // such as the room shutting down.
+    recent_chat: VecDeque<(Instant, String)>,
+    session_ended: Option<SessionEnded>,
+
+    bus: Bus,
+
+    // Events waiting to be returned by Client::events. Only kept while an
+    // Events stream exists (collect_events), so clients driven through the
+    // Stream impl or subscribers don't build up a queue nobody drains.
+    pending_events: VecDeque<StampedEvent>,
+    collect_events: bool,
+}
+
+impl Client {
+    pub fn get_room_info(&self) -> &protocol::RoomInfo {
+        &self.room_info
+    }
+
+    pub fn get_connected(&self) -> &protocol::Connected {
+        &self.connected
+    }
+
+    /// The tags currently in effect for this connection.
+    pub fn tags(&self) -> &[ClientTag] {
+        &self.tags
+    }
+
+    /// The tag set with [`ConnectOptions::version_tag`], if any.
+    pub fn version_tag(&self) -> Option<&str> {
+        self.version_tag.as_ref().map(ClientTag::as_str)
+    }
+
+    /// The items handling currently in effect.
+    pub fn items_handling(&self) -> protocol::ItemsHandlingFlags {
+        self.items_handling
+    }
+
+    /// Replaces the tags in effect with a ConnectUpdate. The version tag is
+    /// kept even if it isn't among `tags`.
+    pub async fn set_tags(
+        &mut self,
+        tags: impl IntoIterator<Item = impl Into<ClientTag>>,
+    ) -> Result<()> {
+        self.update_connection(tags, self.items_handling).await
+    }
+
+    /// Replaces both the tags and the items handling in effect with a single
+    /// ConnectUpdate, such as to switch between playing and tracker mode
+    /// without reconnecting. As with [`Client::set_tags`], the version tag is
+    /// kept. Both are also used for any later reconnect.
+    pub async fn update_connection(
+        &mut self,
+        tags: impl IntoIterator<Item = impl Into<ClientTag>>,
+        items_handling: protocol::ItemsHandlingFlags,
+    ) -> Result<()> {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        if let Some(version_tag) = &self.version_tag {
+            if !self.tags.contains(version_tag) {
+                self.tags.push(version_tag.clone());
+            }
+        }
+        self.items_handling = items_handling;
+        self.send_connect_update().await
+    }
+
+    async fn send_connect_update(&mut self) -> Result<()> {
+        self.send(protocol::ClientMessage::ConnectUpdate(
+            protocol::ConnectUpdate {
+                items_handling: self.items_handling,
+                tags: self.tags.clone(),
+            },
+        ))
+        .await
+    }
+
+    /// The slot_data keys shared by many games, such as death_link.
+    pub fn common_slot_data(&self) -> CommonSlotData {
+        CommonSlotData::from_slot_data(&self.connected.slot_data)
+    }
+
+    /// The data package obtained during the handshake, if
+    /// [`ConnectOptions::fetch_data_package`] was set.
+    pub fn data_package(&self) -> Option<&protocol::DataPackageObject> {
+        self.data_package.as_ref()
+    }
+
+    /// Name lookups for the data package, if one was obtained during the
+    /// handshake.
+    pub fn resolver(&self) -> Option<&Resolver> {
+        self.resolver.as_ref()
+    }
+
+    /// Fetches the data package of one game from the server and adds it to
+    /// [`Client::data_package`] and [`Client::resolver`], such as after an
+    /// [`Event::MissingGameData`]. With [`FetchPolicy::Auto`] the game is also
+    /// stored in the data package cache.
+    ///
+    /// Messages which arrive while waiting for the reply are returned from
+    /// the stream afterwards, as with [`Client::scout_hints`].
+    pub async fn load_game_data(&mut self, game: &str) -> Result<()> {
+        self.send(protocol::ClientMessage::GetDataPackage(
+            protocol::GetDataPackage {
+                games: vec![game.to_string()],
+            },
+        ))
+        .await?;
+
+        // A DataPackage fetched in the background may arrive first, so only
+        // take one with the game in it.
+        let reply = self
+            .wait_for_reply(|message| match message {
+                protocol::ServerMessage::DataPackage(package) => {
+                    package.data.games.contains_key(game)
+                }
+                _ => false,
+            })
+            .await?;
+        let protocol::ServerMessage::DataPackage(package) = reply else {
+            unreachable!("wait_for_reply only returns DataPackage");
+        };
+
+        let mut games = package.data.games;
+        let data = games
+            .remove(game)
+            .ok_or_else(|| Error::Protocol(format!("server did not send data for {}", game)))?;
+
+        self.insert_game_data(game, data);
+
+        Ok(())
+    }
+
+    /// Adds a game to the data package and the resolver, storing it in the
+    /// cache if there is one.
+    fn insert_game_data(&mut self, game: &str, data: protocol::GameData) {
+        if let Some(late) = &mut self.late_games {
+            late.requested.remove(game);
+            if let Some(cache) = &late.cache {
+                if let Err(e) = cache.store(game, &data) {
+                    tracing::warn!(game, error = %e, "failed to cache game data");
+                }
+            }
+        }
+
+        self.resolver
+            .get_or_insert_with(Resolver::default)
+            .insert_game(game, &data);
+        self.data_package
+            .get_or_insert_with(|| protocol::DataPackageObject {
+                games: HashMap::new(),
+            })
+            .games
+            .insert(game.to_string(), data);
+        self.reported_missing_games.remove(game);
+    }
+
+    /// Requests the games in the room which the data package is missing or
+    /// has outdated data for, to be fetched in the background.
+    fn queue_late_games(&mut self) {
+        let Some(late) = &mut self.late_games else {
+            return;
+        };
+
+        let checksums = &self.room_info.datapackage_checksums;
+        let games = self.data_package.as_ref().map(|package| &package.games);
+        let missing: Vec<String> = self
+            .room_info
+            .games
+            .iter()
+            .filter(|game| {
+                let current = games
+                    .and_then(|games| games.get(*game))
+                    .is_some_and(|data| checksums.get(*game).is_none_or(|c| *c == data.checksum));
+                !current && !late.requested.contains(*game)
+            })
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+
+        tracing::debug!(games = ?missing, "fetching data for games which joined the room");
+        late.requested.extend(missing.iter().cloned());
+        self.outbox
+            .push_back(protocol::ClientMessage::GetDataPackage(
+                protocol::GetDataPackage { games: missing },
+            ));
+    }
+
+    /// Sends the messages in the outbox as the connection is ready for them.
+    /// Failures are left for the stream to report, and anything unsent is
+    /// dropped, to be requested again after reconnecting.
+    fn poll_outbox(&mut self, cx: &mut std::task::Context<'_>) {
+        while !self.outbox.is_empty() {
+            match self.ws_writer.poll_ready_unpin(cx) {
+                Poll::Ready(Ok(())) => {
+                    let message = self.outbox.pop_front().expect("outbox isn't empty");
+                    match self.ws_writer.start_send_unpin(message) {
+                        Ok(()) => self.outbox_flushing = true,
+                        Err(e) => tracing::debug!(error = %e, "failed to send queued message"),
+                    }
+                }
+                Poll::Ready(Err(e)) => {
+                    tracing::debug!(error = %e, "failed to send queued messages");
+                    self.outbox.clear();
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        if self.outbox_flushing {
+            if let Poll::Ready(result) = self.ws_writer.poll_flush_unpin(cx) {
+                self.outbox_flushing = false;
+                if let Err(e) = result {
+                    tracing::debug!(error = %e, "failed to send queued messages");
+                }
+            }
+        }
+    }
+
+    /// Adds the games of a DataPackage which were fetched in the background,
+    /// announcing them with [`Event::GameDataLoaded`].
+    fn receive_late_games(&mut self, package: &protocol::DataPackage) {
+        let Some(late) = &self.late_games else {
+            return;
+        };
+
+        let mut loaded: Vec<String> = package
+            .data
+            .games
+            .keys()
+            .filter(|game| late.requested.contains(*game))
+            .cloned()
+            .collect();
+        if loaded.is_empty() {
+            return;
+        }
+
+        loaded.sort();
+        for game in &loaded {
+            self.insert_game_data(game, package.data.games[game].clone());
+        }
+        self.emit_event(Event::GameDataLoaded(loaded));
+    }
+
+    /// The server's clock, as estimated from the time in RoomInfo and
+    /// corrected by round trip times measured with [`Client::ping`].
+    pub fn server_clock(&self) -> ServerClock {
+        self.clock.get()
+    }
+
+    /// The server's clock, shared so that corrections reach anything holding
+    /// it.
+    pub fn shared_clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Runs `future` once the server's clock reaches `server_time`, see
+    /// [`clock::at_server_time`]. The returned future doesn't borrow the
+    /// client, so it can wait while the client is driven elsewhere, which is
+    /// needed for corrections to be picked up.
+    pub fn at_server_time<F: Future>(
+        &self,
+        server_time: f64,
+        future: F,
+    ) -> impl Future<Output = F::Output> {
+        clock::at_server_time(self.clock.clone(), server_time, future)
+    }
+
+    /// Sends a websocket ping, timestamped so the round trip time can be
+    /// measured when the pong arrives. This corrects the server clock, so
+    /// calling it periodically as a keepalive keeps [`Client::at_server_time`]
+    /// accurate. The pong is only seen while the client is driven.
+    pub async fn ping(&mut self) -> Result<()> {
+        let sent = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        self.ws_writer
+            .send_raw(Message::Ping(sent.to_be_bytes().to_vec()))
+            .await
+    }
+
+    /// Sends a raw message to the server.
+    pub async fn send(&mut self, message: protocol::ClientMessage) -> Result<()> {
+        check_message(&message)?;
+
+        match &message {
+            protocol::ClientMessage::SetNotify(notify) => {
+                self.notify_keys.extend(notify.keys.iter().cloned());
+            }
+            protocol::ClientMessage::LocationChecks(checks) => {
+                self.unconfirmed_checks
+                    .extend(checks.locations.iter().copied());
+            }
+            _ => {}
+        }
+
+        self.ws_writer.send(message).await
+    }
+
+    /// The game being played in our slot.
+    pub fn game(&self) -> Option<&str> {
+        self.connected
+            .slot_info
+            .get(&self.connected.slot)
+            .map(|slot| slot.game.as_str())
+    }
+
+    /// Checks locations about to be sent in LocationChecks, returning a
+    /// warning for each one the server would silently ignore. Locations are
+    /// also checked against our game's data package, if there is one, to
+    /// tell IDs from the wrong game apart from locations which just aren't in
+    /// this seed.
+    pub fn validate_location_checks(&self, locations: &[i64]) -> Vec<LocationCheckWarning> {
+        let game_data = self
+            .game()
+            .and_then(|game| self.data_package.as_ref()?.games.get(game));
+        let not_in_game = game_data
+            .map(|data| data.unknown_locations(locations))
+            .unwrap_or_default();
+
+        locations
+            .iter()
+            .copied()
+            .filter(|location| {
+                !self.connected.missing_locations.contains(location)
+                    && !self.connected.checked_locations.contains(location)
+            })
+            .map(|location| {
+                if not_in_game.contains(&location) {
+                    LocationCheckWarning::NotInGame(location)
+                } else {
+                    LocationCheckWarning::NotInSlot(location)
+                }
+            })
+            .collect()
+    }
+
+    /// Informs the server of locations which have been checked.
+    ///
+    /// Unless disabled with [`ConnectOptions::dedup_checks`], locations which
+    /// were already checked when we connected or sent earlier in the session
+    /// are left out, and nothing is sent if no locations remain.
+    ///
+    /// With an [`IdMapper`] set, the locations are in-game IDs, and nothing
+    /// is sent if any of them can't be translated.
+    pub async fn check_locations(&mut self, locations: &[i64]) -> Result<()> {
+        let locations = self.map_locations(locations)?;
+        self.send_location_checks(&locations).await?;
+        Ok(())
+    }
+
+    /// Translates in-game location IDs to Archipelago IDs with the
+    /// [`IdMapper`], or returns them unchanged if none is set.
+    pub fn map_locations(&self, locations: &[i64]) -> Result<Vec<i64>> {
+        let Some(mapper) = &self.id_mapper else {
+            return Ok(locations.to_vec());
+        };
+
+        locations
+            .iter()
+            .map(|&location| {
+                mapper
+                    .location_to_ap(location)
+                    .ok_or(UnmappedId::Location(location).into())
+            })
+            .collect()
+    }
+
+    /// The translation between in-game and Archipelago IDs, if one was set
+    /// with [`ConnectOptions::id_mapper`] or [`Client::set_id_mapper`].
+    pub fn id_mapper(&self) -> Option<&dyn IdMapper> {
+        self.id_mapper.as_deref()
+    }
+
+    pub fn set_id_mapper(&mut self, mapper: Option<Arc<dyn IdMapper>>) {
+        self.id_mapper = mapper;
+    }
+
+    /// Sends a LocationChecks for Archipelago location IDs, returning those
+    /// marked as checked by optimistic checks.
+    pub(crate) async fn send_location_checks(&mut self, locations: &[i64]) -> Result<Vec<i64>> {
+        let locations: Vec<i64> = if self.dedup_checks {
+            let mut seen = LocationSet::new();
+            locations
+                .iter()
+                .copied()
+                .filter(|location| !self.sent_checks.contains(*location) && seen.insert(*location))
+                .collect()
+        } else {
+            locations.to_vec()
+        };
+
+        if locations.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.send(protocol::ClientMessage::LocationChecks(
+            protocol::LocationChecks {
+                locations: locations.clone(),
+            },
+        ))
+        .await?;
+
+        Ok(self.record_location_checks(&locations))
+    }
+
+    /// Records locations which were sent in LocationChecks, marking them as
+    /// checked with optimistic checks. Returns the locations marked.
+    pub(crate) fn record_location_checks(&mut self, locations: &[i64]) -> Vec<i64> {
+        let marked = if self.optimistic_checks {
+            self.mark_checked(locations)
+        } else {
+            Vec::new()
+        };
+        self.sent_checks.extend(locations.iter().copied());
+        marked
+    }
+
+    /// Changes whether [`Client::check_locations`] leaves out duplicates.
+    pub fn set_dedup_checks(&mut self, dedup_checks: bool) {
+        self.dedup_checks = dedup_checks;
+    }
+
+    /// Changes whether [`Client::check_locations`] marks locations as checked
+    /// before the server confirms them. See
+    /// [`ConnectOptions::optimistic_checks`].
+    pub fn set_optimistic_checks(&mut self, optimistic_checks: bool) {
+        self.optimistic_checks = optimistic_checks;
+    }
+
+    /// Moves those of `locations` which are missing in our world to the
+    /// checked locations, remembering them until the server confirms them.
+    /// Locations which aren't ours are left for the server to reject.
+    /// Returns the locations marked.
+    fn mark_checked(&mut self, locations: &[i64]) -> Vec<i64> {
+        let connected = &mut self.connected;
+        let mut batch = Vec::new();
+        connected.missing_locations.retain(|location| {
+            let sent = locations.contains(location);
+            if sent {
+                batch.push(*location);
+            }
+            !sent
+        });
+        if batch.is_empty() {
+            return batch;
+        }
+
+        connected.checked_locations.extend(&batch);
+        self.optimistic_batches.push_back(batch.clone());
+        batch
+    }
+
+    /// Rolls back optimistic checks the server has answered without
+    /// confirming. The server answers LocationChecks in order, so a RoomUpdate
+    /// confirming any location of a batch means it has seen that batch and
+    /// every one before it.
+    fn resolve_optimistic_checks(&mut self, confirmed: &LocationSet) {
+        let Some(answered) = self
+            .optimistic_batches
+            .iter()
+            .rposition(|batch| batch.iter().any(|location| confirmed.contains(*location)))
+        else {
+            return;
+        };
+
+        let rejected: Vec<i64> = self
+            .optimistic_batches
+            .drain(..=answered)
+            .flatten()
+            .filter(|location| self.unconfirmed_checks.remove(*location))
+            .collect();
+        if rejected.is_empty() {
+            return;
+        }
+
+        let connected = &mut self.connected;
+        connected
+            .checked_locations
+            .retain(|location| !rejected.contains(location));
+        connected.missing_locations.extend(&rejected);
+        self.emit_event(Event::ChecksRolledBack(rejected));
+    }
+
+    /// Informs the server of our status, such as reaching our goal.
+    pub async fn status_update(&mut self, status: protocol::ClientStatus) -> Result<()> {
+        self.send(protocol::ClientMessage::StatusUpdate(
+            protocol::StatusUpdate { status },
+        ))
+        .await
+    }
+
+    /// Asks the server to resend every item we've received, as a
+    /// ReceivedItems starting at index 0.
+    pub async fn sync(&mut self) -> Result<()> {
+        self.send(protocol::ClientMessage::Sync(())).await
+    }
+
+    /// Asks the server what items are at the given locations, which it
+    /// answers with a LocationInfo. See [`Client::scout_hints`] for creating
+    /// hints and waiting for the reply.
+    pub async fn location_scouts(
+        &mut self,
+        locations: Vec<i64>,
+        mode: protocol::CreateAsHint,
+    ) -> Result<()> {
+        self.send(protocol::ClientMessage::LocationScouts(
+            protocol::LocationScouts {
+                locations,
+                create_as_hint: mode.into(),
+            },
+        ))
+        .await
+    }
+
+    /// Fetches the values of data storage keys, which the server answers with
+    /// a Retrieved.
+    pub async fn get(&mut self, keys: Vec<String>) -> Result<()> {
+        self.send(protocol::ClientMessage::Get(protocol::Get { keys }))
+            .await
+    }
+
+    /// Applies `operations` to a data storage key, starting from `default` if
+    /// the key has no value yet. If `want_reply` is set, the server answers
+    /// with a SetReply. Keys starting with `_read` are rejected before
+    /// anything is sent.
+    pub async fn set(
+        &mut self,
+        key: impl Into<String>,
+        default: serde_json::Value,
+        want_reply: bool,
+        operations: Vec<protocol::DataStorageOperation>,
+    ) -> Result<()> {
+        self.send(protocol::ClientMessage::Set(protocol::Set {
+            key: key.into(),
+            default,
+            want_reply,
+            operations,
+        }))
+        .await
+    }
+
+    /// Makes sure everything sent so far, in particular status updates such
+    /// as goal completion, has been written to the socket. Returns an error if
+    /// that fails or doesn't finish within `timeout`, in which case the server
+    /// may never have seen them.
+    pub async fn flush_critical(&mut self, timeout: Duration) -> Result<()> {
+        timer::timeout(timeout, self.ws_writer.flush())
+            .await
+            .map_err(|_| Error::Timeout("flushing pending messages"))?
+    }
+
+    /// Closes the connection without waiting on anything else.
+    pub(crate) async fn close(&mut self) -> Result<()> {
+        self.ws_writer.close().await
+    }
+
+    /// Flushes pending messages and closes the connection. If flushing fails,
+    /// the connection is still closed but the flush error is returned, so
+    /// callers know a goal or status update may have been lost.
+    pub async fn disconnect(mut self, timeout: Duration) -> Result<()> {
+        let flushed = self.flush_critical(timeout).await;
+
+        let closed = timer::timeout(timeout, self.ws_writer.close())
+            .await
+            .map_err(|_| Error::Timeout("closing the connection"))
+            .and_then(|result| result);
+
+        flushed.and(closed)
+    }
+
+    /// Sends a chat message to the server, which will be distributed to all
+    /// other clients.
+    pub async fn say(&mut self, text: impl Into<String>) -> Result<()> {
+        let text = text.into();
+        self.send(protocol::ClientMessage::Say(protocol::Say {
+            text: text.clone(),
+        }))
+        .await?;
+
+        if self.suppress_say_echo {
+            if self.pending_says.len() == PENDING_SAYS_KEPT {
+                self.pending_says.pop_front();
+            }
+            self.pending_says.push_back((Instant::now(), text));
+        }
+        Ok(())
+    }
+
+    /// Sends a Bounce to every client matching any of the given games, slots
+    /// or tags. Note that the server treats an empty list as matching nothing,
+    /// not everything, so at least one target must be given.
+    pub async fn bounce(
+        &mut self,
+        games: Vec<String>,
+        slots: Vec<i64>,
+        tags: Vec<String>,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        if games.is_empty() && slots.is_empty() && tags.is_empty() {
+            return Err(BounceError::NoTargets.into());
+        }
+
+        self.send(protocol::ClientMessage::Bounce(protocol::Bounce {
+            games,
+            slots,
+            tags,
+            data,
+        }))
+        .await
+    }
+
+    /// Sends a Bounce to every client playing the same game as us.
+    pub async fn bounce_to_game(&mut self, data: serde_json::Value) -> Result<()> {
+        let game = self.game().ok_or(BounceError::UnknownGame)?.to_string();
+        self.bounce(vec![game], Vec::new(), Vec::new(), data).await
+    }
+
+    /// Sends a Bounce to the clients connected to the given slots.
+    pub async fn bounce_to_slots(
+        &mut self,
+        slots: Vec<i64>,
+        data: serde_json::Value,
+    ) -> Result<()> {
+        self.bounce(Vec::new(), slots, Vec::new(), data).await
+    }
+
+    /// Sends a chat command, such as `!hint`, respecting the server's command
+    /// cooldown according to the [`CooldownPolicy`]. The command helpers all
+    /// go through here; commands sent with [`Client::say`] are not checked.
+    pub async fn send_command(&mut self, command: impl Into<String>) -> Result<()> {
+        let remaining = self.command_cooldown.remaining();
+        if !remaining.is_zero() {
+            match self.command_cooldown.policy() {
+                CooldownPolicy::Reject => return Err(Error::CommandCooldown { remaining }),
+                CooldownPolicy::Queue => timer::sleep(remaining).await,
+            }
+        }
+
+        self.say(command).await?;
+        self.command_cooldown.record();
+        Ok(())
+    }
+
+    /// Asks the server for its options with `!options`. The command cooldown
+    /// is picked up from the reply.
+    pub async fn request_options(&mut self) -> Result<()> {
+        self.send_command("!options").await
+    }
+
+    /// The server's command cooldown, as learned from its replies so far.
+    pub fn command_cooldown(&self) -> &CommandCooldown {
+        &self.command_cooldown
+    }
+
+    pub fn command_cooldown_mut(&mut self) -> &mut CommandCooldown {
+        &mut self.command_cooldown
+    }
+
+    /// Changes our alias using the `!alias` command. The server confirms the
+    /// change by sending a RoomUpdate with the updated player list, which
+    /// [`crate::room::RoomState`] picks up.
+    pub async fn set_alias(&mut self, alias: &str) -> Result<()> {
+        self.send_command(format!("!alias {}", alias)).await
+    }
+
+    /// Starts a server countdown using the `!countdown` command. Progress is
+    /// broadcast to every client as [`protocol::PrintJSON::Countdown`]
+    /// messages, ending with a countdown of 0.
+    ///
+    /// The server refuses countdowns longer than an hour, so those are
+    /// rejected before anything is sent.
+    pub async fn start_countdown(&mut self, seconds: u32) -> Result<()> {
+        check_countdown(seconds)?;
+        self.send_command(format!("!countdown {}", seconds)).await
+    }
+
+    /// Starts a server countdown through the server's admin console, for
+    /// servers where `!countdown` is restricted. This requires having logged
+    /// in with `!admin login <password>` earlier in the session; the result is
+    /// reported as a [`protocol::PrintJSON::AdminCommandResult`].
+    pub async fn start_admin_countdown(&mut self, seconds: u32) -> Result<()> {
+        check_countdown(seconds)?;
+        self.send_command(format!("!admin /countdown {}", seconds))
+            .await
+    }
+
+    /// Scouts locations in our own world, creating hints for them as if
+    /// `!hint_location` had been used.
+    ///
+    /// Locations which don't belong to this slot are rejected before anything
+    /// is sent, as the server silently ignores them. The server broadcasts its
+    /// Hint messages before replying with LocationInfo, which is used to tell
+    /// which of the scouted locations became hints. Any other messages which
+    /// arrive in the meantime are still returned from the stream.
+    ///
+    /// Unless `mode` is [`protocol::CreateAsHint::No`], the scout is also
+    /// refused if our hint points don't cover the room's hint cost for every
+    /// location, with [`ScoutHintError::NotEnoughHintPoints`], or with
+    /// [`ScoutHintError::HintsDisabled`] if checks earn no hint points in
+    /// this room.
+    pub async fn scout_hints(
+        &mut self,
+        locations: Vec<i64>,
+        mode: protocol::CreateAsHint,
+    ) -> Result<ScoutedHints> {
+        let unknown: Vec<i64> = locations
+            .iter()
+            .copied()
+            .filter(|location| {
+                !self.connected.missing_locations.contains(location)
+                    && !self.connected.checked_locations.contains(location)
+            })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(ScoutHintError::UnknownLocations(unknown).into());
+        }
+        if mode != protocol::CreateAsHint::No {
+            self.check_hint_points(locations.len())?;
+        }
+
+        self.location_scouts(locations.clone(), mode).await?;
+
+        let slot = self.connected.slot;
+        let mut new_hints = Vec::new();
+        let info = self
+            .await_location_info(&locations, |message| {
+                if let protocol::ServerMessage::PrintJSON(protocol::PrintJSON::Hint {
+                    item, ..
+                }) = message
+                {
+                    if item.player == slot && locations.contains(&item.location) {
+                        new_hints.push(item.location);
+                    }
+                }
+            })
+            .await?;
+
+        Ok(ScoutedHints {
+            locations: info.locations,
+            new_hints,
+        })
+    }
+
+    /// Checks our hint points cover `count` hints at the room's hint cost.
+    fn check_hint_points(&self, count: usize) -> Result<(), ScoutHintError> {
+        let required = self.hint_cost_absolute() * count as i64;
+        let available = self.connected.hint_points;
+        if available >= required {
+            Ok(())
+        } else if self.room_info.location_check_points <= 0 {
+            Err(ScoutHintError::HintsDisabled)
+        } else {
+            Err(ScoutHintError::NotEnoughHintPoints {
+                required,
+                available,
+            })
+        }
+    }
+
+    /// Scouts locations and waits for the LocationInfo answering them. The
+    /// reply is told apart from answers to other scouts by the locations in
+    /// it, and any other messages which arrive in the meantime are still
+    /// returned from the stream, in order.
+    ///
+    /// Unlike [`Client::scout_hints`], locations in other worlds are allowed,
+    /// and it's up to the server whether to reject them.
+    pub async fn scout_locations(
+        &mut self,
+        locations: Vec<i64>,
+        mode: protocol::CreateAsHint,
+    ) -> Result<protocol::LocationInfo> {
+        self.location_scouts(locations.clone(), mode).await?;
+        self.await_location_info(&locations, |_| {}).await
+    }
+
+    /// Waits for the LocationInfo for exactly the given locations, passing
+    /// every other message to `observe` before buffering it.
+    async fn await_location_info(
+        &mut self,
+        locations: &[i64],
+        mut observe: impl FnMut(&protocol::ServerMessage),
+    ) -> Result<protocol::LocationInfo> {
+        let requested: BTreeSet<i64> = locations.iter().copied().collect();
+
+        loop {
+            let message = self
+                .ws_reader
+                .next()
+                .await
+                .ok_or(Error::ConnectionClosed)??;
+
+            match message {
+                protocol::ServerMessage::LocationInfo(info)
+                    if info
+                        .locations
+                        .iter()
+                        .map(|item| item.location)
+                        .collect::<BTreeSet<_>>()
+                        == requested =>
+                {
+                    return Ok(info);
+                }
+                protocol::ServerMessage::InvalidPacket(invalid)
+                    if invalid.original_cmd.as_deref()
+                        == Some(protocol::Cmd::LocationScouts.as_str()) =>
+                {
+                    return Err(ScoutHintError::Rejected(invalid).into());
+                }
+                message => {
+                    observe(&message);
+                    self.backlog.push_back(message);
+                }
+            }
+        }
+    }
+
+    /// Reads messages until one `is_reply` accepts, buffering the others to
+    /// be returned from the stream afterwards, in order.
+    pub(crate) async fn wait_for_reply(
+        &mut self,
+        mut is_reply: impl FnMut(&protocol::ServerMessage) -> bool,
+    ) -> Result<protocol::ServerMessage> {
+        loop {
+            let message = self
+                .ws_reader
+                .next()
+                .await
+                .ok_or(Error::ConnectionClosed)??;
+
+            if is_reply(&message) {
+                return Ok(message);
+            }
+            self.backlog.push_back(message);
+        }
+    }
+
+    /// Fetches the location groups of a game from the server's data storage,
+    /// mapping each group name to the names of the locations in it.
+    ///
+    /// Messages which arrive while waiting for the reply are returned from
+    /// the stream afterwards, as with [`Client::scout_hints`].
+    pub async fn get_location_name_groups(
+        &mut self,
+        game: &str,
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let key = format!("_read_location_name_groups_{}", game);
+        self.send(protocol::ClientMessage::Get(protocol::Get {
+            keys: vec![key.clone()],
+        }))
+        .await?;
+
+        loop {
+            let message = self
+                .ws_reader
+                .next()
+                .await
+                .ok_or(Error::ConnectionClosed)??;
+
+            match message {
+                protocol::ServerMessage::Retrieved(retrieved)
+                    if retrieved.keys.contains_key(&key) =>
+                {
+                    return match retrieved.keys.get(&key) {
+                        Some(serde_json::Value::Null) | None => {
+                            Err(ResolveError::UnknownGame(game.to_string()).into())
+                        }
+                        Some(groups) => Ok(serde_json::from_value(groups.clone())?),
+                    };
+                }
+                message => self.backlog.push_back(message),
+            }
+        }
+    }
+
+    /// Checks every location in one of a game's location groups in a single
+    /// LocationChecks, returning the IDs which were sent. This requires a data
+    /// package, see [`ConnectOptions::fetch_data_package`].
+    pub async fn check_location_group(&mut self, game: &str, group: &str) -> Result<Vec<i64>> {
+        if self.resolver.is_none() {
+            return Err(Error::MissingDataPackage("checking a location group"));
+        }
+
+        let mut groups = self.get_location_name_groups(game).await?;
+        let names = groups
+            .remove(group)
+            .ok_or_else(|| ResolveError::UnknownGroup {
+                game: game.to_string(),
+                group: group.to_string(),
+            })?;
+
+        let locations = self
+            .resolver
+            .as_ref()
+            .expect("checked above")
+            .location_ids(game, names.iter().map(String::as_str))?;
+        self.send_location_checks(&locations).await?;
+
+        Ok(locations)
+    }
+
+    /// Changes what happens when the server sends a packet which can't be
+    /// parsed.
+    pub fn set_malformed_packet_policy(&mut self, policy: MalformedPacketPolicy) {
+        self.ws_reader.malformed_packets = policy;
+    }
+
+    /// The number of packets skipped because they couldn't be parsed, see
+    /// [`MalformedPacketPolicy::Skip`].
+    pub fn malformed_packet_count(&self) -> u64 {
+        self.ws_reader.malformed_count
+    }
+
+    /// The most recent packet skipped because it couldn't be parsed.
+    pub fn last_malformed_packet(&self) -> Option<&MalformedPacket> {
+        self.ws_reader.last_malformed.as_ref()
+    }
+
+    /// Converts this into a [`SlimClient`], dropping everything but the
+    /// connection and the player list.
+    pub fn into_slim(self) -> SlimClient {
+        SlimClient {
+            ws_reader: self.ws_reader,
+            ws_writer: self.ws_writer,
+            team: self.connected.team,
+            slot: self.connected.slot,
+            players: self.connected.players,
+        }
+    }
+
+    /// Subscribes to this client's events. Each subscription has its own
+    /// queue, filter and [`crate::bus::LagPolicy`], so a slow subscriber never
+    /// holds up the others or the client's owner.
+    pub fn subscribe(&mut self, options: SubscribeOptions) -> Subscription {
+        self.bus.subscribe(options, self.connected.team)
+    }
+
+    /// Re-requests the state the server sent on connecting, for use after a
+    /// suspected desync or by "refresh" buttons in UIs.
+    ///
+    /// This sends a Sync, which makes the server resend every item we have
+    /// received, and reads our hints and status from the data storage. Any
+    /// RoomUpdates which arrive in the meantime are included in the snapshot.
+    /// All messages received along the way, including the full ReceivedItems,
+    /// are still returned from the stream afterwards; [`crate::items::ItemQueue`]
+    /// ignores the items it has already seen.
+    ///
+    /// The server doesn't answer a Sync at all when we haven't received any
+    /// items, so the snapshot is complete once the Retrieved for the data
+    /// storage read arrives, which the server sends after any items. Fails
+    /// with [`Error::Timeout`] if it doesn't arrive within 30 seconds.
+    ///
+    /// The snapshot is also emitted once as [`Event::Resynced`] to
+    /// subscribers and [`Client::events`].
+    pub async fn full_resync(&mut self) -> Result<ResyncSnapshot> {
+        let team = self.connected.team;
+        let slot = self.connected.slot;
+        let hints_key = hints::hints_key(team, slot);
+        let status_key = format!("_read_client_status_{}_{}", team, slot);
+
+        self.sync().await?;
+        self.send(protocol::ClientMessage::Get(protocol::Get {
+            keys: vec![hints_key.clone(), status_key.clone()],
+        }))
+        .await?;
+
+        let mut items = None;
+        let mut room_updates = Vec::new();
+        let read = async {
+            loop {
+                let message = self
+                    .ws_reader
+                    .next()
+                    .await
+                    .ok_or(Error::ConnectionClosed)??;
+
+                let retrieved = match &message {
+                    protocol::ServerMessage::ReceivedItems(received) if received.index == 0 => {
+                        items = Some(received.items.clone());
+                        None
+                    }
+                    protocol::ServerMessage::Retrieved(reply)
+                        if reply.keys.contains_key(&hints_key) =>
+                    {
+                        Some(reply.keys.clone())
+                    }
+                    protocol::ServerMessage::RoomUpdate(update) => {
+                        room_updates.push((**update).clone());
+                        None
+                    }
+                    _ => None,
+                };
+
+                self.backlog.push_back(message);
+                if let Some(retrieved) = retrieved {
+                    return Ok::<_, Error>(retrieved);
+                }
+            }
+        };
+        let mut retrieved = timer::timeout(RESYNC_TIMEOUT, read)
+            .await
+            .map_err(|_| Error::Timeout("waiting for the resync"))??;
+        let hints = match retrieved.remove(&hints_key) {
+            Some(serde_json::Value::Null) | None => Vec::new(),
+            Some(hints) => serde_json::from_value(hints)?,
+        };
+        let client_status = match retrieved.remove(&status_key) {
+            Some(serde_json::Value::Null) | None => None,
+            Some(status) => Some(serde_json::from_value(status)?),
+        };
+
+        let snapshot = ResyncSnapshot {
+            items: items.unwrap_or_default(),
+            hints,
+            client_status,
+            room_updates,
+        };
+
+        self.emit_event(Event::Resynced(Arc::new(snapshot.clone())));
+
+        Ok(snapshot)
+    }
+
+    /// Registers for SetReply messages about the given data storage keys and
+    /// fetches their current values. The registrations are remembered and
+    /// replayed by [`Client::reconnect`], as are any SetNotify sent through
+    /// [`Client::send`].
+    pub async fn set_notify(&mut self, keys: Vec<String>) -> Result<()> {
+        self.send(protocol::ClientMessage::SetNotify(protocol::SetNotify {
+            keys: keys.clone(),
+        }))
+        .await?;
+        self.get(keys).await
+    }
+
+    /// The data storage keys registered with SetNotify.
+    pub fn notify_keys(&self) -> impl Iterator<Item = &str> {
+        self.notify_keys.iter().map(String::as_str)
+    }
+
+    /// The last known value of a key registered with SetNotify, as seen in a
+    /// Retrieved or SetReply message returned from the stream.
+    pub fn notify_value(&self, key: &str) -> Option<&serde_json::Value> {
+        self.notify_values.get(key)
+    }
+
+    /// Opens a new connection to the same slot, with the same name, uuid,
+    /// tags and items handling, replacing the current one. The data package,
+    /// subscribers and any messages not yet returned from the stream are
+    /// kept.
+    ///
+    /// Once connected, a Sync is sent if we had received any items, and any
+    /// LocationChecks the server never confirmed are sent again. Items the
+    /// server resends which were already returned from the stream are left
+    /// out of the ReceivedItems, so each item index is only seen once.
+    ///
+    /// With optimistic checks, the checks being sent again stay marked as
+    /// checked, unless the new Connected shows they aren't in our world.
+    ///
+    /// SetNotify registrations are replayed on the new connection and the
+    /// registered keys fetched again. If any of them changed while we were
+    /// disconnected, [`Event::StorageResynced`] is emitted to subscribers and
+    /// [`Client::events`] listing them.
+    ///
+    /// With [`ConnectOptions::webhost_room`], the room's address is looked up
+    /// again first, and [`Event::AddressChanged`] emitted if it moved.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let mut options = ConnectOptions::new(self.name.clone())
+            .uuid(self.uuid.clone())
+            .tags(self.tags.clone())
+            .items_handling(self.items_handling)
+            .malformed_packets(self.ws_reader.malformed_packets);
+        if let Some(game) = self.game() {
+            options = options.game(game);
+        }
+        options.version_tag = self.version_tag.clone();
+        options.password = self.password.clone();
+
+        let fresh = self
+            .connect_anonymous()
+            .await?
+            .connect_with(options)
+            .await?;
+
+        self.address = fresh.address;
+        self.ws_reader = fresh.ws_reader;
+        self.ws_writer = fresh.ws_writer;
+        self.room_info = fresh.room_info;
+        self.clock.update(|clock| *clock = fresh.clock.get());
+        self.sent_checks
+            .extend(fresh.connected.checked_locations.iter().copied());
+        for location in &fresh.connected.checked_locations {
+            self.unconfirmed_checks.remove(*location);
+        }
+        self.connected = fresh.connected;
+        self.recent_chat.clear();
+        self.pending_says.clear();
+        self.session_ended = None;
+        self.own_join_seen = false;
+
+        // Requests sent on the old connection won't be answered. The Sync
+        // below takes care of any gap in the items.
+        self.outbox.clear();
+        self.outbox_flushing = false;
+        self.awaiting_item_sync = false;
+        if let Some(late) = &mut self.late_games {
+            late.requested.clear();
+        }
+        self.queue_late_games();
+
+        // The server sends every item on connecting, and again in reply to
+        // the Sync, but only if there are any. Until we've been sent an item
+        // there may be none, so nothing is counted as a replay and there's
+        // no gap for a Sync to fill.
+        self.replayed_item_lists = 0;
+        if self.items_handling.can_receive_items() && self.items_received > 0 {
+            self.replayed_item_lists = 2;
+            self.sync().await?;
+        }
+
+        if !self.unconfirmed_checks.is_empty() {
+            let locations = self.unconfirmed_checks.iter().collect();
+            self.send(protocol::ClientMessage::LocationChecks(
+                protocol::LocationChecks { locations },
+            ))
+            .await?;
+        }
+
+        // The fresh Connected has the server's view of our locations, so
+        // optimistic checks it confirmed are done, and the rest are marked
+        // again until the LocationChecks above is answered.
+        let pending: Vec<i64> = self
+            .optimistic_batches
+            .drain(..)
+            .flatten()
+            .filter(|location| self.unconfirmed_checks.contains(*location))
+            .collect();
+        self.mark_checked(&pending);
+        let rejected: Vec<i64> = pending
+            .into_iter()
+            .filter(|location| !self.connected.checked_locations.contains(location))
+            .collect();
+        for location in &rejected {
+            self.unconfirmed_checks.remove(*location);
+        }
+        if !rejected.is_empty() {
+            self.emit_event(Event::ChecksRolledBack(rejected));
+        }
+
+        self.replay_set_notify().await
+    }
+
+    /// Opens a new connection for [`Client::reconnect`], to wherever the
+    /// WebHost room is now if we have one.
+    async fn connect_anonymous(&mut self) -> Result<AnonymousClient> {
+        #[cfg(feature = "tokio-transport")]
+        if let Some(room) = &mut self.webhost_room {
+            let (client, changed) = room.connect_via(self.connector.clone()).await?;
+            if let Some(changed) = changed {
+                tracing::info!(old = %changed.old, new = %changed.new, "room address changed");
+                self.emit_event(Event::AddressChanged {
+                    old: changed.old,
+                    new: changed.new,
+                });
+            }
+            return Ok(client);
+        }
+
+        AnonymousClient::connect_via(self.connector.clone(), &self.address).await
+    }
+
+    /// Reconnects according to the [`ReconnectPolicy`] after the connection
+    /// dropped, returning false if there is no policy or it gave up. Each
+    /// attempt is announced with [`Event::Reconnecting`], and success with
+    /// [`Event::Reconnected`].
+    pub(crate) async fn auto_reconnect(&mut self) -> bool {
+        let Some(policy) = self.reconnect else {
+            return false;
+        };
+
+        let mut last_error = None;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            if !policy.allows(attempt) {
+                self.bus.close();
+                return false;
+            }
+
+            let delay = policy.delay(attempt);
+            self.emit_event(Event::Reconnecting {
+                attempt,
+                delay,
+                last_error: last_error.take(),
+            });
+            timer::sleep(delay).await;
+
+            match self.reconnect().await {
+                Ok(()) => break,
+                Err(e) => {
+                    tracing::warn!(attempt, error = %e, "failed to reconnect");
+                    last_error = Some(e.to_string());
+                }
+            }
+        }
+
+        self.emit_event(Event::Reconnected { attempts: attempt });
+        true
+    }
+
+    /// Waits for the next message, like `StreamExt::next`. If the connection
+    /// drops and a [`ReconnectPolicy`] was set, this reconnects rather than
+    /// ending or returning the websocket error, reporting progress as
+    /// [`Event::Reconnecting`] and [`Event::Reconnected`] events instead.
+    ///
+    /// Dropping this future while it reconnects, such as in a `select!`,
+    /// abandons the reconnect. Call it again to start over.
+    pub async fn recv(&mut self) -> Option<Result<protocol::ServerMessage, MessageStreamError>> {
+        loop {
+            let message = self.next().await;
+            if is_connection_lost(&message) && self.auto_reconnect().await {
+                continue;
+            }
+            if self.taken_over {
+                self.taken_over = false;
+                self.resolve_slot_conflict().await;
+            }
+            return message;
+        }
+    }
+
+    /// Changes what happens when another game client connects to our slot.
+    pub fn set_slot_conflict_policy(&mut self, policy: SlotConflictPolicy) {
+        self.slot_conflict = policy;
+    }
+
+    /// Sends [`Event::SlotTakenOver`] when the server announces another game
+    /// client joining our slot. The first Join to our slot with our own tags
+    /// after connecting is taken to be ours.
+    fn detect_takeover(&mut self, message: &protocol::ServerMessage) {
+        let protocol::ServerMessage::PrintJSON(protocol::PrintJSON::Join {
+            team, slot, tags, ..
+        }) = message
+        else {
+            return;
+        };
+        if *team != self.connected.team || *slot != self.connected.slot {
+            return;
+        }
+
+        let tags: Vec<ClientTag> = tags
+            .iter()
+            .map(|tag| ClientTag::from(tag.as_str()))
+            .collect();
+        if !self.own_join_seen && tags.iter().all(|tag| self.tags.contains(tag)) {
+            self.own_join_seen = true;
+            return;
+        }
+
+        let plays_slot = |tags: &[ClientTag]| {
+            !tags.iter().any(|tag| {
+                matches!(
+                    tag,
+                    ClientTag::Tracker | ClientTag::TextOnly | ClientTag::HintGame
+                )
+            })
+        };
+        if !plays_slot(&tags) || !plays_slot(&self.tags) {
+            return;
+        }
+
+        tracing::warn!(?tags, "another client connected to our slot");
+        self.taken_over = self.slot_conflict != SlotConflictPolicy::Notify;
+        self.emit_event(Event::SlotTakenOver { tags });
+    }
+
+    /// Carries out the [`SlotConflictPolicy`] after another client took over
+    /// our slot.
+    async fn resolve_slot_conflict(&mut self) {
+        match self.slot_conflict {
+            SlotConflictPolicy::Notify => {}
+            SlotConflictPolicy::Yield => {
+                self.reconnect = None;
+                if let Err(e) = self.ws_writer.close().await {
+                    tracing::debug!(error = %e, "failed to close the connection");
+                }
+            }
+            SlotConflictPolicy::Reclaim => {
+                if let Err(e) = self.reconnect().await {
+                    tracing::warn!(error = %e, "failed to reclaim our slot");
+                }
+            }
+        }
+    }
+
+    /// Sends an event which doesn't come from a single server message to
+    /// subscribers and [`Client::events`].
+    fn emit_event(&mut self, event: Event) {
+        let event = self.bus.publish_event(event);
+        if self.collect_events {
+            self.pending_events.push_back(event);
+        }
+    }
+
+    /// Runs the decoders set with [`ConnectOptions::extensions`] against a
+    /// message, emitting an [`Event::Extension`] for each one which matched.
+    fn dispatch_extensions(&mut self, message: &protocol::ServerMessage) {
+        let Some(registry) = self.extensions.clone() else {
+            return;
+        };
+        for event in registry.dispatch(message) {
+            self.emit_event(Event::Extension(Arc::new(event)));
+        }
+    }
+
+    /// Sends [`Event::MissingGameData`] the first time a message refers to a
+    /// game missing from the data package. Nothing is reported without a data
+    /// package, as every game would be missing.
+    fn report_missing_games(&mut self, message: &protocol::ServerMessage) {
+        let Some(resolver) = &self.resolver else {
+            return;
+        };
+
+        let slot = self.connected.slot;
+        let slots: Vec<i64> = match message {
+            protocol::ServerMessage::ReceivedItems(received) => received
+                .items
+                .iter()
+                .map(|item| item.player)
+                .chain([slot])
+                .collect(),
+            protocol::ServerMessage::LocationInfo(info) => info
+                .locations
+                .iter()
+                .map(|item| item.player)
+                .chain([slot])
+                .collect(),
+            protocol::ServerMessage::PrintJSON(print) => print
+                .data()
+                .iter()
+                .filter_map(|part| match part {
+                    protocol::JSONMessagePart::ItemId { player, .. }
+                    | protocol::JSONMessagePart::LocationId { player, .. } => Some(*player),
+                    _ => None,
+                })
+                .collect(),
+            _ => return,
+        };
+
+        let missing: BTreeSet<String> = slots
+            .iter()
+            .filter_map(|slot| self.connected.slot_info.get(slot))
+            .map(|slot| slot.game.as_str())
+            .filter(|game| !resolver.has_game(game) && !self.reported_missing_games.contains(*game))
+            .map(str::to_string)
+            .collect();
+
+        for game in missing {
+            tracing::warn!(
+                game,
+                "data package is missing a game, its names are unknown"
+            );
+            self.reported_missing_games.insert(game.clone());
+            self.emit_event(Event::MissingGameData(game));
+        }
+    }
+
+    /// Keeps the item indexes returned from the stream contiguous. Items
+    /// which were already returned are left out of a full list of items
+    /// resent after reconnecting or a Sync. If the indexes skip ahead or go
+    /// back, which means we're out of step with the server, a Sync is sent and
+    /// items are dropped until the full list arrives. Returns false if
+    /// nothing is left.
+    fn reconcile_received_items(&mut self, received: &mut protocol::ReceivedItems) -> bool {
+        if received.index == 0 {
+            if self.replayed_item_lists > 0 {
+                self.replayed_item_lists -= 1;
+            } else if self.awaiting_item_sync {
+                self.awaiting_item_sync = false;
+            } else {
+                // A full list the user asked for with Client::sync.
+                self.items_received = self.items_received.max(received.items.len() as i64);
+                self.feed_item_queue(received);
+                return true;
+            }
+        } else if self.awaiting_item_sync {
+            return false;
+        } else if received.index != self.items_received {
+            tracing::warn!(
+                expected = self.items_received,
+                index = received.index,
+                "item indexes are out of step with the server, resyncing"
+            );
+            self.emit_event(Event::ItemsDesynced {
+                expected: self.items_received,
+                index: received.index,
+            });
+            self.outbox.push_back(protocol::ClientMessage::Sync(()));
+            self.awaiting_item_sync = true;
+            if received.index > self.items_received {
+                return false;
+            }
+        }
+
+        let seen = usize::try_from(self.items_received - received.index)
+            .unwrap_or_default()
+            .min(received.items.len());
+        received.items.drain(..seen);
+        received.index += seen as i64;
+        if received.items.is_empty() {
+            return false;
+        }
+
+        self.items_received = received.index + received.items.len() as i64;
+        self.feed_item_queue(received);
+        true
+    }
+
+    fn feed_item_queue(&mut self, received: &protocol::ReceivedItems) {
+        if let Some(queue) = &mut self.item_queue {
+            queue.handle(received.clone());
+        }
+    }
+
+    /// The queue of items received, if enabled with
+    /// [`ConnectOptions::item_queue`]. Items are added as they're returned
+    /// from the stream, after gaps in the item indexes have been resolved.
+    pub fn item_queue(&mut self) -> Option<&mut ItemQueue> {
+        self.item_queue.as_mut()
+    }
+
+    async fn replay_set_notify(&mut self) -> Result<()> {
+        if self.notify_keys.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<String> = self.notify_keys.iter().cloned().collect();
+        self.set_notify(keys.clone()).await?;
+
+        let retrieved = loop {
+            let message = self
+                .ws_reader
+                .next()
+                .await
+                .ok_or(Error::ConnectionClosed)??;
+
+            match message {
+                protocol::ServerMessage::Retrieved(retrieved)
+                    if keys.iter().all(|key| retrieved.keys.contains_key(key)) =>
+                {
+                    // Still returned from the stream, so other mirrors of the
+                    // data storage see the new values too.
+                    self.backlog
+                        .push_back(protocol::ServerMessage::Retrieved(retrieved.clone()));
+                    break retrieved;
+                }
+                message => self.backlog.push_back(message),
+            }
+        };
+
+        let mut changed = Vec::new();
+        for (key, value) in retrieved.keys {
+            if self.notify_values.get(&key) != Some(&value) {
+                changed.push(key.clone());
+            }
+            self.notify_values.insert(key, value);
+        }
+
+        if !changed.is_empty() {
+            changed.sort();
+            self.emit_event(Event::StorageResynced(changed));
+        }
+
+        Ok(())
+    }
+
+    /// How far our slot is through its locations, see [`crate::progress`].
+    /// This follows the checks announced in RoomUpdate messages returned from
+    /// the stream.
+    pub fn progress(&self) -> Progress {
+        self.progress.progress(
+            &self.connected.checked_locations,
+            &self.connected.missing_locations,
+        )
+    }
+
+    /// Replaces the thresholds set with [`ConnectOptions::progress_thresholds`].
+    /// Thresholds already passed are left out.
+    pub fn set_progress_thresholds(&mut self, percents: impl IntoIterator<Item = u8>) {
+        self.progress.set_thresholds(percents.into_iter().collect());
+        let progress = self.progress();
+        self.progress.pass_thresholds(&progress);
+    }
+
+    /// Sets how much more locations holding progression items count towards
+    /// [`Client::progress`]. Defaults to
+    /// [`crate::progress::DEFAULT_PROGRESSION_WEIGHT`].
+    pub fn set_progression_weight(&mut self, weight: f64) {
+        self.progress.set_progression_weight(weight);
+    }
+
+    /// Updates the progress after checks or newly known items at our
+    /// locations, emitting [`Event::ProgressThreshold`] for each threshold
+    /// passed.
+    fn update_progress(&mut self, message: &protocol::ServerMessage) {
+        match message {
+            protocol::ServerMessage::RoomUpdate(update) if update.checked_locations.is_some() => {}
+            protocol::ServerMessage::LocationInfo(info) => {
+                self.progress.record_items(&info.locations)
+            }
+            _ => return,
+        }
+
+        let progress = self.progress();
+        for threshold in self.progress.pass_thresholds(&progress) {
+            self.emit_event(Event::ProgressThreshold {
+                threshold,
+                progress,
+            });
+        }
+    }
+
+    /// The number of hint points a hint costs. This follows changes to the
+    /// hint cost announced in RoomUpdate messages returned from the stream.
+    pub fn hint_cost_absolute(&self) -> i64 {
+        let total = self.connected.checked_locations.len() + self.connected.missing_locations.len();
+        room::hint_cost_absolute(self.room_info.hint_cost, total)
+    }
+
+    /// Keeps the RoomInfo and Connected returned by the accessors up to date.
+    fn apply_room_update(&mut self, update: protocol::RoomUpdate) {
+        let room_info = &mut self.room_info;
+        let connected = &mut self.connected;
+        let mut confirmed = None;
+
+        if let Some(version) = update.version {
+            room_info.version = version;
+        }
+        if let Some(generator_version) = update.generator_version {
+            room_info.generator_version = generator_version;
+        }
+        if let Some(tags) = update.tags {
+            room_info.tags = tags;
+        }
+        if let Some(password_required) = update.password_required {
+            room_info.password_required = password_required;
+        }
+        if let Some(permissions) = update.permissions {
+            room_info.permissions.extend(permissions);
+        }
+        if let Some(hint_cost) = update.hint_cost {
+            room_info.hint_cost = hint_cost;
+        }
+        if let Some(location_check_points) = update.location_check_points {
+            room_info.location_check_points = location_check_points;
+        }
+        if let Some(games) = update.games {
+            room_info.games = games;
+        }
+        if let Some(datapackage_checksums) = update.datapackage_checksums {
+            room_info.datapackage_checksums = datapackage_checksums;
+        }
+        if let Some(seed_name) = update.seed_name {
+            room_info.seed_name = seed_name;
+        }
+        if let Some(time) = update.time {
+            room_info.time = time;
+        }
+
+        if let Some(team) = update.team {
+            connected.team = team;
+        }
+        if let Some(slot) = update.slot {
+            connected.slot = slot;
+        }
+        if let Some(players) = update.players {
+            connected.players = players;
+        }
+        if let Some(checked) = update.checked_locations {
+            // Only newly checked locations are sent, so they need to be moved
+            // out of missing_locations by hand.
+            let checked: LocationSet = checked.into_iter().collect();
+            for location in checked.iter() {
+                self.unconfirmed_checks.remove(location);
+            }
+            connected
+                .missing_locations
+                .retain(|location| !checked.contains(*location));
+            for location in checked.iter() {
+                if !connected.checked_locations.contains(&location) {
+                    connected.checked_locations.push(location);
+                }
+            }
+            confirmed = Some(checked);
+        }
+        if let Some(slot_data) = update.slot_data {
+            connected.slot_data = slot_data;
+        }
+        if let Some(slot_info) = update.slot_info {
+            connected.slot_info = slot_info;
+        }
+        if let Some(hint_points) = update.hint_points {
+            connected.hint_points = hint_points;
+        }
+
+        if let Some(confirmed) = confirmed {
+            self.resolve_optimistic_checks(&confirmed);
+        }
+    }
+
+    /// Registers for changes to our hints with SetNotify and fetches them.
+    /// From then on, every hint list the server sends is compared against the
+    /// known hints, and only the differences are emitted as
+    /// [`Event::NewHint`], [`Event::HintFound`] and
+    /// [`Event::HintStatusChanged`].
+    pub async fn watch_hints(&mut self) -> Result<()> {
+        let key = self.hints.key().to_string();
+        self.set_notify(vec![key]).await
+    }
+
+    /// The hints involving our slot, as of the last hint list the server sent.
+    pub fn hints(&self) -> impl Iterator<Item = &protocol::Hint> {
+        self.hints.hints()
+    }
+
+    fn record_hints(&mut self, message: &protocol::ServerMessage) {
+        for change in self.hints.handle(message) {
+            self.emit_event(match change {
+                HintChange::New(hint) => Event::NewHint(hint),
+                HintChange::Found(hint) => Event::HintFound(hint),
+                HintChange::StatusChanged { hint, old } => Event::HintStatusChanged { hint, old },
+            });
+        }
+    }
+
+    fn record_notify_values(&mut self, message: &protocol::ServerMessage) {
+        match message {
+            protocol::ServerMessage::Retrieved(retrieved) => {
+                for (key, value) in &retrieved.keys {
+                    if self.notify_keys.contains(key) {
+                        self.notify_values.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            protocol::ServerMessage::SetReply(reply) if self.notify_keys.contains(&reply.key) => {
+                self.notify_values
+                    .insert(reply.key.clone(), reply.value.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// The address and options for another connection to our slot with the
+    /// Tracker tag, and a publisher to forward its messages to our
+    /// subscribers.
+    pub(crate) fn companion(&self) -> (Arc<dyn Connector>, &str, ConnectOptions, BusPublisher) {
+        let mut options = ConnectOptions::slim(self.name.clone());
+        options.password = self.password.clone();
+        (
+            self.connector.clone(),
+            &self.address,
+            options,
+            self.bus.publisher(),
+        )
+    }
 
-        // TODO: TLS
+    pub(crate) fn take_pending_event(&mut self) -> Option<StampedEvent> {
+        self.pending_events.pop_front()
+    }
 
-        let (ws, _) = connect_async(format!("ws://{}:{}", host, port))
-            .await
-            .context("failed to connect to websocket")?;
+    pub(crate) fn set_collect_events(&mut self, collect: bool) {
+        self.collect_events = collect;
+    }
 
-        let (ws_writer, ws_reader) = ws.split();
+    /// Describes why the session ended, once the stream has ended.
+    pub fn session_ended(&self) -> Option<&SessionEnded> {
+        self.session_ended.as_ref()
+    }
 
-        let mut ws_reader = MessageStream::new(ws_reader, VecDeque::new());
-        let ws_writer = MessageSink::new(ws_writer);
+    fn record_chat(&mut self, message: String) {
+        if self.recent_chat.len() == SESSION_END_CHAT_MESSAGES {
+            self.recent_chat.pop_front();
+        }
+        self.recent_chat.push_back((Instant::now(), message));
+    }
 
-        let room_info = match ws_reader.next().await {
-            Some(Ok(protocol::AnonymousServerMessage::RoomInfo(room_info))) => Ok(room_info),
-            Some(Ok(_)) => Err(anyhow::anyhow!("expected RoomInfo message")),
-            Some(Err(e)) => Err(e.into()),
-            None => Err(anyhow::anyhow!("stream unexpectedly ended")),
-        }?;
+    /// Controls whether our own Say messages, which the server echoes back as
+    /// a Chat PrintJSON, are dropped by [`Client::chat_message`]. This is off
+    /// by default. The raw message stream is not affected.
+    ///
+    /// Only echoes of text sent with [`Client::say`] while this is on are
+    /// dropped, so chat from other clients connected to our slot is kept.
+    /// The echo is recognised as the message most recently returned from the
+    /// stream.
+    pub fn set_suppress_say_echo(&mut self, suppress: bool) {
+        self.suppress_say_echo = suppress;
+        if !suppress {
+            self.pending_says.clear();
+            self.say_echo = None;
+        }
+    }
 
-        let ret = Self {
-            ws_reader,
-            ws_writer,
-            room_info,
+    /// Notes whether `message` is the echo of a Say we sent, taking it off
+    /// the pending echoes so the same text from another client on our slot
+    /// isn't mistaken for ours.
+    fn note_say_echo(&mut self, message: &protocol::ServerMessage) {
+        self.say_echo = None;
+        let protocol::ServerMessage::PrintJSON(protocol::PrintJSON::Chat {
+            team,
+            slot,
+            message: text,
+            ..
+        }) = message
+        else {
+            return;
         };
+        if *team != self.connected.team || *slot != self.connected.slot {
+            return;
+        }
 
-        Ok(ret)
+        // Says the server never echoed, such as !admin commands, are
+        // forgotten after a while.
+        let cutoff = Instant::now().checked_sub(SAY_ECHO_WINDOW);
+        self.pending_says
+            .retain(|(at, _)| cutoff.is_none_or(|cutoff| *at >= cutoff));
+        if let Some(i) = self.pending_says.iter().position(|(_, said)| said == text) {
+            self.pending_says.remove(i);
+            self.say_echo = Some(text.clone());
+        }
     }
 
-    pub async fn get_data_package(&mut self) -> anyhow::Result<protocol::DataPackage> {
-        self.ws_writer
-            .send(protocol::ClientMessage::GetDataPackage(
-                protocol::GetDataPackage {
-                    games: self.room_info.games.clone(),
-                },
-            ))
-            .await?;
+    /// Returns true if `team` is the team we're connected as.
+    pub fn is_my_team(&self, team: i64) -> bool {
+        team == self.connected.team
+    }
 
-        match self
-            .ws_reader
-            .next()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("stream unexpectedly ended"))??
-        {
-            protocol::AnonymousServerMessage::DataPackage(data_package) => Ok(data_package),
-            _ => Err(anyhow::anyhow!("expected DataPackage message")),
+    /// Whether the DeathLink tag is set, so DeathLink bounces are received.
+    pub fn death_link_enabled(&self) -> bool {
+        self.tags.contains(&ClientTag::DeathLink)
+    }
+
+    /// Adds or removes the DeathLink tag, sending a ConnectUpdate if it
+    /// changed.
+    pub async fn set_death_link(&mut self, enabled: bool) -> Result<()> {
+        if enabled == self.death_link_enabled() {
+            return Ok(());
         }
+
+        if enabled {
+            self.tags.push(ClientTag::DeathLink);
+        } else {
+            self.tags.retain(|tag| *tag != ClientTag::DeathLink);
+        }
+        self.send_connect_update().await
     }
 
-    pub async fn connect(
-        mut self,
-        password: Option<String>,
-        game: impl Into<String>,
-        name: impl Into<String>,
-        tags: Vec<impl Into<String>>,
-        items_handling: protocol::ItemsHandlingFlags,
-    ) -> anyhow::Result<Client> {
-        let tags = tags.into_iter().map(|tag| tag.into()).collect();
+    /// Sends a death to every client with the DeathLink tag, timestamped with
+    /// the server's clock and attributed to our slot name. The server also
+    /// echoes it back to us if DeathLink is enabled, but
+    /// [`Client::death_link`] and [`Client::events`] leave it out.
+    pub async fn send_death(&mut self, cause: Option<String>) -> Result<DeathLink> {
+        let death = DeathLink {
+            time: self.clock.get().now(),
+            cause,
+            source: self.name.clone(),
+        };
 
-        self.ws_writer
-            .send(protocol::ClientMessage::Connect(protocol::Connect {
-                password,
-                game: game.into(),
-                name: name.into(),
-                uuid: uuid::Uuid::new_v4().to_string(),
-                version: SUPPORTED_VERSION,
-                items_handling,
-                tags,
-                slot_data: true,
-            }))
-            .await?;
+        self.bounce(
+            Vec::new(),
+            Vec::new(),
+            vec![DEATH_LINK_TAG.to_string()],
+            serde_json::to_value(&death)?,
+        )
+        .await?;
 
-        self.ws_writer.flush().await?;
+        if self.sent_deaths.len() == SENT_DEATHS_KEPT {
+            self.sent_deaths.pop_front();
+        }
+        self.sent_deaths.push_back(death.time);
 
-        let connected = match self
-            .ws_reader
-            .next()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("stream unexpectedly ended"))??
-        {
-            protocol::AnonymousServerMessage::Connected(connected) => Ok(connected),
-            protocol::AnonymousServerMessage::InvalidPacket(invalid) => Err(anyhow::anyhow!(
-                "expected Connected message, got InvalidPacket: {:?}",
-                invalid
-            )),
-            protocol::AnonymousServerMessage::ConnectionRefused(refused) => Err(anyhow::anyhow!(
-                "expected Connected message, got ConnectionRefused: {:?}",
-                refused
-            )),
-            msg => Err(anyhow::anyhow!("expected Connected message, got {:?}", msg)),
-        }?;
+        Ok(death)
+    }
 
-        let (ws_reader, message_buffer) = self.ws_reader.into_inner();
-        let ws_writer = self.ws_writer.into_inner();
-        let room_info = self.room_info;
+    /// Returns true if this is the echo of a death sent by
+    /// [`Client::send_death`].
+    pub fn is_own_death(&self, death: &DeathLink) -> bool {
+        // Times are compared exactly, which relies on serde_json's
+        // float_roundtrip feature to parse them back to the same value.
+        death.source == self.name && self.sent_deaths.contains(&death.time)
+    }
 
-        Ok(Client {
-            ws_reader: MessageStream::new(ws_reader, message_buffer),
-            ws_writer: MessageSink::new(ws_writer),
-            room_info,
-            connected,
-        })
+    /// Extracts a DeathLink from a ServerMessage, if it is a DeathLink bounce
+    /// from another player.
+    pub fn death_link(&self, message: &protocol::ServerMessage) -> Option<DeathLink> {
+        match message {
+            protocol::ServerMessage::Bounced(bounced) => {
+                DeathLink::from_bounced(bounced).filter(|death| !self.is_own_death(death))
+            }
+            _ => None,
+        }
+    }
+
+    /// Decomposes an ItemSend message as seen from our slot, see
+    /// [`ItemSendEvent`].
+    pub fn item_send(&self, message: &protocol::ServerMessage) -> Option<ItemSendEvent> {
+        match message {
+            protocol::ServerMessage::PrintJSON(print) => {
+                ItemSendEvent::from_print(print, self.connected.slot)
+            }
+            _ => None,
+        }
+    }
+
+    /// Extracts a chat message from a ServerMessage, if it is a player chat or
+    /// server broadcast.
+    pub fn chat_message(&self, message: &protocol::ServerMessage) -> Option<ChatMessage> {
+        match message {
+            protocol::ServerMessage::PrintJSON(protocol::PrintJSON::Chat {
+                team,
+                slot,
+                message,
+                ..
+            }) => {
+                if self.suppress_say_echo && self.say_echo.as_ref() == Some(message) {
+                    return None;
+                }
+
+                Some(ChatMessage {
+                    sender: Some((*team, *slot)),
+                    message: message.clone(),
+                })
+            }
+            protocol::ServerMessage::PrintJSON(protocol::PrintJSON::ServerChat {
+                message, ..
+            }) => Some(ChatMessage {
+                sender: None,
+                message: message.clone(),
+            }),
+            _ => None,
+        }
     }
 }
 
-pub struct Client {
-    ws_reader: MessageStream<protocol::ServerMessage>,
-    ws_writer: MessageSink<protocol::ClientMessage>,
+/// The longest countdown the server will start, in seconds.
+const MAX_COUNTDOWN: u32 = 60 * 60;
 
-    room_info: protocol::RoomInfo,
-    connected: protocol::Connected,
+fn check_countdown(seconds: u32) -> Result<()> {
+    if seconds > MAX_COUNTDOWN {
+        return Err(Error::CountdownTooLong {
+            seconds,
+            max: MAX_COUNTDOWN,
+        });
+    }
+
+    Ok(())
 }
 
-impl Client {
-    pub fn get_room_info(&self) -> &protocol::RoomInfo {
-        &self.room_info
+/// Whether a result from the stream means the connection was lost.
+pub(crate) fn is_connection_lost(
+    message: &Option<Result<protocol::ServerMessage, MessageStreamError>>,
+) -> bool {
+    matches!(
+        message,
+        None | Some(Err(MessageStreamError::WebsocketError(_)))
+    )
+}
+
+/// The largest partial text message kept while waiting for the rest of it.
+/// tungstenite's own limit on message size is 64 MiB.
+const MAX_PARTIAL_MESSAGE: usize = 64 << 20;
+
+/// How many texts sent with [`Client::say`] are remembered while waiting for
+/// their echo.
+const PENDING_SAYS_KEPT: usize = 16;
+
+/// How long the echo of a Say is waited for.
+const SAY_ECHO_WINDOW: Duration = Duration::from_secs(30);
+
+/// How many chat messages are remembered to explain a closed session.
+const SESSION_END_CHAT_MESSAGES: usize = 5;
+
+/// How long before the connection closed a chat message must have been
+/// received to be considered related to it.
+const SESSION_END_CHAT_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long [`Client::full_resync`] waits for the server to answer.
+const RESYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Describes a session ended by the server, combining the close frame with
+/// the chat messages received right before it, which usually explain why
+/// (such as "Room closing").
+#[derive(Debug, Clone)]
+pub struct SessionEnded {
+    /// The close code sent by the server, if any.
+    pub code: Option<u16>,
+
+    /// The close reason sent by the server, if any.
+    pub reason: Option<String>,
+
+    /// Chat messages received shortly before the connection closed, oldest
+    /// first.
+    pub last_messages: Vec<String>,
+}
+
+/// The result of scouting locations with hint creation enabled.
+#[derive(Debug)]
+pub struct ScoutedHints {
+    /// The items at the scouted locations.
+    pub locations: Vec<protocol::NetworkItem>,
+
+    /// The scouted locations for which the server broadcast a hint.
+    pub new_hints: Vec<i64>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScoutHintError {
+    #[error("locations do not belong to this slot: {0:?}")]
+    UnknownLocations(Vec<i64>),
+    #[error("not enough hint points: {required} required, {available} available")]
+    NotEnoughHintPoints { required: i64, available: i64 },
+    #[error("hints cost more points than we have, and checks earn none in this room")]
+    HintsDisabled,
+    #[error("server rejected the scout: {0:?}")]
+    Rejected(protocol::InvalidPacket),
+}
+
+/// The server answered the Connect with an InvalidPacket, which usually means
+/// this crate sent something the server couldn't parse.
+///
+/// The Connect is kept as the JSON that was sent, so the problem can be
+/// diagnosed from the error alone. The password is replaced with
+/// `"<redacted>"` so the error is safe to share.
+#[derive(Debug, thiserror::Error)]
+#[error("server rejected the Connect ({:?}): {}; sent {sent}", invalid.r#type, invalid.text)]
+pub struct HandshakeError {
+    pub invalid: protocol::InvalidPacket,
+
+    /// The Connect packet as sent, including the surrounding array.
+    pub sent: String,
+}
+
+impl HandshakeError {
+    fn new(invalid: protocol::InvalidPacket, mut connect: protocol::Connect) -> Self {
+        if connect.password.is_some() {
+            connect.password = Some("<redacted>".to_string());
+        }
+
+        let sent = serde_json::to_string(&[protocol::ClientMessage::Connect(connect)])
+            .unwrap_or_else(|e| format!("<failed to encode: {}>", e));
+
+        Self { invalid, sent }
     }
+}
 
-    pub fn get_connected(&self) -> &protocol::Connected {
-        &self.connected
+/// The prefix of data storage keys which are maintained by the server and
+/// can't be written to.
+const READ_ONLY_PREFIX: &str = "_read";
+
+/// The special keys the server provides under `_read_`, which are easy to
+/// write to by mistake when the prefix is left off.
+const SPECIAL_KEY_PREFIXES: &[&str] = &[
+    "hints_",
+    "slot_data_",
+    "item_name_groups_",
+    "location_name_groups_",
+    "client_status_",
+    "race_mode",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SetKeyError {
+    #[error("data storage key {0:?} is read-only, as keys starting with \"_read\" can't be set")]
+    ReadOnly(String),
+}
+
+/// Checks a data storage key before it is written with Set. Keys which look
+/// like one of the server's special keys without the `_read_` prefix are
+/// allowed, but logged, as they are usually a mistake.
+pub fn check_set_key(key: &str) -> Result<(), SetKeyError> {
+    if key.starts_with(READ_ONLY_PREFIX) {
+        return Err(SetKeyError::ReadOnly(key.to_string()));
+    }
+
+    if SPECIAL_KEY_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+    {
+        tracing::warn!(
+            key,
+            "data storage key looks like the server's special key \"_read_{}\", which it won't update",
+            key
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks a message for mistakes the server would reject, before it is sent.
+pub(crate) fn check_message(message: &protocol::ClientMessage) -> Result<()> {
+    if let protocol::ClientMessage::Set(set) = message {
+        check_set_key(&set.key)?;
     }
+
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BounceError {
+    #[error("bounce has no games, slots or tags to send to")]
+    NoTargets,
+    #[error("our game is missing from the slot info")]
+    UnknownGame,
+}
+
+/// The state fetched by [`Client::full_resync`]. It can be saved in any
+/// [`crate::persist::PersistFormat`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResyncSnapshot {
+    /// Every item we have received, starting from index 0.
+    pub items: Vec<protocol::NetworkItem>,
+
+    /// All hints involving our slot.
+    pub hints: Vec<protocol::Hint>,
+
+    /// Our status as the server knows it.
+    pub client_status: Option<protocol::ClientStatus>,
+
+    /// RoomUpdates which arrived during the resync, oldest first.
+    #[serde(with = "crate::persist::json_text")]
+    pub room_updates: Vec<protocol::RoomUpdate>,
+}
+
+/// A location the server would ignore in LocationChecks, as found by
+/// [`Client::validate_location_checks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationCheckWarning {
+    /// The ID isn't a location of our game at all.
+    NotInGame(i64),
+
+    /// The location exists in our game, or there's no data package to tell,
+    /// but it isn't part of our slot in this seed.
+    NotInSlot(i64),
+}
+
+/// A chat message sent by a player or broadcast by the server.
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    /// The team and slot of the sending player, or None if this was sent by
+    /// the server.
+    pub sender: Option<(i64, i64)>,
+
+    /// The text of the message.
+    pub message: String,
 }
 
 impl Stream for Client {
     type Item = Result<protocol::ServerMessage, MessageStreamError>;
 
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let result = loop {
+            let mut result = match self.backlog.pop_front() {
+                Some(message) => Poll::Ready(Some(Ok(message))),
+                None => self.ws_reader.poll_next_unpin(cx),
+            };
+
+            if let Poll::Ready(Some(Ok(protocol::ServerMessage::ReceivedItems(received)))) =
+                &mut result
+            {
+                if !self.reconcile_received_items(received) {
+                    continue;
+                }
+            }
+            break result;
+        };
+
+        if let Some(rtt) = self.ws_reader.take_rtt() {
+            self.clock.update(|clock| clock.correct_rtt(rtt));
+        }
+
+        if let Poll::Ready(Some(Ok(message))) = &result {
+            if let protocol::ServerMessage::RoomUpdate(update) = message {
+                let update = update.clone();
+                let games_changed =
+                    update.games.is_some() || update.datapackage_checksums.is_some();
+                self.apply_room_update(*update);
+                if games_changed {
+                    self.queue_late_games();
+                }
+            }
+            if let protocol::ServerMessage::DataPackage(package) = message {
+                self.receive_late_games(package);
+            }
+            self.record_notify_values(message);
+            if let protocol::ServerMessage::PrintJSON(print) = message {
+                self.command_cooldown.observe(print);
+            }
+            self.note_say_echo(message);
+            let client = &*self;
+            let events = client.bus.publish(client, message, client.collect_events);
+            self.pending_events.extend(events);
+            self.record_hints(message);
+            self.dispatch_extensions(message);
+            self.report_missing_games(message);
+            self.detect_takeover(message);
+            self.update_progress(message);
+        }
+
+        match &result {
+            Poll::Ready(Some(Ok(protocol::ServerMessage::PrintJSON(
+                protocol::PrintJSON::ServerChat { message, .. }
+                | protocol::PrintJSON::Chat { message, .. },
+            )))) => {
+                let message = message.clone();
+                self.record_chat(message);
+            }
+            Poll::Ready(None) if self.session_ended.is_none() => {
+                let cutoff = Instant::now().checked_sub(SESSION_END_CHAT_WINDOW);
+                let frame = self.ws_reader.close_frame();
+                let ended = SessionEnded {
+                    code: frame.map(|frame| u16::from(frame.code)),
+                    reason: frame
+                        .map(|frame| frame.reason.to_string())
+                        .filter(|reason| !reason.is_empty()),
+                    last_messages: self
+                        .recent_chat
+                        .iter()
+                        .filter(|(at, _)| cutoff.is_none_or(|cutoff| *at >= cutoff))
+                        .map(|(_, message)| message.clone())
+                        .collect(),
+                };
+                self.session_ended = Some(ended);
+                // With a reconnect policy, subscriptions stay open until it
+                // gives up.
+                if self.reconnect.is_none() {
+                    self.bus.close();
+                }
+            }
+            _ => {}
+        }
+
+        self.poll_outbox(cx);
+
+        result
+    }
+}
+
+/// Fetches the data of games missing from the data package obtained during
+/// the handshake, without holding up the stream. Requests go through the
+/// client's outbox, and the replies are returned from the stream as usual
+/// after being added to the resolver.
+struct LateGames {
+    cache: Option<Box<dyn DataPackageCache + Send + Sync>>,
+
+    // Games requested but not yet received.
+    requested: BTreeSet<String>,
+}
+
+/// A client which keeps only its connection and the player list, for
+/// observers which don't need the room state a [`Client`] tracks.
+///
+/// A [`Client`] holds on to the RoomInfo, the Connected packet and the data
+/// package for the whole session. The location lists in Connected alone take
+/// 8 bytes per location in the slot, so around 800 KB for a slot with 100,000
+/// locations, and the data package of a large multiworld can run to several
+/// megabytes. None of this is kept here.
+pub struct SlimClient {
+    ws_reader: MessageStream<protocol::ServerMessage>,
+    ws_writer: MessageSink<protocol::ClientMessage>,
+    team: i64,
+    slot: i64,
+    players: Vec<protocol::NetworkPlayer>,
+}
+
+impl SlimClient {
+    pub fn team(&self) -> i64 {
+        self.team
+    }
+
+    pub fn slot(&self) -> i64 {
+        self.slot
+    }
+
+    /// The players in the room as of connecting.
+    pub fn players(&self) -> &[protocol::NetworkPlayer] {
+        &self.players
+    }
+
+    pub async fn send(&mut self, message: protocol::ClientMessage) -> Result<()> {
+        self.ws_writer.send(message).await
+    }
+
+    pub async fn say(&mut self, text: impl Into<String>) -> Result<()> {
+        self.send(protocol::ClientMessage::Say(protocol::Say {
+            text: text.into(),
+        }))
+        .await
+    }
+}
+
+impl Stream for SlimClient {
+    type Item = Result<protocol::ServerMessage, MessageStreamError>;
+
     fn poll_next(
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
@@ -186,13 +3143,19 @@ where
     fn into_inner(self) -> WsSink {
         self.inner
     }
+
+    /// Sends a websocket message other than a protocol message, such as a
+    /// ping.
+    async fn send_raw(&mut self, message: Message) -> Result<()> {
+        self.inner.send(message).await.map_err(Into::into)
+    }
 }
 
 impl<T> Sink<T> for MessageSink<T>
 where
     T: serde::ser::Serialize + Unpin,
 {
-    type Error = anyhow::Error;
+    type Error = Error;
 
     fn poll_ready(
         mut self: Pin<&mut Self>,
@@ -203,7 +3166,7 @@ where
 
     fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
         let message = Message::text(serde_json::to_string(&[item])?);
-        println!("Sending message: {:?}", message);
+        tracing::trace!(?message, "sending message");
         self.inner.start_send_unpin(message).map_err(Into::into)
     }
 
@@ -233,6 +3196,24 @@ where
     // message types.
     message_buffer: VecDeque<serde_json::Value>,
 
+    // The close frame sent by the server, if the connection was closed.
+    close_frame: Option<CloseFrame<'static>>,
+
+    // The start of a text message which ended partway through a packet, as
+    // some proxies split one message into several. The rest is expected in
+    // the next text message.
+    partial: String,
+
+    // The payload of raw frames received so far for a fragmented message.
+    fragments: Vec<u8>,
+
+    malformed_packets: MalformedPacketPolicy,
+    malformed_count: u64,
+    last_malformed: Option<MalformedPacket>,
+
+    // The round trip time of the last pong answering Client::ping.
+    rtt: Option<Duration>,
+
     phantom: std::marker::PhantomData<T>,
 }
 
@@ -244,13 +3225,158 @@ where
         Self {
             inner,
             message_buffer,
+            close_frame: None,
+            partial: String::new(),
+            fragments: Vec::new(),
+            malformed_packets: MalformedPacketPolicy::Error,
+            malformed_count: 0,
+            last_malformed: None,
+            rtt: None,
             phantom: std::marker::PhantomData,
         }
     }
 
+    fn close_frame(&self) -> Option<&CloseFrame<'static>> {
+        self.close_frame.as_ref()
+    }
+
+    fn take_rtt(&mut self) -> Option<Duration> {
+        self.rtt.take()
+    }
+
     fn into_inner(self) -> (WsStream, VecDeque<serde_json::Value>) {
         (self.inner, self.message_buffer)
     }
+
+    /// Decodes the packets in a text message, returning an error if it should
+    /// be passed on. A message which is cut off partway through is kept, and
+    /// joined with the next one.
+    fn decode_text(&mut self, text: String) -> Option<MessageStreamError> {
+        let partial = std::mem::take(&mut self.partial);
+        if !partial.is_empty() {
+            let joined = partial.clone() + &text;
+            match serde_json::from_str(&joined) {
+                Err(e) if !e.is_eof() => {
+                    // The rest of the partial message never came, so it's
+                    // malformed, and the new message is decoded on its own.
+                    let error = serde_json::from_str::<serde_json::Value>(&partial).unwrap_err();
+                    if let Some(e) = self.malformed(partial, error) {
+                        return Some(e);
+                    }
+                }
+                result => return self.finish_decode(joined, result),
+            }
+        }
+
+        let result = serde_json::from_str(&text);
+        self.finish_decode(text, result)
+    }
+
+    fn finish_decode(
+        &mut self,
+        text: String,
+        result: Result<VecDeque<serde_json::Value>, serde_json::Error>,
+    ) -> Option<MessageStreamError> {
+        match result {
+            Ok(mut messages) => {
+                self.message_buffer.append(&mut messages);
+                None
+            }
+            Err(e) if e.is_eof() && text.len() <= MAX_PARTIAL_MESSAGE => {
+                self.partial = text;
+                None
+            }
+            Err(e) => self.malformed(text, e),
+        }
+    }
+
+    /// Collects a raw frame, decoding the message once its final frame
+    /// arrives.
+    fn decode_frame(&mut self, frame: Frame) -> Option<MessageStreamError> {
+        match frame.header().opcode {
+            OpCode::Data(Data::Text | Data::Continue) => {}
+            _ => return Some(MessageStreamError::UnexpectedMessageType("frame")),
+        }
+
+        let is_final = frame.header().is_final;
+        self.fragments.extend_from_slice(frame.payload());
+        if !is_final {
+            return None;
+        }
+
+        match String::from_utf8(std::mem::take(&mut self.fragments)) {
+            Ok(text) => self.decode_text(text),
+            Err(e) => self.malformed(
+                String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                serde::de::Error::custom(e.utf8_error()),
+            ),
+        }
+    }
+
+    /// Applies the malformed packet policy to a parse failure, returning the
+    /// error if it should be passed on rather than skipped.
+    fn malformed(
+        &mut self,
+        packet: String,
+        error: serde_json::Error,
+    ) -> Option<MessageStreamError> {
+        match self.malformed_packets {
+            MalformedPacketPolicy::Error => Some(error.into()),
+            MalformedPacketPolicy::Skip => {
+                tracing::warn!(%error, packet, "skipping malformed packet");
+                self.malformed_count += 1;
+                self.last_malformed = Some(MalformedPacket {
+                    error: error.to_string(),
+                    packet,
+                });
+                None
+            }
+        }
+    }
+}
+
+/// What happens when another game client connects to our slot, which leaves
+/// two instances of the game fighting over the same items and locations.
+/// Clients connected with the Tracker, TextOnly or HintGame tag don't count,
+/// on either side. Yielding and reclaiming are carried out by
+/// [`Client::recv`], after returning the Join which announced the other
+/// client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlotConflictPolicy {
+    /// Only emit [`Event::SlotTakenOver`].
+    #[default]
+    Notify,
+
+    /// Disconnect, leaving the slot to the other client. The stream ends
+    /// rather than reconnecting.
+    Yield,
+
+    /// Reconnect, taking the slot back. Two clients which both reclaim will
+    /// keep taking it from each other.
+    Reclaim,
+}
+
+/// What happens when the server sends a packet which can't be parsed, such as
+/// a packet type or field added by a newer server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MalformedPacketPolicy {
+    /// Skip the packet, keeping the session alive. Skipped packets are
+    /// counted, and the most recent one is kept for diagnostics.
+    #[default]
+    Skip,
+
+    /// Return the parse error from the stream.
+    Error,
+}
+
+/// A packet skipped because it couldn't be parsed.
+#[derive(Debug, Clone)]
+pub struct MalformedPacket {
+    pub error: String,
+
+    /// The packet as received, or the whole websocket message if it wasn't
+    /// valid JSON.
+    pub packet: String,
 }
 
 // TODO: shouldn't be pub
@@ -259,60 +3385,74 @@ pub enum MessageStreamError {
     #[error("failed to parse message from server: {0}")]
     ParseError(#[from] serde_json::Error),
     #[error("websocket error: {0}")]
-    WebsocketError(#[from] tokio_tungstenite::tungstenite::Error),
+    WebsocketError(#[from] tungstenite::Error),
     #[error("got unexpected message type from server: {0}")]
     UnexpectedMessageType(&'static str),
 }
 
-impl<T> Stream for MessageStream<T>
+impl<T> MessageStream<T>
 where
     T: serde::de::DeserializeOwned + Unpin,
 {
-    type Item = Result<T, MessageStreamError>;
-
-    fn poll_next(
-        mut self: Pin<&mut Self>,
+    /// Polls for the next message without deserializing it.
+    fn poll_next_value(
+        &mut self,
         cx: &mut std::task::Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        // If there are any leftover messages from the last poll, return them
-        // first.
-        if let Some(message) = self.message_buffer.pop_front() {
-            return Poll::Ready(Some(serde_json::from_value(message).map_err(|e| e.into())));
-        }
-
-        match self.inner.poll_next_unpin(cx) {
-            Poll::Ready(Some(Ok(message))) => {
-                match message {
-                    Message::Text(text) => {
-                        // The server can send multiple messages in a single
-                        // websocket text response, so we store them to be used
-                        // when poll_next is called again.
-                        let mut messages: VecDeque<serde_json::Value> =
-                            serde_json::from_str(&text)?;
-
-                        let message = match messages.pop_front() {
-                            Some(message) => message,
-                            None => return Poll::Pending,
-                        };
+    ) -> Poll<Option<Result<serde_json::Value, MessageStreamError>>> {
+        loop {
+            // If there are any leftover messages from the last poll, return
+            // them first.
+            if let Some(message) = self.message_buffer.pop_front() {
+                return Poll::Ready(Some(Ok(message)));
+            }
 
-                        self.message_buffer.append(&mut messages);
+            let message = match self.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
 
-                        let result = serde_json::from_value(message).map_err(|e| e.into());
+            match message {
+                // The server can send multiple messages in a single websocket
+                // text response, so we store them to be returned by the loop.
+                Message::Text(text) => {
+                    if let Some(e) = self.decode_text(text) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                }
 
-                        Poll::Ready(Some(result))
+                // Fragmented messages are normally reassembled by tungstenite
+                // before they get here, but a transport may also pass on the
+                // raw frames.
+                Message::Frame(frame) => {
+                    if let Some(e) = self.decode_frame(frame) {
+                        return Poll::Ready(Some(Err(e)));
                     }
+                }
 
-                    // Ping is handled by the tungstenite library, so we can
-                    // effectively ignore them. We don't use pongs, so there's
-                    // no point in handling them, but it's not worth erroring.
-                    Message::Ping(_) | Message::Pong(_) => Poll::Pending,
+                // Ping is handled by the tungstenite library, so we can
+                // effectively ignore them. Pongs answering Client::ping carry
+                // the time the ping was sent; any others are ignored.
+                Message::Ping(_) => {}
+                Message::Pong(payload) => {
+                    if let Ok(sent) = <[u8; 8]>::try_from(payload.as_slice()) {
+                        let sent = UNIX_EPOCH + Duration::from_micros(u64::from_be_bytes(sent));
+                        if let Ok(rtt) = SystemTime::now().duration_since(sent) {
+                            self.rtt = Some(rtt);
+                        }
+                    }
+                }
 
-                    // If we get a "Close" message, mark this stream as done.
-                    //
-                    // TODO: maybe this should try an extract the reason.
-                    Message::Close(_) => Poll::Ready(None),
+                // If we get a "Close" message, keep the reason around and mark
+                // this stream as done.
+                Message::Close(frame) => {
+                    self.close_frame = frame;
+                    return Poll::Ready(None);
+                }
 
-                    msg => Poll::Ready(Some(Err(MessageStreamError::UnexpectedMessageType(
+                msg => {
+                    return Poll::Ready(Some(Err(MessageStreamError::UnexpectedMessageType(
                         match msg {
                             Message::Text(_) => "text",
                             Message::Binary(_) => "binary",
@@ -321,12 +3461,44 @@ where
                             Message::Close(_) => "close",
                             Message::Frame(_) => "frame",
                         },
-                    )))),
+                    ))))
+                }
+            }
+        }
+    }
+
+    /// Waits for the next message without deserializing it.
+    async fn next_value(&mut self) -> Option<Result<serde_json::Value, MessageStreamError>> {
+        futures::future::poll_fn(|cx| self.poll_next_value(cx)).await
+    }
+}
+
+impl<T> Stream for MessageStream<T>
+where
+    T: serde::de::DeserializeOwned + Unpin,
+{
+    type Item = Result<T, MessageStreamError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match self.poll_next_value(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match T::deserialize(&message) {
+                Ok(message) => return Poll::Ready(Some(Ok(message))),
+                Err(e) => {
+                    if let Some(e) = self.malformed(message.to_string(), e) {
+                        return Poll::Ready(Some(Err(e)));
+                    }
                 }
             }
-            Poll::Ready(Some(Err(inner))) => Poll::Ready(Some(Err(inner)))?,
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Pending => Poll::Pending,
         }
     }
 }
@@ -0,0 +1,215 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::protocol::Bounced;
+use crate::timer;
+
+/// The longest [`at_server_time`] sleeps before checking the clock again, so
+/// corrections made while it waits are taken into account.
+const MAX_SLEEP: Duration = Duration::from_secs(1);
+
+/// Tracks the difference between our clock and the server's.
+///
+/// Timestamps in the protocol (such as in DeathLink bounces) are unix times
+/// taken from the sender's clock, so they need to be compared against the
+/// server's notion of "now" rather than ours.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ServerClock {
+    /// Seconds to add to local time to get server time.
+    offset: f64,
+
+    /// The server timestamp minus local time when it was received, which is
+    /// behind the true offset by the time the timestamp took to arrive.
+    received_offset: f64,
+
+    /// The smoothed round trip time in seconds, half of which is taken as
+    /// the time timestamps take to arrive.
+    rtt: f64,
+}
+
+impl ServerClock {
+    /// Creates a clock from a server timestamp, such as `RoomInfo::time`,
+    /// which was just received.
+    pub fn from_server_time(server_time: f64) -> Self {
+        Self::from_sample(server_time, Duration::ZERO)
+    }
+
+    /// Creates a clock from a server timestamp which was just received, `rtt`
+    /// after sending the request which caused it. The server is assumed to
+    /// have taken the timestamp halfway through.
+    pub fn from_sample(server_time: f64, rtt: Duration) -> Self {
+        let received_offset = server_time - local_now();
+        let rtt = rtt.as_secs_f64();
+        Self {
+            offset: received_offset + rtt / 2.0,
+            received_offset,
+            rtt,
+        }
+    }
+
+    /// Re-corrects the clock with a new round trip time, such as from
+    /// [`crate::client::Client::ping`]. Measurements are smoothed like TCP's,
+    /// so a single slow round trip doesn't throw the clock off.
+    pub fn correct_rtt(&mut self, rtt: Duration) {
+        let rtt = rtt.as_secs_f64();
+        self.rtt = if self.rtt == 0.0 {
+            rtt
+        } else {
+            self.rtt * 7.0 / 8.0 + rtt / 8.0
+        };
+        self.offset = self.received_offset + self.rtt / 2.0;
+    }
+
+    /// The smoothed round trip time to the server, or zero if it was never
+    /// measured.
+    pub fn rtt(&self) -> Duration {
+        Duration::from_secs_f64(self.rtt)
+    }
+
+    /// Seconds to add to local time to get server time.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// The current unix time according to the server.
+    pub fn now(&self) -> f64 {
+        local_now() + self.offset
+    }
+
+    /// Converts a server timestamp to local time.
+    pub fn to_local(&self, server_time: f64) -> f64 {
+        server_time - self.offset
+    }
+
+    /// Converts a local timestamp to server time.
+    pub fn to_server(&self, local_time: f64) -> f64 {
+        local_time + self.offset
+    }
+}
+
+/// A [`ServerClock`] shared between a client and the futures it schedules,
+/// so corrections reach futures which are already waiting.
+#[derive(Debug, Clone, Default)]
+pub struct SharedClock(Arc<Mutex<ServerClock>>);
+
+impl SharedClock {
+    pub fn new(clock: ServerClock) -> Self {
+        Self(Arc::new(Mutex::new(clock)))
+    }
+
+    /// The clock as it is now.
+    pub fn get(&self) -> ServerClock {
+        *self.0.lock().unwrap()
+    }
+
+    pub(crate) fn update(&self, update: impl FnOnce(&mut ServerClock)) {
+        update(&mut self.0.lock().unwrap());
+    }
+}
+
+/// Runs `future` once the server's clock reaches `server_time`, a unix time,
+/// such as a tournament end time agreed in server time. If that time has
+/// passed, or isn't a number, it runs immediately.
+///
+/// The clock is checked at least every second while waiting, so corrections
+/// made in the meantime, such as after a reconnect, move the start time too.
+pub async fn at_server_time<F: Future>(
+    clock: SharedClock,
+    server_time: f64,
+    future: F,
+) -> F::Output {
+    loop {
+        let remaining = server_time - clock.get().now();
+        if remaining.is_nan() || remaining <= 0.0 {
+            break;
+        }
+        let remaining = Duration::try_from_secs_f64(remaining).unwrap_or(MAX_SLEEP);
+        timer::sleep(remaining.min(MAX_SLEEP)).await;
+    }
+
+    future.await
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimestampError {
+    #[error("payload has no numeric {0:?} field")]
+    Missing(String),
+    #[error("timestamp {0} is not a finite number")]
+    NotFinite(f64),
+    #[error("timestamp is {0:?} too old")]
+    TooOld(Duration),
+    #[error("timestamp is {0:?} in the future")]
+    InFuture(Duration),
+}
+
+/// Validates timestamps in Bounce payloads against the server clock, so
+/// custom link protocols can reject stale or bogus events the same way
+/// DeathLink does.
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampValidator {
+    clock: ServerClock,
+    max_age: Duration,
+    max_future: Duration,
+}
+
+impl TimestampValidator {
+    /// Creates a validator accepting timestamps up to `max_age` old. A small
+    /// allowance for timestamps in the future is made for clock jitter.
+    pub fn new(clock: ServerClock, max_age: Duration) -> Self {
+        Self {
+            clock,
+            max_age,
+            max_future: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets how far in the future a timestamp may be.
+    pub fn max_future(mut self, max_future: Duration) -> Self {
+        self.max_future = max_future;
+        self
+    }
+
+    /// Checks a timestamp, in server time, returning it converted to local
+    /// time. Ages too large for a [`Duration`] are reported as
+    /// [`Duration::MAX`].
+    pub fn validate(&self, timestamp: f64) -> Result<f64, TimestampError> {
+        if !timestamp.is_finite() {
+            return Err(TimestampError::NotFinite(timestamp));
+        }
+        let age = self.clock.now() - timestamp;
+
+        if age > self.max_age.as_secs_f64() {
+            let age = Duration::try_from_secs_f64(age).unwrap_or(Duration::MAX);
+            return Err(TimestampError::TooOld(age));
+        }
+
+        if -age > self.max_future.as_secs_f64() {
+            let ahead = Duration::try_from_secs_f64(-age).unwrap_or(Duration::MAX);
+            return Err(TimestampError::InFuture(ahead));
+        }
+
+        Ok(self.clock.to_local(timestamp))
+    }
+
+    /// Validates the timestamp stored in the given field of a Bounced
+    /// payload, such as `time` for DeathLink.
+    pub fn validate_bounce(&self, bounced: &Bounced, field: &str) -> Result<f64, TimestampError> {
+        let timestamp = bounced
+            .data
+            .get(field)
+            .and_then(serde_json::Value::as_f64)
+            .ok_or_else(|| TimestampError::Missing(field.to_string()))?;
+
+        self.validate(timestamp)
+    }
+}
+
+fn local_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
@@ -0,0 +1,68 @@
+use futures::StreamExt;
+use tokio::task::JoinHandle;
+
+use crate::client::{AnonymousClient, Client};
+use crate::error::Result;
+use crate::tasks;
+
+/// A second connection to our slot using the Tracker tag, created by
+/// [`Client::spawn_companion_tracker`].
+///
+/// Tools such as "universal trackers" keep a read-only connection next to the
+/// game's own, so tracking keeps working whatever the game connection is
+/// doing. Everything the companion receives is published to the parent
+/// client's subscribers (see [`Client::subscribe`]). As both connections are
+/// to the same room, broadcasts such as chat are received by both, so
+/// subscribers may see them twice.
+///
+/// The companion disconnects when this is dropped, when the parent client is
+/// dropped or when its connection closes.
+#[derive(Debug)]
+pub struct CompanionTracker {
+    task: JoinHandle<()>,
+}
+
+impl CompanionTracker {
+    /// Returns true while the companion connection is open.
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    /// Disconnects the companion.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for CompanionTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Client {
+    /// Opens a companion connection to our slot with the same credentials
+    /// but the Tracker tag, driven by a task on the current tokio runtime.
+    /// See [`CompanionTracker`].
+    pub async fn spawn_companion_tracker(&self) -> Result<CompanionTracker> {
+        let (connector, address, options, publisher) = self.companion();
+        let mut companion = AnonymousClient::connect_via(connector, address)
+            .await?
+            .connect_with(options)
+            .await?;
+
+        let room = self.get_room_info().seed_name.clone();
+        let slot = self.get_connected().slot;
+        let task = tasks::spawn("archipelago companion tracker", &room, slot, async move {
+            while let Some(Ok(message)) = companion.next().await {
+                if !publisher.publish(&companion, &message) {
+                    break;
+                }
+            }
+
+            let _ = companion.close().await;
+        });
+
+        Ok(CompanionTracker { task })
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::protocol::PrintJSON;
+
+/// What the command helpers, such as [`crate::client::Client::hint`], do with
+/// a command sent while the server's command cooldown is still running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CooldownPolicy {
+    /// Fail with [`crate::Error::CommandCooldown`], which says how long is
+    /// left.
+    #[default]
+    Reject,
+
+    /// Wait for the cooldown to finish, then send the command.
+    Queue,
+}
+
+/// Tracks the server's cooldown between chat commands, so commands the
+/// server would refuse aren't sent.
+///
+/// The cooldown is learned from the server's own replies: the `!options`
+/// listing, and the "please wait" reply to a command sent too early. Until
+/// either has been seen the cooldown is zero, unless set with
+/// [`CommandCooldown::set_cooldown`].
+#[derive(Debug, Clone, Default)]
+pub struct CommandCooldown {
+    cooldown: Duration,
+    policy: CooldownPolicy,
+    ready_at: Option<Instant>,
+}
+
+impl CommandCooldown {
+    pub fn new(policy: CooldownPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// The time the server requires between commands, as far as is known.
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn policy(&self) -> CooldownPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: CooldownPolicy) {
+        self.policy = policy;
+    }
+
+    /// How long until another command can be sent, which is zero if one can
+    /// be sent now.
+    pub fn remaining(&self) -> Duration {
+        self.ready_at.map_or(Duration::ZERO, |ready_at| {
+            ready_at.saturating_duration_since(Instant::now())
+        })
+    }
+
+    /// Records that a command was just sent.
+    pub fn record(&mut self) {
+        self.ready_at = Some(Instant::now() + self.cooldown);
+    }
+
+    /// Updates the cooldown from a server reply, returning true if the reply
+    /// was about the cooldown. A "please wait" reply also means the command
+    /// was refused, so the wait it gives is started over.
+    pub fn observe(&mut self, print: &PrintJSON) -> bool {
+        if !matches!(
+            print,
+            PrintJSON::CommandResult { .. } | PrintJSON::AdminCommandResult { .. }
+        ) {
+            return false;
+        }
+
+        let text: String = print.data().iter().map(|part| part.text()).collect();
+        if let Some(cooldown) = parse_cooldown_option(&text) {
+            self.cooldown = cooldown;
+            true
+        } else if let Some(wait) = parse_wait(&text) {
+            self.cooldown = self.cooldown.max(wait);
+            self.ready_at = Some(Instant::now() + wait);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Finds a cooldown in `!options` output, which lists one `name: value`
+/// option per line, with the value in seconds.
+pub fn parse_cooldown_option(text: &str) -> Option<Duration> {
+    text.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.to_lowercase().contains("cooldown") {
+            return None;
+        }
+        parse_seconds(value.trim())
+    })
+}
+
+/// Finds the wait in a reply refusing a command, such as "Please wait 5
+/// seconds before using another command."
+pub fn parse_wait(text: &str) -> Option<Duration> {
+    let lower = text.to_lowercase();
+    let (_, rest) = lower.split_once("wait")?;
+    let mut words = rest.split_whitespace();
+    let seconds = words.find_map(parse_seconds)?;
+    words
+        .next()
+        .filter(|unit| unit.starts_with("sec"))
+        .map(|_| seconds)
+}
+
+fn parse_seconds(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
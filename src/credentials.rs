@@ -0,0 +1,33 @@
+//! Room password storage in the OS credential store.
+
+use crate::error::Result;
+
+const SERVICE: &str = "archipelago-rs";
+
+fn entry(host: &str, slot: &str) -> Result<keyring::Entry> {
+    Ok(keyring::Entry::new(SERVICE, &format!("{}@{}", slot, host))?)
+}
+
+/// Stores the password for a slot on the given host.
+pub fn store_password(host: &str, slot: &str, password: &str) -> Result<()> {
+    entry(host, slot)?.set_password(password)?;
+    Ok(())
+}
+
+/// Returns the stored password for a slot on the given host, if any.
+pub fn load_password(host: &str, slot: &str) -> Result<Option<String>> {
+    match entry(host, slot)?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes the stored password for a slot on the given host. Removing a
+/// password which isn't stored is not an error.
+pub fn delete_password(host: &str, slot: &str) -> Result<()> {
+    match entry(host, slot)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use futures::{StreamExt, TryStreamExt};
+
+use crate::error::{Error, Result};
+use crate::persist::{Json, PersistFormat};
+use crate::protocol::{DataPackageObject, GameData, RoomInfo};
+
+/// Tracks the progress of fetching a multi-game DataPackage, so a fetch
+/// interrupted by a dropped connection can be resumed on a new connection
+/// without redownloading the games which were already received.
+#[derive(Debug, Default)]
+pub struct DataPackageFetch {
+    pending: Vec<String>,
+    checksums: HashMap<String, String>,
+    received: HashMap<String, GameData>,
+}
+
+impl DataPackageFetch {
+    /// Creates a fetch for the given games.
+    pub fn new(games: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            pending: games.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a fetch for every game in the room, remembering the checksums
+    /// the server advertised so cached data can be checked against them.
+    pub fn for_room(room_info: &RoomInfo) -> Self {
+        Self {
+            pending: room_info.games.clone(),
+            checksums: room_info.datapackage_checksums.clone(),
+            received: HashMap::new(),
+        }
+    }
+
+    /// Provides game data from a local cache. If the checksum matches what the
+    /// server advertised (or no checksum is known), the game no longer needs
+    /// to be fetched and true is returned.
+    pub fn insert_cached(&mut self, game: &str, data: GameData) -> bool {
+        if let Some(checksum) = self.checksums.get(game) {
+            if *checksum != data.checksum {
+                return false;
+            }
+        }
+
+        self.insert(game.to_string(), data);
+        true
+    }
+
+    /// Records game data received from the server.
+    pub(crate) fn insert(&mut self, game: String, data: GameData) {
+        self.pending.retain(|pending| *pending != game);
+        self.received.insert(game, data);
+    }
+
+    /// Loads every pending game which the cache has a matching checksum for.
+    pub fn load_cached(&mut self, cache: &dyn DataPackageCache) {
+        for game in self.pending.clone() {
+            let Some(checksum) = self.checksums.get(&game) else {
+                continue;
+            };
+
+            if let Some(data) = cache.load(&game, checksum) {
+                self.insert(game, data);
+            }
+        }
+    }
+
+    /// Games which have not been received yet.
+    pub fn pending(&self) -> &[String] {
+        &self.pending
+    }
+
+    /// Returns true once data for every game has been received.
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Games which have been received or loaded from a cache.
+    pub fn received_games(&self) -> impl Iterator<Item = &String> {
+        self.received.keys()
+    }
+
+    /// The data received so far.
+    pub fn received(&self) -> &HashMap<String, GameData> {
+        &self.received
+    }
+
+    /// Returns the data received so far as a DataPackageObject.
+    pub fn into_data_package(self) -> DataPackageObject {
+        DataPackageObject {
+            games: self.received,
+        }
+    }
+}
+
+/// A local store of game data, so data packages don't need to be downloaded
+/// every time a client connects.
+pub trait DataPackageCache {
+    /// Returns the cached data for a game, if its checksum matches.
+    fn load(&self, game: &str, checksum: &str) -> Option<GameData>;
+
+    /// Stores the data for a game.
+    fn store(&self, game: &str, data: &GameData) -> Result<()>;
+}
+
+/// A DataPackageCache storing each game as a file named after its checksum,
+/// in JSON unless another [`PersistFormat`] is chosen.
+#[derive(Debug, Clone)]
+pub struct FileCache<F = Json> {
+    dir: PathBuf,
+    format: F,
+}
+
+impl FileCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_format(dir, Json)
+    }
+
+    /// Creates a cache inside the platform's cache directory.
+    pub fn default_location() -> Option<Self> {
+        Self::default_location_with_format(Json)
+    }
+}
+
+impl<F: PersistFormat> FileCache<F> {
+    pub fn with_format(dir: impl Into<PathBuf>, format: F) -> Self {
+        Self {
+            dir: dir.into(),
+            format,
+        }
+    }
+
+    /// Creates a cache inside the platform's cache directory, storing games
+    /// in the given format.
+    pub fn default_location_with_format(format: F) -> Option<Self> {
+        dirs::cache_dir()
+            .map(|dir| Self::with_format(dir.join("archipelago-rs").join("datapackage"), format))
+    }
+
+    fn path(&self, checksum: &str) -> PathBuf {
+        // Game names can contain characters which aren't valid in file names,
+        // so files are named after the checksum alone.
+        let checksum: String = checksum
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect();
+        self.dir.join(format!("{}.{}", checksum, F::EXTENSION))
+    }
+}
+
+impl<F: PersistFormat> DataPackageCache for FileCache<F> {
+    fn load(&self, _game: &str, checksum: &str) -> Option<GameData> {
+        let contents = std::fs::read(self.path(checksum)).ok()?;
+        let data: GameData = self.format.deserialize(&contents).ok()?;
+        (data.checksum == checksum).then_some(data)
+    }
+
+    fn store(&self, _game: &str, data: &GameData) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).map_err(Error::file("create", &self.dir))?;
+        let path = self.path(&data.checksum);
+        std::fs::write(&path, self.format.serialize(data)?).map_err(Error::file("write", &path))?;
+        Ok(())
+    }
+}
+
+/// How `AnonymousClient::connect_with` obtains the data package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FetchPolicy {
+    /// Use cached games where possible and download the rest, storing them in
+    /// the cache. Games which join the room later, or whose checksum changes
+    /// after reconnecting, are fetched in the background while the client
+    /// runs.
+    Auto,
+
+    /// Only use cached games. Games missing from the cache are left out.
+    CacheOnly,
+
+    /// Don't obtain the data package.
+    #[default]
+    Skip,
+}
+
+/// The default number of games parsed at once by [`parse_games`]: the number
+/// of CPUs available.
+pub fn default_parse_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// Parses the `games` object of a DataPackage, running up to `concurrency`
+/// games at a time on tokio's blocking thread pool. Large multiworlds can
+/// have dozens of games with thousands of names each, so this noticeably
+/// reduces the time taken to connect.
+pub async fn parse_games(
+    games: serde_json::Map<String, serde_json::Value>,
+    concurrency: usize,
+) -> Result<HashMap<String, GameData>> {
+    if concurrency <= 1 || games.len() <= 1 {
+        return games
+            .into_iter()
+            .map(|(game, data)| Ok((game, serde_json::from_value(data)?)))
+            .collect();
+    }
+
+    futures::stream::iter(games)
+        .map(|(game, data)| async move {
+            let data = tokio::task::spawn_blocking(move || serde_json::from_value(data))
+                .await
+                .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))?;
+            Ok::<_, Error>((game, data))
+        })
+        .buffer_unordered(concurrency)
+        .try_collect()
+        .await
+}
@@ -0,0 +1,95 @@
+use crate::protocol::{Bounced, DeathLink};
+
+/// The tag which opts a client into DeathLink bounces.
+pub const DEATH_LINK_TAG: &str = "DeathLink";
+
+impl DeathLink {
+    /// Parses a DeathLink from a Bounced, if it was sent to the DeathLink tag
+    /// and has the expected data.
+    pub fn from_bounced(bounced: &Bounced) -> Option<Self> {
+        if !bounced.tags.iter().any(|tag| tag == DEATH_LINK_TAG) {
+            return None;
+        }
+
+        serde_json::from_value(bounced.data.clone()).ok()
+    }
+}
+
+/// Persists the number of deaths counted towards the amnesty, so the count
+/// survives reconnects and restarts.
+pub trait AmnestyStore {
+    fn load(&mut self) -> u32;
+    fn store(&mut self, count: u32);
+}
+
+/// Death amnesty: ignores a number of local deaths before one is sent out as
+/// a DeathLink, so a single mistake doesn't kill everyone.
+///
+/// With an amnesty of 2, the first two deaths are ignored and the third is
+/// sent, after which counting starts over.
+pub struct DeathAmnesty {
+    amnesty: u32,
+    count: u32,
+    store: Option<Box<dyn AmnestyStore + Send>>,
+}
+
+impl DeathAmnesty {
+    pub fn new(amnesty: u32) -> Self {
+        Self {
+            amnesty,
+            count: 0,
+            store: None,
+        }
+    }
+
+    /// Creates an amnesty which sends one out of every `ratio` deaths.
+    pub fn ratio(ratio: u32) -> Self {
+        Self::new(ratio.saturating_sub(1))
+    }
+
+    /// Loads the current count from the store, and saves it back every time
+    /// it changes.
+    pub fn with_store(mut self, mut store: impl AmnestyStore + Send + 'static) -> Self {
+        self.count = store.load();
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    /// The number of deaths which have been ignored since the last one was
+    /// sent.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn amnesty(&self) -> u32 {
+        self.amnesty
+    }
+
+    /// Changes the amnesty. Deaths already counted are kept.
+    pub fn set_amnesty(&mut self, amnesty: u32) {
+        self.amnesty = amnesty;
+    }
+
+    /// Records a local death, returning true if it should be sent as a
+    /// DeathLink.
+    pub fn record_death(&mut self) -> bool {
+        let send = self.count >= self.amnesty;
+        self.count = if send { 0 } else { self.count + 1 };
+
+        if let Some(store) = &mut self.store {
+            store.store(self.count);
+        }
+
+        send
+    }
+}
+
+impl std::fmt::Debug for DeathAmnesty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeathAmnesty")
+            .field("amnesty", &self.amnesty)
+            .field("count", &self.count)
+            .field("has_store", &self.store.is_some())
+            .finish()
+    }
+}
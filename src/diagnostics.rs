@@ -0,0 +1,70 @@
+use std::net::SocketAddr;
+
+/// The stages of establishing a connection to a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectStage {
+    /// Resolving the host name.
+    Dns,
+
+    /// Opening a TCP connection to one of the resolved addresses.
+    Tcp,
+
+    /// Negotiating TLS.
+    Tls,
+
+    /// Upgrading the connection to a websocket.
+    WebSocket,
+
+    /// Waiting for the server's RoomInfo.
+    RoomInfo,
+}
+
+/// A TCP connection attempt to a single resolved address.
+#[derive(Debug, Clone)]
+pub struct AddressAttempt {
+    pub address: SocketAddr,
+
+    /// Why the attempt failed, or None if it succeeded.
+    pub error: Option<String>,
+}
+
+/// What happened while trying to connect, to explain a failed connection.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectDiagnostics {
+    /// The websocket url being connected to.
+    pub url: String,
+
+    /// The stage which failed.
+    pub failed_stage: Option<ConnectStage>,
+
+    /// Every address a TCP connection was attempted to, in order.
+    pub attempts: Vec<AddressAttempt>,
+}
+
+/// The error returned by `AnonymousClient::new` and
+/// [`crate::transport::Connector`]s, carrying diagnostics about the attempt.
+#[derive(Debug, thiserror::Error)]
+#[error("failed to connect to {} during {stage:?}: {source}", diagnostics.url)]
+pub struct ConnectError {
+    pub stage: ConnectStage,
+    pub diagnostics: ConnectDiagnostics,
+    #[source]
+    pub source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl ConnectDiagnostics {
+    /// Creates the error for a failure during `stage`, such as from a
+    /// [`crate::transport::Connector`].
+    pub fn fail(
+        mut self,
+        stage: ConnectStage,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> ConnectError {
+        self.failed_stage = Some(stage);
+        ConnectError {
+            stage,
+            diagnostics: self,
+            source: source.into(),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::client::{
+    BounceError, ConnectOptionsError, HandshakeError, MessageStreamError, ScoutHintError,
+    SetKeyError,
+};
+use crate::diagnostics::ConnectError;
+use crate::id_map::UnmappedId;
+use crate::journal::JournalError;
+use crate::persist::PersistError;
+use crate::protocol::ConnectionRefusedError;
+use crate::resolver::ResolveError;
+
+/// The error type for [`crate::client`], so failures can be matched on by
+/// cause rather than only displayed.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Connecting to the server failed before the handshake, see
+    /// [`ConnectError::stage`] for where.
+    #[error(transparent)]
+    Connect(#[from] ConnectError),
+
+    /// The room requires a password, and neither a password nor a password
+    /// provider was given.
+    #[error("the room requires a password")]
+    PasswordRequired,
+
+    /// The server refused the Connect, after any password retries.
+    #[error("connection refused: {0:?}")]
+    ConnectionRefused(Vec<ConnectionRefusedError>),
+
+    /// The server answered the Connect with an InvalidPacket.
+    #[error(transparent)]
+    Handshake(#[from] HandshakeError),
+
+    /// The server sent something other than what the protocol calls for at
+    /// this point, such as during the handshake.
+    #[error("protocol violation: {0}")]
+    Protocol(String),
+
+    /// The connection closed while waiting for a reply.
+    #[error("connection closed unexpectedly")]
+    ConnectionClosed,
+
+    #[error("websocket error: {0}")]
+    WebSocket(Box<tungstenite::Error>),
+
+    /// A message couldn't be encoded, or a reply couldn't be parsed.
+    #[error("serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    /// Waiting for the named operation took too long.
+    #[error("timed out {0}")]
+    Timeout(&'static str),
+
+    #[error(transparent)]
+    InvalidOptions(#[from] ConnectOptionsError),
+
+    #[error(transparent)]
+    InvalidSetKey(#[from] SetKeyError),
+
+    #[error(transparent)]
+    Bounce(#[from] BounceError),
+
+    #[error(transparent)]
+    Resolve(#[from] ResolveError),
+
+    #[error(transparent)]
+    ScoutHint(#[from] ScoutHintError),
+
+    /// An in-game ID couldn't be translated by the client's
+    /// [`crate::id_map::IdMapper`].
+    #[error(transparent)]
+    Unmapped(#[from] UnmappedId),
+
+    /// The operation needs names from the data package, which wasn't fetched.
+    #[error("{0} requires a data package")]
+    MissingDataPackage(&'static str),
+
+    /// A command was sent during the server's command cooldown with
+    /// [`crate::cooldown::CooldownPolicy::Reject`] in effect.
+    #[error("command cooldown has {remaining:?} left")]
+    CommandCooldown { remaining: Duration },
+
+    #[error("countdown of {seconds} seconds is longer than the maximum of {max}")]
+    CountdownTooLong { seconds: u32, max: u32 },
+
+    /// A local file, such as the data package cache, a journal or a log,
+    /// couldn't be read or written.
+    #[error("failed to {action} {}: {source}", path.display())]
+    File {
+        action: &'static str,
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// A local file was read but couldn't be parsed.
+    #[error("failed to parse {}: {source}", path.display())]
+    Corrupt { path: PathBuf, source: PersistError },
+
+    /// A value couldn't be encoded in a [`crate::persist::PersistFormat`].
+    #[error(transparent)]
+    Persist(#[from] PersistError),
+
+    #[error(transparent)]
+    Journal(#[from] JournalError),
+
+    /// The task driving a [`crate::handle::ClientHandle`] has stopped, for
+    /// the given reason.
+    #[error("client task has stopped: {0}")]
+    TaskStopped(String),
+
+    /// There's no config directory on this platform.
+    #[error("no config directory on this platform")]
+    NoConfigDir,
+
+    #[cfg(feature = "tokio-transport")]
+    #[error(transparent)]
+    WebHost(#[from] crate::webhost::WebHostError),
+
+    /// The OS credential store failed.
+    #[cfg(feature = "keyring")]
+    #[error("credential store error: {0}")]
+    Credentials(#[from] keyring::Error),
+}
+
+impl Error {
+    /// Wraps an I/O error from `action` on the file at `path`.
+    pub(crate) fn file<'a>(
+        action: &'static str,
+        path: &'a Path,
+    ) -> impl FnOnce(std::io::Error) -> Self + 'a {
+        move |source| Error::File {
+            action,
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl From<MessageStreamError> for Error {
+    fn from(error: MessageStreamError) -> Self {
+        match error {
+            MessageStreamError::ParseError(e) => Error::Serde(e),
+            MessageStreamError::WebsocketError(e) => e.into(),
+            MessageStreamError::UnexpectedMessageType(kind) => {
+                Error::Protocol(format!("unexpected {} message", kind))
+            }
+        }
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(error: tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(error))
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,524 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use web_time::Instant;
+
+use crate::client::{ChatMessage, Client, MessageStreamError, ResyncSnapshot};
+use crate::extensions::ExtensionEvent;
+use crate::progress::Progress;
+use crate::protocol::{
+    Bounced, ClientTag, DeathLink, Hint, HintStatus, NetworkItem, PrintJSON, RoomUpdate,
+    ServerMessage,
+};
+
+/// A higher level view of the messages sent by the server.
+///
+/// Events are added as the protocol grows, so matches need a wildcard arm.
+///
+/// Events are cloned for every subscriber they're delivered to, so payloads
+/// which can be large, such as a LocationInfo or DataPackage in
+/// [`Event::Message`], are shared behind an `Arc` rather than copied.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// We received an item. `index` is its position in our list of received
+    /// items, and `local` the item's in-game ID if an
+    /// [`crate::id_map::IdMapper`] is set and knows it.
+    ItemReceived {
+        index: i64,
+        item: NetworkItem,
+        local: Option<i64>,
+    },
+
+    /// A player found an item for someone, as announced by the server.
+    ItemSent {
+        receiving: i64,
+        item: NetworkItem,
+    },
+
+    /// A hint was revealed or updated.
+    Hint {
+        receiving: i64,
+        item: NetworkItem,
+        found: bool,
+    },
+
+    /// A hint involving our slot which we didn't know about, found by
+    /// comparing a refetched or pushed hint list against the known hints.
+    /// See [`Client::watch_hints`].
+    NewHint(Hint),
+
+    /// The item of a known hint was found.
+    HintFound(Hint),
+
+    /// The status of a known hint changed, other than by being found.
+    HintStatusChanged {
+        hint: Hint,
+        old: HintStatus,
+    },
+
+    /// A chat message from a player or the server.
+    Chat(ChatMessage),
+
+    /// The server countdown progressed.
+    Countdown(i64),
+
+    RoomUpdate(Arc<RoomUpdate>),
+
+    /// Locations marked as checked by
+    /// [`crate::client::ConnectOptions::optimistic_checks`] which the server
+    /// didn't confirm. They're missing again.
+    ChecksRolledBack(Vec<i64>),
+
+    /// Another player died, and DeathLink is enabled. Our own deaths are left
+    /// out.
+    DeathLink(DeathLink),
+
+    /// Any Bounced other than a DeathLink.
+    Bounced(Bounced),
+
+    /// A Bounce or data storage value decoded by a decoder registered with
+    /// [`crate::client::ConnectOptions::extensions`]. This is sent as well
+    /// as the event for the message itself.
+    Extension(Arc<ExtensionEvent>),
+
+    /// [`Client::full_resync`] fetched a fresh snapshot of our state.
+    Resynced(Arc<ResyncSnapshot>),
+
+    /// [`Client::reconnect`] found these SetNotify keys changed while we
+    /// were disconnected. Their new values are in [`Client::notify_value`].
+    StorageResynced(Vec<String>),
+
+    /// The connection dropped, and a reconnect attempt will be made after
+    /// `delay`. `last_error` is why the previous attempt failed.
+    Reconnecting {
+        attempt: u32,
+        delay: Duration,
+        last_error: Option<String>,
+    },
+
+    /// The connection was re-established after `attempts` attempts.
+    Reconnected {
+        attempts: u32,
+    },
+
+    /// [`Client::reconnect`] found the WebHost room set with
+    /// [`crate::client::ConnectOptions::webhost_room`] on a new address.
+    AddressChanged {
+        old: String,
+        new: String,
+    },
+
+    /// Names were needed for a game missing from the data package, such as
+    /// a custom game which isn't in the cache. Sent once per game. The game
+    /// can be fetched with [`Client::load_game_data`].
+    MissingGameData(String),
+
+    /// The data of these games was fetched in the background after they
+    /// joined the room, with [`crate::data_package::FetchPolicy::Auto`]. Their
+    /// names can now be resolved, so text rendered earlier may be worth
+    /// rendering again.
+    GameDataLoaded(Vec<String>),
+
+    /// The index of a ReceivedItems didn't follow on from the items already
+    /// received, so items were missed or resent out of step. A Sync was sent,
+    /// and the items are returned from the stream once the full list
+    /// arrives, without repeating any.
+    ItemsDesynced {
+        expected: i64,
+        index: i64,
+    },
+
+    /// Our progress passed one of the thresholds set with
+    /// [`crate::client::ConnectOptions::progress_thresholds`], in percent.
+    /// Each threshold is only passed once.
+    ProgressThreshold {
+        threshold: u8,
+        progress: Progress,
+    },
+
+    /// Another game client connected to our slot, with the given tags. What
+    /// the client does about it is set by
+    /// [`crate::client::SlotConflictPolicy`].
+    SlotTakenOver {
+        tags: Vec<ClientTag>,
+    },
+
+    /// Any message without a more specific event.
+    Message(Arc<ServerMessage>),
+}
+
+/// The type of an [`Event`], without any of its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EventType {
+    ItemReceived,
+    ItemSent,
+    Hint,
+    NewHint,
+    HintFound,
+    HintStatusChanged,
+    Chat,
+    Countdown,
+    RoomUpdate,
+    ChecksRolledBack,
+    DeathLink,
+    Bounced,
+    Extension,
+    Resynced,
+    StorageResynced,
+    Reconnecting,
+    Reconnected,
+    AddressChanged,
+    MissingGameData,
+    GameDataLoaded,
+    ItemsDesynced,
+    ProgressThreshold,
+    SlotTakenOver,
+    Message,
+}
+
+impl Event {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::ItemReceived { .. } => EventType::ItemReceived,
+            Event::ItemSent { .. } => EventType::ItemSent,
+            Event::Hint { .. } => EventType::Hint,
+            Event::NewHint(_) => EventType::NewHint,
+            Event::HintFound(_) => EventType::HintFound,
+            Event::HintStatusChanged { .. } => EventType::HintStatusChanged,
+            Event::Chat(_) => EventType::Chat,
+            Event::Countdown(_) => EventType::Countdown,
+            Event::RoomUpdate(_) => EventType::RoomUpdate,
+            Event::ChecksRolledBack(_) => EventType::ChecksRolledBack,
+            Event::DeathLink(_) => EventType::DeathLink,
+            Event::Bounced(_) => EventType::Bounced,
+            Event::Extension(_) => EventType::Extension,
+            Event::Resynced(_) => EventType::Resynced,
+            Event::StorageResynced(_) => EventType::StorageResynced,
+            Event::Reconnecting { .. } => EventType::Reconnecting,
+            Event::Reconnected { .. } => EventType::Reconnected,
+            Event::AddressChanged { .. } => EventType::AddressChanged,
+            Event::MissingGameData(_) => EventType::MissingGameData,
+            Event::GameDataLoaded(_) => EventType::GameDataLoaded,
+            Event::ItemsDesynced { .. } => EventType::ItemsDesynced,
+            Event::ProgressThreshold { .. } => EventType::ProgressThreshold,
+            Event::SlotTakenOver { .. } => EventType::SlotTakenOver,
+            Event::Message(_) => EventType::Message,
+        }
+    }
+
+    /// The slot this event came from: the player who found the item for item
+    /// and hint events, or the sender of a chat message.
+    pub fn player(&self) -> Option<i64> {
+        match self {
+            Event::ItemReceived { item, .. }
+            | Event::ItemSent { item, .. }
+            | Event::Hint { item, .. } => Some(item.player),
+            Event::NewHint(hint)
+            | Event::HintFound(hint)
+            | Event::HintStatusChanged { hint, .. } => Some(hint.finding_player),
+            Event::Chat(chat) => chat.sender.map(|(_, slot)| slot),
+            _ => None,
+        }
+    }
+
+    /// The team this event came from, for events which carry one: chat from
+    /// a player, and messages such as Join, Goal or Release. Item and hint
+    /// events don't, as the server only sends those within a team.
+    pub fn team(&self) -> Option<i64> {
+        match self {
+            Event::Chat(chat) => chat.sender.map(|(team, _)| team),
+            Event::Message(message) => match &**message {
+                ServerMessage::PrintJSON(print) => print.team(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl EventType {
+    /// The type of event a message is turned into.
+    pub(crate) fn of_message(message: &ServerMessage) -> Self {
+        match message {
+            ServerMessage::ReceivedItems(_) => EventType::ItemReceived,
+            ServerMessage::PrintJSON(PrintJSON::ItemSend { .. }) => EventType::ItemSent,
+            ServerMessage::PrintJSON(PrintJSON::Hint { .. }) => EventType::Hint,
+            ServerMessage::PrintJSON(PrintJSON::Chat { .. } | PrintJSON::ServerChat { .. }) => {
+                EventType::Chat
+            }
+            ServerMessage::PrintJSON(PrintJSON::Countdown { .. }) => EventType::Countdown,
+            ServerMessage::RoomUpdate(_) => EventType::RoomUpdate,
+            ServerMessage::Bounced(bounced) if DeathLink::from_bounced(bounced).is_some() => {
+                EventType::DeathLink
+            }
+            ServerMessage::Bounced(_) => EventType::Bounced,
+            _ => EventType::Message,
+        }
+    }
+}
+
+/// Selects which events to receive from [`Events`].
+///
+/// ```no_run
+/// # use archipelago::events::{EventFilter, EventType};
+/// let filter = EventFilter::new()
+///     .types([EventType::ItemReceived, EventType::Chat])
+///     .from_players([3, 4]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    types: Option<HashSet<EventType>>,
+    players: Option<HashSet<i64>>,
+    team: Option<i64>,
+    my_team_only: bool,
+}
+
+impl EventFilter {
+    /// Creates a filter which accepts every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accepts events of the given types.
+    pub fn types(mut self, types: impl IntoIterator<Item = EventType>) -> Self {
+        self.types = Some(types.into_iter().collect());
+        self
+    }
+
+    /// Only accepts events from the given slots. Events which don't come from
+    /// a player, such as server chat, are rejected.
+    pub fn from_players(mut self, slots: impl IntoIterator<Item = i64>) -> Self {
+        self.players = Some(slots.into_iter().collect());
+        self
+    }
+
+    /// Only accepts events from the given team, such as to ignore other
+    /// teams in a multi-team tournament. Events which don't carry a team
+    /// (see [`Event::team`]) are still accepted.
+    pub fn team(mut self, team: i64) -> Self {
+        self.team = Some(team);
+        self
+    }
+
+    /// Like [`EventFilter::team`], for the team of the client the filter is
+    /// used with. This takes effect when the filter is passed to
+    /// [`Events::filter`] or [`Client::subscribe`].
+    pub fn my_team_only(mut self) -> Self {
+        self.my_team_only = true;
+        self
+    }
+
+    /// Resolves [`EventFilter::my_team_only`] to the given team.
+    pub(crate) fn for_team(mut self, team: i64) -> Self {
+        if self.my_team_only {
+            self.team = Some(team);
+        }
+        self
+    }
+
+    pub fn accepts_type(&self, event_type: EventType) -> bool {
+        self.types
+            .as_ref()
+            .is_none_or(|types| types.contains(&event_type))
+    }
+
+    fn accepts_player(&self, player: Option<i64>) -> bool {
+        match &self.players {
+            None => true,
+            Some(players) => player.is_some_and(|player| players.contains(&player)),
+        }
+    }
+
+    fn accepts_team(&self, team: Option<i64>) -> bool {
+        match (self.team, team) {
+            (Some(ours), Some(team)) => ours == team,
+            _ => true,
+        }
+    }
+
+    pub fn matches(&self, event: &Event) -> bool {
+        self.accepts_type(event.event_type())
+            && self.accepts_player(event.player())
+            && self.accepts_team(event.team())
+    }
+}
+
+/// An [`Event`] along with its place among the events of a client and when
+/// it was received.
+///
+/// Every stream of a client, [`Client::events`] and each subscription alike,
+/// sees the same sequence number for the same event, so events buffered from
+/// several of them can be put back in order.
+#[derive(Debug, Clone)]
+pub struct StampedEvent {
+    /// Increases by one for every event the client emits, starting from 0.
+    /// Numbers of events no stream accepted are skipped.
+    pub seq: u64,
+
+    /// When the message the event came from was received.
+    pub received_at: Instant,
+
+    pub event: Event,
+}
+
+impl StampedEvent {
+    pub(crate) fn new(seq: &AtomicU64, event: Event, received_at: Instant) -> Self {
+        Self {
+            seq: seq.fetch_add(1, Ordering::Relaxed),
+            received_at,
+            event,
+        }
+    }
+
+    /// How long ago the event was received, such as to measure how far
+    /// behind a consumer is.
+    pub fn age(&self) -> Duration {
+        self.received_at.elapsed()
+    }
+}
+
+/// A stream of [`Event`]s from a [`Client`], created by [`Client::events`].
+pub struct Events<'a> {
+    client: &'a mut Client,
+    filter: EventFilter,
+}
+
+impl<'a> Events<'a> {
+    pub(crate) fn new(client: &'a mut Client) -> Self {
+        client.set_collect_events(true);
+        Self {
+            client,
+            filter: EventFilter::new(),
+        }
+    }
+
+    /// Only yields events accepted by `filter`.
+    pub fn filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter.for_team(self.client.get_connected().team);
+        self
+    }
+
+    /// Yields [`StampedEvent`]s instead, with the sequence number and receive
+    /// time of each event.
+    pub fn stamped(self) -> StampedEvents<'a> {
+        StampedEvents(self)
+    }
+
+    fn poll_stamped(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<StampedEvent, MessageStreamError>>> {
+        loop {
+            // The client turns each message into events as it's received.
+            if let Some(event) = self.client.take_pending_event() {
+                if self.filter.matches(&event.event) {
+                    return Poll::Ready(Some(Ok(event)));
+                }
+                continue;
+            }
+
+            match self.client.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(_))) => {}
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Drop for Events<'_> {
+    fn drop(&mut self) {
+        self.client.set_collect_events(false);
+    }
+}
+
+/// Turns a message into events.
+pub(crate) fn dispatch(client: &Client, message: &ServerMessage, push: &mut dyn FnMut(Event)) {
+    match message {
+        ServerMessage::ReceivedItems(received) => {
+            for (index, item) in (received.index..).zip(&received.items) {
+                push(Event::ItemReceived {
+                    index,
+                    item: item.clone(),
+                    local: client
+                        .id_mapper()
+                        .and_then(|mapper| mapper.item_from_ap(item.item)),
+                });
+            }
+        }
+        ServerMessage::PrintJSON(PrintJSON::ItemSend {
+            receiving, item, ..
+        }) => push(Event::ItemSent {
+            receiving: *receiving,
+            item: item.clone(),
+        }),
+        ServerMessage::PrintJSON(PrintJSON::Hint {
+            receiving,
+            item,
+            found,
+            ..
+        }) => push(Event::Hint {
+            receiving: *receiving,
+            item: item.clone(),
+            found: *found,
+        }),
+        // The client decides whether to hide echoes of our own chat.
+        ServerMessage::PrintJSON(PrintJSON::Chat { .. } | PrintJSON::ServerChat { .. }) => {
+            if let Some(chat) = client.chat_message(message) {
+                push(Event::Chat(chat));
+            }
+        }
+        ServerMessage::PrintJSON(PrintJSON::Countdown { countdown, .. }) => {
+            push(Event::Countdown(*countdown))
+        }
+        ServerMessage::RoomUpdate(update) => push(Event::RoomUpdate(Arc::new((**update).clone()))),
+        ServerMessage::Bounced(bounced) => match DeathLink::from_bounced(bounced) {
+            Some(death) if client.is_own_death(&death) => {}
+            Some(death) => push(Event::DeathLink(death)),
+            None => push(Event::Bounced(bounced.clone())),
+        },
+        message => push(Event::Message(Arc::new(message.clone()))),
+    }
+}
+
+impl Stream for Events<'_> {
+    type Item = Result<Event, MessageStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.poll_stamped(cx) {
+            Poll::Ready(Some(Ok(stamped))) => Poll::Ready(Some(Ok(stamped.event))),
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// An [`Events`] stream which yields [`StampedEvent`]s, created by
+/// [`Events::stamped`].
+pub struct StampedEvents<'a>(Events<'a>);
+
+impl Stream for StampedEvents<'_> {
+    type Item = Result<StampedEvent, MessageStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_stamped(cx)
+    }
+}
+
+impl Client {
+    /// Returns a stream of [`Event`]s, optionally filtered with
+    /// [`Events::filter`]. Messages are consumed from the same stream as
+    /// [`Client`]'s own `Stream` implementation.
+    pub fn events(&mut self) -> Events<'_> {
+        Events::new(self)
+    }
+}
@@ -0,0 +1,145 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::protocol::{Bounced, ServerMessage};
+
+type BounceDecoder = Box<dyn Fn(&Bounced) -> Option<Box<dyn Any + Send + Sync>> + Send + Sync>;
+type StorageDecoder =
+    Box<dyn Fn(&str, &serde_json::Value) -> Option<Box<dyn Any + Send + Sync>> + Send + Sync>;
+
+/// A typed message produced by a decoder in an [`ExtensionRegistry`].
+#[derive(Debug)]
+pub struct ExtensionEvent {
+    /// The Bounce tag or storage key which the message was decoded from.
+    pub key: String,
+
+    payload: Box<dyn Any + Send + Sync>,
+}
+
+impl ExtensionEvent {
+    /// Returns the decoded message if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+
+    /// Takes the decoded message if it is of type `T`, otherwise returns the
+    /// event unchanged.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        match self.payload.downcast() {
+            Ok(payload) => Ok(*payload),
+            Err(payload) => Err(Self {
+                key: self.key,
+                payload,
+            }),
+        }
+    }
+}
+
+/// Routes game-specific Bounce and data storage conventions to decoders
+/// registered by game integrations.
+///
+/// Bounce decoders are keyed by tag and run for every Bounced message carrying
+/// that tag. Storage decoders are keyed by a key prefix and run for every
+/// matching key in Retrieved and SetReply messages.
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    bounces: HashMap<String, Vec<BounceDecoder>>,
+    storage: Vec<(String, StorageDecoder)>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a decoder for Bounced messages with the given tag. Returning
+    /// None from the decoder skips the message.
+    pub fn register_bounce<T, F>(&mut self, tag: impl Into<String>, decoder: F)
+    where
+        T: Any + Send + Sync,
+        F: Fn(&Bounced) -> Option<T> + Send + Sync + 'static,
+    {
+        self.bounces
+            .entry(tag.into())
+            .or_default()
+            .push(Box::new(move |bounced| {
+                decoder(bounced).map(|decoded| Box::new(decoded) as Box<dyn Any + Send + Sync>)
+            }));
+    }
+
+    /// Registers a Bounce decoder which deserializes the bounce data as `T`,
+    /// skipping messages which don't match.
+    pub fn register_bounce_json<T>(&mut self, tag: impl Into<String>)
+    where
+        T: DeserializeOwned + Any + Send + Sync,
+    {
+        self.register_bounce(tag, |bounced| {
+            serde_json::from_value::<T>(bounced.data.clone()).ok()
+        });
+    }
+
+    /// Registers a decoder for data storage keys starting with `prefix`.
+    pub fn register_storage<T, F>(&mut self, prefix: impl Into<String>, decoder: F)
+    where
+        T: Any + Send + Sync,
+        F: Fn(&str, &serde_json::Value) -> Option<T> + Send + Sync + 'static,
+    {
+        self.storage.push((
+            prefix.into(),
+            Box::new(move |key, value| {
+                decoder(key, value).map(|decoded| Box::new(decoded) as Box<dyn Any + Send + Sync>)
+            }),
+        ));
+    }
+
+    /// Runs every matching decoder against a message from the server.
+    pub fn dispatch(&self, message: &ServerMessage) -> Vec<ExtensionEvent> {
+        let mut events = Vec::new();
+
+        match message {
+            ServerMessage::Bounced(bounced) => {
+                for tag in &bounced.tags {
+                    for decoder in self.bounces.get(tag).into_iter().flatten() {
+                        if let Some(payload) = decoder(bounced) {
+                            events.push(ExtensionEvent {
+                                key: tag.clone(),
+                                payload,
+                            });
+                        }
+                    }
+                }
+            }
+            ServerMessage::Retrieved(retrieved) => {
+                for (key, value) in &retrieved.keys {
+                    self.dispatch_storage(key, value, &mut events);
+                }
+            }
+            ServerMessage::SetReply(reply) => {
+                self.dispatch_storage(&reply.key, &reply.value, &mut events);
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    fn dispatch_storage(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        events: &mut Vec<ExtensionEvent>,
+    ) {
+        for (prefix, decoder) in &self.storage {
+            if key.starts_with(prefix.as_str()) {
+                if let Some(payload) = decoder(key, value) {
+                    events.push(ExtensionEvent {
+                        key: key.to_string(),
+                        payload,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Fault injection for testing how clients cope with a bad connection.
+//!
+//! [`FaultyTransport`] wraps the websocket a client reads from and mangles
+//! the frames coming through it according to a [`FaultPolicy`]. The policy is
+//! seeded, so a run which turns up a bug can be replayed exactly. Use it
+//! through [`crate::client::AnonymousClient::inject_faults`].
+//!
+//! Requires the `fixtures` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::{Sink, Stream, StreamExt};
+use tungstenite::Message;
+
+type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Which faults a [`FaultyTransport`] injects, and how often. Each
+/// probability is checked independently for every frame, from 0 (never) to 1
+/// (every frame).
+#[derive(Debug, Clone)]
+pub struct FaultPolicy {
+    seed: u64,
+    skip: usize,
+    delay: f64,
+    max_delay: Duration,
+    duplicate: f64,
+    reorder: f64,
+    disconnect: f64,
+    corrupt: f64,
+}
+
+impl FaultPolicy {
+    /// A policy which injects no faults, with the given seed for the faults
+    /// added to it.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            skip: 0,
+            delay: 0.0,
+            max_delay: Duration::ZERO,
+            duplicate: 0.0,
+            reorder: 0.0,
+            disconnect: 0.0,
+            corrupt: 0.0,
+        }
+    }
+
+    /// Passes the first `frames` frames through untouched, such as to let
+    /// the handshake complete.
+    pub fn skip(mut self, frames: usize) -> Self {
+        self.skip = frames;
+        self
+    }
+
+    /// Holds frames back for a random time up to `max`.
+    pub fn delay(mut self, probability: f64, max: Duration) -> Self {
+        self.delay = probability;
+        self.max_delay = max;
+        self
+    }
+
+    /// Delivers frames twice.
+    pub fn duplicate(mut self, probability: f64) -> Self {
+        self.duplicate = probability;
+        self
+    }
+
+    /// Shuffles the packets within a frame.
+    pub fn reorder(mut self, probability: f64) -> Self {
+        self.reorder = probability;
+        self
+    }
+
+    /// Drops the connection instead of delivering a frame.
+    pub fn disconnect(mut self, probability: f64) -> Self {
+        self.disconnect = probability;
+        self
+    }
+
+    /// Replaces a character of a text frame with a control character, so it
+    /// no longer parses.
+    pub fn corrupt(mut self, probability: f64) -> Self {
+        self.corrupt = probability;
+        self
+    }
+}
+
+/// A fault injected by a [`FaultyTransport`], in the order they happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Delay(Duration),
+    Duplicate,
+    Reorder,
+    Disconnect,
+    Corrupt,
+}
+
+/// A websocket stream which injects faults into the frames read from it.
+///
+/// Only frames coming in are affected, while frames sent through its
+/// [`Sink`] are passed through, until a [`Fault::Disconnect`] closes it in
+/// both directions.
+pub struct FaultyTransport<S> {
+    inner: S,
+    policy: FaultPolicy,
+    rng: fastrand::Rng,
+    frames: usize,
+    delayed: Option<(Sleep, Message)>,
+    duplicate: Option<Message>,
+    disconnected: bool,
+    injected: Vec<Fault>,
+}
+
+impl<S> FaultyTransport<S> {
+    pub fn new(inner: S, policy: FaultPolicy) -> Self {
+        Self {
+            inner,
+            rng: fastrand::Rng::with_seed(policy.seed),
+            policy,
+            frames: 0,
+            delayed: None,
+            duplicate: None,
+            disconnected: false,
+            injected: Vec::new(),
+        }
+    }
+
+    /// The faults injected so far.
+    pub fn injected(&self) -> &[Fault] {
+        &self.injected
+    }
+
+    fn roll(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.f64() < probability
+    }
+
+    fn disconnected() -> tungstenite::Error {
+        tungstenite::Error::Io(std::io::ErrorKind::ConnectionReset.into())
+    }
+
+    /// Applies the policy to a frame just read, returning the frame to
+    /// deliver now, if any.
+    fn mangle(&mut self, mut message: Message) -> Option<Result<Message, tungstenite::Error>> {
+        self.frames += 1;
+        if self.frames <= self.policy.skip || !matches!(message, Message::Text(_)) {
+            return Some(Ok(message));
+        }
+
+        if self.roll(self.policy.disconnect) {
+            self.injected.push(Fault::Disconnect);
+            self.disconnected = true;
+            return Some(Err(Self::disconnected()));
+        }
+
+        if self.roll(self.policy.reorder) {
+            if let Some(reordered) = self.reorder(&message) {
+                self.injected.push(Fault::Reorder);
+                message = reordered;
+            }
+        }
+
+        if self.roll(self.policy.corrupt) {
+            self.injected.push(Fault::Corrupt);
+            message = self.corrupt(message);
+        }
+
+        if self.roll(self.policy.duplicate) {
+            self.injected.push(Fault::Duplicate);
+            self.duplicate = Some(message.clone());
+        }
+
+        if self.roll(self.policy.delay) {
+            let max = self.policy.max_delay.as_millis() as u64;
+            let delay = Duration::from_millis(self.rng.u64(0..=max));
+            self.injected.push(Fault::Delay(delay));
+            self.delayed = Some((Box::pin(crate::timer::sleep(delay)), message));
+            return None;
+        }
+
+        Some(Ok(message))
+    }
+
+    fn reorder(&mut self, message: &Message) -> Option<Message> {
+        let Message::Text(text) = message else {
+            return None;
+        };
+        let mut packets: Vec<serde_json::Value> = serde_json::from_str(text).ok()?;
+        if packets.len() < 2 {
+            return None;
+        }
+
+        self.rng.shuffle(&mut packets);
+        serde_json::to_string(&packets).ok().map(Message::Text)
+    }
+
+    fn corrupt(&mut self, message: Message) -> Message {
+        let Message::Text(text) = message else {
+            return message;
+        };
+        if text.is_empty() {
+            return Message::Text(text);
+        }
+
+        let target = self.rng.usize(0..text.chars().count());
+        let corrupted = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i == target { '\u{1}' } else { c })
+            .collect();
+        Message::Text(corrupted)
+    }
+}
+
+impl<S> Stream for FaultyTransport<S>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    type Item = Result<Message, tungstenite::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.disconnected {
+            return Poll::Ready(None);
+        }
+
+        if let Some((sleep, _)) = &mut self.delayed {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            let (_, message) = self.delayed.take().expect("delayed frame was just polled");
+            return Poll::Ready(Some(Ok(message)));
+        }
+
+        if let Some(message) = self.duplicate.take() {
+            return Poll::Ready(Some(Ok(message)));
+        }
+
+        match self.inner.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(message))) => match self.mangle(message) {
+                Some(result) => Poll::Ready(Some(result)),
+
+                // The frame was delayed, so poll the delay to register for
+                // its wakeup.
+                None => self.poll_next(cx),
+            },
+            other => other,
+        }
+    }
+}
+
+impl<S> Sink<Message> for FaultyTransport<S>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    type Error = tungstenite::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.disconnected {
+            return Poll::Ready(Err(Self::disconnected()));
+        }
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        if self.disconnected {
+            return Err(Self::disconnected());
+        }
+        Pin::new(&mut self.inner).start_send(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.disconnected {
+            return Poll::Ready(Err(Self::disconnected()));
+        }
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
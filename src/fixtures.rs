@@ -0,0 +1,132 @@
+//! Canned protocol objects for testing integrations without a server.
+//!
+//! The fixtures describe a two player room, both playing [`GAME`], a small
+//! fake game with [`ITEM_COUNT`] items and [`LOCATION_COUNT`] locations. We
+//! are [`SLOT`] on team 0, and the first [`CHECKED_COUNT`] of our locations
+//! have already been checked. Every call returns a fresh copy, so fixtures can
+//! be modified freely.
+//!
+//! Requires the `fixtures` feature.
+
+use crate::protocol::{
+    Connected, DataPackageObject, GameData, NetworkItem, NetworkItemFlags, ReceivedItems, RoomInfo,
+};
+
+/// The fake game played by every slot.
+pub const GAME: &str = "Fixture Quest";
+
+/// The number of items in [`GAME`].
+pub const ITEM_COUNT: i64 = 50;
+
+/// The number of locations in [`GAME`].
+pub const LOCATION_COUNT: i64 = 50;
+
+/// The ID of the first item; the rest follow contiguously.
+pub const FIRST_ITEM_ID: i64 = 7_700_000;
+
+/// The ID of the first location; the rest follow contiguously.
+pub const FIRST_LOCATION_ID: i64 = 7_710_000;
+
+/// Our slot.
+pub const SLOT: i64 = 1;
+
+/// The slot of the other player.
+pub const OTHER_SLOT: i64 = 2;
+
+/// The number of our locations which were checked before connecting.
+pub const CHECKED_COUNT: i64 = 10;
+
+const CHECKSUM: &str = "f1c7e5d1a0b2c3d4e5f60718293a4b5c6d7e8f90";
+const SEED_NAME: &str = "12345678901234567890";
+
+/// The name of an item in [`GAME`], numbered from 0.
+pub fn item_name(index: i64) -> String {
+    format!("Fixture Item {}", index + 1)
+}
+
+/// The name of a location in [`GAME`], numbered from 0.
+pub fn location_name(index: i64) -> String {
+    format!("Fixture Location {}", index + 1)
+}
+
+/// The RoomInfo sent when connecting.
+pub fn room_info() -> RoomInfo {
+    serde_json::from_value(serde_json::json!({
+        "cmd": "RoomInfo",
+        "version": { "major": 0, "minor": 4, "build": 5, "class": "Version" },
+        "generator_version": { "major": 0, "minor": 4, "build": 5, "class": "Version" },
+        "tags": ["AP"],
+        "password": false,
+        "permissions": { "release": 2, "collect": 2, "remaining": 1 },
+        "hint_cost": 10,
+        "location_check_points": 1,
+        "games": ["Archipelago", GAME],
+        "datapackage_versions": {},
+        "datapackage_checksums": { GAME: CHECKSUM },
+        "seed_name": SEED_NAME,
+        "time": 1_700_000_000.0,
+    }))
+    .expect("fixture RoomInfo should be valid")
+}
+
+/// The Connected response for [`SLOT`].
+pub fn connected() -> Connected {
+    let locations: Vec<i64> = (0..LOCATION_COUNT).map(|i| FIRST_LOCATION_ID + i).collect();
+    let (checked, missing) = locations.split_at(CHECKED_COUNT as usize);
+
+    serde_json::from_value(serde_json::json!({
+        "cmd": "Connected",
+        "team": 0,
+        "slot": SLOT,
+        "players": [
+            { "team": 0, "slot": SLOT, "alias": "Player1", "name": "Player1", "class": "NetworkPlayer" },
+            { "team": 0, "slot": OTHER_SLOT, "alias": "Player2", "name": "Player2", "class": "NetworkPlayer" },
+        ],
+        "missing_locations": missing,
+        "checked_locations": checked,
+        "slot_data": { "goal": 0, "death_link": false, "seed": SEED_NAME },
+        "slot_info": {
+            SLOT.to_string(): { "name": "Player1", "game": GAME, "type": 1, "group_members": [] },
+            OTHER_SLOT.to_string(): { "name": "Player2", "game": GAME, "type": 1, "group_members": [] },
+        },
+        "hint_points": 5,
+    }))
+    .expect("fixture Connected should be valid")
+}
+
+/// The data for [`GAME`].
+pub fn game_data() -> GameData {
+    GameData {
+        item_name_to_id: (0..ITEM_COUNT)
+            .map(|i| (item_name(i), FIRST_ITEM_ID + i))
+            .collect(),
+        location_name_to_id: (0..LOCATION_COUNT)
+            .map(|i| (location_name(i), FIRST_LOCATION_ID + i))
+            .collect(),
+        version: 0,
+        checksum: CHECKSUM.to_string(),
+    }
+}
+
+/// A data package containing [`GAME`].
+pub fn data_package() -> DataPackageObject {
+    DataPackageObject {
+        games: [(GAME.to_string(), game_data())].into_iter().collect(),
+    }
+}
+
+/// The ReceivedItems sent after connecting: one item for each location
+/// [`OTHER_SLOT`] has checked, which is the same number as ours.
+pub fn received_items() -> ReceivedItems {
+    ReceivedItems {
+        index: 0,
+        items: (0..CHECKED_COUNT)
+            .map(|i| NetworkItem {
+                item: FIRST_ITEM_ID + i,
+                location: FIRST_LOCATION_ID + i,
+                player: OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,273 @@
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::Poll;
+
+use futures::{FutureExt, Stream, StreamExt};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::bus::Subscription;
+use crate::client::{check_message, is_connection_lost, Client, MessageStreamError};
+use crate::error::{Error, Result};
+use crate::events::Event;
+use crate::protocol::{self, ClientMessage, ServerMessage};
+use crate::session::subscribe_rollbacks;
+use crate::state::{StateReader, StateTracker};
+use crate::tasks;
+
+enum Command {
+    // Without a sender the message is fire-and-forget, see
+    // ClientHandle::enqueue.
+    Send(ClientMessage, Option<oneshot::Sender<Result<()>>>),
+    Close(oneshot::Sender<Result<()>>),
+}
+
+/// Why the background task stopped, shared with every handle so calls made
+/// afterwards can report it.
+type StopReason = Arc<OnceLock<String>>;
+
+/// A cloneable handle to a [`Client`] driven by a background task.
+///
+/// The handle is `Clone + Send + Sync`, so it can be used from any thread
+/// without wrapping the client in a mutex and manually pumping its stream.
+/// Received messages are delivered through the [`Messages`] stream returned
+/// alongside the handle, and tracked state can be read through
+/// [`ClientHandle::state`].
+#[derive(Clone)]
+pub struct ClientHandle {
+    commands: mpsc::UnboundedSender<Command>,
+    stopped: StopReason,
+    state: StateReader,
+    room_info: Arc<protocol::RoomInfo>,
+    connected: Arc<protocol::Connected>,
+}
+
+/// The messages received by a spawned client.
+pub struct Messages {
+    receiver: mpsc::UnboundedReceiver<Result<ServerMessage, MessageStreamError>>,
+}
+
+impl Stream for Messages {
+    type Item = Result<ServerMessage, MessageStreamError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Client {
+    /// Moves the client into a background task on the current tokio runtime,
+    /// returning a shareable handle and the stream of received messages.
+    ///
+    /// The task stops when the connection closes or every handle has been
+    /// dropped.
+    pub fn spawn(mut self) -> (ClientHandle, Messages) {
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (message_tx, message_rx) = mpsc::unbounded_channel();
+
+        let tracker = StateTracker::new(self.get_room_info(), self.get_connected());
+        let rollbacks = subscribe_rollbacks(&mut self);
+        let stopped = StopReason::default();
+
+        let handle = ClientHandle {
+            commands: command_tx,
+            stopped: stopped.clone(),
+            state: tracker.reader(),
+            room_info: Arc::new(self.get_room_info().clone()),
+            connected: Arc::new(self.get_connected().clone()),
+        };
+
+        let room = self.get_room_info().seed_name.clone();
+        let slot = self.get_connected().slot;
+        tasks::spawn(
+            "archipelago client",
+            &room,
+            slot,
+            run(self, tracker, rollbacks, command_rx, message_tx, stopped),
+        );
+
+        (
+            handle,
+            Messages {
+                receiver: message_rx,
+            },
+        )
+    }
+}
+
+async fn run(
+    mut client: Client,
+    tracker: StateTracker,
+    mut rollbacks: Subscription,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    messages: mpsc::UnboundedSender<Result<ServerMessage, MessageStreamError>>,
+    stopped: StopReason,
+) {
+    let reason = loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(Command::Send(message, done)) => {
+                    let checks = match &message {
+                        ClientMessage::LocationChecks(checks) => Some(checks.locations.clone()),
+                        _ => None,
+                    };
+                    if let Some(locations) = &checks {
+                        tracker.room_mut().record_sent_checks(locations);
+                    }
+
+                    // A failed write means the connection is gone, so nothing
+                    // queued after this message can be sent either.
+                    if let Err(e) = client.send(message).await {
+                        let reason = format!("failed to send message: {}", e);
+                        if let Some(done) = done {
+                            let _ = done.send(Err(Error::TaskStopped(reason.clone())));
+                        }
+                        break reason;
+                    }
+                    if let Some(locations) = checks {
+                        let marked = client.record_location_checks(&locations);
+                        tracker.room_mut().mark_checked(&marked);
+                    }
+                    if let Some(done) = done {
+                        let _ = done.send(Ok(()));
+                    }
+                }
+                Some(Command::Close(done)) => {
+                    let _ = done.send(client.close().await);
+                    break "client was closed".to_string();
+                }
+                None => {
+                    let _ = client.close().await;
+                    return;
+                }
+            },
+            message = client.next() => match message {
+                message if is_connection_lost(&message) && client.auto_reconnect().await => {
+                    continue;
+                }
+                Some(message) => {
+                    if let Ok(message) = &message {
+                        tracker.handle(message);
+                    }
+                    while let Some(Some(Event::ChecksRolledBack(locations))) =
+                        rollbacks.next().now_or_never()
+                    {
+                        tracker.room_mut().roll_back_checks(&locations);
+                    }
+                    // Nobody listening for messages isn't a reason to stop, as
+                    // handles may still be used to send.
+                    let _ = messages.send(message);
+                }
+                None => {
+                    break match client.session_ended().and_then(|ended| ended.reason.clone()) {
+                        Some(reason) => format!("connection closed: {}", reason),
+                        None => "connection closed".to_string(),
+                    };
+                }
+            },
+        }
+    };
+
+    // Commands which were queued behind the failure would otherwise only see
+    // their channel dropped, so answer them with the actual reason.
+    let _ = stopped.set(reason.clone());
+    commands.close();
+    while let Ok(command) = commands.try_recv() {
+        let done = match command {
+            Command::Send(_, done) => done,
+            Command::Close(done) => Some(done),
+        };
+        if let Some(done) = done {
+            let _ = done.send(Err(Error::TaskStopped(reason.clone())));
+        }
+    }
+}
+
+impl ClientHandle {
+    /// Sends a raw message to the server, waiting until it has been written.
+    /// If the background task stops before then, such as because writing an
+    /// earlier message failed, the reason is returned.
+    pub async fn send(&self, message: ClientMessage) -> Result<()> {
+        check_message(&message)?;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        self.commands
+            .send(Command::Send(message, Some(done_tx)))
+            .map_err(|_| self.stopped_error())?;
+        done_rx.await.map_err(|_| self.stopped_error())?
+    }
+
+    /// Queues a raw message without waiting for it to be written, for hot
+    /// paths where waiting on every message is too slow. An error is only
+    /// returned if the background task has already stopped; a later failure
+    /// to write the message is reported to the next call to
+    /// [`ClientHandle::send`] instead.
+    pub fn enqueue(&self, message: ClientMessage) -> Result<()> {
+        check_message(&message)?;
+
+        self.commands
+            .send(Command::Send(message, None))
+            .map_err(|_| self.stopped_error())
+    }
+
+    fn stopped_error(&self) -> Error {
+        // The reason is set before the task stops taking commands, so it's
+        // only missing if the task panicked or its runtime shut down.
+        let reason = self
+            .stopped
+            .get()
+            .map_or("task ended unexpectedly", String::as_str);
+        Error::TaskStopped(reason.to_string())
+    }
+
+    /// Informs the server of locations which have been checked.
+    pub async fn check_locations(&self, locations: &[i64]) -> Result<()> {
+        self.send(ClientMessage::LocationChecks(protocol::LocationChecks {
+            locations: locations.to_vec(),
+        }))
+        .await
+    }
+
+    /// Sends a chat message to the server.
+    pub async fn say(&self, text: impl Into<String>) -> Result<()> {
+        self.send(ClientMessage::Say(protocol::Say { text: text.into() }))
+            .await
+    }
+
+    /// Informs the server of our status, such as reaching our goal.
+    pub async fn status_update(&self, status: protocol::ClientStatus) -> Result<()> {
+        self.send(ClientMessage::StatusUpdate(protocol::StatusUpdate {
+            status,
+        }))
+        .await
+    }
+
+    /// Closes the connection, stopping the background task.
+    pub async fn close(&self) -> Result<()> {
+        let (done_tx, done_rx) = oneshot::channel();
+        self.commands
+            .send(Command::Close(done_tx))
+            .map_err(|_| self.stopped_error())?;
+        done_rx.await.map_err(|_| self.stopped_error())?
+    }
+
+    /// Returns true if the background task has stopped.
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    /// A read-only handle to the state tracked by the background task.
+    pub fn state(&self) -> &StateReader {
+        &self.state
+    }
+
+    pub fn room_info(&self) -> &protocol::RoomInfo {
+        &self.room_info
+    }
+
+    pub fn connected(&self) -> &protocol::Connected {
+        &self.connected
+    }
+}
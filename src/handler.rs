@@ -0,0 +1,152 @@
+//! Callbacks for server messages, as an alternative to matching on
+//! [`ServerMessage`] by hand.
+//!
+//! Implement the methods of [`EventHandler`] for the messages a game cares
+//! about and pass it to [`Client::run`]:
+//!
+//! ```no_run
+//! # use archipelago::client::Client;
+//! # use archipelago::handler::EventHandler;
+//! # use archipelago::protocol::ReceivedItems;
+//! struct Game;
+//!
+//! impl EventHandler for Game {
+//!     async fn on_received_items(
+//!         &mut self,
+//!         client: &mut Client,
+//!         received: ReceivedItems,
+//!     ) -> archipelago::Result<()> {
+//!         for item in received.items {
+//!             println!("got item {}", item.item);
+//!         }
+//!         client.say("thanks!").await
+//!     }
+//! }
+//!
+//! # async fn run(client: &mut Client) -> archipelago::Result<()> {
+//! client.run(&mut Game).await
+//! # }
+//! ```
+
+use crate::client::{Client, MessageStreamError};
+use crate::error::Result;
+use crate::protocol::{
+    Bounced, DataPackage, InvalidPacket, LocationInfo, PrintJSON, ReceivedItems, Retrieved,
+    RoomUpdate, ServerMessage, SetReply,
+};
+
+/// Handles the messages received by [`Client::run`], one method per message
+/// type. Every method does nothing by default.
+///
+/// Returning an error from a method stops [`Client::run`] with that error.
+// Handlers run on the caller's task, so there's no need for their futures to
+// be Send.
+#[allow(async_fn_in_trait)]
+pub trait EventHandler {
+    async fn on_received_items(
+        &mut self,
+        client: &mut Client,
+        received: ReceivedItems,
+    ) -> Result<()> {
+        let _ = (client, received);
+        Ok(())
+    }
+
+    async fn on_location_info(&mut self, client: &mut Client, info: LocationInfo) -> Result<()> {
+        let _ = (client, info);
+        Ok(())
+    }
+
+    /// Called after the client has applied the update to its own state, such
+    /// as [`Client::get_connected`].
+    async fn on_room_update(&mut self, client: &mut Client, update: RoomUpdate) -> Result<()> {
+        let _ = (client, update);
+        Ok(())
+    }
+
+    async fn on_print_json(&mut self, client: &mut Client, print: PrintJSON) -> Result<()> {
+        let _ = (client, print);
+        Ok(())
+    }
+
+    async fn on_bounced(&mut self, client: &mut Client, bounced: Bounced) -> Result<()> {
+        let _ = (client, bounced);
+        Ok(())
+    }
+
+    async fn on_retrieved(&mut self, client: &mut Client, retrieved: Retrieved) -> Result<()> {
+        let _ = (client, retrieved);
+        Ok(())
+    }
+
+    async fn on_set_reply(&mut self, client: &mut Client, reply: SetReply) -> Result<()> {
+        let _ = (client, reply);
+        Ok(())
+    }
+
+    async fn on_data_package(&mut self, client: &mut Client, package: DataPackage) -> Result<()> {
+        let _ = (client, package);
+        Ok(())
+    }
+
+    /// Called when the server rejects a packet. By default this is logged
+    /// and otherwise ignored.
+    async fn on_invalid_packet(
+        &mut self,
+        client: &mut Client,
+        invalid: InvalidPacket,
+    ) -> Result<()> {
+        let _ = client;
+        tracing::warn!(?invalid, "server rejected a packet");
+        Ok(())
+    }
+
+    /// Called when a message couldn't be read. By default this stops
+    /// [`Client::run`] with the error, but returning Ok carries on with the
+    /// next message.
+    async fn on_error(&mut self, client: &mut Client, error: MessageStreamError) -> Result<()> {
+        let _ = client;
+        Err(error.into())
+    }
+}
+
+impl Client {
+    /// Receives messages and passes each to the matching method of
+    /// `handler`, until the connection closes or a method returns an error.
+    ///
+    /// Messages are received with [`Client::recv`], so a lost connection is
+    /// reconnected if a reconnect policy was set.
+    pub async fn run(&mut self, handler: &mut impl EventHandler) -> Result<()> {
+        while let Some(message) = self.recv().await {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    handler.on_error(self, e).await?;
+                    continue;
+                }
+            };
+
+            match message {
+                ServerMessage::ReceivedItems(received) => {
+                    handler.on_received_items(self, received).await?
+                }
+                ServerMessage::LocationInfo(info) => handler.on_location_info(self, info).await?,
+                ServerMessage::RoomUpdate(update) => handler.on_room_update(self, *update).await?,
+                ServerMessage::PrintJSON(print) => handler.on_print_json(self, print).await?,
+                ServerMessage::Bounced(bounced) => handler.on_bounced(self, bounced).await?,
+                ServerMessage::Retrieved(retrieved) => {
+                    handler.on_retrieved(self, retrieved).await?
+                }
+                ServerMessage::SetReply(reply) => handler.on_set_reply(self, reply).await?,
+                ServerMessage::DataPackage(package) => {
+                    handler.on_data_package(self, package).await?
+                }
+                ServerMessage::InvalidPacket(invalid) => {
+                    handler.on_invalid_packet(self, invalid).await?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+//! Building blocks for hint bots: clients which sit in a room and hint items
+//! on behalf of players who ask for them in chat.
+//!
+//! A typical bot loop looks like:
+//!
+//! 1. Turn incoming chat into a [`HintRequest`] with [`CommandParser`].
+//! 2. Check the bot can afford the hint with [`check_hint_cost`].
+//! 3. Issue the hint with [`Client::hint`] or [`Client::hint_location`].
+//! 4. Reply with the Hint message the server broadcasts, rendered with
+//!    [`format_hint`].
+
+use crate::client::{ChatMessage, Client};
+use crate::protocol::PrintJSON;
+use crate::render::Renderer;
+use crate::room::RoomState;
+
+/// A command addressed to the bot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotCommand {
+    /// Hint the location of an item, like `!hint`.
+    Hint(String),
+
+    /// Hint the item at a location, like `!hint_location`.
+    HintLocation(String),
+
+    /// Report how many hint points the bot has.
+    Points,
+}
+
+/// A command along with the player who sent it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HintRequest {
+    pub team: i64,
+    pub slot: i64,
+    pub command: BotCommand,
+}
+
+/// Parses chat messages addressed to the bot, such as `@bot hint Fire Rod`.
+#[derive(Debug, Clone)]
+pub struct CommandParser {
+    mention: String,
+}
+
+impl CommandParser {
+    /// Creates a parser for messages starting with `@name`. The name is
+    /// matched case-insensitively.
+    pub fn new(name: &str) -> Self {
+        Self {
+            mention: format!("@{}", name.to_lowercase()),
+        }
+    }
+
+    /// Parses the text of a message, returning None if it isn't a command
+    /// addressed to the bot.
+    pub fn parse(&self, text: &str) -> Option<BotCommand> {
+        let text = text.trim();
+        let (mention, rest) = text.split_once(char::is_whitespace)?;
+        if mention.to_lowercase() != self.mention {
+            return None;
+        }
+
+        let rest = rest.trim_start();
+        let (verb, argument) = rest
+            .split_once(char::is_whitespace)
+            .map_or((rest, ""), |(verb, argument)| (verb, argument.trim()));
+
+        match (verb.to_lowercase().as_str(), argument) {
+            ("hint", argument) if !argument.is_empty() => {
+                Some(BotCommand::Hint(argument.to_string()))
+            }
+            ("hint_location", argument) if !argument.is_empty() => {
+                Some(BotCommand::HintLocation(argument.to_string()))
+            }
+            ("points", "") => Some(BotCommand::Points),
+            _ => None,
+        }
+    }
+
+    /// Parses a chat message from a player. Server messages are ignored.
+    pub fn parse_chat(&self, message: &ChatMessage) -> Option<HintRequest> {
+        let (team, slot) = message.sender?;
+        let command = self.parse(&message.message)?;
+        Some(HintRequest {
+            team,
+            slot,
+            command,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum HintBotError {
+    #[error("not enough hint points: {required} required, {available} available")]
+    NotEnoughPoints { required: i64, available: i64 },
+}
+
+/// Checks whether the bot has enough hint points for another hint.
+pub fn check_hint_cost(room: &RoomState) -> Result<(), HintBotError> {
+    let required = room.hint_cost_absolute();
+    let available = room.hint_points();
+
+    if available < required {
+        return Err(HintBotError::NotEnoughPoints {
+            required,
+            available,
+        });
+    }
+
+    Ok(())
+}
+
+/// Renders the server's Hint message as a reply. Returns None for other
+/// messages.
+pub fn format_hint(renderer: &Renderer, message: &PrintJSON) -> Option<String> {
+    match message {
+        PrintJSON::Hint { .. } => Some(renderer.render(message)),
+        _ => None,
+    }
+}
+
+impl Client {
+    /// Asks the server for a hint about where an item is, using `!hint`.
+    pub async fn hint(&mut self, item: &str) -> crate::Result<()> {
+        self.send_command(format!("!hint {}", item)).await
+    }
+
+    /// Asks the server for a hint about what is at a location, using
+    /// `!hint_location`.
+    pub async fn hint_location(&mut self, location: &str) -> crate::Result<()> {
+        self.send_command(format!("!hint_location {}", location))
+            .await
+    }
+}
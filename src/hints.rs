@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use crate::protocol::{Hint, HintStatus, ServerMessage};
+
+/// How a hint differs from the last known hint set, as found by
+/// [`HintTracker::update`].
+#[derive(Debug, Clone)]
+pub enum HintChange {
+    /// A hint which wasn't known before.
+    New(Hint),
+
+    /// A known hint whose item has now been found.
+    Found(Hint),
+
+    /// A known hint whose status changed, other than by being found.
+    StatusChanged { hint: Hint, old: HintStatus },
+}
+
+/// Tracks the hints for a slot, turning each refetched or pushed hint list
+/// into just the changes since the last one.
+///
+/// The server always sends the full list, through a Retrieved or a SetReply
+/// for the slot's `_read_hints_{team}_{slot}` key.
+#[derive(Debug, Clone)]
+pub struct HintTracker {
+    key: String,
+
+    // Keyed by finding player and location, which identify a hint.
+    hints: HashMap<(i64, i64), Hint>,
+}
+
+impl HintTracker {
+    /// Creates a tracker for the hints of the given slot.
+    pub fn new(team: i64, slot: i64) -> Self {
+        Self {
+            key: hints_key(team, slot),
+            hints: HashMap::new(),
+        }
+    }
+
+    /// The data storage key the hints are read from.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Every known hint, in no particular order.
+    pub fn hints(&self) -> impl Iterator<Item = &Hint> {
+        self.hints.values()
+    }
+
+    /// Replaces the known hints with a full list, returning what changed.
+    /// Hints missing from the list are forgotten without being reported, as
+    /// the server never removes hints.
+    pub fn update(&mut self, hints: Vec<Hint>) -> Vec<HintChange> {
+        let mut changes = Vec::new();
+        let mut next = HashMap::with_capacity(hints.len());
+
+        for hint in hints {
+            let id = (hint.finding_player, hint.location);
+            match self.hints.get(&id) {
+                None => changes.push(HintChange::New(hint.clone())),
+                Some(old) if hint.found && !old.found => {
+                    changes.push(HintChange::Found(hint.clone()))
+                }
+                Some(old) if hint.status != old.status => changes.push(HintChange::StatusChanged {
+                    hint: hint.clone(),
+                    old: old.status,
+                }),
+                Some(_) => {}
+            }
+            next.insert(id, hint);
+        }
+
+        self.hints = next;
+        changes
+    }
+
+    /// Updates the hints from a Retrieved or SetReply for our key, returning
+    /// what changed. Other messages, and values which aren't a hint list,
+    /// change nothing.
+    pub fn handle(&mut self, message: &ServerMessage) -> Vec<HintChange> {
+        let value = match message {
+            ServerMessage::Retrieved(retrieved) => retrieved.keys.get(&self.key),
+            ServerMessage::SetReply(reply) if reply.key == self.key => Some(&reply.value),
+            _ => None,
+        };
+
+        match value.map(|value| serde_json::from_value(value.clone())) {
+            Some(Ok(hints)) => self.update(hints),
+            Some(Err(e)) => {
+                tracing::warn!(error = %e, key = self.key, "ignoring malformed hint list");
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The read-only data storage key holding the hints of a slot.
+pub fn hints_key(team: i64, slot: i64) -> String {
+    format!("_read_hints_{}_{}", team, slot)
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+use crate::protocol::{PrintJSON, PrintJSONKind};
+use crate::render::{Names, Renderer};
+
+/// A rendered line in a [`MessageHistory`].
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub kind: PrintJSONKind,
+    pub text: String,
+}
+
+/// A bounded scrollback of rendered messages, for clients which want to show
+/// a log without keeping every message around forever.
+#[derive(Debug, Clone)]
+pub struct MessageHistory {
+    capacity: usize,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl MessageHistory {
+    /// Creates a history which keeps at most `capacity` lines, dropping the
+    /// oldest lines first.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the capacity, dropping the oldest lines if needed.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.truncate();
+    }
+
+    /// Adds an already rendered line.
+    pub fn push(&mut self, kind: PrintJSONKind, text: impl Into<String>) {
+        self.entries.push_back(HistoryEntry {
+            kind,
+            text: text.into(),
+        });
+        self.truncate();
+    }
+
+    /// Adds a PrintJSON message, rendered as plain text by `renderer` with
+    /// players, items and locations named by `names`, usually
+    /// [`crate::client::Client::names`].
+    pub fn push_print_json(&mut self, message: &PrintJSON, renderer: &Renderer, names: &Names) {
+        self.push(message.kind(), renderer.render_with(message, names));
+    }
+
+    /// All lines, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    /// Lines of the given kinds, oldest first.
+    pub fn filter<'a>(
+        &'a self,
+        kinds: &'a [PrintJSONKind],
+    ) -> impl DoubleEndedIterator<Item = &'a HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| kinds.contains(&entry.kind))
+    }
+
+    /// Lines containing `query`, ignoring case, oldest first.
+    pub fn search<'a>(&'a self, query: &str) -> impl DoubleEndedIterator<Item = &'a HistoryEntry> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .filter(move |entry| entry.text.to_lowercase().contains(&query))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn truncate(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+}
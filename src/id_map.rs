@@ -0,0 +1,109 @@
+//! Translation between the IDs a game uses internally and Archipelago's IDs.
+//!
+//! Integrations such as ROM hacks often number their items and locations by
+//! in-game tables, with each region of the game offset differently from the
+//! IDs in the data package. Setting an [`IdMapper`] with
+//! [`crate::client::ConnectOptions::id_mapper`] keeps that translation in one
+//! place: [`crate::client::Client::check_locations`] then takes in-game
+//! location IDs, and [`crate::events::Event::ItemReceived`] carries the
+//! in-game ID of each item.
+
+use std::ops::Range;
+
+/// Translates item and location IDs between a game's own numbering and the
+/// IDs used by Archipelago. Each method returns None for IDs it doesn't know.
+pub trait IdMapper: Send + Sync {
+    fn location_to_ap(&self, local: i64) -> Option<i64>;
+
+    fn location_from_ap(&self, ap: i64) -> Option<i64>;
+
+    fn item_to_ap(&self, local: i64) -> Option<i64>;
+
+    fn item_from_ap(&self, ap: i64) -> Option<i64>;
+}
+
+/// An [`IdMapper`] made of regions, each mapping a range of in-game IDs onto
+/// a contiguous run of Archipelago IDs.
+///
+/// ```
+/// # use archipelago::id_map::{IdMapper, OffsetMapper};
+/// let mapper = OffsetMapper::new()
+///     .location_region(0x100..0x180, 1000)
+///     .location_region(0x200..0x220, 2000);
+///
+/// assert_eq!(mapper.location_to_ap(0x201), Some(2001));
+/// assert_eq!(mapper.location_from_ap(1005), Some(0x105));
+/// assert_eq!(mapper.location_to_ap(0x180), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OffsetMapper {
+    locations: Vec<Region>,
+    items: Vec<Region>,
+}
+
+#[derive(Debug, Clone)]
+struct Region {
+    local: Range<i64>,
+    ap_start: i64,
+}
+
+impl Region {
+    fn ap_id(&self, local: i64) -> Option<i64> {
+        self.local
+            .contains(&local)
+            .then(|| self.ap_start + (local - self.local.start))
+    }
+
+    fn local_id(&self, ap: i64) -> Option<i64> {
+        let offset = ap.checked_sub(self.ap_start)?;
+        let local = self.local.start.checked_add(offset)?;
+        (offset >= 0 && self.local.contains(&local)).then_some(local)
+    }
+}
+
+impl OffsetMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps the in-game locations in `local` onto the Archipelago IDs
+    /// starting at `ap_start`.
+    pub fn location_region(mut self, local: Range<i64>, ap_start: i64) -> Self {
+        self.locations.push(Region { local, ap_start });
+        self
+    }
+
+    /// Maps the in-game items in `local` onto the Archipelago IDs starting
+    /// at `ap_start`.
+    pub fn item_region(mut self, local: Range<i64>, ap_start: i64) -> Self {
+        self.items.push(Region { local, ap_start });
+        self
+    }
+}
+
+impl IdMapper for OffsetMapper {
+    fn location_to_ap(&self, local: i64) -> Option<i64> {
+        self.locations.iter().find_map(|region| region.ap_id(local))
+    }
+
+    fn location_from_ap(&self, ap: i64) -> Option<i64> {
+        self.locations.iter().find_map(|region| region.local_id(ap))
+    }
+
+    fn item_to_ap(&self, local: i64) -> Option<i64> {
+        self.items.iter().find_map(|region| region.ap_id(local))
+    }
+
+    fn item_from_ap(&self, ap: i64) -> Option<i64> {
+        self.items.iter().find_map(|region| region.local_id(ap))
+    }
+}
+
+/// An ID which the [`IdMapper`] had no translation for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum UnmappedId {
+    #[error("in-game location {0} has no Archipelago ID")]
+    Location(i64),
+    #[error("in-game item {0} has no Archipelago ID")]
+    Item(i64),
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::protocol::{NetworkItem, NetworkItemFlags, PrintJSON, ReceivedItems};
+
+/// What to do with received items which were found in our own world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LocalItemPolicy {
+    /// Deliver local items like any other item. They can still be told apart
+    /// using [`ReceivedItem::is_local`].
+    #[default]
+    Deliver,
+
+    /// Drop local items. This is useful for games which grant their own items
+    /// when they're picked up, but still receive them from the server after a
+    /// `!collect` despite not requesting local items.
+    Skip,
+}
+
+/// What to do with received items flagged as traps, which many games can
+/// only apply at certain points, such as outside of cutscenes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrapPolicy {
+    /// Deliver traps like any other item.
+    #[default]
+    Deliver,
+
+    /// Hold traps back for the given time after they're received.
+    Delay(Duration),
+
+    /// Hold traps back until [`ItemQueue::release_traps`] is called, such as
+    /// when the game reaches a safe point.
+    Hold,
+}
+
+/// An item received from the server, along with its position in the list of
+/// all items this slot has received.
+#[derive(Debug, Clone)]
+pub struct ReceivedItem {
+    pub index: i64,
+    pub item: NetworkItem,
+
+    /// Whether this item was found in our own world.
+    pub is_local: bool,
+}
+
+/// An ItemSend broadcast broken down into who found which item for whom, as
+/// seen from one slot.
+#[derive(Debug, Clone, Copy)]
+pub struct ItemSendEvent {
+    /// The slot whose world the item was found in.
+    pub sender_slot: i64,
+
+    /// The slot the item belongs to.
+    pub receiver_slot: i64,
+
+    pub item: i64,
+    pub location: i64,
+    pub flags: NetworkItemFlags,
+
+    /// Whether the item belongs to our slot.
+    pub is_for_me: bool,
+
+    /// Whether the item was found in our world.
+    pub is_from_me: bool,
+}
+
+impl ItemSendEvent {
+    /// Decomposes an ItemSend as seen from `slot`, returning None for any
+    /// other message. The server doesn't name the sender, so it's taken from
+    /// the item's player, which is the slot whose world the item was in.
+    pub fn from_print(print: &PrintJSON, slot: i64) -> Option<Self> {
+        let PrintJSON::ItemSend {
+            receiving, item, ..
+        } = print
+        else {
+            return None;
+        };
+
+        Some(Self {
+            sender_slot: item.player,
+            receiver_slot: *receiving,
+            item: item.item,
+            location: item.location,
+            flags: item.flags,
+            is_for_me: *receiving == slot,
+            is_from_me: item.player == slot,
+        })
+    }
+
+    /// Whether a player found one of their own items.
+    pub fn is_self_find(&self) -> bool {
+        self.sender_slot == self.receiver_slot
+    }
+}
+
+/// Turns ReceivedItems packets into a queue of items for the game to grant.
+///
+/// Items which were already received are dropped, so resends of the full item
+/// list (such as in response to Sync) don't result in duplicate grants.
+#[derive(Debug)]
+pub struct ItemQueue {
+    slot: i64,
+    next_index: i64,
+    local_items: LocalItemPolicy,
+    traps: TrapPolicy,
+    items: VecDeque<ReceivedItem>,
+
+    // Traps held back by the trap policy, with the time they become ready,
+    // which is None for traps held until release_traps.
+    held_traps: VecDeque<(Option<Instant>, ReceivedItem)>,
+}
+
+impl ItemQueue {
+    /// Creates a queue for the given slot, which is used to tell which items
+    /// are local.
+    pub fn new(slot: i64) -> Self {
+        Self {
+            slot,
+            next_index: 0,
+            local_items: LocalItemPolicy::default(),
+            traps: TrapPolicy::default(),
+            items: VecDeque::new(),
+            held_traps: VecDeque::new(),
+        }
+    }
+
+    /// Sets how items found in our own world are handled.
+    pub fn set_local_item_policy(&mut self, policy: LocalItemPolicy) {
+        self.local_items = policy;
+    }
+
+    /// Sets how items flagged as traps are handled. Traps already held back
+    /// stay held until they're due or [`ItemQueue::release_traps`] is called.
+    pub fn set_trap_policy(&mut self, policy: TrapPolicy) {
+        self.traps = policy;
+    }
+
+    /// The index the next new item will have.
+    pub fn next_index(&self) -> i64 {
+        self.next_index
+    }
+
+    /// Adds the items from a ReceivedItems packet to the queue.
+    pub fn handle(&mut self, received: ReceivedItems) {
+        for (index, item) in (received.index..).zip(received.items) {
+            if index < self.next_index {
+                continue;
+            }
+            self.next_index = index + 1;
+
+            let is_local = item.player == self.slot;
+            if is_local && self.local_items == LocalItemPolicy::Skip {
+                continue;
+            }
+
+            let received = ReceivedItem {
+                index,
+                item,
+                is_local,
+            };
+            if !received.item.flags.is_trap() {
+                self.items.push_back(received);
+                continue;
+            }
+
+            match self.traps {
+                TrapPolicy::Deliver => self.items.push_back(received),
+                TrapPolicy::Delay(delay) => self
+                    .held_traps
+                    .push_back((Some(Instant::now() + delay), received)),
+                TrapPolicy::Hold => self.held_traps.push_back((None, received)),
+            }
+        }
+    }
+
+    /// Takes the next item to grant. Delayed traps which are due are queued
+    /// behind the items already waiting.
+    pub fn pop(&mut self) -> Option<ReceivedItem> {
+        self.release_due_traps();
+        self.items.pop_front()
+    }
+
+    /// Queues every held trap, whether delayed or held until now, behind the
+    /// items already waiting.
+    pub fn release_traps(&mut self) {
+        self.items
+            .extend(self.held_traps.drain(..).map(|(_, trap)| trap));
+    }
+
+    /// The number of traps held back by the trap policy.
+    pub fn held_traps(&self) -> usize {
+        self.held_traps.len()
+    }
+
+    /// When the next delayed trap is due, so the caller knows when to check
+    /// the queue again.
+    pub fn next_trap_at(&self) -> Option<Instant> {
+        self.held_traps.iter().filter_map(|(at, _)| *at).min()
+    }
+
+    fn release_due_traps(&mut self) {
+        let now = Instant::now();
+        let mut held = VecDeque::with_capacity(self.held_traps.len());
+        for (at, trap) in self.held_traps.drain(..) {
+            match at {
+                Some(at) if at <= now => self.items.push_back(trap),
+                at => held.push_back((at, trap)),
+            }
+        }
+        self.held_traps = held;
+    }
+
+    /// The number of items ready to grant, not counting held traps, even
+    /// delayed ones which are due.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
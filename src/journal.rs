@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::items::ReceivedItem;
+use crate::persist::{self, Json, PersistFormat};
+
+/// Records which received items have been applied to the game's save, so
+/// items are granted exactly once even if the game crashes mid-grant.
+///
+/// Each grant is written to disk twice: once when it starts and once when it
+/// has been applied to the save. If the game crashes in between, the journal
+/// can't know whether the save was written, so the item is reported by
+/// [`GrantJournal::in_doubt`] on the next launch and the game must settle it
+/// with [`GrantJournal::commit`] or [`GrantJournal::abort`], usually by
+/// checking its own save. No further grants can start until it does.
+///
+/// The journal is stored as JSON unless another [`PersistFormat`] is chosen
+/// with [`GrantJournal::open_with_format`].
+///
+/// ```no_run
+/// # use archipelago::items::ItemQueue;
+/// # use archipelago::journal::GrantJournal;
+/// # fn give_item(_: i64) -> archipelago::Result<()> { Ok(()) }
+/// # fn save_has_item(_: i64) -> bool { true }
+/// # fn run(queue: &mut ItemQueue) -> archipelago::Result<()> {
+/// let mut journal = GrantJournal::open("grants.json", "12345", 1)?;
+/// if let Some(index) = journal.in_doubt() {
+///     if save_has_item(index) {
+///         journal.commit(index)?;
+///     } else {
+///         journal.abort(index)?;
+///     }
+/// }
+///
+/// while let Some(item) = queue.pop() {
+///     journal.grant(&item, |item| give_item(item.item.item))?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct GrantJournal<F = Json> {
+    path: PathBuf,
+    format: F,
+    state: JournalState,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct JournalState {
+    seed_name: String,
+    slot: i64,
+
+    // Every index below this has been applied. Items are almost always
+    // granted in order, so this keeps the file small.
+    applied_below: i64,
+
+    // Indices at or above applied_below which have been applied.
+    #[serde(default)]
+    applied: BTreeSet<i64>,
+
+    // The grant which was started but not yet committed or aborted.
+    #[serde(default)]
+    in_progress: Option<i64>,
+}
+
+impl GrantJournal {
+    /// Opens the journal at the given path for a slot in the given seed. A
+    /// missing file, or one written for a different seed or slot, is treated
+    /// as an empty journal, and is only overwritten by the first grant.
+    pub fn open(path: impl Into<PathBuf>, seed_name: &str, slot: i64) -> Result<Self> {
+        Self::open_with_format(path, seed_name, slot, Json)
+    }
+}
+
+impl<F: PersistFormat> GrantJournal<F> {
+    /// Opens the journal like [`GrantJournal::open`], in the given format.
+    pub fn open_with_format(
+        path: impl Into<PathBuf>,
+        seed_name: &str,
+        slot: i64,
+        format: F,
+    ) -> Result<Self> {
+        let path = path.into();
+
+        let fresh = JournalState {
+            seed_name: seed_name.to_string(),
+            slot,
+            ..Default::default()
+        };
+
+        let state = match std::fs::read(&path) {
+            Ok(contents) => {
+                let state: JournalState =
+                    format
+                        .deserialize(&contents)
+                        .map_err(|source| Error::Corrupt {
+                            path: path.clone(),
+                            source,
+                        })?;
+                if state.seed_name == seed_name && state.slot == slot {
+                    state
+                } else {
+                    fresh
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => fresh,
+            Err(e) => return Err(Error::file("read", &path)(e)),
+        };
+
+        Ok(Self {
+            path,
+            format,
+            state,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the item with this index has been applied.
+    pub fn is_applied(&self, index: i64) -> bool {
+        index < self.state.applied_below || self.state.applied.contains(&index)
+    }
+
+    /// The index of a grant which was started but never committed or
+    /// aborted, because the game crashed or was closed during it.
+    pub fn in_doubt(&self) -> Option<i64> {
+        self.state.in_progress
+    }
+
+    /// Records that the item with this index is about to be applied.
+    pub fn begin(&mut self, index: i64) -> Result<()> {
+        if let Some(in_progress) = self.state.in_progress {
+            return Err(JournalError::InProgress { index, in_progress }.into());
+        }
+        if self.is_applied(index) {
+            return Err(JournalError::AlreadyApplied(index).into());
+        }
+
+        self.state.in_progress = Some(index);
+        self.save()
+    }
+
+    /// Records that the item with this index has been applied to the save.
+    pub fn commit(&mut self, index: i64) -> Result<()> {
+        self.finish(index)?;
+
+        self.state.applied.insert(index);
+        while self.state.applied.remove(&self.state.applied_below) {
+            self.state.applied_below += 1;
+        }
+
+        self.save()
+    }
+
+    /// Records that the item with this index was not applied, so it can be
+    /// granted again.
+    pub fn abort(&mut self, index: i64) -> Result<()> {
+        self.finish(index)?;
+        self.save()
+    }
+
+    /// Grants an item unless it has already been applied, returning whether
+    /// `apply` was called. `apply` should give the item to the player and
+    /// write the game's save before returning. If it fails, the grant is
+    /// aborted and the error returned. Failures to update the journal are
+    /// converted into `E`.
+    pub fn grant<E>(
+        &mut self,
+        item: &ReceivedItem,
+        apply: impl FnOnce(&ReceivedItem) -> std::result::Result<(), E>,
+    ) -> std::result::Result<bool, E>
+    where
+        E: From<Error>,
+    {
+        if self.is_applied(item.index) {
+            return Ok(false);
+        }
+
+        self.begin(item.index)?;
+        if let Err(e) = apply(item) {
+            self.abort(item.index)?;
+            return Err(e);
+        }
+        self.commit(item.index)?;
+
+        Ok(true)
+    }
+
+    fn finish(&mut self, index: i64) -> Result<()> {
+        match self.state.in_progress {
+            Some(in_progress) if in_progress == index => {
+                self.state.in_progress = None;
+                Ok(())
+            }
+            _ => Err(JournalError::NotInProgress(index).into()),
+        }
+    }
+
+    /// Writes the journal to disk. The file is replaced atomically, and only
+    /// after the new contents have been flushed, so a crash leaves either the
+    /// old or the new journal behind.
+    fn save(&self) -> Result<()> {
+        persist::write_atomic(&self.path, &self.format.serialize(&self.state)?)
+    }
+}
+
+/// A grant was started or finished out of turn.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum JournalError {
+    #[error("cannot grant item {index} while item {in_progress} is still in progress")]
+    InProgress { index: i64, in_progress: i64 },
+
+    #[error("item {0} was already applied")]
+    AlreadyApplied(i64),
+
+    #[error("item {0} is not in progress")]
+    NotInProgress(i64),
+}
@@ -1,2 +1,56 @@
+pub mod bus;
 pub mod client;
+pub mod clock;
+pub mod companion;
+pub mod cooldown;
+#[cfg(feature = "keyring")]
+pub mod credentials;
+pub mod data_package;
+pub mod deathlink;
+pub mod diagnostics;
+pub mod error;
+pub mod events;
+pub mod extensions;
+#[cfg(feature = "fixtures")]
+pub mod faults;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod handle;
+pub mod handler;
+pub mod hint_bot;
+pub mod hints;
+pub mod history;
+pub mod id_map;
+pub mod items;
+pub mod journal;
+pub mod location_set;
+pub mod observer;
+pub mod persist;
+pub mod profile;
+pub mod progress;
 pub mod protocol;
+pub mod reconnect;
+pub mod render;
+pub mod resolver;
+pub mod room;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod session;
+pub mod session_log;
+pub mod slot_data;
+#[cfg(any(
+    feature = "tokio-transport",
+    all(feature = "browser-transport", target_arch = "wasm32")
+))]
+pub mod smoke;
+pub mod state;
+pub mod storage;
+pub mod tasks;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timer;
+pub mod transport;
+#[cfg(feature = "tokio-transport")]
+pub mod webhost;
+
+pub use error::{Error, Result};
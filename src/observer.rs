@@ -0,0 +1,266 @@
+//! Watching every team of a tournament at once, for casters and organisers.
+//!
+//! Each team is watched through its own Tracker connection, as the server
+//! only sends item messages to the team they belong to. The connections are
+//! merged into one [`TournamentObserver`], which keeps a [`TeamProgress`] per
+//! team for comparing them.
+
+use std::collections::BTreeSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+
+use crate::client::{AnonymousClient, Client, ConnectOptions, MessageStreamError};
+use crate::protocol::{PrintJSON, ServerMessage};
+use crate::transport::Connector;
+#[cfg(any(
+    feature = "tokio-transport",
+    all(feature = "browser-transport", target_arch = "wasm32")
+))]
+use crate::transport::DefaultConnector;
+
+/// The slot to watch a team through.
+#[derive(Clone)]
+pub struct TeamCredentials {
+    label: String,
+    name: String,
+    password: Option<String>,
+}
+
+impl TeamCredentials {
+    /// Watches a team through the slot with the given name. `label` is how
+    /// the team is shown, such as the team's name in the tournament.
+    pub fn new(label: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            name: name.into(),
+            password: None,
+        }
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+}
+
+impl std::fmt::Debug for TeamCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The password is deliberately left out so it doesn't end up in logs.
+        f.debug_struct("TeamCredentials")
+            .field("label", &self.label)
+            .field("name", &self.name)
+            .field("has_password", &self.password.is_some())
+            .finish()
+    }
+}
+
+/// How far a team has come.
+#[derive(Debug, Clone)]
+pub struct TeamProgress {
+    pub label: String,
+    pub team: i64,
+
+    /// The number of players on the team.
+    pub slots: usize,
+
+    /// The slots which reached their goal.
+    pub goals: BTreeSet<i64>,
+
+    /// The slots which released their remaining items.
+    pub released: BTreeSet<i64>,
+
+    /// The number of items found by the team, counted from the ItemSend
+    /// messages received since connecting.
+    pub items_found: u64,
+}
+
+impl TeamProgress {
+    /// The fraction of the team's players who reached their goal, from 0 to
+    /// 1.
+    pub fn goal_fraction(&self) -> f64 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+        self.goals.len() as f64 / self.slots as f64
+    }
+
+    /// Whether every player on the team reached their goal.
+    pub fn is_finished(&self) -> bool {
+        self.slots > 0 && self.goals.len() >= self.slots
+    }
+}
+
+/// A message received by the connection watching `team`.
+#[derive(Debug, Clone)]
+pub struct ObserverUpdate {
+    pub team: i64,
+    pub message: ServerMessage,
+}
+
+/// Tracker connections to several teams of the same room, streaming the
+/// messages from all of them as [`ObserverUpdate`]s.
+///
+/// Progress is updated as the stream is driven. Goals and releases are
+/// broadcast to every team, so they're recorded from whichever connection
+/// sees them first.
+pub struct TournamentObserver {
+    views: Vec<View>,
+
+    // The view polled first next time, so a busy team can't starve the
+    // others.
+    next_view: usize,
+}
+
+struct View {
+    client: Client,
+    progress: TeamProgress,
+    ended: bool,
+}
+
+impl TournamentObserver {
+    /// Connects to every team with the Tracker tag, one after another.
+    #[cfg(any(
+        feature = "tokio-transport",
+        all(feature = "browser-transport", target_arch = "wasm32")
+    ))]
+    pub async fn connect(address: &str, teams: Vec<TeamCredentials>) -> crate::Result<Self> {
+        Self::connect_via(Arc::new(DefaultConnector::default()), address, teams).await
+    }
+
+    /// Like [`TournamentObserver::connect`], but connecting through
+    /// `connector`.
+    pub async fn connect_via(
+        connector: Arc<dyn Connector>,
+        address: &str,
+        teams: Vec<TeamCredentials>,
+    ) -> crate::Result<Self> {
+        let mut views = Vec::with_capacity(teams.len());
+        for credentials in teams {
+            let mut options = ConnectOptions::slim(credentials.name);
+            if let Some(password) = credentials.password {
+                options = options.password(password);
+            }
+
+            let client = AnonymousClient::connect_via(connector.clone(), address)
+                .await?
+                .connect_with(options)
+                .await?;
+
+            let team = client.get_connected().team;
+            let slots = client
+                .get_connected()
+                .players
+                .iter()
+                .filter(|player| player.team == team)
+                .count();
+
+            views.push(View {
+                client,
+                progress: TeamProgress {
+                    label: credentials.label,
+                    team,
+                    slots,
+                    goals: BTreeSet::new(),
+                    released: BTreeSet::new(),
+                    items_found: 0,
+                },
+                ended: false,
+            });
+        }
+
+        Ok(Self {
+            views,
+            next_view: 0,
+        })
+    }
+
+    /// The progress of every team, in the order they were given.
+    pub fn progress(&self) -> impl Iterator<Item = &TeamProgress> {
+        self.views.iter().map(|view| &view.progress)
+    }
+
+    pub fn team(&self, team: i64) -> Option<&TeamProgress> {
+        self.progress().find(|progress| progress.team == team)
+    }
+
+    /// Every team ranked by the fraction of players who reached their goal,
+    /// then by the number of items found.
+    pub fn standings(&self) -> Vec<&TeamProgress> {
+        let mut standings: Vec<&TeamProgress> = self.progress().collect();
+        standings.sort_by(|a, b| {
+            b.goal_fraction()
+                .total_cmp(&a.goal_fraction())
+                .then(b.items_found.cmp(&a.items_found))
+        });
+        standings
+    }
+
+    /// The connection watching `team`.
+    pub fn client(&self, team: i64) -> Option<&Client> {
+        self.views
+            .iter()
+            .find(|view| view.progress.team == team)
+            .map(|view| &view.client)
+    }
+
+    fn record(&mut self, view: usize, message: &ServerMessage) {
+        let ServerMessage::PrintJSON(print) = message else {
+            return;
+        };
+
+        match print {
+            // The server only sends these to the team the item belongs to.
+            PrintJSON::ItemSend { .. } => self.views[view].progress.items_found += 1,
+            PrintJSON::Goal { team, slot, .. } => {
+                if let Some(view) = self.views.iter_mut().find(|v| v.progress.team == *team) {
+                    view.progress.goals.insert(*slot);
+                }
+            }
+            PrintJSON::Release { team, slot, .. } => {
+                if let Some(view) = self.views.iter_mut().find(|v| v.progress.team == *team) {
+                    view.progress.released.insert(*slot);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Stream for TournamentObserver {
+    type Item = Result<ObserverUpdate, MessageStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let count = self.views.len();
+        for offset in 0..count {
+            let index = (self.next_view + offset) % count;
+            let view = &mut self.views[index];
+            if view.ended {
+                continue;
+            }
+
+            match view.client.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => {
+                    let team = view.progress.team;
+                    self.record(index, &message);
+                    self.next_view = (index + 1) % count;
+                    return Poll::Ready(Some(Ok(ObserverUpdate { team, message })));
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    self.next_view = (index + 1) % count;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Ready(None) => view.ended = true,
+                Poll::Pending => {}
+            }
+        }
+
+        if self.views.iter().all(|view| view.ended) {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
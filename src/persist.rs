@@ -0,0 +1,147 @@
+//! File formats for the data the crate persists locally, such as the data
+//! package cache ([`crate::data_package::FileCache`]), the grant journal
+//! ([`crate::journal::GrantJournal`]) and session snapshots
+//! ([`crate::state::TrackedState::save`]).
+//!
+//! JSON is always available and is the default, as it's easy to inspect.
+//! Large caches load noticeably faster from a binary format, so bincode and
+//! MessagePack are available behind the `bincode` and `messagepack` features.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{Error, Result};
+
+/// A serialization format for persisted data.
+pub trait PersistFormat {
+    /// The file extension used for files in this format, without the dot.
+    const EXTENSION: &'static str;
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, PersistError>;
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, PersistError>;
+}
+
+/// A value couldn't be converted to or from a [`PersistFormat`].
+#[derive(Debug, thiserror::Error)]
+#[error("{format} error: {source}")]
+pub struct PersistError {
+    format: &'static str,
+    source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl PersistError {
+    /// Wraps an error from the format with the given name, usually its
+    /// [`PersistFormat::EXTENSION`].
+    pub fn new(
+        format: &'static str,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            format,
+            source: source.into(),
+        }
+    }
+
+    pub fn format(&self) -> &'static str {
+        self.format
+    }
+}
+
+/// Writes `contents` to `path`, creating its directory if needed. The file is
+/// replaced atomically, and only after the new contents have been flushed,
+/// so a crash leaves either the old or the new file behind.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(Error::file("create", parent))?;
+    }
+
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = std::fs::File::create(&tmp).map_err(Error::file("create", &tmp))?;
+    file.write_all(contents)
+        .and_then(|()| file.sync_all())
+        .map_err(Error::file("write", &tmp))?;
+    std::fs::rename(&tmp, path).map_err(Error::file("replace", path))?;
+
+    Ok(())
+}
+
+/// Saves a field as JSON text, with `#[serde(with = "persist::json_text")]`,
+/// for values binary formats can't read back: those which only know their
+/// type from the data, such as a `serde_json::Value`, and protocol types which
+/// leave out fields that are None.
+pub(crate) mod json_text {
+    use serde::de::{DeserializeOwned, Error as _};
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&serde_json::to_string(value).map_err(S::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, T: DeserializeOwned, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        serde_json::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl PersistFormat for Json {
+    const EXTENSION: &'static str = "json";
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, PersistError> {
+        serde_json::to_vec(value).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, PersistError> {
+        serde_json::from_slice(bytes).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+}
+
+/// Requires the `bincode` feature.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl PersistFormat for Bincode {
+    const EXTENSION: &'static str = "bin";
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, PersistError> {
+        bincode::serialize(value).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, PersistError> {
+        bincode::deserialize(bytes).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+}
+
+/// Requires the `messagepack` feature.
+#[cfg(feature = "messagepack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "messagepack")]
+impl PersistFormat for MessagePack {
+    const EXTENSION: &'static str = "msgpack";
+
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, PersistError> {
+        // Named fields keep files readable by other MessagePack tools, and
+        // tolerant of fields being added.
+        rmp_serde::to_vec_named(value).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, PersistError> {
+        rmp_serde::from_slice(bytes).map_err(|e| PersistError::new(Self::EXTENSION, e))
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::ConnectOptions;
+use crate::error::{Error, Result};
+use crate::persist::{self, Json, PersistFormat};
+
+/// A saved set of connection settings.
+///
+/// Passwords are not stored, only whether one is needed, so clients know to
+/// prompt for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Address of the server, as accepted by `AnonymousClient::new`.
+    pub host: String,
+
+    /// The slot name to connect as.
+    pub name: String,
+
+    /// The game to connect as. May be empty for trackers and text clients.
+    #[serde(default)]
+    pub game: String,
+
+    /// Whether the room needs a password.
+    #[serde(default)]
+    pub has_password: bool,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Unique identifier sent with Connect, kept stable across sessions so
+    /// the server can recognize this client.
+    pub uuid: String,
+}
+
+impl Profile {
+    pub fn new(host: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            name: name.into(),
+            game: String::new(),
+            has_password: false,
+            tags: Vec::new(),
+            uuid: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Stores the password for this profile in the OS credential store.
+    #[cfg(feature = "keyring")]
+    pub fn store_password(&self, password: &str) -> Result<()> {
+        crate::credentials::store_password(&self.host, &self.name, password)
+    }
+
+    /// Loads the password for this profile from the OS credential store.
+    #[cfg(feature = "keyring")]
+    pub fn load_password(&self) -> Result<Option<String>> {
+        crate::credentials::load_password(&self.host, &self.name)
+    }
+
+    /// Removes the password for this profile from the OS credential store.
+    #[cfg(feature = "keyring")]
+    pub fn delete_password(&self) -> Result<()> {
+        crate::credentials::delete_password(&self.host, &self.name)
+    }
+
+    /// Builds ConnectOptions from this profile. The password, if needed,
+    /// must be added by the caller.
+    pub fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions::new(self.name.clone())
+            .game(self.game.clone())
+            .tags(self.tags.iter().cloned())
+            .uuid(self.uuid.clone())
+    }
+}
+
+/// Named connection profiles persisted as a JSON file.
+#[derive(Debug)]
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileStore {
+    /// The default location of the profile store, inside the platform's
+    /// config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("archipelago-rs").join("profiles.json"))
+    }
+
+    /// Opens the profile store in the default location.
+    pub fn open_default() -> Result<Self> {
+        let path = Self::default_path().ok_or(Error::NoConfigDir)?;
+        Self::open(path)
+    }
+
+    /// Opens the profile store at the given path. A missing file is treated
+    /// as an empty store.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+
+        let profiles = match std::fs::read(&path) {
+            Ok(contents) => Json
+                .deserialize(&contents)
+                .map_err(|source| Error::Corrupt {
+                    path: path.clone(),
+                    source,
+                })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(Error::file("read", &path)(e)),
+        };
+
+        Ok(Self { path, profiles })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, profile: Profile) -> Option<Profile> {
+        self.profiles.insert(name.into(), profile)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Profile> {
+        self.profiles.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// Writes the profiles back to disk. The file is replaced atomically so a
+    /// crash never leaves a partially written store behind.
+    pub fn save(&self) -> Result<()> {
+        persist::write_atomic(&self.path, &Json.serialize(&self.profiles)?)
+    }
+}
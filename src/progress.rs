@@ -0,0 +1,146 @@
+//! How far a slot is through its game, as a single number for overlays and
+//! bots which announce milestones.
+//!
+//! Progress counts the checked locations of our slot. Once the items at our
+//! locations are known from LocationInfo, such as after scouting them,
+//! locations holding progression items count for more, as they're usually
+//! what stands between a player and their goal. [`crate::client::Client`]
+//! keeps this up to date and emits [`crate::events::Event::ProgressThreshold`]
+//! as the thresholds set with
+//! [`crate::client::ConnectOptions::progress_thresholds`] are passed.
+
+use std::collections::HashSet;
+
+use crate::protocol::NetworkItem;
+
+/// How much more a location holding a progression item counts than any
+/// other location, unless changed with
+/// [`crate::client::Client::set_progression_weight`].
+pub const DEFAULT_PROGRESSION_WEIGHT: f64 = 2.0;
+
+/// A snapshot of how many of a slot's locations have been checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub checked: usize,
+    pub total: usize,
+
+    /// The fraction of locations checked, with locations holding progression
+    /// items weighted more heavily. None if the items at our locations
+    /// aren't known.
+    pub weighted: Option<f64>,
+}
+
+impl Progress {
+    /// The fraction of the slot completed, from 0 to 1: the weighted
+    /// fraction if known, or else the fraction of locations checked.
+    pub fn fraction(&self) -> f64 {
+        self.weighted.unwrap_or_else(|| self.checked_fraction())
+    }
+
+    /// [`Progress::fraction`] as a percentage.
+    pub fn percent(&self) -> f64 {
+        self.fraction() * 100.0
+    }
+
+    /// The fraction of locations checked, ignoring what they hold. A slot
+    /// without locations counts as complete.
+    pub fn checked_fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.checked as f64 / self.total as f64
+    }
+}
+
+/// Keeps what's known about the items at our locations and which progress
+/// thresholds have been passed.
+#[derive(Debug)]
+pub(crate) struct ProgressTracker {
+    // Our locations known to hold progression items.
+    progression: HashSet<i64>,
+    progression_weight: f64,
+
+    // Thresholds in percent, in ascending order, and how many of them have
+    // been passed.
+    thresholds: Vec<u8>,
+    passed: usize,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(thresholds: Vec<u8>) -> Self {
+        let mut tracker = Self {
+            progression: HashSet::new(),
+            progression_weight: DEFAULT_PROGRESSION_WEIGHT,
+            thresholds: Vec::new(),
+            passed: 0,
+        };
+        tracker.set_thresholds(thresholds);
+        tracker
+    }
+
+    pub(crate) fn set_thresholds(&mut self, mut thresholds: Vec<u8>) {
+        thresholds.sort_unstable();
+        thresholds.dedup();
+        self.thresholds = thresholds;
+        self.passed = 0;
+    }
+
+    pub(crate) fn set_progression_weight(&mut self, weight: f64) {
+        self.progression_weight = weight;
+    }
+
+    /// Records the items at our locations from a LocationInfo.
+    pub(crate) fn record_items(&mut self, items: &[NetworkItem]) {
+        for item in items {
+            if item.flags.is_progression() {
+                self.progression.insert(item.location);
+            } else {
+                self.progression.remove(&item.location);
+            }
+        }
+    }
+
+    pub(crate) fn progress(&self, checked: &[i64], missing: &[i64]) -> Progress {
+        let weighted = (!self.progression.is_empty()).then(|| {
+            let weight = |locations: &[i64]| -> f64 {
+                locations
+                    .iter()
+                    .map(|location| {
+                        if self.progression.contains(location) {
+                            self.progression_weight
+                        } else {
+                            1.0
+                        }
+                    })
+                    .sum()
+            };
+
+            let checked = weight(checked);
+            let total = checked + weight(missing);
+            if total > 0.0 {
+                checked / total
+            } else {
+                1.0
+            }
+        });
+
+        Progress {
+            checked: checked.len(),
+            total: checked.len() + missing.len(),
+            weighted,
+        }
+    }
+
+    /// Returns the thresholds which `progress` has passed since the last
+    /// call. Thresholds are only passed once, even if progress goes back.
+    pub(crate) fn pass_thresholds(&mut self, progress: &Progress) -> Vec<u8> {
+        let percent = progress.percent();
+        let passed: Vec<u8> = self.thresholds[self.passed..]
+            .iter()
+            .copied()
+            .take_while(|threshold| percent >= f64::from(*threshold))
+            .collect();
+        self.passed += passed.len();
+        passed
+    }
+}
@@ -1,4 +1,5 @@
-use std::{collections::HashMap, ops::BitOr};
+use std::collections::HashMap;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, RangeInclusive};
 
 use serde::{ser::SerializeMap, Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
@@ -7,21 +8,26 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 ///
 /// These packets are are sent from the multiworld server to the client. They
 /// are not messages which the server accepts.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "cmd")]
+#[non_exhaustive]
 pub enum ServerMessage {
     ReceivedItems(ReceivedItems),
     LocationInfo(LocationInfo),
-    RoomUpdate(RoomUpdate),
+    RoomUpdate(Box<RoomUpdate>),
     PrintJSON(PrintJSON),
     Bounced(Bounced),
     Retrieved(Retrieved),
     SetReply(SetReply),
 
+    /// The answer to a GetDataPackage sent after connecting, such as by
+    /// [`crate::client::Client::load_game_data`].
+    DataPackage(DataPackage),
+
     InvalidPacket(InvalidPacket),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "cmd")]
 pub enum AnonymousServerMessage {
     RoomInfo(RoomInfo),
@@ -32,8 +38,141 @@ pub enum AnonymousServerMessage {
     InvalidPacket(InvalidPacket),
 }
 
+/// The `cmd` of every packet in the protocol, as sent on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cmd {
+    // Server -> Client
+    RoomInfo,
+    ConnectionRefused,
+    Connected,
+    ReceivedItems,
+    LocationInfo,
+    RoomUpdate,
+    PrintJSON,
+    DataPackage,
+    Bounced,
+    InvalidPacket,
+    Retrieved,
+    SetReply,
+
+    // Client -> Server
+    Connect,
+    ConnectUpdate,
+    Sync,
+    LocationChecks,
+    LocationScouts,
+    StatusUpdate,
+    Say,
+    GetDataPackage,
+    Bounce,
+    Get,
+    Set,
+    SetNotify,
+}
+
+impl Cmd {
+    /// The name of this command on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cmd::RoomInfo => "RoomInfo",
+            Cmd::ConnectionRefused => "ConnectionRefused",
+            Cmd::Connected => "Connected",
+            Cmd::ReceivedItems => "ReceivedItems",
+            Cmd::LocationInfo => "LocationInfo",
+            Cmd::RoomUpdate => "RoomUpdate",
+            Cmd::PrintJSON => "PrintJSON",
+            Cmd::DataPackage => "DataPackage",
+            Cmd::Bounced => "Bounced",
+            Cmd::InvalidPacket => "InvalidPacket",
+            Cmd::Retrieved => "Retrieved",
+            Cmd::SetReply => "SetReply",
+            Cmd::Connect => "Connect",
+            Cmd::ConnectUpdate => "ConnectUpdate",
+            Cmd::Sync => "Sync",
+            Cmd::LocationChecks => "LocationChecks",
+            Cmd::LocationScouts => "LocationScouts",
+            Cmd::StatusUpdate => "StatusUpdate",
+            Cmd::Say => "Say",
+            Cmd::GetDataPackage => "GetDataPackage",
+            Cmd::Bounce => "Bounce",
+            Cmd::Get => "Get",
+            Cmd::Set => "Set",
+            Cmd::SetNotify => "SetNotify",
+        }
+    }
+}
+
+impl std::fmt::Display for Cmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ServerMessage {
+    pub fn cmd(&self) -> Cmd {
+        match self {
+            ServerMessage::ReceivedItems(_) => Cmd::ReceivedItems,
+            ServerMessage::LocationInfo(_) => Cmd::LocationInfo,
+            ServerMessage::RoomUpdate(_) => Cmd::RoomUpdate,
+            ServerMessage::PrintJSON(_) => Cmd::PrintJSON,
+            ServerMessage::Bounced(_) => Cmd::Bounced,
+            ServerMessage::Retrieved(_) => Cmd::Retrieved,
+            ServerMessage::SetReply(_) => Cmd::SetReply,
+            ServerMessage::DataPackage(_) => Cmd::DataPackage,
+            ServerMessage::InvalidPacket(_) => Cmd::InvalidPacket,
+        }
+    }
+
+    /// The `cmd` of this message on the wire.
+    pub fn cmd_name(&self) -> &'static str {
+        self.cmd().as_str()
+    }
+}
+
+impl AnonymousServerMessage {
+    pub fn cmd(&self) -> Cmd {
+        match self {
+            AnonymousServerMessage::RoomInfo(_) => Cmd::RoomInfo,
+            AnonymousServerMessage::ConnectionRefused(_) => Cmd::ConnectionRefused,
+            AnonymousServerMessage::Connected(_) => Cmd::Connected,
+            AnonymousServerMessage::DataPackage(_) => Cmd::DataPackage,
+            AnonymousServerMessage::InvalidPacket(_) => Cmd::InvalidPacket,
+        }
+    }
+
+    /// The `cmd` of this message on the wire.
+    pub fn cmd_name(&self) -> &'static str {
+        self.cmd().as_str()
+    }
+}
+
+impl ClientMessage {
+    pub fn cmd(&self) -> Cmd {
+        match self {
+            ClientMessage::Connect(_) => Cmd::Connect,
+            ClientMessage::ConnectUpdate(_) => Cmd::ConnectUpdate,
+            ClientMessage::Sync(_) => Cmd::Sync,
+            ClientMessage::LocationChecks(_) => Cmd::LocationChecks,
+            ClientMessage::LocationScouts(_) => Cmd::LocationScouts,
+            ClientMessage::StatusUpdate(_) => Cmd::StatusUpdate,
+            ClientMessage::Say(_) => Cmd::Say,
+            ClientMessage::GetDataPackage(_) => Cmd::GetDataPackage,
+            ClientMessage::Bounce(_) => Cmd::Bounce,
+            ClientMessage::Get(_) => Cmd::Get,
+            ClientMessage::Set(_) => Cmd::Set,
+            ClientMessage::SetNotify(_) => Cmd::SetNotify,
+        }
+    }
+
+    /// The `cmd` of this message on the wire.
+    pub fn cmd_name(&self) -> &'static str {
+        self.cmd().as_str()
+    }
+}
+
 /// Sent to clients when they connect to an Archipelago server.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct RoomInfo {
     /// Object denoting the version of Archipelago which the server is running.
     pub version: NetworkVersion,
@@ -85,7 +224,7 @@ pub struct RoomInfo {
 
 /// Sent to clients when the server refuses connection. This is sent during the
 /// initial connection handshake.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionRefused {
     /// Optional. When provided, should contain any one of: InvalidSlot,
     /// InvalidGame, IncompatibleVersion, InvalidPassword, or
@@ -93,7 +232,7 @@ pub struct ConnectionRefused {
     pub errors: Vec<ConnectionRefusedError>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ConnectionRefusedError {
     /// InvalidSlot indicates that the sent 'name' field did not match any auth
     /// entry on the server.
@@ -116,7 +255,8 @@ pub enum ConnectionRefusedError {
 }
 
 /// Sent to clients when the connection handshake is successfully completed.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Connected {
     /// Your team number. See NetworkPlayer for more info on team number.
     pub team: i64,
@@ -141,17 +281,73 @@ pub struct Connected {
     pub slot_data: HashMap<String, serde_json::Value>,
 
     /// Maps each slot to a NetworkSlot information.
-    ///
-    /// TODO: the key is actually an i64, but json isn't allowed to have
-    /// non-string keys, so we need to parse it.
-    pub slot_info: HashMap<String, NetworkSlot>,
+    #[serde(with = "slot_map")]
+    pub slot_info: HashMap<i64, NetworkSlot>,
 
     /// Number of hint points that the current player has.
     pub hint_points: i64,
 }
 
+/// (De)serializes maps keyed by slot number, whose keys JSON can only hold as
+/// strings. serde_json converts these itself, but not once an internally
+/// tagged enum such as [`AnonymousServerMessage`] has buffered the map, so the keys
+/// are parsed here.
+mod slot_map {
+    use std::collections::HashMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::NetworkSlot;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<i64, NetworkSlot>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(map.iter().map(|(slot, info)| (slot.to_string(), info)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<i64, NetworkSlot>, D::Error> {
+        parse_keys(HashMap::<String, NetworkSlot>::deserialize(deserializer)?)
+    }
+
+    fn parse_keys<E: Error>(
+        map: HashMap<String, NetworkSlot>,
+    ) -> Result<HashMap<i64, NetworkSlot>, E> {
+        map.into_iter()
+            .map(|(slot, info)| match slot.parse() {
+                Ok(slot) => Ok((slot, info)),
+                Err(_) => Err(E::custom(format!("invalid slot number {:?}", slot))),
+            })
+            .collect()
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            map: &Option<HashMap<i64, NetworkSlot>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match map {
+                Some(map) => super::serialize(map, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<HashMap<i64, NetworkSlot>>, D::Error> {
+            Option::<HashMap<String, NetworkSlot>>::deserialize(deserializer)?
+                .map(parse_keys)
+                .transpose()
+        }
+    }
+}
+
 /// Sent to clients when they receive an item.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReceivedItems {
     /// The next empty slot in the list of items for the receiving client.
     pub index: i64,
@@ -162,7 +358,7 @@ pub struct ReceivedItems {
 
 /// Sent to clients to acknowledge a received LocationScouts packet and responds
 /// with the item in the location(s) being scouted.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationInfo {
     /// Contains list of item(s) in the location(s) scouted.
     pub locations: Vec<NetworkItem>,
@@ -179,12 +375,91 @@ pub struct LocationInfo {
 /// - missing_locations: Never sent in this packet. If needed, it is the inverse of checked_locations.
 ///
 /// All arguments for this packet are optional, only changes are sent.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct RoomUpdate {
-    // TODO: this
+    /// Sent in the event of an alias rename. Always sends all players, whether
+    /// connected or not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<Vec<NetworkPlayer>>,
+
+    /// May be a partial update, containing new locations that were checked,
+    /// especially from a coop partner in the same slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked_locations: Option<Vec<i64>>,
+
+    /// Number of hint points that the current player has.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_points: Option<i64>,
+
+    /// Mapping of Permission name to Permission.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<HashMap<PermissionName, Permission>>,
+
+    /// The percentage of total locations that need to be checked to receive
+    /// a hint from the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint_cost: Option<i64>,
+
+    /// The amount of hint points you receive per item/location check
+    /// completed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location_check_points: Option<i64>,
+
+    /// The server's tags, such as `WebHost`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+
+    /// Whether a password is now required to join the room.
+    #[serde(default, rename = "password", skip_serializing_if = "Option::is_none")]
+    pub password_required: Option<bool>,
+
+    /// The version of Archipelago which the server is running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<NetworkVersion>,
+
+    /// The version of Archipelago which generated the multiworld.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_version: Option<NetworkVersion>,
+
+    /// List of games present in this multiworld.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub games: Option<Vec<String>>,
+
+    /// Checksum hash of the individual games' data packages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datapackage_checksums: Option<HashMap<String, String>>,
+
+    /// Uniquely identifying name of this generation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_name: Option<String>,
+
+    /// Unix time stamp of "now".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+
+    /// Our team number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub team: Option<i64>,
+
+    /// Our slot number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<i64>,
+
+    /// Slot related data, which differs per game.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot_data: Option<HashMap<String, serde_json::Value>>,
+
+    /// Maps each slot to a NetworkSlot information.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "slot_map::option"
+    )]
+    pub slot_info: Option<HashMap<i64, NetworkSlot>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum PrintJSON {
     /// A player received an item.
@@ -284,11 +559,90 @@ pub enum PrintJSON {
     },
 }
 
+/// The type of a PrintJSON message, without any of its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrintJSONKind {
+    ItemSend,
+    ItemCheat,
+    Hint,
+    Join,
+    Part,
+    Chat,
+    ServerChat,
+    Tutorial,
+    TagsChanged,
+    CommandResult,
+    AdminCommandResult,
+    Goal,
+    Release,
+    Collect,
+    Countdown,
+}
+
+impl PrintJSON {
+    pub fn kind(&self) -> PrintJSONKind {
+        match self {
+            PrintJSON::ItemSend { .. } => PrintJSONKind::ItemSend,
+            PrintJSON::ItemCheat { .. } => PrintJSONKind::ItemCheat,
+            PrintJSON::Hint { .. } => PrintJSONKind::Hint,
+            PrintJSON::Join { .. } => PrintJSONKind::Join,
+            PrintJSON::Part { .. } => PrintJSONKind::Part,
+            PrintJSON::Chat { .. } => PrintJSONKind::Chat,
+            PrintJSON::ServerChat { .. } => PrintJSONKind::ServerChat,
+            PrintJSON::Tutorial { .. } => PrintJSONKind::Tutorial,
+            PrintJSON::TagsChanged { .. } => PrintJSONKind::TagsChanged,
+            PrintJSON::CommandResult { .. } => PrintJSONKind::CommandResult,
+            PrintJSON::AdminCommandResult { .. } => PrintJSONKind::AdminCommandResult,
+            PrintJSON::Goal { .. } => PrintJSONKind::Goal,
+            PrintJSON::Release { .. } => PrintJSONKind::Release,
+            PrintJSON::Collect { .. } => PrintJSONKind::Collect,
+            PrintJSON::Countdown { .. } => PrintJSONKind::Countdown,
+        }
+    }
+
+    /// The message parts, which every kind of PrintJSON has.
+    pub fn data(&self) -> &[JSONMessagePart] {
+        match self {
+            PrintJSON::ItemSend { data, .. }
+            | PrintJSON::ItemCheat { data, .. }
+            | PrintJSON::Hint { data, .. }
+            | PrintJSON::Join { data, .. }
+            | PrintJSON::Part { data, .. }
+            | PrintJSON::Chat { data, .. }
+            | PrintJSON::ServerChat { data, .. }
+            | PrintJSON::Tutorial { data }
+            | PrintJSON::TagsChanged { data, .. }
+            | PrintJSON::CommandResult { data }
+            | PrintJSON::AdminCommandResult { data }
+            | PrintJSON::Goal { data, .. }
+            | PrintJSON::Release { data, .. }
+            | PrintJSON::Collect { data, .. }
+            | PrintJSON::Countdown { data, .. } => data,
+        }
+    }
+
+    /// The team of the player this message is about, for the kinds which
+    /// carry one.
+    pub fn team(&self) -> Option<i64> {
+        match self {
+            PrintJSON::ItemCheat { team, .. }
+            | PrintJSON::Join { team, .. }
+            | PrintJSON::Part { team, .. }
+            | PrintJSON::Chat { team, .. }
+            | PrintJSON::TagsChanged { team, .. }
+            | PrintJSON::Goal { team, .. }
+            | PrintJSON::Release { team, .. }
+            | PrintJSON::Collect { team, .. } => Some(*team),
+            _ => None,
+        }
+    }
+}
+
 /// Sent to clients to provide what is known as a 'data package' which contains
 /// information to enable a client to most easily communicate with the
 /// Archipelago server. Contents include things like location id to name
 /// mappings, among others; see Data Package Contents for more info.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPackage {
     /// The data package as a JSON object.
     pub data: DataPackageObject,
@@ -296,7 +650,7 @@ pub struct DataPackage {
 
 /// Sent to clients after a client requested this message be sent to them, more
 /// info in the Bounce package.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bounced {
     /// Optional. Game names this message is targeting
     #[serde(default)]
@@ -312,12 +666,12 @@ pub struct Bounced {
 
     /// The data in the Bounce package copied
     #[serde(default)]
-    data: serde_json::Value,
+    pub data: serde_json::Value,
 }
 
 /// Sent to clients if the server caught a problem with a packet. This only
 /// occurs for errors that are explicitly checked for.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvalidPacket {
     /// The PacketProblemType that was detected in the packet.
     pub r#type: PacketProblemType,
@@ -335,7 +689,7 @@ pub struct InvalidPacket {
 /// the future.
 ///
 /// Other packet types may be added in the future.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[non_exhaustive]
 pub enum PacketProblemType {
@@ -347,7 +701,7 @@ pub enum PacketProblemType {
 }
 
 /// Sent to clients as a response the a Get package.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Retrieved {
     /// A key-value collection containing all the values for the keys requested
     /// in the Get package.
@@ -361,7 +715,7 @@ pub struct Retrieved {
 }
 
 /// Sent to clients in response to a Set package if want_reply was set to true, or if the client has registered to receive updates for a certain key using the SetNotify package. SetReply packages are sent even if a Set package did not alter the value for the key.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetReply {
     /// The key that was updated.
     pub key: String,
@@ -374,10 +728,11 @@ pub struct SetReply {
 }
 
 /// Client -> Server messages
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "cmd")]
 pub enum ClientMessage {
     Connect(Connect),
+    ConnectUpdate(ConnectUpdate),
     Sync(SyncRequest),
     LocationChecks(LocationChecks),
     LocationScouts(LocationScouts),
@@ -391,7 +746,7 @@ pub enum ClientMessage {
 }
 
 /// Sent by the client to initiate a connection to an Archipelago game session.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Connect {
     /// If the game session requires a password, it should be passed here.
     pub password: Option<String>,
@@ -413,9 +768,7 @@ pub struct Connect {
     pub items_handling: ItemsHandlingFlags,
 
     /// Denotes special features or capabilities that the sender is capable of.
-    /// Tags.
-    /// TODO: switch back to pub tags: Vec<ClientTag>,
-    pub tags: Vec<String>,
+    pub tags: Vec<ClientTag>,
 
     /// If true, the Connect answer will contain slot_data
     pub slot_data: bool,
@@ -424,10 +777,146 @@ pub struct Connect {
 // Sent to server to request a ReceivedItems packet to synchronize items.
 pub type SyncRequest = ();
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Implements the set operations shared by the bit flag types, given the
+/// names of their flags.
+macro_rules! flags {
+    ($name:ident { $($flag:ident),+ $(,)? }) => {
+        impl $name {
+            /// No flags set.
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// Every known flag set.
+            pub const fn all() -> Self {
+                Self(0 $(| Self::$flag.0)+)
+            }
+
+            /// The flags as sent over the network.
+            pub const fn bits(&self) -> u8 {
+                self.0
+            }
+
+            /// Flags from their network representation, or None if any
+            /// unknown bits are set.
+            pub const fn from_bits(bits: u8) -> Option<Self> {
+                if bits & !Self::all().0 == 0 {
+                    Some(Self(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// Flags from their network representation, dropping unknown
+            /// bits.
+            pub const fn from_bits_truncate(bits: u8) -> Self {
+                Self(bits & Self::all().0)
+            }
+
+            pub const fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            /// Whether every flag in `other` is set.
+            pub const fn contains(&self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Whether any flag in `other` is set.
+            pub const fn intersects(&self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+
+            pub fn set(&mut self, other: Self, value: bool) {
+                if value {
+                    self.insert(other);
+                } else {
+                    self.remove(other);
+                }
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self::Output {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: Self) {
+                self.0 |= rhs.0;
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = Self;
+
+            fn bitand(self, rhs: Self) -> Self::Output {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl BitAndAssign for $name {
+            fn bitand_assign(&mut self, rhs: Self) {
+                self.0 &= rhs.0;
+            }
+        }
+
+        impl Not for $name {
+            type Output = Self;
+
+            /// The known flags which aren't set.
+            fn not(self) -> Self::Output {
+                Self::from_bits_truncate(!self.0)
+            }
+        }
+
+        impl std::fmt::Debug for $name {
+            /// Lists the names of the flags which are set, such as
+            /// `ItemsHandlingFlags(CAN_RECEIVE_ITEMS | HAS_LOCAL_ITEMS)`.
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let mut names = Vec::new();
+                $(
+                    if self.contains(Self::$flag) {
+                        names.push(stringify!($flag).to_string());
+                    }
+                )+
+                let unknown = self.0 & !Self::all().0;
+                if unknown != 0 {
+                    names.push(format!("{:#b}", unknown));
+                }
+
+                if names.is_empty() {
+                    write!(f, "{}(empty)", stringify!($name))
+                } else {
+                    write!(f, "{}({})", stringify!($name), names.join(" | "))
+                }
+            }
+        }
+    };
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ItemsHandlingFlags(u8);
 
+flags!(ItemsHandlingFlags {
+    CAN_RECEIVE_ITEMS,
+    HAS_LOCAL_ITEMS,
+    REQUEST_STARTING_INVENTORY,
+});
+
 impl ItemsHandlingFlags {
+    pub const NONE: Self = Self(0);
     pub const CAN_RECEIVE_ITEMS: Self = Self(0b1);
     pub const HAS_LOCAL_ITEMS: Self = Self(0b10);
     pub const REQUEST_STARTING_INVENTORY: Self = Self(0b100);
@@ -445,31 +934,20 @@ impl ItemsHandlingFlags {
     }
 }
 
-impl BitOr for ItemsHandlingFlags {
-    type Output = Self;
-
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Self(self.0 | rhs.0)
-    }
-}
-
 /// Update arguments from the Connect package, currently only updating tags and
 /// items_handling is supported.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectUpdate {
     /// Flags configuring which items should be sent by the server.
     pub items_handling: ItemsHandlingFlags,
 
     /// Denotes special features or capabilities that the sender is capable of.
-    /// Tags.
-    ///
-    /// TODO: switch back to pub tags: Vec<ClientTag>,
-    pub tags: Vec<String>,
+    pub tags: Vec<ClientTag>,
 }
 
 /// Sent to server to inform it of locations that the client has checked. Used
 /// to inform the server of new checks that are made, as well as to sync state.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationChecks {
     /// The ids of the locations checked by the client. May contain any number
     /// of checks, even ones sent before; duplicates do not cause issues with
@@ -489,7 +967,7 @@ pub struct LocationChecks {
 /// useful in cases where an item appears in the game world, such as 'ledge
 /// items' in A Link to the Past. To do this, set the create_as_hint parameter
 /// to a non-zero value.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocationScouts {
     /// The ids of the locations seen by the client. May contain any number of
     /// locations, even ones sent before; duplicates do not cause issues with
@@ -504,25 +982,45 @@ pub struct LocationScouts {
     pub create_as_hint: i64,
 }
 
+/// Typed values for `LocationScouts::create_as_hint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum CreateAsHint {
+    /// Only scout the locations.
+    No = 0,
+
+    /// Create hints for the locations and broadcast all of them.
+    Broadcast = 1,
+
+    /// Create hints for the locations, only broadcasting new hints.
+    BroadcastNew = 2,
+}
+
+impl From<CreateAsHint> for i64 {
+    fn from(value: CreateAsHint) -> Self {
+        value as i64
+    }
+}
+
 /// Sent to the server to update on the sender's status. Examples include
 /// readiness or goal completion. (Example: defeated Ganon in A Link to the
 /// Past)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusUpdate {
     /// One of Client States. Send as int. Follow the link for more information.
-    status: ClientStatus,
+    pub status: ClientStatus,
 }
 
 /// Basic chat command which sends text to the server to be distributed to other
 /// clients.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Say {
     /// Text to send to others.
-    text: String,
+    pub text: String,
 }
 
 /// Requests the data package from the server. Does not require client authentication.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetDataPackage {
     /// Optional. If specified, will only send back the specified data. Such as,
     /// ["Factorio"] -> Datapackage with only Factorio data.
@@ -532,7 +1030,7 @@ pub struct GetDataPackage {
 /// Send this message to the server, tell it which clients should receive the
 /// message and the server will forward the message to all those targets to
 /// which any one requirement applies.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bounce {
     /// Optional. Game names that should receive this message
     pub games: Vec<String>,
@@ -550,7 +1048,7 @@ pub struct Bounce {
 /// Used to request a single or multiple values from the server's data storage,
 /// see the Set package for how to write values to the data storage. A Get
 /// package will be answered with a Retrieved package.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Get {
     /// Keys to retrieve the values for.
     pub keys: Vec<String>,
@@ -576,7 +1074,7 @@ pub struct Get {
 /// shared across worlds or just saved for later. Values for keys in the data
 /// storage can be retrieved with a Get package, or monitored with a SetNotify
 /// package. Keys that start with _read_ cannot be set.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Set {
     /// The key to manipulate. Can never start with "_read".
     pub key: String,
@@ -600,8 +1098,8 @@ pub struct Set {
 /// operation to be applied, provided in the form of a string, as well as the
 /// value to be used for that operation, Example: {"operation": "add", "value":
 /// 12}
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "operation", rename_all = "snake_case")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "operation", content = "value", rename_all = "snake_case")]
 pub enum DataStorageOperation {
     /// Sets the current value of the key to value.
     Replace(serde_json::Value),
@@ -649,14 +1147,14 @@ pub enum DataStorageOperation {
 }
 
 /// Used to register your current session for receiving all SetReply packages of certain keys to allow your client to keep track of changes.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetNotify {
     /// Keys to receive all SetReply packages for.
     pub keys: Vec<String>,
 }
 
 // Appendix types
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkPlayer {
     pub team: i64,
     pub slot: i64,
@@ -664,24 +1162,48 @@ pub struct NetworkPlayer {
     pub name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The classification of an item. An item with none of these flags set is
+/// filler.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NetworkItemFlags(u8);
 
+flags!(NetworkItemFlags {
+    PROGRESSION,
+    USEFUL,
+    TRAP,
+});
+
 impl NetworkItemFlags {
+    pub const NONE: Self = Self(0);
+
+    /// The item may unlock logical advancement.
+    pub const PROGRESSION: Self = Self(0b1);
+
+    /// The item is useful, but not required for advancement.
+    pub const USEFUL: Self = Self(0b10);
+
+    /// The item is a trap.
+    pub const TRAP: Self = Self(0b100);
+
     pub fn is_progression(&self) -> bool {
-        self.0 & 0b1 != 0
+        self.contains(Self::PROGRESSION)
     }
 
     pub fn is_important(&self) -> bool {
-        self.0 & 0b10 != 0
+        self.contains(Self::USEFUL)
     }
 
     pub fn is_trap(&self) -> bool {
-        self.0 & 0b100 != 0
+        self.contains(Self::TRAP)
+    }
+
+    /// Whether the item has no other classification.
+    pub fn is_filler(&self) -> bool {
+        self.is_empty()
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkItem {
     pub item: i64,
     pub location: i64,
@@ -689,11 +1211,14 @@ pub struct NetworkItem {
     pub flags: NetworkItemFlags,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum JSONMessagePart {
     PlayerId {
         text: String,
+
+        // The slot is the text itself, and the server doesn't send this.
+        #[serde(default)]
         player: i64,
     },
     PlayerName {
@@ -730,7 +1255,24 @@ pub enum JSONMessagePart {
     },
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl JSONMessagePart {
+    /// The raw text of this part. For id parts, this is the id itself.
+    pub fn text(&self) -> &str {
+        match self {
+            JSONMessagePart::PlayerId { text, .. }
+            | JSONMessagePart::PlayerName { text }
+            | JSONMessagePart::ItemId { text, .. }
+            | JSONMessagePart::ItemName { text, .. }
+            | JSONMessagePart::LocationId { text, .. }
+            | JSONMessagePart::LocationName { text, .. }
+            | JSONMessagePart::EntranceName { text }
+            | JSONMessagePart::Color { text, .. }
+            | JSONMessagePart::Text { text } => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum JSONColor {
     Bold,
@@ -753,7 +1295,7 @@ pub enum JSONColor {
     WhiteBg,
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum ClientStatus {
     Unknown = 0,
@@ -763,7 +1305,7 @@ pub enum ClientStatus {
     Goal = 30,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct NetworkVersion {
     pub major: i64,
     pub minor: i64,
@@ -781,7 +1323,7 @@ impl serde::Serialize for NetworkVersion {
     }
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, Clone, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
 pub enum SlotType {
     Spectator = 0,
@@ -789,7 +1331,7 @@ pub enum SlotType {
     Group = 2,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkSlot {
     pub name: String,
     pub game: String,
@@ -797,7 +1339,7 @@ pub struct NetworkSlot {
     pub group_members: Vec<i64>, // Only populated if type == Group
 }
 
-#[derive(Debug, Hash, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum PermissionName {
     /// Dictates what is allowed when it comes to a player releasing their run.
@@ -836,7 +1378,7 @@ pub enum PermissionName {
     Remaining,
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
 #[serde(rename_all = "snake_case")]
 #[repr(u8)]
 pub enum Permission {
@@ -847,23 +1389,40 @@ pub enum Permission {
     AutoEnabled = 0b111,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hint {
-    receiving_player: i64,
-    finding_player: i64,
-    location: i64,
-    item: i64,
-    found: bool,
-    entrance: String,             // TODO: default to empty string
-    item_flags: NetworkItemFlags, // TODO: default to 0
+    pub receiving_player: i64,
+    pub finding_player: i64,
+    pub location: i64,
+    pub item: i64,
+    pub found: bool,
+    pub entrance: String,             // TODO: default to empty string
+    pub item_flags: NetworkItemFlags, // TODO: default to 0
+
+    /// Older servers don't send a status, which is treated as
+    /// [`HintStatus::Unspecified`].
+    #[serde(default)]
+    pub status: HintStatus,
+}
+
+/// The priority a receiving player gave a hint, or whether it was found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum HintStatus {
+    #[default]
+    Unspecified = 0,
+    NoPriority = 10,
+    Avoid = 20,
+    Priority = 30,
+    Found = 40,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPackageObject {
     pub games: HashMap<String, GameData>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameData {
     pub item_name_to_id: HashMap<String, i64>,
     pub location_name_to_id: HashMap<String, i64>,
@@ -871,31 +1430,169 @@ pub struct GameData {
     pub checksum: String,
 }
 
-/*
-#[derive(Debug, Serialize, Deserialize)]
+impl GameData {
+    /// Returns true if the game has an item with this ID. This scans every
+    /// item, so use [`crate::resolver::Resolver`] for repeated lookups.
+    pub fn contains_item_id(&self, id: i64) -> bool {
+        self.item_name_to_id.values().any(|item| *item == id)
+    }
+
+    /// Returns true if the game has a location with this ID. This scans every
+    /// location, so use [`crate::resolver::Resolver`] for repeated lookups.
+    pub fn contains_location_id(&self, id: i64) -> bool {
+        self.location_name_to_id
+            .values()
+            .any(|location| *location == id)
+    }
+
+    /// The lowest and highest item IDs of the game, or None if it has no
+    /// items.
+    pub fn item_id_range(&self) -> Option<RangeInclusive<i64>> {
+        id_range(self.item_name_to_id.values())
+    }
+
+    /// The lowest and highest location IDs of the game, or None if it has no
+    /// locations. Worlds usually allocate their IDs contiguously, but there
+    /// may be gaps inside the range.
+    pub fn location_id_range(&self) -> Option<RangeInclusive<i64>> {
+        id_range(self.location_name_to_id.values())
+    }
+
+    /// Returns the locations which don't belong to this game, in the order
+    /// given. The server silently ignores these in LocationChecks.
+    pub fn unknown_locations(&self, locations: &[i64]) -> Vec<i64> {
+        let known: std::collections::HashSet<i64> =
+            self.location_name_to_id.values().copied().collect();
+        locations
+            .iter()
+            .copied()
+            .filter(|location| !known.contains(location))
+            .collect()
+    }
+}
+
+fn id_range<'a>(ids: impl Iterator<Item = &'a i64>) -> Option<RangeInclusive<i64>> {
+    ids.fold(None, |range, &id| match range {
+        None => Some(id..=id),
+        Some(range) => Some(*range.start().min(&id)..=*range.end().max(&id)),
+    })
+}
+
+/// A tag sent in Connect and ConnectUpdate, denoting special features or
+/// capabilities of the client.
+///
+/// Tags are plain strings on the wire. The ones the server gives meaning to
+/// have their own variants, and anything else, such as a game's version tag,
+/// is kept as [`ClientTag::Other`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ClientTag {
+    /// Signifies that this client is a reference client.
     AP,
+
+    /// Client participates in the DeathLink mechanic.
     DeathLink,
+
+    /// Client observes a slot without playing it, and needs no game.
     Tracker,
+
+    /// Client only sends and receives chat, and needs no game.
     TextOnly,
-    Other(String), // TODO: ensure this serializes as expected
+
+    /// Client is a hint game, which needs no game and is limited to hints.
+    HintGame,
+
+    /// Client does not want to receive text messages.
+    NoText,
+
+    Other(String),
 }
 
-impl Into<ClientTag> for &str {
-    fn into(self) -> ClientTag {
+impl ClientTag {
+    pub fn as_str(&self) -> &str {
         match self {
+            ClientTag::AP => "AP",
+            ClientTag::DeathLink => "DeathLink",
+            ClientTag::Tracker => "Tracker",
+            ClientTag::TextOnly => "TextOnly",
+            ClientTag::HintGame => "HintGame",
+            ClientTag::NoText => "NoText",
+            ClientTag::Other(tag) => tag,
+        }
+    }
+
+    /// Whether the tag allows connecting without a game.
+    pub fn is_gameless(&self) -> bool {
+        matches!(
+            self,
+            ClientTag::Tracker | ClientTag::TextOnly | ClientTag::HintGame
+        )
+    }
+}
+
+impl From<&str> for ClientTag {
+    fn from(tag: &str) -> Self {
+        match tag {
             "AP" => ClientTag::AP,
             "DeathLink" => ClientTag::DeathLink,
             "Tracker" => ClientTag::Tracker,
             "TextOnly" => ClientTag::TextOnly,
-            _ => ClientTag::Other(self.to_string()),
+            "HintGame" => ClientTag::HintGame,
+            "NoText" => ClientTag::NoText,
+            _ => ClientTag::Other(tag.to_string()),
+        }
+    }
+}
+
+impl From<String> for ClientTag {
+    fn from(tag: String) -> Self {
+        match ClientTag::from(tag.as_str()) {
+            ClientTag::Other(_) => ClientTag::Other(tag),
+            known => known,
         }
     }
 }
- */
 
+impl std::fmt::Display for ClientTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for ClientTag {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ClientTag {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Serialize for ClientTag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ClientTag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(ClientTag::from)
+    }
+}
+
+/// The data of a DeathLink Bounce, sent to every client with the DeathLink
+/// tag when a player dies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DeathLink {
+    /// Unix time of the death, used to tell deaths apart.
     pub time: f64,
+
+    /// Optional. Text explaining the death, such as "Player was eaten".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cause: Option<String>,
+
+    /// The name of the player who died.
     pub source: String,
 }
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+/// How a [`crate::client::Client`] reconnects after its connection drops.
+/// Reconnecting is off unless a policy is set with
+/// [`crate::client::ConnectOptions::reconnect`].
+///
+/// Attempts are spaced out with exponential backoff, starting at
+/// `initial_delay` and doubling up to `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    max_attempts: Option<u32>,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Retries forever, waiting 1 second before the first attempt and up to
+    /// a minute between later ones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up after this many failed attempts in a row.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Whether the given attempt, counting from 1, should be made.
+    pub fn allows(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt <= max)
+    }
+
+    /// How long to wait before the given attempt, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
@@ -0,0 +1,346 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::client::Client;
+use crate::protocol::{
+    JSONColor, JSONMessagePart, NetworkItemFlags, NetworkPlayer, NetworkSlot, PrintJSON,
+    PrintJSONKind,
+};
+use crate::resolver::Resolver;
+
+/// Translates the fixed strings the server puts in PrintJSON messages, such as
+/// " found their " or " sent ", so clients can present messages in another
+/// language.
+///
+/// Only plain text parts are passed through the localizer; player, item,
+/// location and entrance names are left intact.
+pub trait Localizer {
+    /// Returns the translation of `text` in a message of the given kind, or
+    /// None to keep the original.
+    fn translate(&self, kind: PrintJSONKind, text: &str) -> Option<String>;
+}
+
+impl<F> Localizer for F
+where
+    F: Fn(PrintJSONKind, &str) -> Option<String>,
+{
+    fn translate(&self, kind: PrintJSONKind, text: &str) -> Option<String> {
+        self(kind, text)
+    }
+}
+
+/// How a kind of name is styled when rendered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    /// The color to use, for renderers which support colors.
+    pub color: Option<JSONColor>,
+
+    /// Text placed before the name, such as an emoji.
+    pub prefix: String,
+
+    /// Text placed after the name.
+    pub suffix: String,
+}
+
+impl Style {
+    pub fn color(color: JSONColor) -> Self {
+        Self {
+            color: Some(color),
+            ..Default::default()
+        }
+    }
+}
+
+/// Styles used by renderers for items, players and locations. The default
+/// approximates the palette of the official clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleSheet {
+    pub progression_item: Style,
+    pub useful_item: Style,
+    pub trap_item: Style,
+    pub filler_item: Style,
+    pub player: Style,
+    pub location: Style,
+    pub entrance: Style,
+}
+
+impl Default for StyleSheet {
+    fn default() -> Self {
+        Self {
+            progression_item: Style::color(JSONColor::Magenta),
+            useful_item: Style::color(JSONColor::Blue),
+            trap_item: Style::color(JSONColor::Red),
+            filler_item: Style::color(JSONColor::Cyan),
+            player: Style::color(JSONColor::Yellow),
+            location: Style::color(JSONColor::Green),
+            entrance: Style::color(JSONColor::Blue),
+        }
+    }
+}
+
+impl StyleSheet {
+    /// The style for an item with the given flags. Progression takes priority
+    /// over useful, which takes priority over trap.
+    pub fn item(&self, flags: NetworkItemFlags) -> &Style {
+        if flags.is_progression() {
+            &self.progression_item
+        } else if flags.is_important() {
+            &self.useful_item
+        } else if flags.is_trap() {
+            &self.trap_item
+        } else {
+            &self.filler_item
+        }
+    }
+
+    /// The style for a message part, if it is a name.
+    pub fn part(&self, part: &JSONMessagePart) -> Option<&Style> {
+        match part {
+            JSONMessagePart::PlayerId { .. } | JSONMessagePart::PlayerName { .. } => {
+                Some(&self.player)
+            }
+            JSONMessagePart::ItemId { flags, .. } | JSONMessagePart::ItemName { flags, .. } => {
+                Some(self.item(*flags))
+            }
+            JSONMessagePart::LocationId { .. } | JSONMessagePart::LocationName { .. } => {
+                Some(&self.location)
+            }
+            JSONMessagePart::EntranceName { .. } => Some(&self.entrance),
+            JSONMessagePart::Color { .. } | JSONMessagePart::Text { .. } => None,
+        }
+    }
+}
+
+/// What a [`Renderer`] needs to name ItemId, LocationId and PlayerId parts,
+/// which carry IDs rather than names. Parts which can't be named are rendered
+/// as their ID.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Names<'a> {
+    /// Names items and locations, if the data package was fetched.
+    pub resolver: Option<&'a Resolver>,
+
+    /// Our team, as player IDs are only unique within a team.
+    pub team: i64,
+
+    /// Names players by their alias.
+    pub players: &'a [NetworkPlayer],
+
+    /// The game of each slot, which item and location
+    /// IDs are looked up in.
+    pub slot_info: Option<&'a HashMap<i64, NetworkSlot>>,
+}
+
+impl<'a> Names<'a> {
+    fn player(&self, slot: i64) -> Option<&'a str> {
+        self.players
+            .iter()
+            .find(|player| player.team == self.team && player.slot == slot)
+            .map(|player| player.alias.as_str())
+    }
+
+    fn game(&self, slot: i64) -> Option<&'a str> {
+        self.slot_info?.get(&slot).map(|slot| slot.game.as_str())
+    }
+
+    /// The name for an ID part, or None if it isn't an ID part or can't be
+    /// named. With a resolver, items and locations of games missing from the
+    /// DataPackage are named as unknown, see
+    /// [`Resolver::item_name_or_unknown`].
+    fn name(&self, part: &JSONMessagePart) -> Option<Cow<'a, str>> {
+        match part {
+            JSONMessagePart::PlayerId { text, .. } => {
+                self.player(text.parse().ok()?).map(Cow::Borrowed)
+            }
+            JSONMessagePart::ItemId { text, player, .. } => {
+                let game = self.game(*player).unwrap_or_default();
+                Some(
+                    self.resolver?
+                        .item_name_or_unknown(game, text.parse().ok()?),
+                )
+            }
+            JSONMessagePart::LocationId { text, player } => {
+                let game = self.game(*player).unwrap_or_default();
+                Some(
+                    self.resolver?
+                        .location_name_or_unknown(game, text.parse().ok()?),
+                )
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Client {
+    /// The names a [`Renderer`] needs for this client's room.
+    pub fn names(&self) -> Names<'_> {
+        let connected = self.get_connected();
+        Names {
+            resolver: self.resolver(),
+            team: connected.team,
+            players: &connected.players,
+            slot_info: Some(&connected.slot_info),
+        }
+    }
+}
+
+/// Renders PrintJSON messages to text.
+#[derive(Default)]
+pub struct Renderer {
+    localizer: Option<Box<dyn Localizer + Send + Sync>>,
+    style_sheet: StyleSheet,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the localizer used to translate fixed strings.
+    pub fn with_localizer(mut self, localizer: impl Localizer + Send + Sync + 'static) -> Self {
+        self.localizer = Some(Box::new(localizer));
+        self
+    }
+
+    /// Sets the styles used for names.
+    pub fn with_style_sheet(mut self, style_sheet: StyleSheet) -> Self {
+        self.style_sheet = style_sheet;
+        self
+    }
+
+    pub fn style_sheet(&self) -> &StyleSheet {
+        &self.style_sheet
+    }
+
+    /// Renders a message as plain text. Colors are ignored, but style
+    /// prefixes and suffixes are applied. ID parts are rendered as the ID,
+    /// see [`Renderer::render_with`] to name them.
+    pub fn render(&self, message: &PrintJSON) -> String {
+        self.render_parts(message.kind(), message.data())
+    }
+
+    /// Renders message parts as plain text.
+    pub fn render_parts(&self, kind: PrintJSONKind, parts: &[JSONMessagePart]) -> String {
+        self.render_parts_with(kind, parts, &Names::default())
+    }
+
+    /// Renders a message as plain text, naming ID parts with `names`.
+    pub fn render_with(&self, message: &PrintJSON, names: &Names) -> String {
+        self.render_parts_with(message.kind(), message.data(), names)
+    }
+
+    /// Renders message parts as plain text, naming ID parts with `names`.
+    pub fn render_parts_with(
+        &self,
+        kind: PrintJSONKind,
+        parts: &[JSONMessagePart],
+        names: &Names,
+    ) -> String {
+        parts
+            .iter()
+            .map(|part| self.render_part(kind, part, names))
+            .collect()
+    }
+
+    /// Renders a message with ANSI escape codes for the colors of the style
+    /// sheet and Color parts, naming ID parts with `names`.
+    #[cfg(feature = "ansi")]
+    pub fn render_ansi(&self, message: &PrintJSON, names: &Names) -> String {
+        self.render_parts_ansi(message.kind(), message.data(), names)
+    }
+
+    /// Renders message parts with ANSI escape codes, see
+    /// [`Renderer::render_ansi`].
+    #[cfg(feature = "ansi")]
+    pub fn render_parts_ansi(
+        &self,
+        kind: PrintJSONKind,
+        parts: &[JSONMessagePart],
+        names: &Names,
+    ) -> String {
+        let mut rendered = String::new();
+        for part in parts {
+            let text = self.render_part(kind, part, names);
+            let color = match part {
+                JSONMessagePart::Color { color, .. } => Some(*color),
+                part => self.style_sheet.part(part).and_then(|style| style.color),
+            };
+
+            match color {
+                Some(color) => {
+                    rendered.push_str(&format!("\x1b[{}m{}\x1b[0m", ansi_code(color), text))
+                }
+                None => rendered.push_str(&text),
+            }
+        }
+        rendered
+    }
+
+    fn render_part<'a>(
+        &self,
+        kind: PrintJSONKind,
+        part: &'a JSONMessagePart,
+        names: &Names<'a>,
+    ) -> Cow<'a, str> {
+        match part {
+            JSONMessagePart::Text { text } | JSONMessagePart::Color { text, .. } => {
+                self.localize(kind, text)
+            }
+            part => {
+                let name = names
+                    .name(part)
+                    .unwrap_or_else(|| Cow::Borrowed(part.text()));
+                match self.style_sheet.part(part) {
+                    Some(style) if !style.prefix.is_empty() || !style.suffix.is_empty() => {
+                        Cow::Owned(format!("{}{}{}", style.prefix, name, style.suffix))
+                    }
+                    _ => name,
+                }
+            }
+        }
+    }
+
+    fn localize<'a>(&self, kind: PrintJSONKind, text: &'a str) -> Cow<'a, str> {
+        match self
+            .localizer
+            .as_ref()
+            .and_then(|localizer| localizer.translate(kind, text))
+        {
+            Some(translated) => Cow::Owned(translated),
+            None => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl std::fmt::Debug for Renderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Renderer")
+            .field("has_localizer", &self.localizer.is_some())
+            .field("style_sheet", &self.style_sheet)
+            .finish()
+    }
+}
+
+/// The SGR parameter for a color.
+#[cfg(feature = "ansi")]
+fn ansi_code(color: JSONColor) -> u8 {
+    match color {
+        JSONColor::Bold => 1,
+        JSONColor::Underline => 4,
+        JSONColor::Black => 30,
+        JSONColor::Red => 31,
+        JSONColor::Green => 32,
+        JSONColor::Yellow => 33,
+        JSONColor::Blue => 34,
+        JSONColor::Magenta => 35,
+        JSONColor::Cyan => 36,
+        JSONColor::White => 37,
+        JSONColor::BlackBg => 40,
+        JSONColor::RedBg => 41,
+        JSONColor::GreenBg => 42,
+        JSONColor::YellowBg => 43,
+        JSONColor::BlueBg => 44,
+        JSONColor::MagentaBg => 45,
+        JSONColor::CyanBg => 46,
+        JSONColor::WhiteBg => 47,
+    }
+}
@@ -0,0 +1,256 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
+use crate::protocol::{DataPackageObject, GameData, NetworkItem, NetworkSlot};
+
+/// Translates between item and location names and their IDs, for every game
+/// in a DataPackage.
+///
+/// Names are interned, so each one is stored once no matter how many lookup
+/// tables refer to it. Resolvers built with the same [`NamePool`] also share
+/// names with each other.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    games: HashMap<Arc<str>, GameNames>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GameNames {
+    item_ids: HashMap<Arc<str>, i64>,
+    location_ids: HashMap<Arc<str>, i64>,
+
+    // Most clients never render names, so the ID to name tables are only
+    // built the first time a game's names are looked up.
+    item_names: OnceLock<HashMap<i64, Arc<str>>>,
+    location_names: OnceLock<HashMap<i64, Arc<str>>>,
+}
+
+/// Interned game, item and location names, shared between [`Resolver`]s.
+///
+/// Tools holding many rooms, such as bots or web trackers, usually see the
+/// same games over and over, so building every resolver from one pool keeps
+/// a single copy of each name.
+#[derive(Debug, Clone, Default)]
+pub struct NamePool {
+    names: HashSet<Arc<str>>,
+}
+
+impl NamePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pooled copy of a name, adding it if needed.
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(name) = self.names.get(name) {
+            return name.clone();
+        }
+
+        let name: Arc<str> = Arc::from(name);
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// The number of distinct names in the pool.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Drops names which are no longer used by any resolver.
+    pub fn prune(&mut self) {
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+    }
+}
+
+/// The names of a [`NetworkItem`]'s item, location and player, from
+/// [`Resolver::received_item`] or [`Resolver::scouted_item`]. Each is `None`
+/// if it isn't in the DataPackage or slot info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemNames<'a> {
+    pub item: Option<&'a str>,
+    pub location: Option<&'a str>,
+
+    /// The name of the slot in [`NetworkItem::player`].
+    pub player: Option<&'a str>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ResolveError {
+    #[error("unknown game: {0}")]
+    UnknownGame(String),
+    #[error("unknown location group {group:?} in {game}")]
+    UnknownGroup { game: String, group: String },
+    #[error("unknown locations in {game}: {names:?}")]
+    UnknownLocations { game: String, names: Vec<String> },
+}
+
+impl Resolver {
+    pub fn new(data_package: &DataPackageObject) -> Self {
+        Self::with_pool(data_package, &mut NamePool::new())
+    }
+
+    /// Builds a resolver whose names are shared through `pool`.
+    pub fn with_pool(data_package: &DataPackageObject, pool: &mut NamePool) -> Self {
+        let games = data_package
+            .games
+            .iter()
+            .map(|(game, data)| {
+                let names = GameNames {
+                    item_ids: intern_names(pool, &data.item_name_to_id),
+                    location_ids: intern_names(pool, &data.location_name_to_id),
+                    ..Default::default()
+                };
+                (pool.intern(game), names)
+            })
+            .collect();
+
+        Self { games }
+    }
+
+    /// Adds or replaces the names of one game, such as one fetched with
+    /// [`crate::client::Client::load_game_data`].
+    pub fn insert_game(&mut self, game: &str, data: &GameData) {
+        let mut pool = NamePool::new();
+        let names = GameNames {
+            item_ids: intern_names(&mut pool, &data.item_name_to_id),
+            location_ids: intern_names(&mut pool, &data.location_name_to_id),
+            ..Default::default()
+        };
+        self.games.insert(Arc::from(game), names);
+    }
+
+    /// Whether the DataPackage included the given game.
+    pub fn has_game(&self, game: &str) -> bool {
+        self.games.contains_key(game)
+    }
+
+    pub fn item_id(&self, game: &str, name: &str) -> Option<i64> {
+        self.games.get(game)?.item_ids.get(name).copied()
+    }
+
+    pub fn location_id(&self, game: &str, name: &str) -> Option<i64> {
+        self.games.get(game)?.location_ids.get(name).copied()
+    }
+
+    pub fn item_name(&self, game: &str, id: i64) -> Option<&str> {
+        let names = self.games.get(game)?;
+        names
+            .item_names
+            .get_or_init(|| invert(&names.item_ids))
+            .get(&id)
+            .map(AsRef::as_ref)
+    }
+
+    pub fn location_name(&self, game: &str, id: i64) -> Option<&str> {
+        let names = self.games.get(game)?;
+        names
+            .location_names
+            .get_or_init(|| invert(&names.location_ids))
+            .get(&id)
+            .map(AsRef::as_ref)
+    }
+
+    /// Names an item we received, such as from a ReceivedItems. The item is
+    /// from `game`, our own game, while the location belongs to the slot in
+    /// [`NetworkItem::player`] and is looked up in that slot's game.
+    pub fn received_item<'a>(
+        &'a self,
+        item: &NetworkItem,
+        game: &str,
+        slot_info: &'a HashMap<i64, NetworkSlot>,
+    ) -> ItemNames<'a> {
+        let slot = slot_info.get(&item.player);
+        ItemNames {
+            item: self.item_name(game, item.item),
+            location: slot.and_then(|slot| self.location_name(&slot.game, item.location)),
+            player: slot.map(|slot| slot.name.as_str()),
+        }
+    }
+
+    /// Names an item at one of our locations, such as from a LocationInfo.
+    /// The location is from `game`, our own game, while the item belongs to
+    /// the slot in [`NetworkItem::player`] and is looked up in that slot's
+    /// game.
+    pub fn scouted_item<'a>(
+        &'a self,
+        item: &NetworkItem,
+        game: &str,
+        slot_info: &'a HashMap<i64, NetworkSlot>,
+    ) -> ItemNames<'a> {
+        let slot = slot_info.get(&item.player);
+        ItemNames {
+            item: slot.and_then(|slot| self.item_name(&slot.game, item.item)),
+            location: self.location_name(game, item.location),
+            player: slot.map(|slot| slot.name.as_str()),
+        }
+    }
+
+    /// Like [`Resolver::item_name`], falling back to "Unknown Item (id)" for
+    /// items which can't be named, such as when the game is missing from the
+    /// DataPackage.
+    pub fn item_name_or_unknown(&self, game: &str, id: i64) -> Cow<'_, str> {
+        match self.item_name(game, id) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("Unknown Item ({})", id)),
+        }
+    }
+
+    /// Like [`Resolver::location_name`], falling back to "Unknown Location
+    /// (id)".
+    pub fn location_name_or_unknown(&self, game: &str, id: i64) -> Cow<'_, str> {
+        match self.location_name(game, id) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("Unknown Location ({})", id)),
+        }
+    }
+
+    /// Resolves a list of location names, such as the members of a location
+    /// group, to IDs. Fails if any of the names are unknown, listing all of
+    /// them.
+    pub fn location_ids<'a>(
+        &self,
+        game: &str,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<i64>, ResolveError> {
+        let locations = &self
+            .games
+            .get(game)
+            .ok_or_else(|| ResolveError::UnknownGame(game.to_string()))?
+            .location_ids;
+
+        let mut ids = Vec::new();
+        let mut unknown = Vec::new();
+        for name in names {
+            match locations.get(name) {
+                Some(id) => ids.push(*id),
+                None => unknown.push(name.to_string()),
+            }
+        }
+
+        if !unknown.is_empty() {
+            return Err(ResolveError::UnknownLocations {
+                game: game.to_string(),
+                names: unknown,
+            });
+        }
+
+        Ok(ids)
+    }
+}
+
+/// Interns every name in a name to ID table.
+fn intern_names(pool: &mut NamePool, names: &HashMap<String, i64>) -> HashMap<Arc<str>, i64> {
+    names
+        .iter()
+        .map(|(name, id)| (pool.intern(name), *id))
+        .collect()
+}
+
+fn invert(ids: &HashMap<Arc<str>, i64>) -> HashMap<i64, Arc<str>> {
+    ids.iter().map(|(name, id)| (*id, name.clone())).collect()
+}
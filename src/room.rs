@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::location_set::LocationSet;
+use crate::protocol::{
+    Connected, NetworkPlayer, Permission, PermissionName, PrintJSON, RoomInfo, RoomUpdate,
+};
+
+/// The parts of the room's state which can change during a session, kept up
+/// to date by applying RoomUpdate packets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomState {
+    team: i64,
+    slot: i64,
+    players: Vec<NetworkPlayer>,
+    checked_locations: LocationSet,
+
+    // Only locations we saw being checked are attributed here; the rest of
+    // checked_locations were checked before we connected.
+    checked_by: HashMap<i64, CheckedBy>,
+    sent_checks: LocationSet,
+    missing_locations: LocationSet,
+    hint_points: i64,
+    hint_cost: i64,
+    permissions: HashMap<PermissionName, Permission>,
+}
+
+/// Which client checked a location in our world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckedBy {
+    /// This client checked the location.
+    Us,
+
+    /// Another client connected to the same slot, such as a co-op partner,
+    /// checked the location.
+    Partner,
+
+    /// The location was already checked when we connected.
+    Unknown,
+}
+
+/// A player whose alias changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerRename {
+    pub team: i64,
+    pub slot: i64,
+    pub old_alias: String,
+    pub new_alias: String,
+}
+
+/// What changed when a RoomUpdate was applied.
+#[derive(Debug, Clone, Default)]
+pub struct RoomDelta {
+    /// Locations which were checked for the first time.
+    pub new_checks: Vec<i64>,
+
+    /// Permissions which changed, with their new value.
+    pub permission_changes: Vec<(PermissionName, Permission)>,
+
+    /// Players whose alias changed.
+    pub renamed_players: Vec<PlayerRename>,
+
+    /// Players which were not known before.
+    pub new_players: Vec<NetworkPlayer>,
+
+    /// The new number of hint points, if it changed.
+    pub hint_points: Option<i64>,
+
+    /// The new cost of a hint in points, if it changed.
+    pub hint_cost: Option<i64>,
+}
+
+impl RoomDelta {
+    /// Returns true if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.new_checks.is_empty()
+            && self.permission_changes.is_empty()
+            && self.renamed_players.is_empty()
+            && self.new_players.is_empty()
+            && self.hint_points.is_none()
+            && self.hint_cost.is_none()
+    }
+}
+
+/// Converts a hint cost, a percentage of a world's locations, into the number
+/// of hint points a hint costs, the same way as the server does: rounded down,
+/// but never less than one point unless hints are free.
+pub fn hint_cost_absolute(hint_cost: i64, total_locations: usize) -> i64 {
+    if hint_cost <= 0 {
+        return 0;
+    }
+
+    (hint_cost * total_locations as i64 / 100).max(1)
+}
+
+impl RoomState {
+    pub fn new(room_info: &RoomInfo, connected: &Connected) -> Self {
+        Self {
+            team: connected.team,
+            slot: connected.slot,
+            players: connected.players.clone(),
+            checked_locations: connected.checked_locations.iter().copied().collect(),
+            checked_by: HashMap::new(),
+            sent_checks: LocationSet::new(),
+            missing_locations: connected.missing_locations.iter().copied().collect(),
+            hint_points: connected.hint_points,
+            hint_cost: room_info.hint_cost,
+            permissions: room_info.permissions.clone(),
+        }
+    }
+
+    pub fn players(&self) -> &[NetworkPlayer] {
+        &self.players
+    }
+
+    pub fn player(&self, team: i64, slot: i64) -> Option<&NetworkPlayer> {
+        self.players
+            .iter()
+            .find(|player| player.team == team && player.slot == slot)
+    }
+
+    /// The name a player should be shown as: their alias if they set one
+    /// with `!alias`, which the server reports in place of the name
+    /// otherwise.
+    pub fn display_name(&self, team: i64, slot: i64) -> Option<&str> {
+        self.player(team, slot).map(|player| player.alias.as_str())
+    }
+
+    /// Our own display name.
+    pub fn own_display_name(&self) -> Option<&str> {
+        self.display_name(self.team, self.slot)
+    }
+
+    pub fn checked_locations(&self) -> &LocationSet {
+        &self.checked_locations
+    }
+
+    /// Returns which client checked a location in our world, if it has been
+    /// checked.
+    pub fn checked_by(&self, location: i64) -> Option<CheckedBy> {
+        self.checked_by.get(&location).copied().or_else(|| {
+            self.checked_locations
+                .contains(location)
+                .then_some(CheckedBy::Unknown)
+        })
+    }
+
+    /// Records locations this client is sending in LocationChecks, so they
+    /// can be told apart from locations checked by co-op partners.
+    pub fn record_sent_checks(&mut self, locations: &[i64]) {
+        self.sent_checks.extend(locations.iter().copied());
+    }
+
+    /// Marks locations as checked by us before the server confirms them,
+    /// mirroring the optimistic checks of [`crate::client::Client`]. The
+    /// returned delta contains them as a synthetic confirmation, and the
+    /// server's later RoomUpdate won't report them again.
+    pub fn mark_checked(&mut self, locations: &[i64]) -> RoomDelta {
+        let mut delta = RoomDelta::default();
+        for &location in locations {
+            if self.checked_locations.insert(location) {
+                self.missing_locations.remove(location);
+                self.checked_by.insert(location, CheckedBy::Us);
+                delta.new_checks.push(location);
+            }
+        }
+
+        delta
+    }
+
+    /// Undoes [`RoomState::mark_checked`] for checks the client rolled back
+    /// because the server never confirmed them, see
+    /// [`crate::events::Event::ChecksRolledBack`].
+    pub fn roll_back_checks(&mut self, locations: &[i64]) {
+        for &location in locations {
+            if self.checked_locations.remove(location) {
+                self.missing_locations.insert(location);
+                self.checked_by.remove(&location);
+            }
+        }
+    }
+
+    /// Uses ItemSend messages to attribute checks in our world before the
+    /// matching RoomUpdate arrives.
+    pub fn apply_print_json(&mut self, message: &PrintJSON) {
+        if let PrintJSON::ItemSend { item, .. } = message {
+            if item.player == self.slot {
+                let location = item.location;
+                let by = self.attribute(location);
+                self.checked_by.entry(location).or_insert(by);
+            }
+        }
+    }
+
+    fn attribute(&self, location: i64) -> CheckedBy {
+        if self.sent_checks.contains(location) {
+            CheckedBy::Us
+        } else {
+            CheckedBy::Partner
+        }
+    }
+
+    pub fn missing_locations(&self) -> &LocationSet {
+        &self.missing_locations
+    }
+
+    pub fn hint_points(&self) -> i64 {
+        self.hint_points
+    }
+
+    /// The percentage of our total locations a hint costs.
+    pub fn hint_cost(&self) -> i64 {
+        self.hint_cost
+    }
+
+    /// The number of locations in our world, checked or not.
+    pub fn total_locations(&self) -> usize {
+        self.checked_locations.len() + self.missing_locations.len()
+    }
+
+    /// The number of hint points a hint costs. See [`hint_cost_absolute`].
+    pub fn hint_cost_absolute(&self) -> i64 {
+        hint_cost_absolute(self.hint_cost, self.total_locations())
+    }
+
+    pub fn permissions(&self) -> &HashMap<PermissionName, Permission> {
+        &self.permissions
+    }
+
+    /// Applies a RoomUpdate, returning exactly what changed.
+    pub fn apply_room_update(&mut self, update: RoomUpdate) -> RoomDelta {
+        let mut delta = RoomDelta::default();
+
+        if let Some(checked) = update.checked_locations {
+            for location in checked {
+                if self.checked_locations.insert(location) {
+                    self.missing_locations.remove(location);
+                    let by = self.attribute(location);
+                    self.checked_by.entry(location).or_insert(by);
+                    delta.new_checks.push(location);
+                }
+            }
+        }
+
+        if let Some(permissions) = update.permissions {
+            for (name, permission) in permissions {
+                if self.permissions.insert(name, permission) != Some(permission) {
+                    delta.permission_changes.push((name, permission));
+                }
+            }
+        }
+
+        if let Some(players) = update.players {
+            for player in &players {
+                match self
+                    .players
+                    .iter()
+                    .find(|old| old.team == player.team && old.slot == player.slot)
+                {
+                    Some(old) if old.alias != player.alias => {
+                        delta.renamed_players.push(PlayerRename {
+                            team: player.team,
+                            slot: player.slot,
+                            old_alias: old.alias.clone(),
+                            new_alias: player.alias.clone(),
+                        })
+                    }
+                    Some(_) => {}
+                    None => delta.new_players.push(player.clone()),
+                }
+            }
+
+            self.players = players;
+        }
+
+        if let Some(hint_cost) = update.hint_cost {
+            let old = self.hint_cost_absolute();
+            self.hint_cost = hint_cost;
+            if self.hint_cost_absolute() != old {
+                delta.hint_cost = Some(self.hint_cost_absolute());
+            }
+        }
+
+        if let Some(hint_points) = update.hint_points {
+            if hint_points != self.hint_points {
+                self.hint_points = hint_points;
+                delta.hint_points = Some(hint_points);
+            }
+        }
+
+        delta
+    }
+}
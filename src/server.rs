@@ -0,0 +1,539 @@
+//! Building blocks for the server end of the protocol, for proxies, relays
+//! and custom multiworld hosts.
+//!
+//! A [`Server`] accepts websocket connections and answers the RoomInfo and
+//! Connect handshake for the slots in its [`ServerConfig`]. Every message a
+//! connected client sends afterwards is passed to a [`ServerHandler`] along
+//! with the [`ServerSession`] it came from, except GetDataPackage, which is
+//! always answered from the config. What the rest mean, such as which items
+//! a location check sends, is up to the handler.
+//!
+//! ```no_run
+//! # async fn run() -> std::io::Result<()> {
+//! use archipelago::protocol::{ClientMessage, PrintJSON, ServerMessage};
+//! use archipelago::server::{Server, ServerConfig, ServerHandler, ServerSession, Slot};
+//!
+//! struct Echo;
+//!
+//! impl ServerHandler for Echo {
+//!     async fn on_message(
+//!         &self,
+//!         session: &ServerSession,
+//!         message: ClientMessage,
+//!     ) -> archipelago::Result<()> {
+//!         if let ClientMessage::Say(say) = message {
+//!             session.send(vec![ServerMessage::PrintJSON(PrintJSON::ServerChat {
+//!                 data: Vec::new(),
+//!                 message: say.text,
+//!             })])?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let config = ServerConfig::new("seed").slot(Slot::new(1, "Player1", "A Game"));
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:38281").await?;
+//! Server::new(config, Echo).serve(listener).await
+//! # }
+//! ```
+//!
+//! Requires the `server` feature.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tungstenite::Message;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::SUPPORTED_VERSION;
+use crate::error::{Error, Result};
+use crate::protocol::{
+    AnonymousServerMessage, ClientMessage, ClientTag, Connect, Connected, ConnectionRefused,
+    ConnectionRefusedError, DataPackage, DataPackageObject, InvalidPacket, NetworkPlayer,
+    NetworkSlot, PacketProblemType, Permission, PermissionName, RoomInfo, ServerMessage, SlotType,
+};
+
+/// A slot clients can connect to.
+#[derive(Debug, Clone)]
+pub struct Slot {
+    pub team: i64,
+    pub slot: i64,
+    pub name: String,
+    pub game: String,
+
+    /// Every location in the slot's world. Those not checked are sent as
+    /// missing.
+    pub locations: Vec<i64>,
+    pub checked_locations: Vec<i64>,
+
+    /// Sent to clients which ask for slot data.
+    pub slot_data: HashMap<String, serde_json::Value>,
+}
+
+impl Slot {
+    /// A slot on team 0 without any locations or slot data.
+    pub fn new(slot: i64, name: impl Into<String>, game: impl Into<String>) -> Self {
+        Self {
+            team: 0,
+            slot,
+            name: name.into(),
+            game: game.into(),
+            locations: Vec::new(),
+            checked_locations: Vec::new(),
+            slot_data: HashMap::new(),
+        }
+    }
+
+    pub fn team(mut self, team: i64) -> Self {
+        self.team = team;
+        self
+    }
+
+    pub fn locations(mut self, locations: Vec<i64>) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    pub fn checked_locations(mut self, checked: Vec<i64>) -> Self {
+        self.checked_locations = checked;
+        self
+    }
+
+    pub fn slot_data(mut self, slot_data: HashMap<String, serde_json::Value>) -> Self {
+        self.slot_data = slot_data;
+        self
+    }
+}
+
+/// The room a [`Server`] hosts.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    seed_name: String,
+    slots: Vec<Slot>,
+    password: Option<String>,
+    data_package: DataPackageObject,
+    hint_cost: i64,
+    location_check_points: i64,
+}
+
+impl ServerConfig {
+    pub fn new(seed_name: impl Into<String>) -> Self {
+        Self {
+            seed_name: seed_name.into(),
+            slots: Vec::new(),
+            password: None,
+            data_package: DataPackageObject {
+                games: HashMap::new(),
+            },
+            hint_cost: 10,
+            location_check_points: 1,
+        }
+    }
+
+    pub fn slot(mut self, slot: Slot) -> Self {
+        self.slots.push(slot);
+        self
+    }
+
+    /// Refuses connections which don't give this password.
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// The games sent in answer to GetDataPackage. Their checksums are
+    /// announced in the RoomInfo.
+    pub fn data_package(mut self, data_package: DataPackageObject) -> Self {
+        self.data_package = data_package;
+        self
+    }
+
+    /// The percentage of a slot's locations a hint costs. Defaults to 10.
+    pub fn hint_cost(mut self, hint_cost: i64) -> Self {
+        self.hint_cost = hint_cost;
+        self
+    }
+
+    /// The hint points earned per location checked. Defaults to 1.
+    pub fn location_check_points(mut self, points: i64) -> Self {
+        self.location_check_points = points;
+        self
+    }
+
+    #[allow(deprecated)]
+    fn room_info(&self) -> RoomInfo {
+        let mut games: Vec<String> = self.slots.iter().map(|slot| slot.game.clone()).collect();
+        games.sort();
+        games.dedup();
+
+        RoomInfo {
+            version: SUPPORTED_VERSION,
+            generator_version: SUPPORTED_VERSION,
+            tags: vec!["AP".to_string()],
+            password_required: self.password.is_some(),
+            permissions: [
+                (PermissionName::Release, Permission::Enabled),
+                (PermissionName::Collect, Permission::Enabled),
+                (PermissionName::Remaining, Permission::Enabled),
+            ]
+            .into_iter()
+            .collect(),
+            hint_cost: self.hint_cost,
+            location_check_points: self.location_check_points,
+            games,
+            datapackage_versions: HashMap::new(),
+            datapackage_checksums: self
+                .data_package
+                .games
+                .iter()
+                .map(|(game, data)| (game.clone(), data.checksum.clone()))
+                .collect(),
+            seed_name: self.seed_name.clone(),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+        }
+    }
+
+    /// Finds the slot `connect` asks for, or why it was refused.
+    fn check_connect(&self, connect: &Connect) -> Result<&Slot, Vec<ConnectionRefusedError>> {
+        let mut errors = Vec::new();
+        if self.password.is_some() && connect.password != self.password {
+            errors.push(ConnectionRefusedError::InvalidPassword);
+        }
+
+        let slot = self.slots.iter().find(|slot| slot.name == connect.name);
+        match slot {
+            Some(slot) => {
+                // Clients which don't play the game may connect with any.
+                let any_game = connect.tags.iter().any(|tag| {
+                    matches!(
+                        tag,
+                        ClientTag::Tracker | ClientTag::TextOnly | ClientTag::HintGame
+                    )
+                });
+                if !any_game && connect.game != slot.game {
+                    errors.push(ConnectionRefusedError::InvalidGame);
+                }
+            }
+            None => errors.push(ConnectionRefusedError::InvalidSlot),
+        }
+
+        match slot {
+            Some(slot) if errors.is_empty() => Ok(slot),
+            _ => Err(errors),
+        }
+    }
+
+    fn connected(&self, slot: &Slot, connect: &Connect) -> Connected {
+        Connected {
+            team: slot.team,
+            slot: slot.slot,
+            players: self
+                .slots
+                .iter()
+                .map(|other| NetworkPlayer {
+                    team: other.team,
+                    slot: other.slot,
+                    alias: other.name.clone(),
+                    name: other.name.clone(),
+                })
+                .collect(),
+            missing_locations: slot
+                .locations
+                .iter()
+                .copied()
+                .filter(|location| !slot.checked_locations.contains(location))
+                .collect(),
+            checked_locations: slot.checked_locations.clone(),
+            slot_data: if connect.slot_data {
+                slot.slot_data.clone()
+            } else {
+                HashMap::new()
+            },
+            slot_info: self
+                .slots
+                .iter()
+                .map(|other| {
+                    let info = NetworkSlot {
+                        name: other.name.clone(),
+                        game: other.game.clone(),
+                        r#type: SlotType::Player,
+                        group_members: Vec::new(),
+                    };
+                    (other.slot, info)
+                })
+                .collect(),
+            hint_points: 0,
+        }
+    }
+
+    fn data_package_reply(&self, games: &[String]) -> DataPackage {
+        let mut data = self.data_package.clone();
+        if !games.is_empty() {
+            data.games.retain(|game, _| games.contains(game));
+        }
+        DataPackage { data }
+    }
+}
+
+/// Handles the messages from connected clients. Each connection is served
+/// on a task of its own, so the futures must be Send.
+pub trait ServerHandler: Send + Sync + 'static {
+    /// Called once a client has been sent Connected, before any of its
+    /// messages are handled. Returning an error closes the connection.
+    fn on_connect(&self, session: &ServerSession) -> impl Future<Output = Result<()>> + Send {
+        let _ = session;
+        async { Ok(()) }
+    }
+
+    /// Called with each message from a connected client. Returning an error
+    /// closes the connection.
+    fn on_message(
+        &self,
+        session: &ServerSession,
+        message: ClientMessage,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Called once a connected client has gone, however the connection
+    /// ended.
+    fn on_disconnect(&self, session: &ServerSession) -> impl Future<Output = ()> + Send {
+        let _ = session;
+        async {}
+    }
+}
+
+/// A connected client. Sessions are cheap to clone, so handlers can keep
+/// them to send to other clients, such as when relaying.
+#[derive(Debug, Clone)]
+pub struct ServerSession {
+    inner: Arc<SessionInner>,
+}
+
+#[derive(Debug)]
+struct SessionInner {
+    id: u64,
+    slot: Slot,
+    connect: Connect,
+    writer: mpsc::UnboundedSender<Message>,
+}
+
+impl ServerSession {
+    /// Identifies this connection among every one the server has accepted.
+    pub fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    /// The slot the client connected to.
+    pub fn slot(&self) -> &Slot {
+        &self.inner.slot
+    }
+
+    /// The Connect the client sent.
+    pub fn connect_packet(&self) -> &Connect {
+        &self.inner.connect
+    }
+
+    /// Queues `messages` to be sent to the client in a single frame. Fails
+    /// with [`Error::ConnectionClosed`] once the client has gone.
+    pub fn send(&self, messages: Vec<ServerMessage>) -> Result<()> {
+        send_frame(&self.inner.writer, &messages)
+    }
+
+    /// Closes the connection once the messages queued so far are sent.
+    pub fn close(&self) {
+        let _ = self.inner.writer.send(Message::Close(None));
+    }
+}
+
+fn send_frame<T: serde::Serialize>(
+    writer: &mpsc::UnboundedSender<Message>,
+    messages: &[T],
+) -> Result<()> {
+    let frame = serde_json::to_string(messages)?;
+    writer
+        .send(Message::Text(frame))
+        .map_err(|_| Error::ConnectionClosed)
+}
+
+/// Serves a [`ServerConfig`] to clients, passing their messages to a
+/// [`ServerHandler`].
+pub struct Server<H> {
+    config: Arc<ServerConfig>,
+    handler: Arc<H>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl<H> Clone for Server<H> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            handler: self.handler.clone(),
+            next_id: self.next_id.clone(),
+        }
+    }
+}
+
+impl<H: ServerHandler> Server<H> {
+    pub fn new(config: ServerConfig, handler: H) -> Self {
+        Self {
+            config: Arc::new(config),
+            handler: Arc::new(handler),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Accepts connections from `listener`, serving each on a task of its
+    /// own, until accepting fails.
+    pub async fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, address) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.serve_connection(stream).await {
+                    tracing::debug!(%address, error = %e, "connection ended with an error");
+                }
+            });
+        }
+    }
+
+    /// Serves a single connection, such as one accepted elsewhere, until it
+    /// closes.
+    pub async fn serve_connection<S>(&self, stream: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let socket = tokio_tungstenite::accept_async(stream).await?;
+        let (mut sink, mut stream) = socket.split();
+
+        let (writer, mut outgoing) = mpsc::unbounded_channel();
+        let writer_task = tokio::spawn(async move {
+            while let Some(message) = outgoing.recv().await {
+                let close = matches!(message, Message::Close(_));
+                if sink.send(message).await.is_err() || close {
+                    break;
+                }
+            }
+        });
+
+        let result = self.run_connection(&writer, &mut stream).await;
+
+        // Sessions kept by the handler would otherwise keep the writer open.
+        writer_task.abort();
+        result
+    }
+
+    async fn run_connection<S>(
+        &self,
+        writer: &mpsc::UnboundedSender<Message>,
+        stream: &mut S,
+    ) -> Result<()>
+    where
+        S: futures::Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+    {
+        let mut session = None;
+        let result = self.handle_messages(writer, stream, &mut session).await;
+        if let Some(session) = &session {
+            self.handler.on_disconnect(session).await;
+        }
+        result
+    }
+
+    async fn handle_messages<S>(
+        &self,
+        writer: &mpsc::UnboundedSender<Message>,
+        stream: &mut S,
+        session: &mut Option<ServerSession>,
+    ) -> Result<()>
+    where
+        S: futures::Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+    {
+        send_frame(
+            writer,
+            &[AnonymousServerMessage::RoomInfo(self.config.room_info())],
+        )?;
+
+        while let Some(messages) = next_messages(stream).await? {
+            for message in messages {
+                match (message, &*session) {
+                    (ClientMessage::GetDataPackage(request), _) => {
+                        let reply = self.config.data_package_reply(&request.games);
+                        send_frame(writer, &[AnonymousServerMessage::DataPackage(reply)])?;
+                    }
+                    (ClientMessage::Connect(connect), None) => {
+                        match self.config.check_connect(&connect) {
+                            Ok(slot) => {
+                                let connected = self.config.connected(slot, &connect);
+                                send_frame(
+                                    writer,
+                                    &[AnonymousServerMessage::Connected(connected)],
+                                )?;
+
+                                let connected = ServerSession {
+                                    inner: Arc::new(SessionInner {
+                                        id: self.next_id.fetch_add(1, Ordering::Relaxed),
+                                        slot: slot.clone(),
+                                        connect,
+                                        writer: writer.clone(),
+                                    }),
+                                };
+                                let connected = session.insert(connected);
+                                self.handler.on_connect(connected).await?;
+                            }
+                            Err(errors) => {
+                                let refused = ConnectionRefused { errors };
+                                send_frame(
+                                    writer,
+                                    &[AnonymousServerMessage::ConnectionRefused(refused)],
+                                )?;
+                            }
+                        }
+                    }
+                    (message, Some(session)) => self.handler.on_message(session, message).await?,
+                    (message, None) => {
+                        let invalid = InvalidPacket {
+                            r#type: PacketProblemType::Cmd,
+                            original_cmd: Some(message.cmd_name().to_string()),
+                            text: "connect to a slot first".to_string(),
+                        };
+                        send_frame(writer, &[AnonymousServerMessage::InvalidPacket(invalid)])?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the messages in the next text frame, or None once the client has
+/// closed the connection.
+async fn next_messages<S>(stream: &mut S) -> Result<Option<Vec<ClientMessage>>>
+where
+    S: futures::Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    while let Some(frame) = stream.next().await {
+        match frame? {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,117 @@
+use std::pin::Pin;
+use std::sync::RwLockReadGuard;
+use std::task::{Context, Poll};
+
+use futures::{Stream, StreamExt};
+
+use crate::bus::{SubscribeOptions, Subscription};
+use crate::client::{Client, MessageStreamError};
+use crate::error::Result;
+use crate::events::{Event, EventFilter, EventType};
+use crate::protocol::ServerMessage;
+use crate::room::RoomDelta;
+use crate::state::{StateReader, StateTracker, TrackedState};
+
+/// A message returned by a [`Session`], along with what it changed.
+#[derive(Debug, Clone)]
+pub struct SessionUpdate {
+    pub message: ServerMessage,
+
+    /// For RoomUpdate messages, what changed in the room.
+    pub room_delta: Option<RoomDelta>,
+}
+
+/// Wraps a [`Client`], keeping a [`TrackedState`] up to date from every
+/// message it receives: received items by index, checked and missing
+/// locations, players and their aliases, hint points and permissions.
+///
+/// This is the same bookkeeping [`Client::spawn`] does in the background,
+/// for clients which drive the connection themselves.
+pub struct Session {
+    client: Client,
+    tracker: StateTracker,
+    rollbacks: Subscription,
+}
+
+impl Session {
+    pub fn new(mut client: Client) -> Self {
+        let tracker = StateTracker::new(client.get_room_info(), client.get_connected());
+        let rollbacks = subscribe_rollbacks(&mut client);
+        Self {
+            client,
+            tracker,
+            rollbacks,
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Gives access to the client, such as for sending messages. Checks sent
+    /// this way aren't attributed to us in the room state, so prefer
+    /// [`Session::check_locations`].
+    pub fn client_mut(&mut self) -> &mut Client {
+        &mut self.client
+    }
+
+    pub fn into_client(self) -> Client {
+        self.client
+    }
+
+    /// Locks the state for reading. Keep the guard short-lived, and don't
+    /// hold it across awaits.
+    pub fn state(&self) -> RwLockReadGuard<'_, TrackedState> {
+        self.tracker.read()
+    }
+
+    /// A read-only handle to the state, for other tasks and threads.
+    pub fn reader(&self) -> StateReader {
+        self.tracker.reader()
+    }
+
+    /// Informs the server of locations which have been checked, recording
+    /// them in the room state. With
+    /// [`crate::client::ConnectOptions::optimistic_checks`], the returned
+    /// delta contains the locations marked as checked right away, see
+    /// [`crate::room::RoomState::mark_checked`].
+    pub async fn check_locations(&mut self, locations: &[i64]) -> Result<RoomDelta> {
+        let mapped = self.client.map_locations(locations)?;
+        self.tracker.room_mut().record_sent_checks(&mapped);
+        let marked = self.client.send_location_checks(&mapped).await?;
+        Ok(self.tracker.room_mut().mark_checked(&marked))
+    }
+}
+
+impl Stream for Session {
+    type Item = Result<SessionUpdate, MessageStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = match self.client.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(message))) => message,
+            Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let room_delta = self.tracker.handle(&message);
+        while let Poll::Ready(Some(Event::ChecksRolledBack(locations))) =
+            self.rollbacks.poll_next_unpin(cx)
+        {
+            self.tracker.room_mut().roll_back_checks(&locations);
+        }
+
+        Poll::Ready(Some(Ok(SessionUpdate {
+            message,
+            room_delta,
+        })))
+    }
+}
+
+/// Subscribes to the optimistic checks the client rolls back, so a tracked
+/// room state can undo them as well.
+pub(crate) fn subscribe_rollbacks(client: &mut Client) -> Subscription {
+    client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ChecksRolledBack])),
+    )
+}
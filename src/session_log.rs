@@ -0,0 +1,275 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::protocol::{NetworkItem, ServerMessage};
+use crate::render::Renderer;
+
+/// The format of a [`SessionLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// One line per entry, starting with a UTC timestamp and the entry kind.
+    #[default]
+    Text,
+
+    /// One JSON object per line, with the fields of [`LogEntry`].
+    JsonLines,
+}
+
+/// When to start a new log file. The current file is renamed with a `.1`
+/// suffix, the previous `.1` to `.2` and so on, and the oldest is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl Rotation {
+    /// Rotates once the log reaches `max_bytes`, keeping 5 old files.
+    pub fn new(max_bytes: u64) -> Self {
+        Self { max_bytes, keep: 5 }
+    }
+
+    /// Sets how many rotated files are kept.
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+}
+
+/// An entry in a [`SessionLog`].
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    /// Local unix time the entry was recorded at.
+    pub time: f64,
+
+    /// The PrintJSON type, such as `ItemSend` or `Chat`, or `ItemReceived`
+    /// for items in a ReceivedItems.
+    pub kind: String,
+
+    /// The rendered message.
+    pub text: String,
+
+    /// For `ItemReceived`, the index of the item and the item itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<NetworkItem>,
+}
+
+/// Writes the messages and received items of a session to a timestamped log
+/// file, such as for tournament audit logs.
+///
+/// Pass every message from the client's stream to [`SessionLog::record`].
+/// Each entry is written as soon as it is recorded, so the log is complete up
+/// to the last message even if the program is killed.
+#[derive(Debug)]
+pub struct SessionLog {
+    path: PathBuf,
+    format: LogFormat,
+    rotation: Option<Rotation>,
+    renderer: Renderer,
+    file: File,
+    size: u64,
+}
+
+impl SessionLog {
+    /// Opens the log at the given path, appending to it if it already exists.
+    pub fn open(path: impl Into<PathBuf>, format: LogFormat) -> Result<Self> {
+        let path = path.into();
+        let (file, size) = open_append(&path)?;
+
+        Ok(Self {
+            path,
+            format,
+            rotation: None,
+            renderer: Renderer::new(),
+            file,
+            size,
+        })
+    }
+
+    /// Rotates the log file according to `rotation`.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    /// Sets the renderer used for PrintJSON messages.
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = renderer;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records a message received by `client`. PrintJSON messages are
+    /// rendered, and every item in a ReceivedItems gets its own entry with
+    /// names looked up through the client's data package, if it has one.
+    /// Other messages are ignored.
+    pub fn record(&mut self, client: &Client, message: &ServerMessage) -> Result<()> {
+        match message {
+            ServerMessage::PrintJSON(print) => self.write(LogEntry {
+                time: now(),
+                kind: format!("{:?}", print.kind()),
+                text: self.renderer.render_with(print, &client.names()),
+                index: None,
+                item: None,
+            }),
+            ServerMessage::ReceivedItems(received) => {
+                for (index, item) in (received.index..).zip(&received.items) {
+                    self.write(LogEntry {
+                        time: now(),
+                        kind: "ItemReceived".to_string(),
+                        text: describe_received(client, item),
+                        index: Some(index),
+                        item: Some(item.clone()),
+                    })?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes an entry to the log.
+    pub fn write(&mut self, entry: LogEntry) -> Result<()> {
+        let line = match self.format {
+            LogFormat::Text => format!(
+                "{} [{}] {}\n",
+                format_utc(entry.time),
+                entry.kind,
+                entry.text
+            ),
+            LogFormat::JsonLines => {
+                let mut line = serde_json::to_string(&entry)?;
+                line.push('\n');
+                line
+            }
+        };
+
+        if let Some(rotation) = self.rotation {
+            if self.size > 0 && self.size + line.len() as u64 > rotation.max_bytes {
+                self.rotate(rotation)?;
+            }
+        }
+
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(Error::file("write", &self.path))?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self, rotation: Rotation) -> Result<()> {
+        let rotated = |n: usize| {
+            let mut path = self.path.clone().into_os_string();
+            path.push(format!(".{}", n));
+            PathBuf::from(path)
+        };
+
+        if rotation.keep == 0 {
+            std::fs::remove_file(&self.path).map_err(Error::file("remove", &self.path))?;
+        } else {
+            // Missing files are expected until the log has rotated `keep`
+            // times, so failures to remove or rename them are ignored.
+            let _ = std::fs::remove_file(rotated(rotation.keep));
+            for n in (1..rotation.keep).rev() {
+                let _ = std::fs::rename(rotated(n), rotated(n + 1));
+            }
+            std::fs::rename(&self.path, rotated(1)).map_err(Error::file("rotate", &self.path))?;
+        }
+
+        let (file, size) = open_append(&self.path)?;
+        self.file = file;
+        self.size = size;
+
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> Result<(File, u64)> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(Error::file("create", parent))?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(Error::file("open", path))?;
+    let size = file.metadata().map_err(Error::file("open", path))?.len();
+
+    Ok((file, size))
+}
+
+fn describe_received(client: &Client, item: &NetworkItem) -> String {
+    let connected = client.get_connected();
+    let names = client
+        .resolver()
+        .zip(client.game())
+        .map(|(resolver, game)| resolver.received_item(item, game, &connected.slot_info));
+    let sender = connected
+        .players
+        .iter()
+        .find(|player| player.team == connected.team && player.slot == item.player);
+
+    let item_name = names
+        .and_then(|names| names.item)
+        .map_or_else(|| format!("item {}", item.item), str::to_string);
+    let location_name = names
+        .and_then(|names| names.location)
+        .map_or_else(|| format!("location {}", item.location), str::to_string);
+    let sender_name = sender.map_or_else(|| format!("player {}", item.player), |p| p.alias.clone());
+
+    format!(
+        "Received {} from {} ({})",
+        item_name, sender_name, location_name
+    )
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since| since.as_secs_f64())
+}
+
+/// Formats a unix time as an RFC 3339 UTC timestamp with millisecond
+/// precision.
+fn format_utc(time: f64) -> String {
+    let millis = (time * 1000.0) as i64;
+    let secs = millis.div_euclid(1000);
+    let days = secs.div_euclid(86_400);
+    let day_secs = secs.rem_euclid(86_400);
+
+    // Converts days since 1970-01-01 to a civil date, from Howard Hinnant's
+    // date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60,
+        millis.rem_euclid(1000)
+    )
+}
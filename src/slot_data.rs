@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+/// Keys which games use for the DeathLink setting in slot_data.
+const DEATH_LINK_KEYS: &[&str] = &["death_link", "deathlink", "DeathLink"];
+
+/// Keys which games use for the seed in slot_data.
+const SEED_KEYS: &[&str] = &["seed", "seed_name"];
+
+/// The slot_data keys which many games share by convention, extracted so
+/// generic tools can honor them without game-specific knowledge. Everything
+/// else is kept in `rest`.
+#[derive(Debug, Clone, Default)]
+pub struct CommonSlotData {
+    /// Whether DeathLink was enabled for this slot. Games store this as
+    /// either a bool or an integer option.
+    pub death_link: Option<bool>,
+
+    /// The goal option, which differs per game.
+    pub goal: Option<serde_json::Value>,
+
+    /// The seed, for games which include one.
+    pub seed: Option<serde_json::Value>,
+
+    /// All keys which weren't extracted.
+    pub rest: HashMap<String, serde_json::Value>,
+}
+
+impl CommonSlotData {
+    pub fn from_slot_data(slot_data: &HashMap<String, serde_json::Value>) -> Self {
+        let mut data = Self::default();
+
+        for (key, value) in slot_data {
+            if DEATH_LINK_KEYS.contains(&key.as_str()) {
+                let enabled = value
+                    .as_bool()
+                    .or_else(|| value.as_i64().map(|value| value != 0));
+                if enabled.is_some() {
+                    data.death_link = enabled;
+                    continue;
+                }
+            } else if key == "goal" {
+                data.goal = Some(value.clone());
+                continue;
+            } else if SEED_KEYS.contains(&key.as_str()) && data.seed.is_none() {
+                data.seed = Some(value.clone());
+                continue;
+            }
+
+            data.rest.insert(key.clone(), value.clone());
+        }
+
+        data
+    }
+}
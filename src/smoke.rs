@@ -0,0 +1,48 @@
+//! The end-to-end flow of a simple text client: connect, complete the
+//! handshake, and stream messages until the server closes the connection.
+//!
+//! `examples/basic.rs` runs this against a live server, and the tests run it
+//! against [`crate::testing::MockServer`], so the whole path is exercised
+//! without one.
+
+use futures::StreamExt;
+
+use crate::client::{AnonymousClient, ConnectOptions};
+use crate::error::Result;
+use crate::protocol::{ItemsHandlingFlags, ServerMessage};
+
+/// Options for a text client named `name`, receiving every item and its
+/// starting inventory, as the official text client does.
+pub fn text_client(name: impl Into<String>, game: impl Into<String>) -> ConnectOptions {
+    ConnectOptions::new(name)
+        .game(game)
+        .tags(["AP", "TextClient"])
+        .items_handling(
+            ItemsHandlingFlags::CAN_RECEIVE_ITEMS
+                | ItemsHandlingFlags::HAS_LOCAL_ITEMS
+                | ItemsHandlingFlags::REQUEST_STARTING_INVENTORY,
+        )
+}
+
+/// Connects to `url` with `options` and passes every message to
+/// `on_message` until the server closes the connection. Returns how many
+/// messages were received.
+pub async fn run(
+    url: impl AsRef<str>,
+    options: ConnectOptions,
+    mut on_message: impl FnMut(&ServerMessage),
+) -> Result<usize> {
+    let client = AnonymousClient::new(url).await?;
+    tracing::info!("connected, starting handshake");
+
+    let mut client = client.connect_with(options).await?;
+    tracing::info!(slot = client.get_connected().slot, "handshake complete");
+
+    let mut received = 0;
+    while let Some(message) = client.next().await.transpose()? {
+        on_message(&message);
+        received += 1;
+    }
+
+    Ok(received)
+}
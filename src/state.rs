@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::persist::{self, PersistFormat};
+use crate::protocol::{Connected, NetworkItem, RoomInfo, ServerMessage};
+use crate::room::{RoomDelta, RoomState};
+
+/// State tracked from the messages of a session: the room, every item
+/// received so far, and a mirror of the data storage keys we've seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedState {
+    room: RoomState,
+    received_items: Vec<NetworkItem>,
+
+    // Binary formats such as bincode can't read back a serde_json::Value,
+    // whose type is only known from the data.
+    #[serde(with = "persist::json_text")]
+    storage: HashMap<String, serde_json::Value>,
+}
+
+impl TrackedState {
+    pub fn room(&self) -> &RoomState {
+        &self.room
+    }
+
+    /// Every item received so far, in index order.
+    pub fn received_items(&self) -> &[NetworkItem] {
+        &self.received_items
+    }
+
+    /// The last known value of every data storage key seen in a Retrieved or
+    /// SetReply message.
+    pub fn storage(&self) -> &HashMap<String, serde_json::Value> {
+        &self.storage
+    }
+
+    /// Writes the state to `path` in `format`, such as to show where a
+    /// session left off without connecting. The file is replaced atomically.
+    pub fn save<F: PersistFormat>(&self, path: impl AsRef<Path>, format: &F) -> Result<()> {
+        persist::write_atomic(path.as_ref(), &format.serialize(self)?)
+    }
+
+    /// Reads a state written by [`TrackedState::save`] in the same format.
+    pub fn load<F: PersistFormat>(path: impl AsRef<Path>, format: &F) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path).map_err(Error::file("read", path))?;
+        format
+            .deserialize(&contents)
+            .map_err(|source| Error::Corrupt {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+}
+
+/// Keeps a [`TrackedState`] up to date, and hands out [`StateReader`]s so
+/// other tasks and threads can read it while the network task owns the
+/// client.
+#[derive(Debug)]
+pub struct StateTracker {
+    state: Arc<RwLock<TrackedState>>,
+}
+
+impl StateTracker {
+    pub fn new(room_info: &RoomInfo, connected: &Connected) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(TrackedState {
+                room: RoomState::new(room_info, connected),
+                received_items: Vec::new(),
+                storage: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Creates a read-only handle to the state.
+    pub fn reader(&self) -> StateReader {
+        StateReader {
+            state: self.state.clone(),
+        }
+    }
+
+    /// Updates the state from a message, returning what changed in the room
+    /// for RoomUpdate messages.
+    pub fn handle(&self, message: &ServerMessage) -> Option<RoomDelta> {
+        let mut state = self.write();
+
+        match message {
+            ServerMessage::RoomUpdate(update) => {
+                return Some(state.room.apply_room_update((**update).clone()));
+            }
+            ServerMessage::ReceivedItems(received) => {
+                // An index of 0 is a full resend of our items, otherwise the
+                // index is where the new items start.
+                let start = usize::try_from(received.index).unwrap_or(0);
+                state.received_items.truncate(start);
+                state.received_items.extend(received.items.iter().cloned());
+            }
+            ServerMessage::PrintJSON(print) => state.room.apply_print_json(print),
+            ServerMessage::Retrieved(retrieved) => {
+                for (key, value) in &retrieved.keys {
+                    state.storage.insert(key.clone(), value.clone());
+                }
+            }
+            ServerMessage::SetReply(reply) => {
+                state.storage.insert(reply.key.clone(), reply.value.clone());
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    /// Locks the state for reading.
+    pub fn read(&self) -> RwLockReadGuard<'_, TrackedState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Gives mutable access to the room, for recording our own checks.
+    pub fn room_mut(&self) -> impl std::ops::DerefMut<Target = RoomState> + '_ {
+        RoomGuard(self.write())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, TrackedState> {
+        // A panic while holding the lock can't leave the state half updated
+        // in a way that matters to readers, so poisoning is ignored.
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct RoomGuard<'a>(RwLockWriteGuard<'a, TrackedState>);
+
+impl std::ops::Deref for RoomGuard<'_> {
+    type Target = RoomState;
+
+    fn deref(&self) -> &RoomState {
+        &self.0.room
+    }
+}
+
+impl std::ops::DerefMut for RoomGuard<'_> {
+    fn deref_mut(&mut self) -> &mut RoomState {
+        &mut self.0.room
+    }
+}
+
+/// A cheap, cloneable, read-only handle to a [`TrackedState`].
+#[derive(Debug, Clone)]
+pub struct StateReader {
+    state: Arc<RwLock<TrackedState>>,
+}
+
+impl StateReader {
+    /// Locks the state for reading. Keep the guard short-lived, as it blocks
+    /// the tracker from applying new messages.
+    pub fn read(&self) -> RwLockReadGuard<'_, TrackedState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns a copy of the current state.
+    pub fn snapshot(&self) -> TrackedState {
+        self.read().clone()
+    }
+}
@@ -0,0 +1,164 @@
+//! Typed access to the server's data storage.
+//!
+//! The protocol spreads data storage over five packets: Get is answered by
+//! Retrieved, Set by SetReply if asked for, and SetNotify registers for a
+//! SetReply on every change. [`DataStorage`] pairs each request with its
+//! answer and deserializes the values.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use serde::de::DeserializeOwned;
+
+use crate::bus::{SubscribeOptions, Subscription};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::events::{Event, EventFilter, EventType};
+use crate::protocol::{self, DataStorageOperation, ServerMessage, SetReply};
+
+/// The data storage of a [`Client`]'s room, created by
+/// [`Client::data_storage`].
+///
+/// Messages which arrive while waiting for a reply are returned from the
+/// client's stream afterwards, as with [`Client::scout_hints`].
+pub struct DataStorage<'a> {
+    client: &'a mut Client,
+}
+
+impl Client {
+    pub fn data_storage(&mut self) -> DataStorage<'_> {
+        DataStorage { client: self }
+    }
+}
+
+impl DataStorage<'_> {
+    /// Fetches the values of `keys`, deserialized as `T`. Keys without a
+    /// value are null, so use an `Option` for keys which may not be set.
+    pub async fn get<T: DeserializeOwned>(&mut self, keys: &[&str]) -> Result<HashMap<String, T>> {
+        let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        self.client.get(keys.clone()).await?;
+
+        let reply = self
+            .client
+            .wait_for_reply(|message| {
+                matches!(
+                    message,
+                    ServerMessage::Retrieved(retrieved)
+                        if keys.iter().all(|key| retrieved.keys.contains_key(key))
+                )
+            })
+            .await?;
+        let ServerMessage::Retrieved(mut retrieved) = reply else {
+            unreachable!("wait_for_reply only returns Retrieved");
+        };
+
+        keys.into_iter()
+            .map(|key| {
+                let value = retrieved.keys.remove(&key).unwrap_or_default();
+                Ok((key, serde_json::from_value(value)?))
+            })
+            .collect()
+    }
+
+    /// Fetches the value of a single key, see [`DataStorage::get`].
+    pub async fn get_one<T: DeserializeOwned>(&mut self, key: &str) -> Result<T> {
+        let mut values = self.get(&[key]).await?;
+        Ok(values.remove(key).expect("get returns every requested key"))
+    }
+
+    /// Applies `operations` to a key, starting from null if it has no value,
+    /// and returns the server's reply with the old and new value.
+    pub async fn set(
+        &mut self,
+        key: &str,
+        operations: Vec<DataStorageOperation>,
+    ) -> Result<SetReply> {
+        self.set_with_default(key, serde_json::Value::Null, operations)
+            .await
+    }
+
+    /// Like [`DataStorage::set`], starting from `default` if the key has no
+    /// value.
+    ///
+    /// The reply is the first SetReply for the key after the Set was sent.
+    /// If the key is also registered with SetNotify, a change made by another
+    /// client just before ours can be taken for the reply.
+    pub async fn set_with_default(
+        &mut self,
+        key: &str,
+        default: serde_json::Value,
+        operations: Vec<DataStorageOperation>,
+    ) -> Result<SetReply> {
+        self.client.set(key, default, true, operations).await?;
+
+        let reply = self
+            .client
+            .wait_for_reply(|message| match message {
+                ServerMessage::SetReply(reply) => reply.key == key,
+                ServerMessage::InvalidPacket(invalid) => {
+                    invalid.original_cmd.as_deref() == Some(protocol::Cmd::Set.as_str())
+                }
+                _ => false,
+            })
+            .await?;
+
+        match reply {
+            ServerMessage::SetReply(reply) => Ok(reply),
+            ServerMessage::InvalidPacket(invalid) => Err(Error::Protocol(format!(
+                "server rejected the Set: {}",
+                invalid.text
+            ))),
+            _ => unreachable!("wait_for_reply only returns SetReply or InvalidPacket"),
+        }
+    }
+
+    /// Registers for changes to a key with SetNotify, returning a stream of
+    /// the SetReply for every change. Like [`Client::subscribe`], changes are
+    /// only delivered while the client is being driven.
+    pub async fn subscribe(&mut self, key: &str) -> Result<StorageSubscription> {
+        let subscription = self.client.subscribe(
+            SubscribeOptions::new().filter(EventFilter::new().types([EventType::Message])),
+        );
+        self.client.set_notify(vec![key.to_string()]).await?;
+
+        Ok(StorageSubscription {
+            key: key.to_string(),
+            subscription,
+        })
+    }
+}
+
+/// The changes to a data storage key, created by [`DataStorage::subscribe`].
+#[derive(Debug)]
+pub struct StorageSubscription {
+    key: String,
+    subscription: Subscription,
+}
+
+impl StorageSubscription {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Stream for StorageSubscription {
+    type Item = SetReply;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.subscription).poll_next(cx) {
+                Poll::Ready(Some(Event::Message(message))) => match &*message {
+                    ServerMessage::SetReply(reply) if reply.key == self.key => {
+                        return Poll::Ready(Some(reply.clone()));
+                    }
+                    _ => {}
+                },
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Background tasks spawned by the crate.
+//!
+//! Every task is named and runs inside a `tracing` span carrying the room's
+//! seed name and our slot, so they can be told apart in tokio-console. Names
+//! are only attached when built with `--cfg tokio_unstable`, which
+//! tokio-console requires anyway.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+static TASK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of background tasks spawned by the crate which are still
+/// running, such as those behind [`crate::client::Client::spawn`]. Useful for
+/// checking tasks aren't leaked in tests.
+pub fn task_count() -> usize {
+    TASK_COUNT.load(Ordering::SeqCst)
+}
+
+/// Decrements the task count when the task finishes or is aborted.
+struct TaskGuard;
+
+impl TaskGuard {
+    fn new() -> Self {
+        TASK_COUNT.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        TASK_COUNT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spawns a named, instrumented task on the current tokio runtime.
+pub(crate) fn spawn<F>(name: &'static str, room: &str, slot: i64, future: F) -> JoinHandle<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let span = tracing::info_span!("archipelago", task = name, room, slot);
+    let guard = TaskGuard::new();
+    let future = async move {
+        let _guard = guard;
+        future.await
+    }
+    .instrument(span);
+
+    #[cfg(tokio_unstable)]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn task")
+    }
+
+    #[cfg(not(tokio_unstable))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
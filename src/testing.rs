@@ -0,0 +1,397 @@
+//! An in-process mock server, for writing integration tests for game clients
+//! without running a real Archipelago server.
+//!
+//! A [`MockRoom`] describes the room, defaulting to the room from
+//! [`crate::fixtures`]. Serving it as a [`MockServer`] answers the handshake
+//! in the background: the RoomInfo, any GetDataPackage, and the Connect.
+//! Each client which connects is then handed to the test as a
+//! [`MockConnection`], to script what the server sends and check what the
+//! client sent.
+//!
+//! ```no_run
+//! # async fn run() -> archipelago::Result<()> {
+//! use archipelago::client::ConnectOptions;
+//! use archipelago::fixtures;
+//! use archipelago::testing::{MockRoom, MockServer};
+//!
+//! let mut server = MockServer::in_memory(MockRoom::new());
+//! let client = server
+//!     .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+//!     .await?;
+//! let mut connection = server.accept().await.expect("server stopped");
+//!
+//! connection.send_items(fixtures::received_items().items).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requires the `testing` feature.
+
+use std::io;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tungstenite::Message;
+use web_time::Instant;
+
+use crate::client::{AnonymousClient, Client, ConnectOptions};
+use crate::diagnostics::{ConnectDiagnostics, ConnectError, ConnectStage};
+use crate::error::{Error, Result};
+use crate::fixtures;
+use crate::protocol::{
+    AnonymousServerMessage, ClientMessage, Connect, Connected, ConnectionRefused,
+    ConnectionRefusedError, DataPackage, DataPackageObject, NetworkItem, ReceivedItems, RoomInfo,
+    RoomUpdate, ServerMessage,
+};
+use crate::transport::{BoxTransport, Connection, Connector, TokioConnector};
+
+/// The room served by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockRoom {
+    room_info: RoomInfo,
+    connected: Connected,
+    data_package: DataPackageObject,
+    password: Option<String>,
+    on_connect: Vec<ServerMessage>,
+}
+
+impl Default for MockRoom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockRoom {
+    /// The room from [`crate::fixtures`], sending
+    /// [`fixtures::received_items`] after connecting.
+    pub fn new() -> Self {
+        Self {
+            room_info: fixtures::room_info(),
+            connected: fixtures::connected(),
+            data_package: fixtures::data_package(),
+            password: None,
+            on_connect: vec![ServerMessage::ReceivedItems(fixtures::received_items())],
+        }
+    }
+
+    pub fn room_info(mut self, room_info: RoomInfo) -> Self {
+        self.room_info = room_info;
+        self
+    }
+
+    /// The Connected sent to every client, whatever slot it asked for.
+    pub fn connected(mut self, connected: Connected) -> Self {
+        self.connected = connected;
+        self
+    }
+
+    /// The games sent in answer to GetDataPackage.
+    pub fn data_package(mut self, data_package: DataPackageObject) -> Self {
+        self.data_package = data_package;
+        self
+    }
+
+    /// Refuses connections which don't give this password, and marks the
+    /// room as password protected in its RoomInfo. Without one, any password
+    /// is accepted, as on a real server.
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// The messages sent right after Connected, replacing the default
+    /// ReceivedItems.
+    pub fn on_connect(mut self, messages: Vec<ServerMessage>) -> Self {
+        self.on_connect = messages;
+        self
+    }
+}
+
+/// A running mock server. Connections are accepted and answered in the
+/// background until the server is dropped.
+pub struct MockServer {
+    url: String,
+    connector: Arc<dyn Connector>,
+    connections: mpsc::UnboundedReceiver<MockConnection>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Serves `room` on a random port of localhost.
+    pub async fn listen(room: MockRoom) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+
+        let room = Arc::new(room);
+        let (sender, connections) = mpsc::unbounded_channel();
+        let listener = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(room.clone(), stream, sender.clone()));
+            }
+        });
+
+        Ok(Self {
+            url,
+            connector: Arc::new(TokioConnector),
+            connections,
+            listener: Some(listener),
+        })
+    }
+
+    /// Serves `room` over in-memory pipes, reached through
+    /// [`MockServer::connector`]. Nothing is bound, so tests can run in
+    /// parallel without running out of ports.
+    pub fn in_memory(room: MockRoom) -> Self {
+        let (sender, connections) = mpsc::unbounded_channel();
+        Self {
+            url: "ws://mock".to_string(),
+            connector: Arc::new(PipeConnector {
+                room: Arc::new(room),
+                sender,
+            }),
+            connections,
+            listener: None,
+        }
+    }
+
+    /// The url to connect to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The connector to connect with, to pass to
+    /// [`AnonymousClient::connect_via`].
+    pub fn connector(&self) -> Arc<dyn Connector> {
+        self.connector.clone()
+    }
+
+    /// Connects a client with `options`. The connection is then waiting in
+    /// [`MockServer::accept`].
+    pub async fn connect(&self, options: ConnectOptions) -> Result<Client> {
+        AnonymousClient::connect_via(self.connector(), &self.url)
+            .await?
+            .connect_with(options)
+            .await
+    }
+
+    /// Waits for the next client to complete the handshake. Returns None
+    /// once no more clients can connect.
+    pub async fn accept(&mut self) -> Option<MockConnection> {
+        self.connections.recv().await
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(listener) = &self.listener {
+            listener.abort();
+        }
+    }
+}
+
+/// Connects to a [`MockServer::in_memory`], serving each connection on a
+/// task of its own.
+struct PipeConnector {
+    room: Arc<MockRoom>,
+    sender: mpsc::UnboundedSender<MockConnection>,
+}
+
+impl Connector for PipeConnector {
+    fn connect<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> BoxFuture<'a, std::result::Result<Connection, ConnectError>> {
+        Box::pin(async move {
+            let diagnostics = ConnectDiagnostics {
+                url: url.to_string(),
+                ..Default::default()
+            };
+            if self.sender.is_closed() {
+                return Err(diagnostics.fail(ConnectStage::Tcp, "mock server was dropped"));
+            }
+
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            tokio::spawn(serve(self.room.clone(), server, self.sender.clone()));
+
+            let upgrade_started = Instant::now();
+            match tokio_tungstenite::client_async(url, client).await {
+                Ok((ws, _)) => Ok(Connection {
+                    transport: Box::new(ws),
+                    diagnostics,
+                    upgrade_started,
+                }),
+                Err(e) => Err(diagnostics.fail(ConnectStage::WebSocket, e)),
+            }
+        })
+    }
+}
+
+/// Completes the handshake with a client and hands the connection to the
+/// test. Clients which disconnect or are refused are dropped.
+async fn serve<S>(room: Arc<MockRoom>, stream: S, sender: mpsc::UnboundedSender<MockConnection>)
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let mut connection = MockConnection {
+        transport: Box::new(socket),
+        room,
+        connect: None,
+        next_index: 0,
+    };
+    let room = connection.room.clone();
+
+    let mut room_info = room.room_info.clone();
+    room_info.password_required |= room.password.is_some();
+    let room_info = AnonymousServerMessage::RoomInfo(room_info);
+    if connection.send_raw(&[room_info]).await.is_err() {
+        return;
+    }
+
+    while let Ok(Some(messages)) = connection.recv().await {
+        for message in messages {
+            let reply = match message {
+                ClientMessage::Connect(connect)
+                    if room.password.is_some() && connect.password != room.password =>
+                {
+                    AnonymousServerMessage::ConnectionRefused(ConnectionRefused {
+                        errors: vec![ConnectionRefusedError::InvalidPassword],
+                    })
+                }
+                ClientMessage::Connect(connect) => {
+                    connection.connect = Some(connect);
+                    let connected = AnonymousServerMessage::Connected(room.connected.clone());
+                    if connection.send_raw(&[connected]).await.is_err()
+                        || (!room.on_connect.is_empty()
+                            && connection.send(room.on_connect.clone()).await.is_err())
+                    {
+                        return;
+                    }
+                    let _ = sender.send(connection);
+                    return;
+                }
+                // Nothing else is answered before the client has connected.
+                _ => continue,
+            };
+
+            if connection.send_raw(&[reply]).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The server's side of a connected client.
+pub struct MockConnection {
+    transport: BoxTransport,
+    room: Arc<MockRoom>,
+    connect: Option<Connect>,
+
+    // The index of the next item sent with MockConnection::send_items.
+    next_index: i64,
+}
+
+impl MockConnection {
+    /// The Connect the client sent.
+    pub fn connect_packet(&self) -> &Connect {
+        self.connect
+            .as_ref()
+            .expect("connections are handed over after Connect")
+    }
+
+    /// Sends `messages` in a single frame.
+    pub async fn send(&mut self, messages: Vec<ServerMessage>) -> Result<()> {
+        for message in &messages {
+            if let ServerMessage::ReceivedItems(received) = message {
+                self.next_index = self
+                    .next_index
+                    .max(received.index + received.items.len() as i64);
+            }
+        }
+        self.send_raw(&messages).await
+    }
+
+    /// Sends `messages` in a single frame as they are, for packets
+    /// [`ServerMessage`] can't express, such as malformed ones.
+    pub async fn send_raw<T: serde::Serialize>(&mut self, messages: &[T]) -> Result<()> {
+        let frame = serde_json::to_string(messages)?;
+        self.transport.send(Message::Text(frame)).await?;
+        Ok(())
+    }
+
+    /// Sends `items` as the next ReceivedItems, following on from the items
+    /// sent so far.
+    pub async fn send_items(&mut self, items: Vec<NetworkItem>) -> Result<()> {
+        let index = self.next_index;
+        self.send(vec![ServerMessage::ReceivedItems(ReceivedItems {
+            index,
+            items,
+        })])
+        .await
+    }
+
+    pub async fn room_update(&mut self, update: RoomUpdate) -> Result<()> {
+        self.send(vec![ServerMessage::RoomUpdate(Box::new(update))])
+            .await
+    }
+
+    /// The websocket itself, for tests which need control over individual
+    /// frames, such as pings or fragmented messages.
+    pub fn transport(&mut self) -> &mut BoxTransport {
+        &mut self.transport
+    }
+
+    /// Receives the messages in the next frame from the client. Returns None
+    /// once the client has disconnected.
+    ///
+    /// GetDataPackage is answered from the room, as a real server would, and
+    /// left out of what's returned.
+    pub async fn recv(&mut self) -> Result<Option<Vec<ClientMessage>>> {
+        while let Some(frame) = self.transport.next().await {
+            let text = match frame? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            let mut messages: Vec<ClientMessage> = serde_json::from_str(&text)?;
+            let mut replies = Vec::new();
+            messages.retain(|message| match message {
+                ClientMessage::GetDataPackage(request) => {
+                    let mut data = self.room.data_package.clone();
+                    if !request.games.is_empty() {
+                        data.games.retain(|game, _| request.games.contains(game));
+                    }
+                    replies.push(AnonymousServerMessage::DataPackage(DataPackage { data }));
+                    false
+                }
+                _ => true,
+            });
+            if !replies.is_empty() {
+                self.send_raw(&replies).await?;
+            }
+            if !messages.is_empty() {
+                return Ok(Some(messages));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Receives the next frame from the client, failing if it disconnected.
+    pub async fn expect_recv(&mut self) -> Result<Vec<ClientMessage>> {
+        self.recv().await?.ok_or(Error::ConnectionClosed)
+    }
+
+    /// Closes the connection, as the server does when shutting down.
+    pub async fn close(mut self) -> Result<()> {
+        self.transport.close().await?;
+        Ok(())
+    }
+}
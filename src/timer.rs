@@ -0,0 +1,97 @@
+//! Timers which work both natively and in the browser.
+//!
+//! Natively these use tokio's timer. On wasm32 there is no tokio runtime, so
+//! the browser's `setTimeout` is used through gloo-timers instead. Anything in
+//! the crate which waits, such as timeouts, keepalives and reconnect backoff,
+//! should go through here rather than using `tokio::time` directly.
+
+use std::future::Future;
+use std::time::Duration;
+
+/// Returned by [`timeout`] when the duration elapses first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("deadline has elapsed")]
+pub struct Elapsed;
+
+/// Waits until `duration` has elapsed.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Waits until `duration` has elapsed.
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await
+}
+
+/// Runs `future`, giving up if it doesn't finish within `duration`.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| Elapsed)
+}
+
+/// Runs `future`, giving up if it doesn't finish within `duration`.
+#[cfg(target_arch = "wasm32")]
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    use futures::future::{select, Either};
+
+    let future = std::pin::pin!(future);
+    let sleep = std::pin::pin!(sleep(duration));
+    match select(future, sleep).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+/// Exponentially increasing delays between retries.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    factor: u32,
+    next: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
+
+impl Backoff {
+    /// Creates a backoff starting at `initial` and doubling up to `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            factor: 2,
+            next: initial,
+        }
+    }
+
+    /// Sets how much the delay grows after each attempt. Defaults to 2.
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor.max(1);
+        self
+    }
+
+    /// Returns the delay before the next attempt, and increases the one after.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = self.next.saturating_mul(self.factor).min(self.max);
+        delay
+    }
+
+    /// Sleeps for the next delay.
+    pub async fn wait(&mut self) {
+        sleep(self.next_delay()).await
+    }
+
+    /// Starts over from the initial delay, such as after a successful attempt.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
@@ -0,0 +1,408 @@
+//! The websocket connection underneath a client.
+//!
+//! A client reads and writes websocket frames through a [`Transport`], a
+//! [`Stream`] and [`Sink`] of tungstenite [`Message`]s, which don't depend on
+//! any runtime. Connections are opened by a [`Connector`], which is kept to
+//! open new ones when reconnecting.
+//!
+//! With the default `tokio-transport` feature, [`TokioConnector`] connects
+//! over TCP with tokio and tokio-tungstenite, and is what
+//! [`crate::client::AnonymousClient::new`] uses. On other runtimes, implement
+//! [`Connector`] over the websocket library of your choice and pass it to
+//! [`crate::client::AnonymousClient::connect_via`].
+//!
+//! For wasm32-unknown-unknown, disable the default features and enable
+//! `browser-transport` to connect with the browser's WebSocket API instead,
+//! through `BrowserConnector`. The client itself works the same way, but
+//! anything which spawns tasks, such as [`crate::client::Client::spawn`] and
+//! companion connections, still needs a tokio runtime and isn't available in
+//! the browser. Builds with the `atomics` target feature, where the browser's
+//! WebSocket could end up shared between threads, aren't supported.
+
+use futures::future::BoxFuture;
+use futures::{Sink, Stream};
+use tungstenite::Message;
+use web_time::Instant;
+
+use crate::diagnostics::{ConnectDiagnostics, ConnectError};
+
+/// A websocket connection to a server. Anything which is both a [`Stream`]
+/// and a [`Sink`] of websocket messages is a transport.
+pub trait Transport:
+    Stream<Item = Result<Message, tungstenite::Error>>
+    + Sink<Message, Error = tungstenite::Error>
+    + Send
+    + Unpin
+{
+}
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<Message, tungstenite::Error>>
+        + Sink<Message, Error = tungstenite::Error>
+        + Send
+        + Unpin
+{
+}
+
+pub type BoxTransport = Box<dyn Transport>;
+
+/// A connection opened by a [`Connector`].
+pub struct Connection {
+    pub transport: BoxTransport,
+
+    /// What happened while connecting. The url is the websocket url which
+    /// was connected to, such as after falling back from `wss://` to `ws://`,
+    /// and is used for reconnecting.
+    pub diagnostics: ConnectDiagnostics,
+
+    /// When the websocket upgrade was requested. The server sends its
+    /// RoomInfo as soon as the upgrade completes, so the time from here
+    /// until it arrives is used as a first estimate of the round trip time.
+    pub upgrade_started: Instant,
+}
+
+/// Opens connections to servers.
+pub trait Connector: Send + Sync {
+    /// Connects to `url`, in any of the forms accepted by
+    /// [`crate::client::AnonymousClient::new`].
+    fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Connection, ConnectError>>;
+}
+
+/// The connector used by [`crate::client::AnonymousClient::new`]:
+/// `BrowserConnector` when built for wasm32 with the `browser-transport`
+/// feature, or else [`TokioConnector`].
+#[cfg(all(
+    feature = "browser-transport",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+pub type DefaultConnector = BrowserConnector;
+
+/// The connector used by [`crate::client::AnonymousClient::new`]:
+/// `BrowserConnector` when built for wasm32 with the `browser-transport`
+/// feature, or else [`TokioConnector`].
+#[cfg(all(
+    feature = "tokio-transport",
+    not(all(feature = "browser-transport", target_arch = "wasm32"))
+))]
+pub type DefaultConnector = TokioConnector;
+
+#[cfg(feature = "tokio-transport")]
+pub use self::tcp::TokioConnector;
+
+#[cfg(all(
+    feature = "browser-transport",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+pub use self::browser::BrowserConnector;
+
+#[cfg(feature = "tokio-transport")]
+mod tcp {
+    use futures::future::BoxFuture;
+    use tokio_tungstenite::{client_async, MaybeTlsStream};
+    use web_time::Instant;
+
+    use super::{Connection, Connector};
+    use crate::diagnostics::{AddressAttempt, ConnectDiagnostics, ConnectError, ConnectStage};
+
+    /// Connects over TCP using tokio, with TLS from native-tls.
+    ///
+    /// The url may start with `ws://` or `wss://` to choose whether TLS is
+    /// used. Without a scheme, `wss://` is tried first, falling back to
+    /// `ws://` if the TLS or websocket handshake fails, as the official
+    /// clients do. The port defaults to 38281.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TokioConnector;
+
+    impl Connector for TokioConnector {
+        fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Connection, ConnectError>> {
+            Box::pin(async move {
+                if let Some(address) = url.strip_prefix("wss://") {
+                    return connect_with_scheme(address, true).await;
+                }
+                if let Some(address) = url.strip_prefix("ws://") {
+                    return connect_with_scheme(address, false).await;
+                }
+
+                match connect_with_scheme(url, true).await {
+                    Ok(connection) => Ok(connection),
+                    Err(e) if matches!(e.stage, ConnectStage::Tls | ConnectStage::WebSocket) => {
+                        tracing::debug!(error = %e, "wss connection failed, falling back to ws");
+                        connect_with_scheme(url, false).await
+                    }
+                    Err(e) => Err(e),
+                }
+            })
+        }
+    }
+
+    async fn connect_with_scheme(address: &str, tls: bool) -> Result<Connection, ConnectError> {
+        let (host, port) = address
+            .rsplit_once(':')
+            .map_or_else(|| (address, None), |(host, port)| (host, Some(port)));
+        let port = port.unwrap_or("38281");
+
+        let scheme = if tls { "wss" } else { "ws" };
+        let ws_url = format!("{}://{}:{}", scheme, host, port);
+        let mut diagnostics = ConnectDiagnostics {
+            url: ws_url.clone(),
+            ..Default::default()
+        };
+
+        let port: u16 = match port.parse() {
+            Ok(port) => port,
+            Err(e) => return Err(diagnostics.fail(ConnectStage::Dns, e)),
+        };
+
+        let addresses: Vec<_> = match tokio::net::lookup_host((host, port)).await {
+            Ok(addresses) => addresses.collect(),
+            Err(e) => return Err(diagnostics.fail(ConnectStage::Dns, e)),
+        };
+
+        let mut tcp = None;
+        let mut last_error = None;
+        for address in addresses {
+            match tokio::net::TcpStream::connect(address).await {
+                Ok(stream) => {
+                    diagnostics.attempts.push(AddressAttempt {
+                        address,
+                        error: None,
+                    });
+                    tcp = Some(stream);
+                    break;
+                }
+                Err(e) => {
+                    diagnostics.attempts.push(AddressAttempt {
+                        address,
+                        error: Some(e.to_string()),
+                    });
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let tcp = match (tcp, last_error) {
+            (Some(tcp), _) => tcp,
+            (None, Some(e)) => return Err(diagnostics.fail(ConnectStage::Tcp, e)),
+            (None, None) => {
+                return Err(
+                    diagnostics.fail(ConnectStage::Dns, "host did not resolve to any address")
+                )
+            }
+        };
+
+        let stream = if tls {
+            let connector = match native_tls::TlsConnector::new() {
+                Ok(connector) => tokio_native_tls::TlsConnector::from(connector),
+                Err(e) => return Err(diagnostics.fail(ConnectStage::Tls, e)),
+            };
+            match connector.connect(host, tcp).await {
+                Ok(stream) => MaybeTlsStream::NativeTls(stream),
+                Err(e) => return Err(diagnostics.fail(ConnectStage::Tls, e)),
+            }
+        } else {
+            MaybeTlsStream::Plain(tcp)
+        };
+
+        let upgrade_started = Instant::now();
+        let ws = match client_async(ws_url.as_str(), stream).await {
+            Ok((ws, _)) => ws,
+            Err(e) => return Err(diagnostics.fail(ConnectStage::WebSocket, e)),
+        };
+
+        Ok(Connection {
+            transport: Box::new(ws),
+            diagnostics,
+            upgrade_started,
+        })
+    }
+}
+
+// The browser's WebSocket lives on the JavaScript side, so it isn't Send.
+// Without the atomics target feature, wasm32 is single threaded and nothing
+// can ever be moved to another thread, so it's safe to pretend otherwise.
+// With it, that no longer holds, so the transport isn't offered at all.
+#[cfg(all(
+    feature = "browser-transport",
+    target_arch = "wasm32",
+    target_feature = "atomics"
+))]
+compile_error!(
+    "the browser-transport feature doesn't support the atomics target feature, \
+     as the browser's WebSocket can't be shared between threads"
+);
+
+#[cfg(all(
+    feature = "browser-transport",
+    target_arch = "wasm32",
+    not(target_feature = "atomics")
+))]
+mod browser {
+    use std::borrow::Cow;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures::future::BoxFuture;
+    use futures::{Sink, Stream};
+    use gloo_net::websocket::futures::WebSocket;
+    use gloo_net::websocket::{Message as BrowserMessage, State, WebSocketError};
+    use tungstenite::protocol::CloseFrame;
+    use tungstenite::Message;
+    use web_time::Instant;
+
+    use super::{Connection, Connector};
+    use crate::diagnostics::{ConnectDiagnostics, ConnectError, ConnectStage};
+
+    /// Connects with the browser's WebSocket API, for clients compiled to
+    /// wasm32-unknown-unknown.
+    ///
+    /// The browser resolves the host and handles TLS itself, so every
+    /// failure before the connection opens is reported as
+    /// [`ConnectStage::WebSocket`], without the addresses tried. Without a
+    /// scheme, `wss://` is used with no fallback to `ws://`, as pages served
+    /// over https can't open insecure websockets anyway. The port defaults to
+    /// 38281.
+    ///
+    /// Browsers answer pings themselves and don't let pages send them, so
+    /// [`crate::client::Client::ping`] does nothing and the round trip time
+    /// isn't measured.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct BrowserConnector;
+
+    impl Connector for BrowserConnector {
+        fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Connection, ConnectError>> {
+            Box::pin(async move {
+                let address = url
+                    .strip_prefix("wss://")
+                    .or_else(|| url.strip_prefix("ws://"))
+                    .unwrap_or(url);
+                let scheme = if url.starts_with("ws://") {
+                    "ws"
+                } else {
+                    "wss"
+                };
+                let ws_url = if address.contains(':') {
+                    format!("{}://{}", scheme, address)
+                } else {
+                    format!("{}://{}:38281", scheme, address)
+                };
+                let diagnostics = ConnectDiagnostics {
+                    url: ws_url.clone(),
+                    ..Default::default()
+                };
+
+                let upgrade_started = Instant::now();
+                let mut socket = match WebSocket::open(&ws_url) {
+                    Ok(ws) => BrowserSocket(Some(ws)),
+                    Err(e) => return Err(diagnostics.fail(ConnectStage::WebSocket, e)),
+                };
+
+                // Ready once the socket has either opened or failed.
+                let _ = futures::future::poll_fn(|cx| Pin::new(&mut socket).poll_ready(cx)).await;
+                if !matches!(socket.0.as_ref().map(WebSocket::state), Some(State::Open)) {
+                    return Err(diagnostics.fail(ConnectStage::WebSocket, "connection failed"));
+                }
+
+                Ok(Connection {
+                    transport: Box::new(socket),
+                    diagnostics,
+                    upgrade_started,
+                })
+            })
+        }
+    }
+
+    /// A browser WebSocket, which is closed when the sink is closed.
+    struct BrowserSocket(Option<WebSocket>);
+
+    // SAFETY: see the comment on this module.
+    unsafe impl Send for BrowserSocket {}
+
+    fn to_tungstenite(e: WebSocketError) -> tungstenite::Error {
+        tungstenite::Error::Io(io::Error::other(e.to_string()))
+    }
+
+    impl Stream for BrowserSocket {
+        type Item = Result<Message, tungstenite::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let Some(ws) = self.0.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            let message = match futures::ready!(Pin::new(ws).poll_next(cx)) {
+                Some(Ok(BrowserMessage::Text(text))) => Ok(Message::Text(text)),
+                Some(Ok(BrowserMessage::Bytes(bytes))) => Ok(Message::Binary(bytes)),
+                // Passed on as a close frame, so the client keeps the reason
+                // the same way it does for tungstenite.
+                Some(Err(WebSocketError::ConnectionClose(event))) => {
+                    Ok(Message::Close(Some(CloseFrame {
+                        code: event.code.into(),
+                        reason: Cow::Owned(event.reason),
+                    })))
+                }
+                Some(Err(e)) => Err(to_tungstenite(e)),
+                None => return Poll::Ready(None),
+            };
+            Poll::Ready(Some(message))
+        }
+    }
+
+    impl Sink<Message> for BrowserSocket {
+        type Error = tungstenite::Error;
+
+        fn poll_ready(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            match self.0.as_mut() {
+                Some(ws) => Pin::new(ws).poll_ready(cx).map_err(to_tungstenite),
+                None => Poll::Ready(Err(tungstenite::Error::AlreadyClosed)),
+            }
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+            let message = match item {
+                Message::Text(text) => BrowserMessage::Text(text),
+                Message::Binary(bytes) => BrowserMessage::Bytes(bytes),
+                Message::Close(frame) => {
+                    if let Some(ws) = self.0.take() {
+                        let _ = match frame {
+                            Some(frame) => {
+                                ws.close(Some(frame.code.into()), Some(frame.reason.as_ref()))
+                            }
+                            None => ws.close(None, None),
+                        };
+                    }
+                    return Ok(());
+                }
+                // The browser handles pings and pongs itself.
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => return Ok(()),
+            };
+
+            match self.0.as_mut() {
+                Some(ws) => Pin::new(ws).start_send(message).map_err(to_tungstenite),
+                None => Err(tungstenite::Error::AlreadyClosed),
+            }
+        }
+
+        fn poll_flush(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            if let Some(ws) = self.0.take() {
+                let _ = ws.close(None, None);
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::client::AnonymousClient;
+use crate::error::Result;
+use crate::timer;
+use crate::transport::{Connector, DefaultConnector};
+
+/// A room hosted on an Archipelago WebHost, such as archipelago.gg.
+///
+/// WebHost rooms are shut down when idle and may come back up on a different
+/// port, so rather than connecting to a fixed address, the current port is
+/// looked up from the WebHost every time we connect. Passing the room to
+/// [`crate::client::ConnectOptions::webhost_room`] makes
+/// [`crate::client::Client::reconnect`] do the same.
+#[derive(Debug, Clone)]
+pub struct WebHostRoom {
+    tls: bool,
+    host: String,
+    port: Option<u16>,
+    room_id: String,
+    last_address: Option<String>,
+}
+
+/// Emitted when reconnecting to a WebHost room found it on a new address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressChanged {
+    pub old: String,
+    pub new: String,
+}
+
+impl WebHostRoom {
+    /// Parses a room URL, such as `https://archipelago.gg/room/abc123`. Plain
+    /// `http://` URLs are accepted for self-hosted WebHosts, whose rooms are
+    /// then connected to without TLS.
+    pub fn parse(url: &str) -> Result<Self, WebHostError> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(WebHostError::InvalidUrl(
+                "room url must start with https:// or http://",
+            ));
+        };
+        let (authority, path) = rest
+            .split_once('/')
+            .ok_or(WebHostError::InvalidUrl("room url is missing a path"))?;
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| WebHostError::InvalidUrl("room url has an invalid port"))?;
+                (host, Some(port))
+            }
+            None => (authority, None),
+        };
+        if host.is_empty() {
+            return Err(WebHostError::InvalidUrl("room url is missing a host"));
+        }
+        let room_id = path
+            .strip_prefix("room/")
+            .map(|id| id.trim_end_matches('/'))
+            .filter(|id| !id.is_empty() && !id.contains('/'))
+            .ok_or(WebHostError::InvalidUrl(
+                "room url must look like /room/<id>",
+            ))?;
+
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            room_id: room_id.to_string(),
+            last_address: None,
+        })
+    }
+
+    /// The WebHost's host name, without the port.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn room_id(&self) -> &str {
+        &self.room_id
+    }
+
+    /// The address used by the last successful connection, if any.
+    pub fn last_address(&self) -> Option<&str> {
+        self.last_address.as_deref()
+    }
+
+    /// Looks up the address the room is currently being served on. Visiting
+    /// the room page first wakes the room up if it was shut down.
+    ///
+    /// Rooms on an `https://` WebHost are given as `host:port`, leaving the
+    /// scheme to the connector, and rooms on an `http://` WebHost as
+    /// `ws://host:port`.
+    pub async fn resolve(&self) -> Result<String, WebHostError> {
+        self.get(&format!("/room/{}", self.room_id)).await?;
+
+        let status = self
+            .get(&format!("/api/room_status/{}", self.room_id))
+            .await?;
+        let status: serde_json::Value =
+            serde_json::from_str(&status).map_err(WebHostError::InvalidStatus)?;
+        let port = status
+            .get("last_port")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or(WebHostError::MissingPort)?;
+
+        Ok(match self.tls {
+            true => format!("{}:{}", self.host, port),
+            false => format!("ws://{}:{}", self.host, port),
+        })
+    }
+
+    /// Resolves the room's current address and connects to it. If the room
+    /// moved since the last connection, the change is returned alongside the
+    /// client.
+    pub async fn connect(&mut self) -> Result<(AnonymousClient, Option<AddressChanged>)> {
+        self.connect_via(Arc::new(DefaultConnector::default()))
+            .await
+    }
+
+    /// Connects like [`WebHostRoom::connect`], through `connector`.
+    pub async fn connect_via(
+        &mut self,
+        connector: Arc<dyn Connector>,
+    ) -> Result<(AnonymousClient, Option<AddressChanged>)> {
+        let address = self.resolve().await?;
+        let client = AnonymousClient::connect_via(connector, &address).await?;
+
+        let changed = match self.last_address.replace(address.clone()) {
+            Some(old) if old != address => Some(AddressChanged { old, new: address }),
+            _ => None,
+        };
+
+        Ok((client, changed))
+    }
+
+    /// Fetches a page from the WebHost, returning the response body.
+    async fn get(&self, path: &str) -> Result<String, WebHostError> {
+        let port = self.port.unwrap_or(if self.tls { 443 } else { 80 });
+        let host = match self.port {
+            Some(port) => format!("{}:{}", self.host, port),
+            None => self.host.clone(),
+        };
+
+        let request = async {
+            let tcp = tokio::net::TcpStream::connect((self.host.as_str(), port)).await?;
+            if self.tls {
+                let connector =
+                    tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new()?);
+                let stream = connector.connect(&self.host, tcp).await?;
+                http_get(stream, &host, path).await
+            } else {
+                http_get(tcp, &host, path).await
+            }
+        };
+        timer::timeout(HTTP_TIMEOUT, request)
+            .await
+            .map_err(|_| WebHostError::Timeout)?
+    }
+}
+
+/// Performs an HTTP/1.0 GET over `stream`, returning the response body.
+/// HTTP/1.0 keeps the server from using chunked encoding, so the body is
+/// everything after the headers, up to any Content-Length.
+async fn http_get<S>(mut stream: S, host: &str, path: &str) -> Result<String, WebHostError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: archipelago-rs\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed = httparse::Response::new(&mut headers);
+    let body_start = match parsed.parse(&response) {
+        Ok(httparse::Status::Complete(len)) => len,
+        _ => return Err(WebHostError::MalformedResponse),
+    };
+    let status = parsed.code.ok_or(WebHostError::MalformedResponse)?;
+    if status != 200 {
+        return Err(WebHostError::Status(status));
+    }
+
+    let mut body = &response[body_start..];
+    let length = parsed
+        .headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("content-length"))
+        .and_then(|header| std::str::from_utf8(header.value).ok())
+        .and_then(|value| value.trim().parse::<usize>().ok());
+    if let Some(length) = length {
+        body = body.get(..length).ok_or(WebHostError::MalformedResponse)?;
+    }
+
+    Ok(String::from_utf8_lossy(body).into_owned())
+}
+
+/// How long a request to the WebHost may take.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Looking up a WebHost room failed.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum WebHostError {
+    #[error("invalid room url: {0}")]
+    InvalidUrl(&'static str),
+
+    #[error("http request failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("tls error: {0}")]
+    Tls(#[from] native_tls::Error),
+
+    #[error("timed out waiting for the webhost")]
+    Timeout,
+
+    #[error("malformed http response")]
+    MalformedResponse,
+
+    #[error("unexpected http status: {0}")]
+    Status(u16),
+
+    #[error("failed to parse room status: {0}")]
+    InvalidStatus(serde_json::Error),
+
+    #[error("room status is missing last_port")]
+    MissingPort,
+}
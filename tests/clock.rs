@@ -0,0 +1,109 @@
+//! Tests for estimating and scheduling against the server's clock.
+
+mod common;
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use archipelago::clock::{
+    at_server_time, ServerClock, SharedClock, TimestampError, TimestampValidator,
+};
+use futures::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+
+use common::{chat, connect, next_message};
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+#[test]
+fn corrects_for_round_trip_time() {
+    let server_time = now() + 100.0;
+    let mut clock = ServerClock::from_sample(server_time, Duration::from_millis(200));
+    assert!((clock.offset() - 100.1).abs() < 0.05);
+
+    clock.correct_rtt(Duration::from_millis(1000));
+    assert_eq!(clock.rtt(), Duration::from_millis(300));
+    assert!((clock.offset() - 100.15).abs() < 0.05);
+}
+
+#[tokio::test]
+async fn runs_at_server_time() {
+    let clock = SharedClock::new(ServerClock::from_server_time(now() + 50.0));
+    let start = Instant::now();
+
+    let target = clock.get().now() + 0.2;
+    let ran_at = at_server_time(clock.clone(), target, async { clock.get().now() }).await;
+
+    assert!(ran_at >= target);
+    assert!(start.elapsed() >= Duration::from_millis(150));
+}
+
+#[tokio::test]
+async fn runs_now_at_a_server_time_which_is_not_a_number() {
+    let clock = SharedClock::new(ServerClock::from_server_time(now()));
+    let ran = tokio::time::timeout(
+        Duration::from_secs(1),
+        at_server_time(clock, f64::NAN, async { true }),
+    )
+    .await;
+    assert_eq!(ran, Ok(true));
+}
+
+#[test]
+fn rejects_timestamps_outside_the_window() {
+    let clock = ServerClock::from_server_time(now());
+    let validator = TimestampValidator::new(clock, Duration::from_secs(60));
+
+    assert!(validator.validate(clock.now() - 1.0).is_ok());
+    assert!(matches!(
+        validator.validate(clock.now() - 120.0),
+        Err(TimestampError::TooOld(_))
+    ));
+    assert!(matches!(
+        validator.validate(clock.now() + 10.0),
+        Err(TimestampError::InFuture(_))
+    ));
+}
+
+#[test]
+fn rejects_huge_and_non_finite_timestamps() {
+    let clock = ServerClock::from_server_time(now());
+    let validator = TimestampValidator::new(clock, Duration::from_secs(60));
+
+    assert!(matches!(
+        validator.validate(-1e20),
+        Err(TimestampError::TooOld(Duration::MAX))
+    ));
+    assert!(matches!(
+        validator.validate(1e20),
+        Err(TimestampError::InFuture(Duration::MAX))
+    ));
+    for timestamp in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(matches!(
+            validator.validate(timestamp),
+            Err(TimestampError::NotFinite(_))
+        ));
+    }
+}
+
+#[tokio::test]
+async fn ping_measures_round_trip_time() {
+    let (mut client, mut server) = connect().await;
+    // The first estimate comes from the handshake.
+    let handshake_rtt = client.server_clock().rtt();
+    assert!(handshake_rtt > Duration::ZERO);
+
+    client.ping().await.unwrap();
+    // Reading the ping queues a pong, which flushing sends ahead of the chat.
+    let ping = server.socket().next().await.unwrap().unwrap();
+    assert!(matches!(ping, Message::Ping(_)));
+    server.socket().flush().await.unwrap();
+    server.send_messages(vec![chat("hello")]).await;
+    next_message(&mut client).await;
+
+    assert_ne!(client.server_clock().rtt(), handshake_rtt);
+}
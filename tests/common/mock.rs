@@ -0,0 +1,106 @@
+//! A mock server for the integration tests: [`archipelago::testing`], with
+//! helpers which panic instead of returning errors.
+
+use std::ops::{Deref, DerefMut};
+
+use archipelago::client::{AnonymousClient, Client, ConnectOptions};
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, ServerMessage};
+use archipelago::testing::{MockConnection, MockRoom, MockServer};
+use archipelago::transport::BoxTransport;
+
+use super::TIMEOUT;
+
+/// A [`MockConnection`] which panics instead of returning errors, and gives
+/// up on a client which stays quiet for longer than [`TIMEOUT`].
+pub struct Connection(pub MockConnection);
+
+impl Connection {
+    /// Sends the given messages in a single frame.
+    pub async fn send(&mut self, messages: Vec<serde_json::Value>) {
+        self.0.send_raw(&messages).await.unwrap();
+    }
+
+    pub async fn send_messages(&mut self, messages: Vec<ServerMessage>) {
+        self.0.send(messages).await.unwrap();
+    }
+
+    /// Receives the messages from the next frame the client sends.
+    pub async fn recv(&mut self) -> Vec<ClientMessage> {
+        tokio::time::timeout(TIMEOUT, self.0.expect_recv())
+            .await
+            .expect("timed out waiting for the client")
+            .unwrap()
+    }
+
+    /// The websocket itself, for sending and receiving individual frames.
+    pub fn socket(&mut self) -> &mut BoxTransport {
+        self.0.transport()
+    }
+}
+
+impl Deref for Connection {
+    type Target = MockConnection;
+
+    fn deref(&self) -> &MockConnection {
+        &self.0
+    }
+}
+
+impl DerefMut for Connection {
+    fn deref_mut(&mut self) -> &mut MockConnection {
+        &mut self.0
+    }
+}
+
+/// A mock room which sends nothing after Connected, so each test starts from
+/// a quiet connection.
+pub fn room() -> MockRoom {
+    MockRoom::new().on_connect(vec![])
+}
+
+/// Options to connect as [`fixtures::SLOT`].
+pub fn options() -> ConnectOptions {
+    ConnectOptions::new("Player1").game(fixtures::GAME)
+}
+
+/// Connects a client as [`fixtures::SLOT`] to a fresh mock server.
+pub async fn connect() -> (Client, Connection) {
+    connect_with_scheme("ws://").await
+}
+
+/// Like [`connect`], prefixing the server's address with `scheme`.
+pub async fn connect_with_scheme(scheme: &str) -> (Client, Connection) {
+    let (client, connection, _) = connect_with_options(scheme, options()).await;
+    (client, connection)
+}
+
+/// Like [`connect_with_scheme`], with the given options. The server is
+/// returned as well, for tests which expect the client to connect again.
+pub async fn connect_with_options(
+    scheme: &str,
+    options: ConnectOptions,
+) -> (Client, Connection, MockServer) {
+    let mut server = MockServer::listen(room()).await.unwrap();
+    let address = server.url().trim_start_matches("ws://");
+    let client = AnonymousClient::new(format!("{scheme}{address}"))
+        .await
+        .unwrap()
+        .connect_with(options)
+        .await
+        .unwrap();
+
+    let connection = accept(&mut server).await;
+    (client, connection, server)
+}
+
+/// Waits for the next client to complete the Connect handshake. The mock
+/// server doesn't speak TLS, so clients trying it first are dropped and only
+/// their fallback arrives here.
+pub async fn accept(server: &mut MockServer) -> Connection {
+    let connection = tokio::time::timeout(TIMEOUT, server.accept())
+        .await
+        .expect("timed out waiting for a client")
+        .expect("the server stopped");
+    Connection(connection)
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the integration tests. Those which need a mock server
+//! are in [`mock`], on top of [`archipelago::testing`].
+
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use archipelago::client::Client;
+use archipelago::protocol::{PrintJSON, ServerMessage};
+use futures::StreamExt;
+
+#[cfg(feature = "testing")]
+mod mock;
+#[cfg(feature = "testing")]
+#[allow(unused_imports)]
+pub use mock::*;
+
+/// How long to wait on the client or server before failing the test.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn next_message(client: &mut Client) -> ServerMessage {
+    tokio::time::timeout(TIMEOUT, client.next())
+        .await
+        .expect("timed out waiting for a message")
+        .expect("stream ended")
+        .unwrap()
+}
+
+pub fn chat(message: &str) -> ServerMessage {
+    ServerMessage::PrintJSON(PrintJSON::ServerChat {
+        data: Vec::new(),
+        message: message.to_string(),
+    })
+}
+
+pub fn chat_text(message: &ServerMessage) -> &str {
+    match message {
+        ServerMessage::PrintJSON(PrintJSON::ServerChat { message, .. }) => message,
+        message => panic!("expected chat, got {:?}", message),
+    }
+}
@@ -0,0 +1,81 @@
+//! Tests for the companion tracker connection next to a game client.
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{ClientTag, ItemsHandlingFlags};
+use archipelago::testing::MockServer;
+use futures::StreamExt;
+
+use common::{room, TIMEOUT};
+
+#[tokio::test]
+async fn connects_as_a_tracker_and_publishes_to_subscribers() {
+    let mut server = MockServer::in_memory(room().password("hunter2"));
+    let mut client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .password("hunter2"),
+        )
+        .await
+        .unwrap();
+    let _game_connection = server.accept().await.unwrap();
+    let mut items = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ItemReceived])),
+    );
+
+    let companion = client.spawn_companion_tracker().await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let connect = connection.connect_packet();
+    assert_eq!(connect.name, "Player1");
+    assert_eq!(connect.password.as_deref(), Some("hunter2"));
+    assert!(connect.tags.contains(&ClientTag::Tracker));
+    assert_eq!(connect.items_handling, ItemsHandlingFlags::NONE);
+    assert!(!connect.slot_data);
+
+    // What the companion receives reaches the parent's subscribers, without
+    // the parent's stream being polled.
+    connection
+        .send_items(fixtures::received_items().items)
+        .await
+        .unwrap();
+    let event = tokio::time::timeout(TIMEOUT, items.next()).await.unwrap();
+    assert!(
+        matches!(event, Some(Event::ItemReceived { index: 0, .. })),
+        "{event:?}"
+    );
+    assert!(companion.is_running());
+
+    companion.stop();
+    let closed = tokio::time::timeout(TIMEOUT, connection.recv())
+        .await
+        .unwrap();
+    assert!(matches!(closed, Ok(None) | Err(_)), "{closed:?}");
+}
+
+#[tokio::test]
+async fn stops_when_its_connection_closes() {
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let _game_connection = server.accept().await.unwrap();
+
+    let companion = client.spawn_companion_tracker().await.unwrap();
+    server.accept().await.unwrap().close().await.unwrap();
+
+    tokio::time::timeout(TIMEOUT, async {
+        while companion.is_running() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
@@ -0,0 +1,84 @@
+//! Tests for respecting the server's command cooldown.
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::client::ConnectOptions;
+use archipelago::cooldown::CooldownPolicy;
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, JSONMessagePart, PrintJSON, ServerMessage};
+use archipelago::Error;
+
+use common::{connect, connect_with_options, next_message, TIMEOUT};
+
+fn command_result(text: &str) -> ServerMessage {
+    ServerMessage::PrintJSON(PrintJSON::CommandResult {
+        data: vec![JSONMessagePart::Text {
+            text: text.to_string(),
+        }],
+    })
+}
+
+#[tokio::test]
+async fn rejects_commands_during_cooldown() {
+    let (mut client, mut server) = connect().await;
+
+    server
+        .send_messages(vec![command_result(
+            "Please wait 30 seconds before using another command.",
+        )])
+        .await;
+    next_message(&mut client).await;
+    assert_eq!(
+        client.command_cooldown().cooldown(),
+        Duration::from_secs(30)
+    );
+
+    match client.hint("Fire Rod").await {
+        Err(Error::CommandCooldown { remaining }) => {
+            assert!(remaining > Duration::from_secs(29));
+            assert!(remaining <= Duration::from_secs(30));
+        }
+        result => panic!("expected a cooldown error, got {:?}", result),
+    }
+
+    // Plain chat isn't held back.
+    client.say("hello").await.unwrap();
+    let sent = server.recv().await;
+    assert!(matches!(&sent[..], [ClientMessage::Say(say)] if say.text == "hello"));
+}
+
+#[tokio::test]
+async fn queues_commands_during_cooldown() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .command_cooldown(CooldownPolicy::Queue);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+
+    server
+        .send_messages(vec![command_result(
+            "Server options:\nhint_cost: 10\ncommand_cooldown: 0.2",
+        )])
+        .await;
+    next_message(&mut client).await;
+    assert_eq!(
+        client.command_cooldown().cooldown(),
+        Duration::from_millis(200)
+    );
+
+    client.hint("Fire Rod").await.unwrap();
+    let start = std::time::Instant::now();
+    tokio::time::timeout(TIMEOUT, client.hint_location("Link's House"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    let first = server.recv().await;
+    assert!(matches!(&first[..], [ClientMessage::Say(say)] if say.text == "!hint Fire Rod"));
+    let second = server.recv().await;
+    assert!(
+        matches!(&second[..], [ClientMessage::Say(say)] if say.text == "!hint_location Link's House")
+    );
+}
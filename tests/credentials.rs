@@ -0,0 +1,132 @@
+//! Tests for storing room passwords, against an in-memory credential store.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use archipelago::credentials::{delete_password, load_password, store_password};
+use archipelago::Error;
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+
+/// Every password stored, keyed by service and user. Unlike keyring's own
+/// mock, entries for the same user share their password.
+static STORE: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+
+#[derive(Debug)]
+struct MemoryCredential {
+    key: String,
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_password(&self, password: &str) -> keyring::Result<()> {
+        if password.is_empty() {
+            return Err(keyring::Error::Invalid(
+                "password".to_string(),
+                "is empty".to_string(),
+            ));
+        }
+        STORE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(self.key.clone(), password.to_string());
+        Ok(())
+    }
+
+    fn get_password(&self) -> keyring::Result<String> {
+        STORE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .get(&self.key)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_password(&self) -> keyring::Result<()> {
+        STORE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .remove(&self.key)
+            .map(|_| ())
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct MemoryStore;
+
+impl CredentialBuilderApi for MemoryStore {
+    fn build(
+        &self,
+        _target: Option<&str>,
+        service: &str,
+        user: &str,
+    ) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            key: format!("{service}/{user}"),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn use_memory_store() {
+    keyring::set_default_credential_builder(Box::new(MemoryStore));
+}
+
+#[test]
+fn stores_passwords_per_slot_and_host() {
+    use_memory_store();
+    assert_eq!(load_password("archipelago.gg", "Stored").unwrap(), None);
+
+    store_password("archipelago.gg", "Stored", "hunter2").unwrap();
+    store_password("localhost", "Stored", "swordfish").unwrap();
+    assert_eq!(
+        load_password("archipelago.gg", "Stored")
+            .unwrap()
+            .as_deref(),
+        Some("hunter2")
+    );
+    assert_eq!(
+        load_password("localhost", "Stored").unwrap().as_deref(),
+        Some("swordfish")
+    );
+    assert_eq!(load_password("archipelago.gg", "Other").unwrap(), None);
+
+    // Storing again replaces the password.
+    store_password("archipelago.gg", "Stored", "correct horse").unwrap();
+    assert_eq!(
+        load_password("archipelago.gg", "Stored")
+            .unwrap()
+            .as_deref(),
+        Some("correct horse")
+    );
+}
+
+#[test]
+fn deletes_passwords_even_when_missing() {
+    use_memory_store();
+    store_password("archipelago.gg", "Deleted", "hunter2").unwrap();
+
+    delete_password("archipelago.gg", "Deleted").unwrap();
+    assert_eq!(load_password("archipelago.gg", "Deleted").unwrap(), None);
+    delete_password("archipelago.gg", "Deleted").unwrap();
+}
+
+#[test]
+fn reports_store_errors() {
+    use_memory_store();
+    let err = store_password("archipelago.gg", "Rejected", "").expect_err("the store refuses");
+    assert!(
+        matches!(err, Error::Credentials(keyring::Error::Invalid(..))),
+        "{err:?}"
+    );
+}
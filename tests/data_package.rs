@@ -0,0 +1,198 @@
+//! Tests for fetching the data package game by game, resuming and caching.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::data_package::{DataPackageCache, DataPackageFetch, FetchPolicy};
+use archipelago::fixtures;
+use archipelago::protocol::{DataPackageObject, GameData, RoomInfo};
+use archipelago::testing::{MockRoom, MockServer};
+use archipelago::Error;
+
+const OTHER_GAME: &str = "Other Game";
+
+/// Game data for [`OTHER_GAME`], with a checksum of its own.
+fn other_game_data() -> GameData {
+    let mut data = fixtures::game_data();
+    data.checksum = "0123456789abcdef".to_string();
+    data
+}
+
+/// The fixture room with [`OTHER_GAME`] added after ours.
+fn room_info() -> RoomInfo {
+    let mut room_info = fixtures::room_info();
+    room_info.games = vec![fixtures::GAME.to_string(), OTHER_GAME.to_string()];
+    room_info
+        .datapackage_checksums
+        .insert(OTHER_GAME.to_string(), other_game_data().checksum);
+    room_info
+}
+
+fn data_package(games: &[(&str, GameData)]) -> DataPackageObject {
+    DataPackageObject {
+        games: games
+            .iter()
+            .map(|(game, data)| (game.to_string(), data.clone()))
+            .collect(),
+    }
+}
+
+/// A cache kept in memory, which stays readable after a clone is handed to
+/// a client.
+#[derive(Clone, Default)]
+struct MemoryCache(Arc<Mutex<HashMap<String, GameData>>>);
+
+impl DataPackageCache for MemoryCache {
+    fn load(&self, _game: &str, checksum: &str) -> Option<GameData> {
+        self.0.lock().unwrap().get(checksum).cloned()
+    }
+
+    fn store(&self, _game: &str, data: &GameData) -> archipelago::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(data.checksum.clone(), data.clone());
+        Ok(())
+    }
+}
+
+impl MemoryCache {
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+#[test]
+fn only_accepts_cached_games_with_matching_checksums() {
+    let mut fetch = DataPackageFetch::for_room(&room_info());
+    assert_eq!(fetch.pending(), [fixtures::GAME, OTHER_GAME]);
+
+    let mut stale = fixtures::game_data();
+    stale.checksum = "stale".to_string();
+    assert!(!fetch.insert_cached(fixtures::GAME, stale));
+    assert!(fetch.insert_cached(fixtures::GAME, fixtures::game_data()));
+    assert_eq!(fetch.pending(), [OTHER_GAME]);
+    assert!(!fetch.is_complete());
+
+    // Without known checksums, any cached data is taken.
+    let mut fetch = DataPackageFetch::new([OTHER_GAME]);
+    assert!(fetch.insert_cached(OTHER_GAME, other_game_data()));
+    assert!(fetch.is_complete());
+    assert!(fetch.into_data_package().games.contains_key(OTHER_GAME));
+}
+
+#[test]
+fn loads_pending_games_from_a_cache() {
+    let cache = MemoryCache::default();
+    cache.store(OTHER_GAME, &other_game_data()).unwrap();
+
+    let mut fetch = DataPackageFetch::for_room(&room_info());
+    fetch.load_cached(&cache);
+    assert_eq!(fetch.pending(), [fixtures::GAME]);
+    assert_eq!(
+        fetch.received_games().collect::<Vec<_>>(),
+        [&OTHER_GAME.to_string()]
+    );
+}
+
+#[tokio::test]
+async fn resumes_on_a_new_connection() {
+    // The first server fails partway through, after sending our game.
+    let server = MockServer::in_memory(
+        MockRoom::new()
+            .room_info(room_info())
+            .data_package(data_package(&[(fixtures::GAME, fixtures::game_data())])),
+    );
+    let mut fetch = DataPackageFetch::for_room(&room_info());
+    let mut client = AnonymousClient::connect_via(server.connector(), server.url())
+        .await
+        .unwrap();
+    let err = client
+        .fetch_data_package(&mut fetch)
+        .await
+        .expect_err("the server has no data for the other game");
+    assert!(matches!(err, Error::Protocol(_)), "{err:?}");
+    assert_eq!(fetch.pending(), [OTHER_GAME]);
+
+    // The second only has the other game, so our game must not be asked for
+    // again.
+    let server = MockServer::in_memory(
+        MockRoom::new()
+            .room_info(room_info())
+            .data_package(data_package(&[(OTHER_GAME, other_game_data())])),
+    );
+    let mut client = AnonymousClient::connect_via(server.connector(), server.url())
+        .await
+        .unwrap();
+    client.fetch_data_package(&mut fetch).await.unwrap();
+    assert!(fetch.is_complete());
+
+    let data_package = fetch.into_data_package();
+    assert_eq!(data_package.games.len(), 2);
+    assert_eq!(
+        data_package.games[OTHER_GAME].checksum,
+        other_game_data().checksum
+    );
+}
+
+#[tokio::test]
+async fn stores_fetched_games_for_later_connections() {
+    let mut room_info = fixtures::room_info();
+    room_info.games = vec![fixtures::GAME.to_string()];
+    let room = || {
+        MockRoom::new()
+            .room_info(room_info.clone())
+            .on_connect(vec![])
+    };
+    let cache = MemoryCache::default();
+
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .fetch_data_package(FetchPolicy::Auto)
+                .data_package_cache(cache.clone()),
+        )
+        .await
+        .unwrap();
+    server.accept().await.unwrap();
+    assert!(client
+        .data_package()
+        .unwrap()
+        .games
+        .contains_key(fixtures::GAME));
+    assert_eq!(cache.len(), 1);
+
+    // A server without any data package still works from the cache.
+    let mut server = MockServer::in_memory(room().data_package(data_package(&[])));
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .fetch_data_package(FetchPolicy::CacheOnly)
+                .data_package_cache(cache.clone()),
+        )
+        .await
+        .unwrap();
+    server.accept().await.unwrap();
+    assert!(client
+        .data_package()
+        .unwrap()
+        .games
+        .contains_key(fixtures::GAME));
+
+    // Without the cache, CacheOnly leaves the game out.
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .fetch_data_package(FetchPolicy::CacheOnly),
+        )
+        .await
+        .unwrap();
+    server.accept().await.unwrap();
+    assert!(client.data_package().unwrap().games.is_empty());
+}
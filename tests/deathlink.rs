@@ -0,0 +1,134 @@
+//! Tests for the death amnesty and persisting its count.
+
+use std::sync::{Arc, Mutex};
+
+use archipelago::deathlink::{AmnestyStore, DeathAmnesty};
+use archipelago::protocol::DeathLink;
+
+/// A store whose count stays readable after being handed to the amnesty.
+#[derive(Clone, Default)]
+struct SharedStore(Arc<Mutex<u32>>);
+
+impl SharedStore {
+    fn get(&self) -> u32 {
+        *self.0.lock().unwrap()
+    }
+}
+
+impl AmnestyStore for SharedStore {
+    fn load(&mut self) -> u32 {
+        self.get()
+    }
+
+    fn store(&mut self, count: u32) {
+        *self.0.lock().unwrap() = count;
+    }
+}
+
+fn record(amnesty: &mut DeathAmnesty, deaths: usize) -> Vec<bool> {
+    (0..deaths).map(|_| amnesty.record_death()).collect()
+}
+
+#[test]
+fn ignores_deaths_up_to_the_amnesty() {
+    let mut amnesty = DeathAmnesty::new(2);
+    assert_eq!(
+        record(&mut amnesty, 6),
+        [false, false, true, false, false, true]
+    );
+    assert_eq!(amnesty.count(), 0);
+
+    // Without an amnesty every death is sent.
+    let mut amnesty = DeathAmnesty::new(0);
+    assert_eq!(record(&mut amnesty, 3), [true, true, true]);
+}
+
+#[test]
+fn sends_one_in_every_ratio() {
+    let mut amnesty = DeathAmnesty::ratio(3);
+    assert_eq!(amnesty.amnesty(), 2);
+    assert_eq!(record(&mut amnesty, 3), [false, false, true]);
+
+    // A ratio of zero behaves like one rather than underflowing.
+    assert_eq!(DeathAmnesty::ratio(0).amnesty(), 0);
+}
+
+#[test]
+fn keeps_the_count_when_the_amnesty_changes() {
+    let mut amnesty = DeathAmnesty::new(5);
+    record(&mut amnesty, 2);
+    assert_eq!(amnesty.count(), 2);
+
+    // Lowering the amnesty below the count sends the next death.
+    amnesty.set_amnesty(1);
+    assert_eq!(amnesty.count(), 2);
+    assert!(amnesty.record_death());
+    assert_eq!(amnesty.count(), 0);
+}
+
+#[test]
+fn persists_the_count_across_restarts() {
+    let store = SharedStore::default();
+    let mut amnesty = DeathAmnesty::new(3).with_store(store.clone());
+    record(&mut amnesty, 2);
+    assert_eq!(store.get(), 2);
+    drop(amnesty);
+
+    // A new amnesty carries on from the stored count.
+    let mut amnesty = DeathAmnesty::new(3).with_store(store.clone());
+    assert_eq!(amnesty.count(), 2);
+    assert_eq!(record(&mut amnesty, 2), [false, true]);
+    assert_eq!(store.get(), 0);
+}
+
+#[test]
+fn death_times_survive_a_trip_through_json() {
+    // Without float_roundtrip, serde_json parses this back one ULP off.
+    let death = DeathLink {
+        time: 1_700_517_519.135_038_1,
+        cause: None,
+        source: "Player1".to_string(),
+    };
+    let json = serde_json::to_string(&death).unwrap();
+    let parsed: DeathLink = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.time.to_bits(), death.time.to_bits(), "{json}");
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn recognizes_the_echo_of_our_own_death() {
+    use archipelago::client::ConnectOptions;
+    use archipelago::fixtures;
+    use archipelago::protocol::{ClientMessage, ServerMessage};
+    use archipelago::testing::{MockRoom, MockServer};
+    use futures::StreamExt;
+
+    let mut server = MockServer::in_memory(MockRoom::new().on_connect(vec![]));
+    let mut client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    let death = client.send_death(Some("fell".to_string())).await.unwrap();
+    let sent = connection.expect_recv().await.unwrap();
+    let [ClientMessage::Bounce(bounce)] = &sent[..] else {
+        panic!("expected a Bounce, got {sent:?}");
+    };
+    let echo: ServerMessage = serde_json::from_value(serde_json::json!({
+        "cmd": "Bounced",
+        "tags": bounce.tags,
+        "data": bounce.data,
+    }))
+    .unwrap();
+    connection.send(vec![echo]).await.unwrap();
+
+    let message = client.next().await.unwrap().unwrap();
+    let ServerMessage::Bounced(bounced) = &message else {
+        panic!("expected a Bounced, got {message:?}");
+    };
+    let echoed = DeathLink::from_bounced(bounced).unwrap();
+    assert_eq!(echoed.time.to_bits(), death.time.to_bits());
+    assert!(client.is_own_death(&echoed));
+    assert!(client.death_link(&message).is_none());
+}
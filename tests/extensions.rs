@@ -0,0 +1,79 @@
+//! Tests for decoding game-specific messages with an ExtensionRegistry.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::extensions::ExtensionRegistry;
+use archipelago::fixtures;
+use futures::{FutureExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+
+use common::{connect_with_options, next_message};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Move {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Score(i64);
+
+fn registry() -> ExtensionRegistry {
+    let mut registry = ExtensionRegistry::new();
+    registry.register_bounce_json::<Move>("Chess");
+    registry.register_storage("chess_score_", |_, value| value.as_i64().map(Score));
+    registry
+}
+
+#[tokio::test]
+async fn decodes_matching_messages() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .extensions(registry());
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut events = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::Extension])),
+    );
+
+    let messages = vec![
+        // No decoder for the tag.
+        json!({ "cmd": "Bounced", "tags": ["Checkers"], "data": { "from": "a2", "to": "a3" } }),
+        // The data isn't a move, so the decoder fails.
+        json!({ "cmd": "Bounced", "tags": ["Chess"], "data": { "resign": true } }),
+        json!({ "cmd": "Bounced", "tags": ["Chess"], "data": { "from": "e2", "to": "e4" } }),
+        // Only the key with the prefix is decoded.
+        json!({ "cmd": "Retrieved", "keys": { "chess_score_1": 3, "other": 4 } }),
+        json!({ "cmd": "SetReply", "key": "chess_score_2", "value": "none", "original_value": null }),
+    ];
+    let count = messages.len();
+    server.send(messages).await;
+    // Every message is still returned from the stream, decoded or not.
+    for _ in 0..count {
+        next_message(&mut client).await;
+    }
+
+    let mut decoded = Vec::new();
+    while let Some(Some(event)) = events.next().now_or_never() {
+        match event {
+            Event::Extension(event) => decoded.push(event),
+            event => panic!("expected an extension event, got {:?}", event),
+        }
+    }
+
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[0].key, "Chess");
+    assert_eq!(
+        decoded[0].downcast_ref::<Move>(),
+        Some(&Move {
+            from: "e2".to_string(),
+            to: "e4".to_string()
+        })
+    );
+    assert_eq!(decoded[1].key, "chess_score_1");
+    assert_eq!(decoded[1].downcast_ref::<Score>(), Some(&Score(3)));
+    assert!(decoded[1].downcast_ref::<Move>().is_none());
+}
@@ -0,0 +1,98 @@
+//! Tests for injecting faults into the connection.
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::faults::{Fault, FaultPolicy, FaultyTransport};
+use archipelago::fixtures;
+use archipelago::testing::MockServer;
+use futures::{stream, StreamExt};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use common::{accept, chat, chat_text, next_message};
+
+fn frames() -> Vec<Result<Message, tungstenite::Error>> {
+    (0..20)
+        .map(|i| Message::Text(format!("[{}, {}, {}]", i, i + 100, i + 200)))
+        .map(Ok)
+        .collect()
+}
+
+async fn run(policy: FaultPolicy) -> (Vec<String>, Vec<Fault>) {
+    let mut transport = FaultyTransport::new(stream::iter(frames()), policy);
+    let mut received = Vec::new();
+    while let Some(Ok(message)) = transport.next().await {
+        received.push(message.into_text().unwrap());
+    }
+    (received, transport.injected().to_vec())
+}
+
+#[tokio::test]
+async fn faults_are_reproducible_from_the_seed() {
+    let policy = FaultPolicy::new(7)
+        .delay(0.2, Duration::from_millis(5))
+        .duplicate(0.2)
+        .reorder(0.2)
+        .corrupt(0.2);
+
+    let (received, injected) = run(policy.clone()).await;
+    assert!(!injected.is_empty());
+    assert_eq!(run(policy).await, (received, injected));
+}
+
+#[tokio::test]
+async fn reorders_packets_within_frames() {
+    let (received, injected) = run(FaultPolicy::new(1).reorder(1.0)).await;
+    assert_eq!(received.len(), 20);
+    assert!(injected.iter().all(|fault| *fault == Fault::Reorder));
+
+    for (i, text) in received.iter().enumerate() {
+        let mut packets: Vec<i64> = serde_json::from_str(text).unwrap();
+        packets.sort();
+        let i = i as i64;
+        assert_eq!(packets, [i, i + 100, i + 200]);
+    }
+}
+
+#[tokio::test]
+async fn corrupted_frames_do_not_parse() {
+    let (received, _) = run(FaultPolicy::new(2).skip(5).corrupt(1.0)).await;
+    assert!(serde_json::from_str::<serde_json::Value>(&received[4]).is_ok());
+    assert!(received[5..]
+        .iter()
+        .all(|text| serde_json::from_str::<serde_json::Value>(text).is_err()));
+}
+
+#[tokio::test]
+async fn disconnect_ends_the_stream() {
+    let mut transport = FaultyTransport::new(
+        stream::iter(frames()),
+        FaultPolicy::new(3).skip(2).disconnect(1.0),
+    );
+    assert!(transport.next().await.unwrap().is_ok());
+    assert!(transport.next().await.unwrap().is_ok());
+    assert!(transport.next().await.unwrap().is_err());
+    assert!(transport.next().await.is_none());
+    assert_eq!(transport.injected(), [Fault::Disconnect]);
+}
+
+#[tokio::test]
+async fn client_sees_duplicated_messages() {
+    let mut mock = MockServer::listen(common::room()).await.unwrap();
+
+    // RoomInfo has already been read, so only Connected is skipped.
+    let mut client = AnonymousClient::new(mock.url())
+        .await
+        .unwrap()
+        .inject_faults(FaultPolicy::new(4).skip(1).duplicate(1.0))
+        .connect_with(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut server = accept(&mut mock).await;
+
+    server.send_messages(vec![chat("hello")]).await;
+    assert_eq!(chat_text(&next_message(&mut client).await), "hello");
+    assert_eq!(chat_text(&next_message(&mut client).await), "hello");
+}
@@ -0,0 +1,50 @@
+//! Checks the canned protocol objects are consistent with each other.
+
+use archipelago::fixtures;
+use archipelago::protocol::AnonymousServerMessage;
+use archipelago::room::RoomState;
+
+#[test]
+fn fixtures_are_consistent() {
+    let room_info = fixtures::room_info();
+    let connected = fixtures::connected();
+    let data_package = fixtures::data_package();
+
+    assert!(room_info.games.iter().any(|game| game == fixtures::GAME));
+    assert_eq!(
+        room_info.datapackage_checksums[fixtures::GAME],
+        data_package.games[fixtures::GAME].checksum
+    );
+
+    assert_eq!(connected.slot_info[&fixtures::SLOT].game, fixtures::GAME);
+    let value = serde_json::to_value(AnonymousServerMessage::Connected(connected.clone())).unwrap();
+    assert_eq!(
+        value["slot_info"][fixtures::SLOT.to_string()]["game"],
+        fixtures::GAME
+    );
+
+    // Going through the tagged enum buffers the map, which serde_json can't
+    // convert string keys from by itself.
+    let AnonymousServerMessage::Connected(parsed) = serde_json::from_value(value).unwrap() else {
+        panic!("expected Connected");
+    };
+    assert_eq!(parsed.slot_info[&fixtures::SLOT].game, fixtures::GAME);
+
+    let room = RoomState::new(&room_info, &connected);
+    assert_eq!(room.total_locations() as i64, fixtures::LOCATION_COUNT);
+    assert_eq!(
+        room.checked_locations().len() as i64,
+        fixtures::CHECKED_COUNT
+    );
+
+    let game_data = fixtures::game_data();
+    assert_eq!(game_data.item_name_to_id.len() as i64, fixtures::ITEM_COUNT);
+    for location in room.checked_locations().iter() {
+        assert!(game_data.contains_location_id(location));
+    }
+
+    let received = fixtures::received_items();
+    for item in &received.items {
+        assert!(game_data.contains_item_id(item.item));
+    }
+}
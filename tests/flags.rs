@@ -0,0 +1,59 @@
+//! Tests for building and inspecting bit flags.
+
+use archipelago::protocol::{ItemsHandlingFlags, NetworkItemFlags};
+
+#[test]
+fn combines_flags() {
+    let mut flags = ItemsHandlingFlags::CAN_RECEIVE_ITEMS;
+    flags |= ItemsHandlingFlags::HAS_LOCAL_ITEMS;
+    assert!(flags.contains(ItemsHandlingFlags::CAN_RECEIVE_ITEMS));
+    assert!(!flags.contains(ItemsHandlingFlags::all()));
+    assert!(flags.intersects(ItemsHandlingFlags::all()));
+    assert_eq!(
+        flags & ItemsHandlingFlags::HAS_LOCAL_ITEMS,
+        ItemsHandlingFlags::HAS_LOCAL_ITEMS
+    );
+    assert_eq!(!flags, ItemsHandlingFlags::REQUEST_STARTING_INVENTORY);
+
+    flags.remove(ItemsHandlingFlags::CAN_RECEIVE_ITEMS);
+    assert_eq!(flags.bits(), 0b10);
+    flags.set(ItemsHandlingFlags::HAS_LOCAL_ITEMS, false);
+    assert!(flags.is_empty());
+    assert_eq!(flags, ItemsHandlingFlags::empty());
+}
+
+#[test]
+fn converts_from_bits() {
+    assert_eq!(
+        NetworkItemFlags::from_bits(0b101),
+        Some(NetworkItemFlags::PROGRESSION | NetworkItemFlags::TRAP)
+    );
+    assert_eq!(NetworkItemFlags::from_bits(0b1000), None);
+    assert_eq!(
+        NetworkItemFlags::from_bits_truncate(0b1010),
+        NetworkItemFlags::USEFUL
+    );
+    assert_eq!(NetworkItemFlags::all().bits(), 0b111);
+
+    let flags: NetworkItemFlags = serde_json::from_str("3").unwrap();
+    assert!(flags.is_progression() && flags.is_important() && !flags.is_filler());
+    assert_eq!(serde_json::to_string(&flags).unwrap(), "3");
+}
+
+#[test]
+fn debug_names_the_flags() {
+    assert_eq!(
+        format!(
+            "{:?}",
+            ItemsHandlingFlags::CAN_RECEIVE_ITEMS | ItemsHandlingFlags::HAS_LOCAL_ITEMS
+        ),
+        "ItemsHandlingFlags(CAN_RECEIVE_ITEMS | HAS_LOCAL_ITEMS)"
+    );
+    assert_eq!(
+        format!("{:?}", NetworkItemFlags::NONE),
+        "NetworkItemFlags(empty)"
+    );
+
+    let unknown: NetworkItemFlags = serde_json::from_str("12").unwrap();
+    assert_eq!(format!("{:?}", unknown), "NetworkItemFlags(TRAP | 0b1000)");
+}
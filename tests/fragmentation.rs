@@ -0,0 +1,92 @@
+//! Tests for messages which reach the client in pieces: websocket messages
+//! fragmented into continuation frames, and text messages split partway
+//! through a packet by a proxy.
+
+mod common;
+
+use archipelago::client::MalformedPacketPolicy;
+use futures::SinkExt;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::Message;
+
+use common::{chat, chat_text, connect, next_message, Connection};
+
+/// Encodes chat messages as a single frame's worth of text.
+fn chat_frame(messages: &[&str]) -> String {
+    let messages: Vec<_> = messages
+        .iter()
+        .map(|message| serde_json::to_value(chat(message)).unwrap())
+        .collect();
+    serde_json::Value::Array(messages).to_string()
+}
+
+/// Splits text into pieces of at most `size` bytes.
+fn pieces(text: &str, size: usize) -> Vec<Vec<u8>> {
+    text.as_bytes().chunks(size).map(<[u8]>::to_vec).collect()
+}
+
+/// Sends text as a websocket message fragmented into continuation frames.
+async fn send_fragmented(server: &mut Connection, text: &str, size: usize) {
+    let pieces = pieces(text, size);
+    let last = pieces.len() - 1;
+    for (i, piece) in pieces.into_iter().enumerate() {
+        let opcode = if i == 0 {
+            OpCode::Data(Data::Text)
+        } else {
+            OpCode::Data(Data::Continue)
+        };
+        let frame = Frame::message(piece, opcode, i == last);
+        server.socket().feed(Message::Frame(frame)).await.unwrap();
+    }
+    server.socket().flush().await.unwrap();
+}
+
+/// Sends text split into several complete text messages.
+async fn send_split(server: &mut Connection, text: &str, size: usize) {
+    for piece in pieces(text, size) {
+        let piece = String::from_utf8(piece).unwrap();
+        server.socket().send(Message::Text(piece)).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn continuation_frames_are_reassembled() {
+    let (mut client, mut server) = connect().await;
+
+    send_fragmented(&mut server, &chat_frame(&["1", "2"]), 7).await;
+    send_fragmented(&mut server, &chat_frame(&["3"]), 1).await;
+
+    for expected in ["1", "2", "3"] {
+        assert_eq!(chat_text(&next_message(&mut client).await), expected);
+    }
+}
+
+#[tokio::test]
+async fn split_text_messages_are_joined() {
+    let (mut client, mut server) = connect().await;
+    client.set_malformed_packet_policy(MalformedPacketPolicy::Error);
+
+    send_split(&mut server, &chat_frame(&["1", "2"]), 10).await;
+    server.send_messages(vec![chat("3")]).await;
+
+    for expected in ["1", "2", "3"] {
+        assert_eq!(chat_text(&next_message(&mut client).await), expected);
+    }
+}
+
+#[tokio::test]
+async fn abandoned_partial_message_is_skipped() {
+    let (mut client, mut server) = connect().await;
+
+    let text = chat_frame(&["lost"]);
+    server
+        .socket()
+        .send(Message::Text(text[..text.len() / 2].to_string()))
+        .await
+        .unwrap();
+    server.send_messages(vec![chat("1")]).await;
+
+    assert_eq!(chat_text(&next_message(&mut client).await), "1");
+    assert_eq!(client.malformed_packet_count(), 1);
+}
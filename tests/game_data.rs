@@ -0,0 +1,208 @@
+//! Tests for naming items of games missing from the data package.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::data_package::{DataPackageCache, FetchPolicy};
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, DataPackage, DataPackageObject, GameData, ServerMessage,
+};
+use archipelago::render::Renderer;
+use archipelago::testing::MockServer;
+use futures::{FutureExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+
+use common::{accept, connect, next_message, Connection, TIMEOUT};
+
+const CUSTOM_SLOT: i64 = 3;
+
+fn item_from_custom_game() -> serde_json::Value {
+    serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "CommandResult",
+        "data": [
+            { "text": "Found " },
+            { "type": "item_id", "text": "5", "flags": 0, "player": CUSTOM_SLOT },
+        ],
+    })
+}
+
+/// Reads the client's next frame straight off the socket, so GetDataPackage
+/// reaches the test rather than being answered by the mock server.
+async fn recv_request(server: &mut Connection) -> Vec<ClientMessage> {
+    let frame = tokio::time::timeout(TIMEOUT, server.socket().next())
+        .await
+        .expect("timed out waiting for the client")
+        .expect("client disconnected")
+        .unwrap();
+    match frame {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        frame => panic!("expected text, got {frame:?}"),
+    }
+}
+
+#[tokio::test]
+async fn reports_and_loads_missing_games() {
+    let (mut client, mut server) = connect().await;
+    let mut missing = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::MissingGameData])),
+    );
+
+    // Without a data package nothing is reported, so load our own game.
+    let load = tokio::spawn(async move {
+        let request = recv_request(&mut server).await;
+        assert!(matches!(
+            &request[..],
+            [ClientMessage::GetDataPackage(get)] if get.games == [fixtures::GAME]
+        ));
+        server
+            .send_messages(vec![ServerMessage::DataPackage(DataPackage {
+                data: fixtures::data_package(),
+            })])
+            .await;
+        server
+    });
+    tokio::time::timeout(TIMEOUT, client.load_game_data(fixtures::GAME))
+        .await
+        .unwrap()
+        .unwrap();
+    let mut server = load.await.unwrap();
+    assert!(client.resolver().unwrap().has_game(fixtures::GAME));
+
+    server
+        .send(vec![serde_json::json!({
+            "cmd": "RoomUpdate",
+            "slot_info": {
+                "1": { "name": "Player1", "game": fixtures::GAME, "type": 1, "group_members": [] },
+                "2": { "name": "Player2", "game": fixtures::GAME, "type": 1, "group_members": [] },
+                "3": { "name": "Player3", "game": "Custom Game", "type": 1, "group_members": [] },
+            },
+        })])
+        .await;
+    server
+        .send(vec![item_from_custom_game(), item_from_custom_game()])
+        .await;
+
+    next_message(&mut client).await;
+    let ServerMessage::PrintJSON(print) = next_message(&mut client).await else {
+        panic!("expected PrintJSON");
+    };
+    next_message(&mut client).await;
+
+    assert_eq!(
+        Renderer::new().render_with(&print, &client.names()),
+        "Found Unknown Item (5)"
+    );
+
+    // Reported once, however many messages refer to the game.
+    let event = tokio::time::timeout(TIMEOUT, missing.next()).await.unwrap();
+    assert!(matches!(event, Some(Event::MissingGameData(game)) if game == "Custom Game"));
+    assert!(missing.next().now_or_never().is_none());
+}
+
+/// A cache holding [`fixtures::GAME`], which records the games stored in it.
+#[derive(Clone, Default)]
+struct MemoryCache {
+    stored: Arc<Mutex<Vec<String>>>,
+}
+
+impl DataPackageCache for MemoryCache {
+    fn load(&self, game: &str, checksum: &str) -> Option<GameData> {
+        let data = fixtures::game_data();
+        (game == fixtures::GAME && checksum == data.checksum).then_some(data)
+    }
+
+    fn store(&self, game: &str, _data: &GameData) -> archipelago::Result<()> {
+        self.stored.lock().unwrap().push(game.to_string());
+        Ok(())
+    }
+}
+
+fn custom_game_data() -> GameData {
+    GameData {
+        item_name_to_id: [("Custom Item".to_string(), 5)].into_iter().collect(),
+        location_name_to_id: Default::default(),
+        version: 0,
+        checksum: "custom".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn fetches_games_added_to_the_room() {
+    // Only our own game is in the room to begin with, and it's cached, so
+    // nothing is fetched during the handshake.
+    let mut room_info = fixtures::room_info();
+    room_info.games = vec![fixtures::GAME.to_string()];
+    let mut mock = MockServer::listen(common::room().room_info(room_info))
+        .await
+        .unwrap();
+
+    let cache = MemoryCache::default();
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .fetch_data_package(FetchPolicy::Auto)
+        .data_package_cache(cache.clone());
+    let mut client = AnonymousClient::new(mock.url())
+        .await
+        .unwrap()
+        .connect_with(options)
+        .await
+        .unwrap();
+    let mut server = accept(&mut mock).await;
+    let mut loaded = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::GameDataLoaded])),
+    );
+
+    server
+        .send(vec![serde_json::json!({
+            "cmd": "RoomUpdate",
+            "games": [fixtures::GAME, "Custom Game"],
+            "datapackage_checksums": {
+                fixtures::GAME: fixtures::game_data().checksum,
+                "Custom Game": "custom",
+            },
+        })])
+        .await;
+    assert!(matches!(
+        next_message(&mut client).await,
+        ServerMessage::RoomUpdate(_)
+    ));
+
+    // The request goes out without waiting on the caller, and the reply is
+    // still returned from the stream.
+    let reply = tokio::spawn(async move {
+        let request = recv_request(&mut server).await;
+        assert!(matches!(
+            &request[..],
+            [ClientMessage::GetDataPackage(get)] if get.games == ["Custom Game"]
+        ));
+        server
+            .send_messages(vec![ServerMessage::DataPackage(DataPackage {
+                data: DataPackageObject {
+                    games: [("Custom Game".to_string(), custom_game_data())]
+                        .into_iter()
+                        .collect(),
+                },
+            })])
+            .await;
+        server
+    });
+    assert!(matches!(
+        next_message(&mut client).await,
+        ServerMessage::DataPackage(_)
+    ));
+    let _server = reply.await.unwrap();
+
+    let event = tokio::time::timeout(TIMEOUT, loaded.next()).await.unwrap();
+    assert!(matches!(event, Some(Event::GameDataLoaded(games)) if games == ["Custom Game"]));
+
+    let resolver = client.resolver().unwrap();
+    assert!(resolver.has_game(fixtures::GAME));
+    assert_eq!(resolver.item_name("Custom Game", 5).unwrap(), "Custom Item");
+    assert_eq!(*cache.stored.lock().unwrap(), ["Custom Game"]);
+}
@@ -0,0 +1,213 @@
+//! Packet-level golden tests against the reference Python Archipelago server.
+//!
+//! These are skipped unless `ARCHIPELAGO_GOLDEN_SERVER` is set to a shell
+//! command which starts a server (for example `python3 MultiServer.py --port
+//! 38281 golden.archipelago` or an equivalent `docker run`). The following
+//! variables are also read:
+//!
+//! - `ARCHIPELAGO_GOLDEN_ADDR`: address the server listens on (default
+//!   `localhost:38281`)
+//! - `ARCHIPELAGO_GOLDEN_SLOT`: slot name to connect as (required)
+//! - `ARCHIPELAGO_GOLDEN_GAME`: game of that slot (required)
+//! - `ARCHIPELAGO_GOLDEN_PASS`: room password, if any
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::client::{AnonymousClient, Client};
+use archipelago::protocol::{
+    Bounce, ClientMessage, DataStorageOperation, Get, ItemsHandlingFlags, LocationChecks,
+    LocationScouts, ServerMessage, Set,
+};
+use futures::StreamExt;
+
+struct Golden {
+    addr: String,
+    slot: String,
+    game: String,
+    password: Option<String>,
+    _server: tokio::process::Child,
+}
+
+async fn start_server() -> Option<Golden> {
+    let command = match std::env::var("ARCHIPELAGO_GOLDEN_SERVER") {
+        Ok(command) => command,
+        Err(_) => {
+            eprintln!("ARCHIPELAGO_GOLDEN_SERVER not set, skipping golden tests");
+            return None;
+        }
+    };
+
+    let server = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .kill_on_drop(true)
+        .spawn()
+        .expect("failed to start golden server");
+
+    let addr =
+        std::env::var("ARCHIPELAGO_GOLDEN_ADDR").unwrap_or_else(|_| "localhost:38281".into());
+
+    // The server takes a moment to load the multidata, so poll until it
+    // accepts connections.
+    let mut attempts = 0;
+    while AnonymousClient::new(&addr).await.is_err() {
+        attempts += 1;
+        assert!(attempts < 60, "golden server never became reachable");
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Some(Golden {
+        addr,
+        slot: std::env::var("ARCHIPELAGO_GOLDEN_SLOT").expect("missing ARCHIPELAGO_GOLDEN_SLOT"),
+        game: std::env::var("ARCHIPELAGO_GOLDEN_GAME").expect("missing ARCHIPELAGO_GOLDEN_GAME"),
+        password: std::env::var("ARCHIPELAGO_GOLDEN_PASS").ok(),
+        _server: server,
+    })
+}
+
+/// Reads messages until one matches, failing the test if none arrives in time.
+async fn expect_message<T>(
+    client: &mut Client,
+    what: &str,
+    mut matcher: impl FnMut(ServerMessage) -> Option<T>,
+) -> T {
+    // A real server is slower to answer than the mock one.
+    let result = tokio::time::timeout(common::TIMEOUT * 2, async {
+        while let Some(message) = client.next().await {
+            let message = message.expect("failed to parse message from golden server");
+            if let Some(found) = matcher(message) {
+                return Some(found);
+            }
+        }
+        None
+    })
+    .await;
+
+    match result {
+        Ok(Some(found)) => found,
+        Ok(None) => panic!("stream ended while waiting for {}", what),
+        Err(_) => panic!("timed out waiting for {}", what),
+    }
+}
+
+#[tokio::test]
+async fn golden_session() {
+    let Some(golden) = start_server().await else {
+        return;
+    };
+
+    let anonymous = AnonymousClient::new(&golden.addr).await.unwrap();
+    assert!(!anonymous.get_room_info().games.is_empty());
+
+    let mut client = anonymous
+        .connect(
+            golden.password.clone(),
+            golden.game.clone(),
+            golden.slot.clone(),
+            vec!["AP"],
+            ItemsHandlingFlags::CAN_RECEIVE_ITEMS,
+        )
+        .await
+        .unwrap();
+
+    let team = client.get_connected().team;
+    let slot = client.get_connected().slot;
+    assert!(client.get_connected().slot_info.contains_key(&slot));
+
+    // Data storage: Set with want_reply must answer with the old and new value,
+    // and a following Get must observe the new value.
+    let key = format!("golden_{}_{}", team, slot);
+    client
+        .send(ClientMessage::Set(Set {
+            key: key.clone(),
+            default: 0.into(),
+            want_reply: true,
+            operations: vec![
+                DataStorageOperation::Replace(0.into()),
+                DataStorageOperation::Add(5.into()),
+            ],
+        }))
+        .await
+        .unwrap();
+    let (value, original_value) = expect_message(&mut client, "SetReply", |msg| match msg {
+        ServerMessage::SetReply(reply) if reply.key == key => {
+            Some((reply.value, reply.original_value))
+        }
+        _ => None,
+    })
+    .await;
+    assert_eq!(value, serde_json::json!(5));
+    assert!(original_value.is_number());
+
+    client
+        .send(ClientMessage::Get(Get {
+            keys: vec![key.clone()],
+        }))
+        .await
+        .unwrap();
+    let retrieved = expect_message(&mut client, "Retrieved", |msg| match msg {
+        ServerMessage::Retrieved(retrieved) => Some(retrieved),
+        _ => None,
+    })
+    .await;
+    assert_eq!(retrieved.keys.get(&key), Some(&serde_json::json!(5)));
+
+    // Bounce: a message targeting our own slot must come back to us unchanged.
+    let data = serde_json::json!({ "golden": true });
+    client
+        .send(ClientMessage::Bounce(Bounce {
+            games: vec![],
+            slots: vec![slot],
+            tags: vec![],
+            data: data.clone(),
+        }))
+        .await
+        .unwrap();
+    expect_message(&mut client, "Bounced", |msg| match msg {
+        ServerMessage::Bounced(bounced) if bounced.slots == vec![slot] => Some(()),
+        _ => None,
+    })
+    .await;
+
+    // Scouts and checks against the first missing location.
+    let Some(&location) = client.get_connected().missing_locations.first() else {
+        return;
+    };
+
+    client
+        .send(ClientMessage::LocationScouts(LocationScouts {
+            locations: vec![location],
+            create_as_hint: 0,
+        }))
+        .await
+        .unwrap();
+    let info = expect_message(&mut client, "LocationInfo", |msg| match msg {
+        ServerMessage::LocationInfo(info) => Some(info),
+        _ => None,
+    })
+    .await;
+    assert_eq!(info.locations.len(), 1);
+    assert_eq!(info.locations[0].location, location);
+
+    client
+        .send(ClientMessage::LocationChecks(LocationChecks {
+            locations: vec![location],
+        }))
+        .await
+        .unwrap();
+    expect_message(&mut client, "RoomUpdate", |msg| match msg {
+        ServerMessage::RoomUpdate(_) => Some(()),
+        _ => None,
+    })
+    .await;
+
+    // Hints: the server answers a !hint command with PrintJSON.
+    client.say("!hint").await.unwrap();
+    expect_message(&mut client, "PrintJSON", |msg| match msg {
+        ServerMessage::PrintJSON(_) => Some(()),
+        _ => None,
+    })
+    .await;
+}
@@ -0,0 +1,154 @@
+//! Tests for driving a client from a background task through handles.
+
+mod common;
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::handle::ClientHandle;
+use archipelago::protocol::{ClientMessage, RoomUpdate, Say, ServerMessage};
+use archipelago::room::CheckedBy;
+use archipelago::testing::{MockConnection, MockServer};
+use archipelago::Error;
+use futures::StreamExt;
+
+use common::{room, TIMEOUT};
+
+async fn spawn_client() -> (ClientHandle, archipelago::handle::Messages, MockConnection) {
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let connection = server.accept().await.unwrap();
+    let (handle, messages) = client.spawn();
+    (handle, messages, connection)
+}
+
+#[test]
+fn handles_are_shareable() {
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+    assert_shareable::<ClientHandle>();
+}
+
+#[tokio::test]
+async fn sends_from_every_handle_and_tracks_state() {
+    let (handle, mut messages, mut connection) = spawn_client().await;
+    assert_eq!(handle.connected().slot, fixtures::SLOT);
+
+    let location = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let other = handle.clone();
+    tokio::spawn(async move { other.check_locations(&[location]).await })
+        .await
+        .unwrap()
+        .unwrap();
+    handle.say("hello").await.unwrap();
+
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(matches!(
+        &sent[..],
+        [ClientMessage::LocationChecks(checks)] if checks.locations == [location]
+    ));
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(matches!(&sent[..], [ClientMessage::Say(say)] if say.text == "hello"));
+
+    connection
+        .send_items(fixtures::received_items().items)
+        .await
+        .unwrap();
+    let message = tokio::time::timeout(TIMEOUT, messages.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(message, ServerMessage::ReceivedItems(_)));
+    assert_eq!(
+        handle.state().read().received_items().len(),
+        fixtures::received_items().items.len()
+    );
+}
+
+#[tokio::test]
+async fn follows_the_clients_optimistic_checks() {
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .optimistic_checks(true),
+        )
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let (handle, mut messages) = client.spawn();
+
+    let confirmed = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let rejected = confirmed + 1;
+    handle
+        .check_locations(&[confirmed, rejected])
+        .await
+        .unwrap();
+    connection.expect_recv().await.unwrap();
+    assert!(!handle
+        .state()
+        .read()
+        .room()
+        .missing_locations()
+        .contains(rejected));
+
+    let mut room_update = RoomUpdate::default();
+    room_update.checked_locations = Some(vec![confirmed]);
+    connection.room_update(room_update).await.unwrap();
+    tokio::time::timeout(TIMEOUT, messages.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    let state = handle.state().read();
+    assert_eq!(state.room().checked_by(confirmed), Some(CheckedBy::Us));
+    assert_eq!(state.room().checked_by(rejected), None);
+    assert!(state.room().missing_locations().contains(rejected));
+}
+
+#[tokio::test]
+async fn reports_why_the_task_stopped() {
+    let (handle, mut messages, connection) = spawn_client().await;
+
+    connection.close().await.unwrap();
+    let next = tokio::time::timeout(TIMEOUT, messages.next())
+        .await
+        .unwrap();
+    assert!(next.is_none(), "{next:?}");
+    assert!(handle.is_closed());
+
+    let err = handle.say("anyone?").await.expect_err("the task stopped");
+    assert!(
+        matches!(&err, Error::TaskStopped(reason) if reason.starts_with("connection closed")),
+        "{err:?}"
+    );
+    let err = handle
+        .enqueue(ClientMessage::Say(Say {
+            text: "anyone?".to_string(),
+        }))
+        .expect_err("the task stopped");
+    assert!(matches!(err, Error::TaskStopped(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn closes_for_every_handle() {
+    let (handle, mut messages, mut connection) = spawn_client().await;
+    let other = handle.clone();
+
+    handle.close().await.unwrap();
+    assert!(connection.recv().await.unwrap().is_none());
+    assert!(tokio::time::timeout(TIMEOUT, messages.next())
+        .await
+        .unwrap()
+        .is_none());
+
+    let err = other.say("hello").await.expect_err("the client was closed");
+    assert!(
+        matches!(&err, Error::TaskStopped(reason) if reason == "client was closed"),
+        "{err:?}"
+    );
+}
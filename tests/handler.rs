@@ -0,0 +1,63 @@
+//! Tests for dispatching messages to an EventHandler.
+
+mod common;
+
+use archipelago::client::Client;
+use archipelago::handler::EventHandler;
+use archipelago::protocol::{ClientMessage, PrintJSON, ReceivedItems, ServerMessage};
+use archipelago::{fixtures, Result};
+use futures::SinkExt;
+
+use common::{chat, connect, TIMEOUT};
+
+#[derive(Default)]
+struct Recorder {
+    items: usize,
+    chats: Vec<String>,
+}
+
+impl EventHandler for Recorder {
+    async fn on_received_items(
+        &mut self,
+        _client: &mut Client,
+        received: ReceivedItems,
+    ) -> Result<()> {
+        self.items += received.items.len();
+        Ok(())
+    }
+
+    async fn on_print_json(&mut self, client: &mut Client, print: PrintJSON) -> Result<()> {
+        if let PrintJSON::ServerChat { message, .. } = print {
+            client.say(format!("echo {}", message)).await?;
+            self.chats.push(message);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn runs_until_the_connection_closes() {
+    let (mut client, mut server) = connect().await;
+
+    let server = tokio::spawn(async move {
+        server
+            .send_messages(vec![
+                ServerMessage::ReceivedItems(fixtures::received_items()),
+                chat("hello"),
+            ])
+            .await;
+        let reply = server.recv().await;
+        assert!(matches!(&reply[..], [ClientMessage::Say(say)] if say.text == "echo hello"));
+        server.socket().close().await.unwrap();
+    });
+
+    let mut recorder = Recorder::default();
+    tokio::time::timeout(TIMEOUT, client.run(&mut recorder))
+        .await
+        .unwrap()
+        .unwrap();
+    server.await.unwrap();
+
+    assert_eq!(recorder.items, fixtures::received_items().items.len());
+    assert_eq!(recorder.chats, ["hello"]);
+}
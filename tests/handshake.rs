@@ -0,0 +1,104 @@
+//! Tests for errors during the Connect handshake.
+
+mod common;
+
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::fixtures;
+use archipelago::protocol::{
+    AnonymousServerMessage, ClientMessage, InvalidPacket, PacketProblemType, RoomInfo,
+};
+use archipelago::transport::BoxTransport;
+use archipelago::Error;
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+use common::TIMEOUT;
+
+/// Accepts a single client and sends it `room_info`, leaving the rest of the
+/// handshake to the test. [`archipelago::testing`] always answers the Connect
+/// itself, which is what these tests need to avoid.
+async fn serve_room_info(room_info: RoomInfo) -> (String, JoinHandle<BoxTransport>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket: BoxTransport =
+            Box::new(tokio_tungstenite::accept_async(stream).await.unwrap());
+        send(&mut socket, AnonymousServerMessage::RoomInfo(room_info)).await;
+        socket
+    });
+    (url, server)
+}
+
+async fn send(socket: &mut BoxTransport, message: AnonymousServerMessage) {
+    let frame = serde_json::to_string(&[message]).unwrap();
+    socket.send(Message::Text(frame)).await.unwrap();
+}
+
+#[tokio::test]
+async fn invalid_packet_echoes_the_connect() {
+    let (url, server) = serve_room_info(fixtures::room_info()).await;
+    let server = tokio::spawn(async move {
+        let mut socket = server.await.unwrap();
+        let frame = tokio::time::timeout(TIMEOUT, socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let connect: Vec<ClientMessage> = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+        assert!(matches!(connect[..], [ClientMessage::Connect(_)]));
+
+        let invalid = AnonymousServerMessage::InvalidPacket(InvalidPacket {
+            r#type: PacketProblemType::Arguments,
+            original_cmd: Some("Connect".to_string()),
+            text: "bad items_handling".to_string(),
+        });
+        send(&mut socket, invalid).await;
+        socket
+    });
+
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .password("hunter2");
+    let error = AnonymousClient::new(url)
+        .await
+        .unwrap()
+        .connect_with(options)
+        .await
+        .err()
+        .expect("the Connect should be rejected");
+    let _server = server.await.unwrap();
+
+    let Error::Handshake(handshake) = error else {
+        panic!("expected a handshake error, got {:?}", error);
+    };
+    assert_eq!(handshake.invalid.text, "bad items_handling");
+
+    let sent: serde_json::Value = serde_json::from_str(&handshake.sent).unwrap();
+    assert_eq!(sent[0]["cmd"], "Connect");
+    assert_eq!(sent[0]["name"], "Player1");
+    assert_eq!(sent[0]["password"], "<redacted>");
+    assert!(!handshake.to_string().contains("hunter2"));
+}
+
+#[tokio::test]
+async fn missing_password_fails_before_connecting() {
+    let mut room_info = fixtures::room_info();
+    room_info.password_required = true;
+    let (url, server) = serve_room_info(room_info).await;
+
+    let client = AnonymousClient::new(url).await.unwrap();
+    let mut socket = server.await.unwrap();
+    assert!(client.requires_password());
+
+    let options = ConnectOptions::new("Player1").game(fixtures::GAME);
+    let error = client.connect_with(options).await.err().unwrap();
+    assert!(matches!(error, Error::PasswordRequired));
+
+    // Nothing was sent before the client went away.
+    let frame = tokio::time::timeout(TIMEOUT, socket.next()).await.unwrap();
+    assert!(!matches!(frame, Some(Ok(Message::Text(_)))));
+}
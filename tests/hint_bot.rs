@@ -0,0 +1,55 @@
+//! Tests for the hint bot command parser.
+
+use archipelago::client::ChatMessage;
+use archipelago::hint_bot::{BotCommand, CommandParser, HintRequest};
+
+#[test]
+fn parses_commands_addressed_to_the_bot() {
+    let parser = CommandParser::new("HintBot");
+
+    assert_eq!(
+        parser.parse("@hintbot hint Fire Rod"),
+        Some(BotCommand::Hint("Fire Rod".to_string()))
+    );
+    assert_eq!(
+        parser.parse("  @HintBot   HINT_LOCATION  Link's House  "),
+        Some(BotCommand::HintLocation("Link's House".to_string()))
+    );
+    assert_eq!(parser.parse("@hintbot points"), Some(BotCommand::Points));
+}
+
+#[test]
+fn ignores_other_messages() {
+    let parser = CommandParser::new("hintbot");
+
+    assert_eq!(parser.parse("hint Fire Rod"), None);
+    assert_eq!(parser.parse("@otherbot hint Fire Rod"), None);
+    assert_eq!(parser.parse("@hintbot"), None);
+    assert_eq!(parser.parse("@hintbot hint"), None);
+    assert_eq!(parser.parse("@hintbot points please"), None);
+    assert_eq!(parser.parse("@hintbot dance"), None);
+}
+
+#[test]
+fn parses_chat_from_players_only() {
+    let parser = CommandParser::new("hintbot");
+
+    let from_player = ChatMessage {
+        sender: Some((0, 3)),
+        message: "@hintbot hint Hookshot".to_string(),
+    };
+    assert_eq!(
+        parser.parse_chat(&from_player),
+        Some(HintRequest {
+            team: 0,
+            slot: 3,
+            command: BotCommand::Hint("Hookshot".to_string()),
+        })
+    );
+
+    let from_server = ChatMessage {
+        sender: None,
+        message: "@hintbot hint Hookshot".to_string(),
+    };
+    assert_eq!(parser.parse_chat(&from_server), None);
+}
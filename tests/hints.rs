@@ -0,0 +1,182 @@
+//! Tests for turning refetched or pushed hint lists into hint events.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::hints::{hints_key, HintChange, HintTracker};
+use archipelago::protocol::{ClientMessage, Hint, HintStatus, NetworkItemFlags, ServerMessage};
+use archipelago::testing::MockServer;
+use futures::{FutureExt, StreamExt};
+use serde_json::json;
+
+use common::{room, TIMEOUT};
+
+fn hint(location: i64, status: HintStatus) -> Hint {
+    Hint {
+        receiving_player: fixtures::SLOT,
+        finding_player: fixtures::OTHER_SLOT,
+        location,
+        item: fixtures::FIRST_ITEM_ID,
+        found: status == HintStatus::Found,
+        entrance: String::new(),
+        item_flags: NetworkItemFlags::default(),
+        status,
+    }
+}
+
+fn set_reply(key: &str, hints: &[Hint]) -> ServerMessage {
+    serde_json::from_value(json!({
+        "cmd": "SetReply",
+        "key": key,
+        "value": hints,
+        "original_value": null,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn reports_only_what_changed() {
+    let mut tracker = HintTracker::new(0, fixtures::SLOT);
+    let first = hint(1, HintStatus::Unspecified);
+    let second = hint(2, HintStatus::NoPriority);
+
+    let changes = tracker.update(vec![first.clone(), second.clone()]);
+    assert_eq!(changes.len(), 2);
+    assert!(changes
+        .iter()
+        .all(|change| matches!(change, HintChange::New(_))));
+
+    // The same list again changes nothing.
+    assert!(tracker
+        .update(vec![first.clone(), second.clone()])
+        .is_empty());
+
+    let changes = tracker.update(vec![
+        hint(1, HintStatus::Found),
+        hint(2, HintStatus::Priority),
+        hint(3, HintStatus::Avoid),
+    ]);
+    assert_eq!(changes.len(), 3);
+    assert!(matches!(&changes[0], HintChange::Found(hint) if hint.location == 1));
+    assert!(matches!(
+        &changes[1],
+        HintChange::StatusChanged { hint, old: HintStatus::NoPriority }
+            if hint.location == 2 && hint.status == HintStatus::Priority
+    ));
+    assert!(matches!(&changes[2], HintChange::New(hint) if hint.location == 3));
+    assert_eq!(tracker.hints().count(), 3);
+}
+
+#[test]
+fn ignores_other_keys_and_malformed_lists() {
+    let mut tracker = HintTracker::new(0, fixtures::SLOT);
+    assert_eq!(tracker.key(), hints_key(0, fixtures::SLOT));
+
+    let other_slot = hints_key(0, fixtures::OTHER_SLOT);
+    assert!(tracker
+        .handle(&set_reply(&other_slot, &[hint(1, HintStatus::Unspecified)]))
+        .is_empty());
+
+    let malformed: ServerMessage = serde_json::from_value(json!({
+        "cmd": "SetReply",
+        "key": tracker.key(),
+        "value": { "not": "a hint list" },
+        "original_value": null,
+    }))
+    .unwrap();
+    assert!(tracker.handle(&malformed).is_empty());
+    assert_eq!(tracker.hints().count(), 0);
+
+    let retrieved: ServerMessage = serde_json::from_value(json!({
+        "cmd": "Retrieved",
+        "keys": { tracker.key(): [hint(1, HintStatus::Unspecified)] },
+    }))
+    .unwrap();
+    assert_eq!(tracker.handle(&retrieved).len(), 1);
+}
+
+#[tokio::test]
+async fn emits_events_for_pushed_hints() {
+    let mut server = MockServer::in_memory(room());
+    let mut client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let mut events = client.subscribe(SubscribeOptions::new().filter(EventFilter::new().types([
+        EventType::NewHint,
+        EventType::HintFound,
+        EventType::HintStatusChanged,
+    ])));
+
+    client.watch_hints().await.unwrap();
+    let key = hints_key(0, fixtures::SLOT);
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(matches!(
+        &sent[..],
+        [ClientMessage::SetNotify(notify)] if notify.keys == [key.clone()]
+    ));
+
+    let unspecified = hint(1, HintStatus::Unspecified);
+    connection
+        .send(vec![
+            set_reply(&key, std::slice::from_ref(&unspecified)),
+            set_reply(&key, &[unspecified]),
+            set_reply(&key, &[hint(1, HintStatus::Found)]),
+        ])
+        .await
+        .unwrap();
+    for _ in 0..3 {
+        tokio::time::timeout(TIMEOUT, client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    assert!(matches!(events.next().await, Some(Event::NewHint(hint)) if hint.location == 1));
+    assert!(matches!(events.next().await, Some(Event::HintFound(hint)) if hint.location == 1));
+    // The unchanged list in between was left out.
+    assert!(events.next().now_or_never().is_none());
+    assert_eq!(client.hints().count(), 1);
+}
+
+#[tokio::test]
+async fn only_queues_events_for_an_events_stream() {
+    let mut server = MockServer::in_memory(room());
+    let mut client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let mut events = client
+        .subscribe(SubscribeOptions::new().filter(EventFilter::new().types([EventType::NewHint])));
+
+    client.watch_hints().await.unwrap();
+    connection.expect_recv().await.unwrap();
+    let key = hints_key(0, fixtures::SLOT);
+    connection
+        .send(vec![
+            set_reply(&key, &[hint(1, HintStatus::Unspecified)]),
+            set_reply(&key, &[hint(2, HintStatus::Unspecified)]),
+        ])
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        tokio::time::timeout(TIMEOUT, client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+    assert!(matches!(events.next().await, Some(Event::NewHint(_))));
+    assert!(matches!(events.next().await, Some(Event::NewHint(_))));
+
+    // Nothing was kept for Client::events while no Events stream existed, so
+    // one created now has no stale events to replay.
+    let stale = client.events().next().now_or_never();
+    assert!(stale.is_none(), "{stale:?}");
+}
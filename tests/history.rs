@@ -0,0 +1,122 @@
+//! Tests for the scrollback of rendered messages.
+
+use archipelago::fixtures;
+use archipelago::history::MessageHistory;
+use archipelago::protocol::{Connected, PrintJSON, PrintJSONKind};
+use archipelago::render::{Names, Renderer};
+use archipelago::resolver::Resolver;
+
+fn item_send() -> PrintJSON {
+    serde_json::from_value(serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "ItemSend",
+        "receiving": fixtures::OTHER_SLOT,
+        "item": {
+            "item": fixtures::FIRST_ITEM_ID,
+            "location": fixtures::FIRST_LOCATION_ID,
+            "player": fixtures::SLOT,
+            "flags": 0,
+        },
+        "data": [
+            { "type": "player_id", "text": fixtures::SLOT.to_string() },
+            { "text": " sent " },
+            {
+                "type": "item_id",
+                "text": fixtures::FIRST_ITEM_ID.to_string(),
+                "flags": 0,
+                "player": fixtures::OTHER_SLOT,
+            },
+            { "text": " to " },
+            { "type": "player_id", "text": fixtures::OTHER_SLOT.to_string() },
+        ],
+    }))
+    .unwrap()
+}
+
+fn chat(message: &str) -> PrintJSON {
+    serde_json::from_value(serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "Chat",
+        "team": 0,
+        "slot": fixtures::OTHER_SLOT,
+        "message": message,
+        "data": [{ "text": format!("Player2: {}", message) }],
+    }))
+    .unwrap()
+}
+
+fn names<'a>(resolver: &'a Resolver, connected: &'a Connected) -> Names<'a> {
+    Names {
+        resolver: Some(resolver),
+        team: connected.team,
+        players: &connected.players,
+        slot_info: Some(&connected.slot_info),
+    }
+}
+
+#[test]
+fn names_players_and_items() {
+    let resolver = Resolver::new(&fixtures::data_package());
+    let connected = fixtures::connected();
+    let mut history = MessageHistory::new(10);
+
+    history.push_print_json(
+        &item_send(),
+        &Renderer::new(),
+        &names(&resolver, &connected),
+    );
+
+    let entry = history.iter().next().unwrap();
+    assert_eq!(entry.kind, PrintJSONKind::ItemSend);
+    assert_eq!(
+        entry.text,
+        format!("Player1 sent {} to Player2", fixtures::item_name(0))
+    );
+
+    // Names can be searched for, which IDs couldn't be.
+    let item = fixtures::item_name(0).to_uppercase();
+    assert_eq!(history.search(&item).count(), 1);
+}
+
+#[test]
+fn drops_the_oldest_lines() {
+    let mut history = MessageHistory::new(3);
+    for i in 0..5 {
+        history.push(PrintJSONKind::Chat, format!("line {}", i));
+    }
+    let lines: Vec<_> = history.iter().map(|entry| entry.text.as_str()).collect();
+    assert_eq!(lines, ["line 2", "line 3", "line 4"]);
+
+    history.set_capacity(1);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history.iter().next().unwrap().text, "line 4");
+}
+
+#[test]
+fn filters_and_searches() {
+    let resolver = Resolver::new(&fixtures::data_package());
+    let connected = fixtures::connected();
+    let names = names(&resolver, &connected);
+    let renderer = Renderer::new();
+    let mut history = MessageHistory::new(10);
+
+    history.push_print_json(&chat("anyone seen the Button?"), &renderer, &names);
+    history.push_print_json(&item_send(), &renderer, &names);
+    history.push_print_json(&chat("gg"), &renderer, &names);
+
+    let chat: Vec<_> = history
+        .filter(&[PrintJSONKind::Chat])
+        .map(|entry| entry.text.as_str())
+        .collect();
+    assert_eq!(chat, ["Player2: anyone seen the Button?", "Player2: gg"]);
+
+    let found: Vec<_> = history.search("button").map(|entry| entry.kind).collect();
+    assert_eq!(found, [PrintJSONKind::Chat]);
+    assert_eq!(
+        history.search("PLAYER2").next_back().unwrap().text,
+        "Player2: gg"
+    );
+
+    history.clear();
+    assert!(history.is_empty());
+}
@@ -0,0 +1,83 @@
+//! Tests for translating in-game IDs with an IdMapper.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::id_map::{OffsetMapper, UnmappedId};
+use archipelago::protocol::{ClientMessage, ServerMessage};
+use archipelago::Error;
+use futures::StreamExt;
+
+use common::{connect_with_options, next_message, TIMEOUT};
+
+/// Our in-game locations and items are numbered from 0x100.
+fn mapper() -> OffsetMapper {
+    OffsetMapper::new()
+        .location_region(
+            0x100..0x100 + fixtures::LOCATION_COUNT,
+            fixtures::FIRST_LOCATION_ID,
+        )
+        .item_region(0x100..0x100 + fixtures::ITEM_COUNT, fixtures::FIRST_ITEM_ID)
+}
+
+#[tokio::test]
+async fn translates_checks_and_received_items() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .id_mapper(mapper());
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut items = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ItemReceived])),
+    );
+
+    let local = 0x100 + fixtures::CHECKED_COUNT;
+    client.check_locations(&[local]).await.unwrap();
+    let sent = server.recv().await;
+    assert!(matches!(
+        &sent[..],
+        [ClientMessage::LocationChecks(checks)]
+            if checks.locations == [fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT]
+    ));
+
+    // Nothing is sent if any location can't be translated.
+    let error = client
+        .check_locations(&[local + 1, 0x99])
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Unmapped(UnmappedId::Location(0x99))));
+
+    server
+        .send_messages(vec![ServerMessage::ReceivedItems(
+            fixtures::received_items(),
+        )])
+        .await;
+    next_message(&mut client).await;
+
+    let event = tokio::time::timeout(TIMEOUT, items.next()).await.unwrap();
+    assert!(matches!(
+        event,
+        Some(Event::ItemReceived {
+            index: 0,
+            local: Some(0x100),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn offset_regions_round_trip() {
+    use archipelago::id_map::IdMapper;
+
+    let mapper = OffsetMapper::new()
+        .item_region(0..10, 100)
+        .item_region(50..60, 110);
+
+    assert_eq!(mapper.item_to_ap(55), Some(115));
+    assert_eq!(mapper.item_from_ap(115), Some(55));
+    assert_eq!(mapper.item_from_ap(99), None);
+    assert_eq!(mapper.item_to_ap(10), None);
+    assert_eq!(mapper.location_to_ap(0), None);
+}
@@ -0,0 +1,101 @@
+//! Tests for resyncing when ReceivedItems indexes are out of step.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, NetworkItem, NetworkItemFlags, ReceivedItems, ServerMessage,
+};
+use futures::StreamExt;
+
+use common::{connect_with_options, next_message, TIMEOUT};
+
+fn received_items(index: i64, count: i64) -> ServerMessage {
+    ServerMessage::ReceivedItems(ReceivedItems {
+        index,
+        items: (index..index + count)
+            .map(|i| NetworkItem {
+                item: fixtures::FIRST_ITEM_ID + i,
+                location: fixtures::FIRST_LOCATION_ID + i,
+                player: fixtures::OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    })
+}
+
+async fn next_items(client: &mut archipelago::client::Client) -> (i64, usize) {
+    match next_message(client).await {
+        ServerMessage::ReceivedItems(received) => (received.index, received.items.len()),
+        message => panic!("expected ReceivedItems, got {:?}", message),
+    }
+}
+
+#[tokio::test]
+async fn resyncs_after_a_gap() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .item_queue(true);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut desynced = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ItemsDesynced])),
+    );
+
+    server.send_messages(vec![received_items(0, 2)]).await;
+    assert_eq!(next_items(&mut client).await, (0, 2));
+
+    // Items 2 to 4 went missing. The client asks for the full list, and
+    // drops what arrives in the meantime.
+    server
+        .send_messages(vec![received_items(5, 1), received_items(6, 1)])
+        .await;
+    let reply = tokio::spawn(async move {
+        let sync = server.recv().await;
+        assert!(matches!(sync[..], [ClientMessage::Sync(())]));
+        server.send_messages(vec![received_items(0, 7)]).await;
+        server
+    });
+    assert_eq!(next_items(&mut client).await, (2, 5));
+    let _server = reply.await.unwrap();
+
+    let event = tokio::time::timeout(TIMEOUT, desynced.next())
+        .await
+        .unwrap();
+    assert!(matches!(
+        event,
+        Some(Event::ItemsDesynced {
+            expected: 2,
+            index: 5
+        })
+    ));
+
+    let queue = client.item_queue().unwrap();
+    let indexes: Vec<i64> = std::iter::from_fn(|| queue.pop())
+        .map(|item| item.index)
+        .collect();
+    assert_eq!(indexes, (0..7).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn leaves_out_items_resent_out_of_step() {
+    let options = ConnectOptions::new("Player1").game(fixtures::GAME);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+
+    server.send_messages(vec![received_items(0, 3)]).await;
+    assert_eq!(next_items(&mut client).await, (0, 3));
+
+    // Only the item we haven't seen is returned, and the full list which
+    // follows the Sync adds nothing new.
+    server.send_messages(vec![received_items(1, 3)]).await;
+    assert_eq!(next_items(&mut client).await, (3, 1));
+
+    let sync = server.recv().await;
+    assert!(matches!(sync[..], [ClientMessage::Sync(())]));
+    server
+        .send_messages(vec![received_items(0, 4), received_items(4, 1)])
+        .await;
+    assert_eq!(next_items(&mut client).await, (4, 1));
+}
@@ -0,0 +1,102 @@
+//! Tests for breaking item messages down for trackers.
+
+use std::time::Duration;
+
+use archipelago::items::{ItemQueue, ItemSendEvent, TrapPolicy};
+use archipelago::protocol::{NetworkItem, NetworkItemFlags, PrintJSON, ReceivedItems};
+
+fn item_send(sender: i64, receiver: i64) -> PrintJSON {
+    PrintJSON::ItemSend {
+        data: Vec::new(),
+        receiving: receiver,
+        item: NetworkItem {
+            item: 100,
+            location: 200,
+            player: sender,
+            flags: NetworkItemFlags::default(),
+        },
+    }
+}
+
+#[test]
+fn classifies_item_sends() {
+    let sent = ItemSendEvent::from_print(&item_send(1, 2), 1).unwrap();
+    assert_eq!((sent.sender_slot, sent.receiver_slot), (1, 2));
+    assert_eq!((sent.item, sent.location), (100, 200));
+    assert!(sent.is_from_me && !sent.is_for_me);
+
+    let received = ItemSendEvent::from_print(&item_send(2, 1), 1).unwrap();
+    assert!(received.is_for_me && !received.is_from_me);
+
+    let own = ItemSendEvent::from_print(&item_send(1, 1), 1).unwrap();
+    assert!(own.is_for_me && own.is_from_me && own.is_self_find());
+
+    let others = ItemSendEvent::from_print(&item_send(2, 3), 1).unwrap();
+    assert!(!others.is_for_me && !others.is_from_me);
+
+    let countdown = PrintJSON::Countdown {
+        data: Vec::new(),
+        countdown: 3,
+    };
+    assert!(ItemSendEvent::from_print(&countdown, 1).is_none());
+}
+
+/// Items 0 to 3, of which 1 and 3 are traps.
+fn with_traps() -> ReceivedItems {
+    ReceivedItems {
+        index: 0,
+        items: (0..4)
+            .map(|i| NetworkItem {
+                item: i,
+                location: 200 + i,
+                player: 2,
+                flags: if i % 2 == 1 {
+                    NetworkItemFlags::TRAP
+                } else {
+                    NetworkItemFlags::NONE
+                },
+            })
+            .collect(),
+    }
+}
+
+fn drain(queue: &mut ItemQueue) -> Vec<i64> {
+    std::iter::from_fn(|| queue.pop())
+        .map(|item| item.index)
+        .collect()
+}
+
+#[test]
+fn holds_traps_until_released() {
+    let mut queue = ItemQueue::new(1);
+    queue.set_trap_policy(TrapPolicy::Hold);
+    queue.handle(with_traps());
+
+    assert_eq!(drain(&mut queue), [0, 2]);
+    assert_eq!(queue.held_traps(), 2);
+    assert_eq!(queue.next_trap_at(), None);
+
+    queue.release_traps();
+    assert_eq!(drain(&mut queue), [1, 3]);
+    assert_eq!(queue.held_traps(), 0);
+}
+
+#[test]
+fn delays_traps() {
+    let mut queue = ItemQueue::new(1);
+    queue.set_trap_policy(TrapPolicy::Delay(Duration::from_millis(50)));
+    queue.handle(with_traps());
+
+    assert_eq!(drain(&mut queue), [0, 2]);
+    let due = queue.next_trap_at().unwrap();
+
+    std::thread::sleep(due.saturating_duration_since(std::time::Instant::now()));
+    assert_eq!(drain(&mut queue), [1, 3]);
+}
+
+#[test]
+fn delivers_traps_by_default() {
+    let mut queue = ItemQueue::new(1);
+    queue.handle(with_traps());
+    assert_eq!(drain(&mut queue), [0, 1, 2, 3]);
+}
@@ -0,0 +1,202 @@
+//! Tests for the grant journal, including recovering from crashes part way
+//! through a grant or a write.
+
+use std::cell::Cell;
+use std::path::PathBuf;
+
+use archipelago::items::ReceivedItem;
+use archipelago::journal::{GrantJournal, JournalError};
+use archipelago::protocol::{NetworkItem, NetworkItemFlags};
+use archipelago::Error;
+
+const SEED: &str = "12345";
+const SLOT: i64 = 1;
+
+fn journal_path(dir: &tempfile::TempDir) -> PathBuf {
+    dir.path().join("grants.json")
+}
+
+fn open(dir: &tempfile::TempDir) -> GrantJournal {
+    GrantJournal::open(journal_path(dir), SEED, SLOT).unwrap()
+}
+
+fn item(index: i64) -> ReceivedItem {
+    ReceivedItem {
+        index,
+        item: NetworkItem {
+            item: 1000 + index,
+            location: 2000 + index,
+            player: 2,
+            flags: NetworkItemFlags::default(),
+        },
+        is_local: false,
+    }
+}
+
+#[test]
+fn commits_survive_reopening() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+
+    journal.begin(0).unwrap();
+    assert_eq!(journal.in_doubt(), Some(0));
+    journal.commit(0).unwrap();
+    assert_eq!(journal.in_doubt(), None);
+
+    // Out of order, so index 1 is still missing.
+    journal.begin(2).unwrap();
+    journal.commit(2).unwrap();
+
+    let journal = open(&dir);
+    assert!(journal.is_applied(0));
+    assert!(!journal.is_applied(1));
+    assert!(journal.is_applied(2));
+    assert_eq!(journal.in_doubt(), None);
+}
+
+#[test]
+fn aborted_grants_can_be_retried() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+
+    journal.begin(0).unwrap();
+    journal.abort(0).unwrap();
+    assert!(!journal.is_applied(0));
+    assert_eq!(journal.in_doubt(), None);
+
+    journal.begin(0).unwrap();
+    journal.commit(0).unwrap();
+    assert!(open(&dir).is_applied(0));
+}
+
+#[test]
+fn rejects_grants_out_of_turn() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+    journal.begin(0).unwrap();
+
+    let err = journal.begin(1).expect_err("0 is still in progress");
+    assert!(matches!(
+        err,
+        Error::Journal(JournalError::InProgress {
+            index: 1,
+            in_progress: 0
+        })
+    ));
+    let err = journal.commit(1).expect_err("1 was never started");
+    assert!(matches!(
+        err,
+        Error::Journal(JournalError::NotInProgress(1))
+    ));
+
+    journal.commit(0).unwrap();
+    let err = journal.begin(0).expect_err("0 was already applied");
+    assert!(matches!(
+        err,
+        Error::Journal(JournalError::AlreadyApplied(0))
+    ));
+}
+
+#[test]
+fn grants_each_item_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+    let applied = Cell::new(0);
+
+    let apply = |_: &ReceivedItem| -> archipelago::Result<()> {
+        applied.set(applied.get() + 1);
+        Ok(())
+    };
+    assert!(journal.grant(&item(0), apply).unwrap());
+    assert!(!journal.grant(&item(0), apply).unwrap());
+    assert_eq!(applied.get(), 1);
+
+    // A failed grant is aborted, so it can be granted again later.
+    let err = journal
+        .grant(&item(1), |_| Err(Error::NoConfigDir))
+        .expect_err("apply failed");
+    assert!(matches!(err, Error::NoConfigDir));
+    assert!(!journal.is_applied(1));
+    assert_eq!(journal.in_doubt(), None);
+    assert!(journal.grant(&item(1), apply).unwrap());
+}
+
+#[test]
+fn reports_grants_missing_their_commit() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+    journal.begin(0).unwrap();
+    journal.commit(0).unwrap();
+
+    // The game crashes between starting the grant and committing it.
+    journal.begin(1).unwrap();
+    drop(journal);
+
+    let mut journal = open(&dir);
+    assert_eq!(journal.in_doubt(), Some(1));
+    assert!(!journal.is_applied(1));
+    assert!(matches!(
+        journal.grant(&item(2), |_| Ok::<_, Error>(())),
+        Err(Error::Journal(JournalError::InProgress { .. }))
+    ));
+
+    // The game checks its save and finds the item there.
+    journal.commit(1).unwrap();
+    assert!(journal.grant(&item(2), |_| Ok::<_, Error>(())).unwrap());
+
+    let journal = open(&dir);
+    assert_eq!(journal.in_doubt(), None);
+    assert!((0..3).all(|index| journal.is_applied(index)));
+}
+
+#[test]
+fn ignores_a_partly_written_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+    journal.begin(0).unwrap();
+    journal.commit(0).unwrap();
+
+    // The game crashes while writing the next version of the journal,
+    // before it replaces the old one.
+    let tmp = dir.path().join("grants.json.tmp");
+    std::fs::write(&tmp, br#"{"seed_name":"12345","slot":1,"applied_bel"#).unwrap();
+    drop(journal);
+
+    let mut journal = open(&dir);
+    assert!(journal.is_applied(0));
+    assert_eq!(journal.in_doubt(), None);
+
+    // The leftover is overwritten by the next write.
+    journal.begin(1).unwrap();
+    journal.commit(1).unwrap();
+    assert!(open(&dir).is_applied(1));
+    assert!(!tmp.exists());
+}
+
+#[test]
+fn starts_over_for_another_seed_or_slot() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut journal = open(&dir);
+    journal.begin(0).unwrap();
+    journal.commit(0).unwrap();
+
+    let journal = GrantJournal::open(journal_path(&dir), "67890", SLOT).unwrap();
+    assert!(!journal.is_applied(0));
+    let journal = GrantJournal::open(journal_path(&dir), SEED, 2).unwrap();
+    assert!(!journal.is_applied(0));
+
+    // Only replaced once something is granted.
+    assert!(open(&dir).is_applied(0));
+}
+
+#[test]
+fn rejects_a_corrupt_journal() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(journal_path(&dir), b"not a journal").unwrap();
+
+    let err = GrantJournal::open(journal_path(&dir), SEED, SLOT).expect_err("journal is corrupt");
+    assert!(
+        matches!(&err, Error::Corrupt { path, .. } if *path == journal_path(&dir)),
+        "{err:?}"
+    );
+}
@@ -0,0 +1,68 @@
+//! Tests for watching several teams at once.
+
+mod common;
+
+use archipelago::fixtures;
+use archipelago::observer::{TeamCredentials, TournamentObserver};
+use archipelago::protocol::{NetworkItem, NetworkItemFlags, PrintJSON, ServerMessage};
+use archipelago::testing::MockServer;
+use futures::StreamExt;
+
+use common::{accept, TIMEOUT};
+
+#[tokio::test]
+async fn tracks_progress_per_team() {
+    let mut server = MockServer::listen(common::room()).await.unwrap();
+    let mut observer = TournamentObserver::connect(
+        server.url(),
+        vec![
+            TeamCredentials::new("Red", "Player1"),
+            TeamCredentials::new("Blue", "Player2").password("secret"),
+        ],
+    )
+    .await
+    .unwrap();
+    let mut red = accept(&mut server).await;
+    let mut blue = accept(&mut server).await;
+    assert_eq!(blue.connect_packet().password.as_deref(), Some("secret"));
+
+    red.send_messages(vec![ServerMessage::PrintJSON(PrintJSON::Goal {
+        data: Vec::new(),
+        team: 0,
+        slot: fixtures::SLOT,
+    })])
+    .await;
+    blue.send_messages(vec![ServerMessage::PrintJSON(PrintJSON::ItemSend {
+        data: Vec::new(),
+        receiving: fixtures::SLOT,
+        item: NetworkItem {
+            item: fixtures::FIRST_ITEM_ID,
+            location: fixtures::FIRST_LOCATION_ID,
+            player: fixtures::OTHER_SLOT,
+            flags: NetworkItemFlags::default(),
+        },
+    })])
+    .await;
+
+    for _ in 0..2 {
+        tokio::time::timeout(TIMEOUT, observer.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    let progress: Vec<_> = observer.progress().collect();
+    assert_eq!(progress[0].label, "Red");
+    assert_eq!(progress[0].slots, 2);
+    assert!(progress[0].goals.contains(&fixtures::SLOT));
+    assert_eq!(progress[0].goal_fraction(), 0.5);
+    assert_eq!(progress[1].items_found, 1);
+
+    let standings: Vec<&str> = observer
+        .standings()
+        .iter()
+        .map(|team| team.label.as_str())
+        .collect();
+    assert_eq!(standings, ["Red", "Blue"]);
+}
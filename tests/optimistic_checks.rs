@@ -0,0 +1,93 @@
+//! Tests for marking checks before the server confirms them.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::{Client, ConnectOptions};
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use futures::{FutureExt, StreamExt};
+use serde_json::json;
+
+use common::{connect_with_options, next_message};
+
+fn is_checked(client: &Client, location: i64) -> bool {
+    let connected = client.get_connected();
+    let checked = connected.checked_locations.contains(&location);
+    assert_ne!(checked, connected.missing_locations.contains(&location));
+    checked
+}
+
+#[tokio::test]
+async fn confirmed_checks_stay_checked() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .optimistic_checks(true);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut rolled_back = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ChecksRolledBack])),
+    );
+
+    let location = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    client.check_locations(&[location]).await.unwrap();
+    assert!(is_checked(&client, location));
+
+    server.recv().await;
+    server
+        .send(vec![
+            json!({ "cmd": "RoomUpdate", "checked_locations": [location] }),
+        ])
+        .await;
+    next_message(&mut client).await;
+
+    assert!(is_checked(&client, location));
+    assert_eq!(
+        client
+            .get_connected()
+            .checked_locations
+            .iter()
+            .filter(|l| **l == location)
+            .count(),
+        1
+    );
+    assert!(rolled_back.next().now_or_never().is_none());
+}
+
+#[tokio::test]
+async fn unconfirmed_checks_are_rolled_back() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .optimistic_checks(true);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut rolled_back = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ChecksRolledBack])),
+    );
+
+    let confirmed = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let rejected = confirmed + 1;
+    // Not in our world, so not marked.
+    let foreign = fixtures::FIRST_LOCATION_ID + fixtures::LOCATION_COUNT + 100;
+    client
+        .check_locations(&[confirmed, rejected, foreign])
+        .await
+        .unwrap();
+    assert!(is_checked(&client, confirmed));
+    assert!(is_checked(&client, rejected));
+    assert!(!client.get_connected().checked_locations.contains(&foreign));
+
+    // The server answers the LocationChecks with only one of them.
+    server.recv().await;
+    server
+        .send(vec![
+            json!({ "cmd": "RoomUpdate", "checked_locations": [confirmed] }),
+        ])
+        .await;
+    next_message(&mut client).await;
+
+    assert!(is_checked(&client, confirmed));
+    assert!(!is_checked(&client, rejected));
+    assert!(matches!(
+        rolled_back.next().now_or_never(),
+        Some(Some(Event::ChecksRolledBack(locations))) if locations == [rejected]
+    ));
+}
@@ -0,0 +1,174 @@
+//! Conformance tests for the ordering guarantees documented on
+//! [`archipelago::client::Client`], run against an in-process mock server
+//! built from the canned fixtures.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, DataStorageOperation, NetworkItem, NetworkItemFlags, ReceivedItems, Retrieved,
+    ServerMessage, Set, SetReply,
+};
+use futures::StreamExt;
+
+use common::{chat, chat_text, connect, next_message, TIMEOUT};
+
+fn received_items(indices: std::ops::Range<i64>) -> ServerMessage {
+    ServerMessage::ReceivedItems(ReceivedItems {
+        index: indices.start,
+        items: indices
+            .map(|i| NetworkItem {
+                item: fixtures::FIRST_ITEM_ID + i,
+                location: fixtures::FIRST_LOCATION_ID + i,
+                player: fixtures::OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    })
+}
+
+#[tokio::test]
+async fn items_arrive_in_index_order() {
+    let (mut client, mut server) = connect().await;
+
+    server
+        .send_messages(vec![received_items(0..3), received_items(3..5)])
+        .await;
+    server.send_messages(vec![received_items(5..10)]).await;
+
+    let mut events = client.events();
+    let mut indices = Vec::new();
+    while indices.len() < 10 {
+        let event = tokio::time::timeout(TIMEOUT, events.next())
+            .await
+            .expect("timed out waiting for an event")
+            .expect("stream ended")
+            .unwrap();
+
+        if let Event::ItemReceived { index, item, .. } = event {
+            assert_eq!(item.item, fixtures::FIRST_ITEM_ID + index);
+            indices.push(index);
+        }
+    }
+
+    assert_eq!(indices, (0..10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn set_reply_follows_its_set() {
+    let (mut client, mut server) = connect().await;
+
+    client
+        .send(ClientMessage::Set(Set {
+            key: "ordering".to_string(),
+            default: serde_json::json!(0),
+            want_reply: true,
+            operations: vec![DataStorageOperation::Add(serde_json::json!(1))],
+        }))
+        .await
+        .unwrap();
+
+    let server = tokio::spawn(async move {
+        let set = server.recv().await;
+        assert!(matches!(set[..], [ClientMessage::Set(_)]));
+        let get = server.recv().await;
+        assert!(matches!(get[..], [ClientMessage::Get(_)]));
+
+        let key = format!("_read_location_name_groups_{}", fixtures::GAME);
+        server.send_messages(vec![chat("before")]).await;
+        server
+            .send_messages(vec![
+                ServerMessage::SetReply(SetReply {
+                    key: "ordering".to_string(),
+                    value: serde_json::json!(1),
+                    original_value: serde_json::json!(0),
+                }),
+                ServerMessage::Retrieved(Retrieved {
+                    keys: [(key, serde_json::json!({}))].into_iter().collect(),
+                }),
+            ])
+            .await;
+        server.send_messages(vec![chat("after")]).await;
+        server
+    });
+
+    // Messages arriving while waiting for the Retrieved are buffered, and must
+    // come out of the stream in the order the server sent them.
+    client
+        .get_location_name_groups(fixtures::GAME)
+        .await
+        .unwrap();
+    let _server = server.await.unwrap();
+
+    assert_eq!(chat_text(&next_message(&mut client).await), "before");
+    match next_message(&mut client).await {
+        ServerMessage::SetReply(reply) => assert_eq!(reply.key, "ordering"),
+        message => panic!("expected SetReply, got {:?}", message),
+    }
+    assert_eq!(chat_text(&next_message(&mut client).await), "after");
+}
+
+#[tokio::test]
+async fn chat_arrives_in_order_across_frames() {
+    let (mut client, mut server) = connect().await;
+    let mut subscription = client
+        .subscribe(SubscribeOptions::new().filter(EventFilter::new().types([EventType::Chat])));
+
+    server.send_messages(vec![chat("1"), chat("2")]).await;
+    server.send_messages(vec![chat("3")]).await;
+    server
+        .send_messages(vec![chat("4"), chat("5"), chat("6")])
+        .await;
+
+    let mut streamed = Vec::new();
+    for _ in 0..6 {
+        streamed.push(chat_text(&next_message(&mut client).await).to_string());
+    }
+    assert_eq!(streamed, ["1", "2", "3", "4", "5", "6"]);
+
+    let mut published = Vec::new();
+    for _ in 0..6 {
+        match subscription.next().await {
+            Some(Event::Chat(chat)) => published.push(chat.message),
+            event => panic!("expected chat, got {:?}", event),
+        }
+    }
+    assert_eq!(published, streamed);
+}
+
+#[tokio::test]
+async fn events_are_stamped_in_order() {
+    let (mut client, mut server) = connect().await;
+    let mut subscription = client
+        .subscribe(SubscribeOptions::new().filter(EventFilter::new().types([EventType::Chat])))
+        .stamped();
+
+    server
+        .send_messages(vec![received_items(0..2), chat("1"), chat("2")])
+        .await;
+
+    let mut events = client.events().stamped();
+    let mut streamed = Vec::new();
+    while streamed.len() < 4 {
+        let event = tokio::time::timeout(TIMEOUT, events.next())
+            .await
+            .expect("timed out waiting for an event")
+            .expect("stream ended")
+            .unwrap();
+        streamed.push(event);
+    }
+    drop(events);
+
+    let seqs: Vec<u64> = streamed.iter().map(|event| event.seq).collect();
+    assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // The subscription sees the same numbers for the chat events.
+    for expected in &streamed[2..] {
+        let event = subscription.next().await.unwrap();
+        assert!(matches!(event.event, Event::Chat(_)));
+        assert_eq!(event.seq, expected.seq);
+        assert_eq!(event.received_at, expected.received_at);
+    }
+}
@@ -0,0 +1,102 @@
+//! Tests for the formats persisted data is stored in.
+
+use std::collections::HashMap;
+
+use archipelago::data_package::{DataPackageCache, FileCache};
+use archipelago::persist::{Json, PersistFormat};
+use archipelago::protocol::GameData;
+
+fn game_data() -> GameData {
+    GameData {
+        item_name_to_id: HashMap::from([
+            ("Feeling of Satisfaction".to_string(), 69696969),
+            ("Button Activation".to_string(), 69696968),
+        ]),
+        location_name_to_id: HashMap::from([
+            ("The Button".to_string(), 69696969),
+            ("Ünïcödé Lócâtïön ✓".to_string(), 69696970),
+        ]),
+        version: 0,
+        checksum: "ef8c9d4b0d6e07d0d1a3e0aa0ca1b4c3f5d8a9c1".to_string(),
+    }
+}
+
+fn assert_same(read: &GameData, data: &GameData) {
+    assert_eq!(read.item_name_to_id, data.item_name_to_id);
+    assert_eq!(read.location_name_to_id, data.location_name_to_id);
+    assert_eq!(read.version, data.version);
+    assert_eq!(read.checksum, data.checksum);
+}
+
+/// Checks `format` reads back what it wrote, both directly and through a
+/// [`FileCache`], and refuses bytes it didn't write.
+fn check_format<F: PersistFormat + Clone>(format: F) {
+    let data = game_data();
+    let bytes = format.serialize(&data).unwrap();
+    let read: GameData = format.deserialize(&bytes).unwrap();
+    assert_same(&read, &data);
+
+    let dir = tempfile::tempdir().unwrap();
+    let cache = FileCache::with_format(dir.path(), format.clone());
+    cache.store("Clique", &data).unwrap();
+    assert!(dir
+        .path()
+        .join(format!("{}.{}", data.checksum, F::EXTENSION))
+        .exists());
+    assert_same(&cache.load("Clique", &data.checksum).unwrap(), &data);
+
+    let truncated = &bytes[..bytes.len() / 2];
+    for corrupt in [&[][..], truncated, b"\xff\xfe garbage \x00\x01"] {
+        let err = format
+            .deserialize::<GameData>(corrupt)
+            .expect_err("corrupt input should be rejected");
+        assert_eq!(err.format(), F::EXTENSION);
+    }
+}
+
+#[test]
+fn json_round_trips() {
+    check_format(Json);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode_round_trips() {
+    check_format(archipelago::persist::Bincode);
+}
+
+#[cfg(feature = "messagepack")]
+#[test]
+fn messagepack_round_trips() {
+    check_format(archipelago::persist::MessagePack);
+}
+
+#[test]
+fn cache_ignores_corrupt_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = game_data();
+    let cache = FileCache::new(dir.path());
+    cache.store("Clique", &data).unwrap();
+
+    let path = dir.path().join(format!("{}.json", data.checksum));
+    std::fs::write(&path, b"{\"item_name_to_id\": {").unwrap();
+    assert!(cache.load("Clique", &data.checksum).is_none());
+}
+
+#[test]
+fn cache_ignores_files_for_other_checksums() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = game_data();
+    let cache = FileCache::new(dir.path());
+    cache.store("Clique", &data).unwrap();
+
+    // A file whose name matches but whose contents were written for another
+    // version of the game.
+    let stale = GameData {
+        checksum: "0000".to_string(),
+        ..game_data()
+    };
+    let path = dir.path().join(format!("{}.json", data.checksum));
+    std::fs::write(&path, Json.serialize(&stale).unwrap()).unwrap();
+    assert!(cache.load("Clique", &data.checksum).is_none());
+}
@@ -0,0 +1,91 @@
+//! Tests for saving connection profiles and connecting with them.
+
+use archipelago::fixtures;
+use archipelago::profile::{Profile, ProfileStore};
+use archipelago::protocol::ClientTag;
+use archipelago::testing::{MockRoom, MockServer};
+use archipelago::Error;
+
+fn profile() -> Profile {
+    let mut profile = Profile::new("archipelago.gg:38281", "Player1");
+    profile.game = fixtures::GAME.to_string();
+    profile.has_password = true;
+    profile.tags = vec!["DeathLink".to_string()];
+    profile
+}
+
+#[test]
+fn new_profiles_get_their_own_uuid() {
+    let first = Profile::new("localhost", "Player1");
+    let second = Profile::new("localhost", "Player1");
+    assert!(!first.uuid.is_empty());
+    assert_ne!(first.uuid, second.uuid);
+}
+
+#[test]
+fn saves_and_reopens_profiles() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config").join("profiles.json");
+
+    // A missing file is an empty store.
+    let mut store = ProfileStore::open(&path).unwrap();
+    assert_eq!(store.names().count(), 0);
+
+    let saved = profile();
+    assert!(store.insert("main", saved.clone()).is_none());
+    store.insert("alt", Profile::new("localhost", "Player2"));
+    store.save().unwrap();
+    assert!(!path.with_extension("json.tmp").exists());
+
+    let mut store = ProfileStore::open(&path).unwrap();
+    assert_eq!(store.path(), path);
+    assert_eq!(store.names().collect::<Vec<_>>(), ["alt", "main"]);
+    assert_eq!(store.get("main"), Some(&saved));
+
+    assert!(store.remove("alt").is_some());
+    store.save().unwrap();
+    let store = ProfileStore::open(&path).unwrap();
+    assert_eq!(store.names().collect::<Vec<_>>(), ["main"]);
+}
+
+#[test]
+fn never_stores_passwords() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("profiles.json");
+    let mut store = ProfileStore::open(&path).unwrap();
+    store.insert("main", profile());
+    store.save().unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains("\"has_password\":true"), "{contents}");
+    assert!(!contents.contains("\"password\""), "{contents}");
+}
+
+#[test]
+fn rejects_corrupt_stores() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("profiles.json");
+    std::fs::write(&path, "{ not json").unwrap();
+
+    let err = ProfileStore::open(&path).expect_err("the store is corrupt");
+    assert!(
+        matches!(&err, Error::Corrupt { path: corrupt, .. } if *corrupt == path),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn connects_with_the_profile_settings() {
+    let profile = profile();
+    let mut server = MockServer::in_memory(MockRoom::new().on_connect(vec![]));
+    let _client = server.connect(profile.connect_options()).await.unwrap();
+    let connection = server.accept().await.unwrap();
+
+    let connect = connection.connect_packet();
+    assert_eq!(connect.name, profile.name);
+    assert_eq!(connect.game, profile.game);
+    assert_eq!(connect.uuid, profile.uuid);
+    assert!(connect.tags.contains(&ClientTag::DeathLink));
+    // The password is left to the caller.
+    assert_eq!(connect.password, None);
+}
@@ -0,0 +1,82 @@
+//! Tests for the progress metric and its thresholds.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{LocationInfo, NetworkItem, NetworkItemFlags, ServerMessage};
+use futures::{FutureExt, StreamExt};
+
+use common::{connect_with_options, next_message, TIMEOUT};
+
+fn location(index: i64) -> i64 {
+    fixtures::FIRST_LOCATION_ID + index
+}
+
+#[tokio::test]
+async fn passes_thresholds_as_locations_are_checked() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .progress_thresholds([50, 10, 25]);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    let mut thresholds = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::ProgressThreshold])),
+    );
+
+    let progress = client.progress();
+    assert_eq!(progress.checked as i64, fixtures::CHECKED_COUNT);
+    assert_eq!(progress.total as i64, fixtures::LOCATION_COUNT);
+    assert_eq!(progress.weighted, None);
+    assert_eq!(progress.percent(), 20.0);
+
+    // 10% was passed before connecting, so only 25% is announced.
+    server
+        .send(vec![serde_json::json!({
+            "cmd": "RoomUpdate",
+            "checked_locations": [location(10), location(11), location(12)],
+        })])
+        .await;
+    next_message(&mut client).await;
+
+    let event = tokio::time::timeout(TIMEOUT, thresholds.next())
+        .await
+        .unwrap();
+    assert!(matches!(
+        event,
+        Some(Event::ProgressThreshold { threshold: 25, progress }) if progress.checked == 13
+    ));
+    assert!(thresholds.next().now_or_never().is_none());
+}
+
+#[tokio::test]
+async fn weights_progression_locations() {
+    let options = ConnectOptions::new("Player1").game(fixtures::GAME);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+
+    // One checked and one unchecked location hold progression items.
+    let item = |index: i64, flags| NetworkItem {
+        item: fixtures::FIRST_ITEM_ID,
+        location: location(index),
+        player: fixtures::SLOT,
+        flags,
+    };
+    server
+        .send_messages(vec![ServerMessage::LocationInfo(LocationInfo {
+            locations: vec![
+                item(0, NetworkItemFlags::PROGRESSION),
+                item(20, NetworkItemFlags::PROGRESSION),
+                item(30, NetworkItemFlags::USEFUL),
+            ],
+        })])
+        .await;
+    next_message(&mut client).await;
+
+    // Each progression location counts twice: 11 of 52.
+    let progress = client.progress();
+    assert_eq!(progress.weighted, Some(11.0 / 52.0));
+
+    client.set_progression_weight(1.0);
+    assert_eq!(client.progress().fraction(), 0.2);
+}
@@ -0,0 +1,142 @@
+//! Tests for reconnecting automatically after the connection drops.
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::{Client, ConnectOptions};
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, NetworkItem, NetworkItemFlags, ReceivedItems, ServerMessage,
+};
+use archipelago::reconnect::ReconnectPolicy;
+use futures::StreamExt;
+
+use common::{accept, connect_with_options, next_message, TIMEOUT};
+
+fn received_items(index: i64, count: i64) -> ServerMessage {
+    ServerMessage::ReceivedItems(ReceivedItems {
+        index,
+        items: (index..index + count)
+            .map(|i| NetworkItem {
+                item: fixtures::FIRST_ITEM_ID + i,
+                location: fixtures::FIRST_LOCATION_ID + i,
+                player: fixtures::OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    })
+}
+
+async fn next_items(client: &mut Client) -> (i64, usize) {
+    match next_message(client).await {
+        ServerMessage::ReceivedItems(received) => (received.index, received.items.len()),
+        message => panic!("expected ReceivedItems, got {:?}", message),
+    }
+}
+
+#[tokio::test]
+async fn reconnects_and_resyncs() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .reconnect(ReconnectPolicy::new().initial_delay(Duration::from_millis(10)));
+    let (mut client, mut server, mut mock) = connect_with_options("ws://", options).await;
+    let mut subscription = client.subscribe(
+        SubscribeOptions::new()
+            .filter(EventFilter::new().types([EventType::Reconnecting, EventType::Reconnected])),
+    );
+
+    server.send_messages(vec![received_items(0, 2)]).await;
+    let message = tokio::time::timeout(TIMEOUT, client.recv()).await.unwrap();
+    assert!(matches!(message, Some(Ok(ServerMessage::ReceivedItems(_)))));
+
+    // Never confirmed by the server, so it should be sent again.
+    let unconfirmed = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    client.check_locations(&[unconfirmed]).await.unwrap();
+    drop(server);
+
+    let server = tokio::spawn(async move {
+        let mut server = accept(&mut mock).await;
+
+        let sync = server.recv().await;
+        assert!(matches!(sync[..], [ClientMessage::Sync(_)]));
+        let checks = server.recv().await;
+        match &checks[..] {
+            [ClientMessage::LocationChecks(checks)] => {
+                assert_eq!(checks.locations, [unconfirmed])
+            }
+            messages => panic!("expected LocationChecks, got {:?}", messages),
+        }
+
+        // Both the Connect and the Sync resend every item.
+        server.send_messages(vec![received_items(0, 3)]).await;
+        server.send_messages(vec![received_items(0, 3)]).await;
+        server.send_messages(vec![received_items(3, 1)]).await;
+        server
+    });
+
+    let mut indices = Vec::new();
+    while indices.len() < 2 {
+        let message = tokio::time::timeout(TIMEOUT, client.recv())
+            .await
+            .expect("timed out waiting for a message")
+            .expect("stream ended")
+            .unwrap();
+        if let ServerMessage::ReceivedItems(received) = message {
+            indices.push((received.index, received.items.len()));
+        }
+    }
+    let _server = server.await.unwrap();
+
+    // Items 0 and 1 were already returned before the connection dropped.
+    assert_eq!(indices, [(2, 1), (3, 1)]);
+
+    assert!(matches!(
+        subscription.next().await,
+        Some(Event::Reconnecting { attempt: 1, .. })
+    ));
+    assert!(matches!(
+        subscription.next().await,
+        Some(Event::Reconnected { attempts: 1 })
+    ));
+}
+
+#[tokio::test]
+async fn counts_no_replays_without_items() {
+    let options = ConnectOptions::new("Player1").game(fixtures::GAME);
+    let (mut client, server, mut mock) = connect_with_options("ws://", options).await;
+    drop(server);
+
+    // Nothing was received, so there's nothing for the server to resend and
+    // no Sync to send.
+    let server = tokio::spawn(async move { accept(&mut mock).await });
+    client.reconnect().await.unwrap();
+    let mut server = server.await.unwrap();
+
+    server.send_messages(vec![received_items(0, 2)]).await;
+    assert_eq!(next_items(&mut client).await, (0, 2));
+
+    // The full list answering the Sync after a gap ends the resync, so the
+    // items which follow it are returned.
+    server.send_messages(vec![received_items(4, 1)]).await;
+    let reply = tokio::spawn(async move {
+        let sync = server.recv().await;
+        assert!(matches!(sync[..], [ClientMessage::Sync(_)]));
+        server
+            .send_messages(vec![received_items(0, 5), received_items(5, 1)])
+            .await;
+        server
+    });
+    assert_eq!(next_items(&mut client).await, (2, 3));
+    assert_eq!(next_items(&mut client).await, (5, 1));
+    let mut server = reply.await.unwrap();
+
+    // A Sync of our own is answered with the full list.
+    client.sync().await.unwrap();
+    let sync = server.recv().await;
+    assert!(matches!(sync[..], [ClientMessage::Sync(_)]));
+    server.send_messages(vec![received_items(0, 6)]).await;
+    assert_eq!(next_items(&mut client).await, (0, 6));
+}
@@ -0,0 +1,98 @@
+//! Tests for rendering PrintJSON messages with names.
+
+use archipelago::fixtures;
+use archipelago::protocol::{JSONColor, JSONMessagePart, NetworkItemFlags, PrintJSON};
+use archipelago::render::{Names, Renderer};
+use archipelago::resolver::Resolver;
+
+fn item_send() -> PrintJSON {
+    serde_json::from_value(serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "ItemSend",
+        "receiving": fixtures::OTHER_SLOT,
+        "item": {
+            "item": fixtures::FIRST_ITEM_ID,
+            "location": fixtures::FIRST_LOCATION_ID + 1,
+            "player": fixtures::SLOT,
+            "flags": 1,
+        },
+        "data": [
+            { "type": "player_id", "text": fixtures::SLOT.to_string() },
+            { "text": " sent " },
+            {
+                "type": "item_id",
+                "text": fixtures::FIRST_ITEM_ID.to_string(),
+                "flags": 1,
+                "player": fixtures::OTHER_SLOT,
+            },
+            { "text": " to " },
+            { "type": "player_id", "text": fixtures::OTHER_SLOT.to_string() },
+            { "text": " (" },
+            {
+                "type": "location_id",
+                "text": (fixtures::FIRST_LOCATION_ID + 1).to_string(),
+                "player": fixtures::SLOT,
+            },
+            { "text": ")" },
+        ],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn names_id_parts() {
+    let resolver = Resolver::new(&fixtures::data_package());
+    let connected = fixtures::connected();
+    let names = Names {
+        resolver: Some(&resolver),
+        team: connected.team,
+        players: &connected.players,
+        slot_info: Some(&connected.slot_info),
+    };
+
+    let renderer = Renderer::new();
+    assert_eq!(
+        renderer.render_with(&item_send(), &names),
+        format!(
+            "Player1 sent {} to Player2 ({})",
+            fixtures::item_name(0),
+            fixtures::location_name(1)
+        )
+    );
+
+    // Without names, IDs are left as they are.
+    assert_eq!(
+        renderer.render(&item_send()),
+        format!(
+            "1 sent {} to 2 ({})",
+            fixtures::FIRST_ITEM_ID,
+            fixtures::FIRST_LOCATION_ID + 1
+        )
+    );
+}
+
+#[cfg(feature = "ansi")]
+#[test]
+fn renders_ansi_colors() {
+    let parts = [
+        JSONMessagePart::Color {
+            text: "Warning".to_string(),
+            color: JSONColor::Red,
+        },
+        JSONMessagePart::Text {
+            text: ": ".to_string(),
+        },
+        JSONMessagePart::ItemName {
+            text: "Sword".to_string(),
+            flags: NetworkItemFlags::default(),
+            player: fixtures::SLOT,
+        },
+    ];
+
+    let rendered = Renderer::new().render_parts_ansi(
+        archipelago::protocol::PrintJSONKind::Chat,
+        &parts,
+        &Names::default(),
+    );
+    assert_eq!(rendered, "\x1b[31mWarning\x1b[0m: \x1b[36mSword\x1b[0m");
+}
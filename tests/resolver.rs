@@ -0,0 +1,81 @@
+//! Tests for naming items and locations across games.
+
+use std::collections::HashMap;
+
+use archipelago::protocol::{
+    DataPackageObject, GameData, NetworkItem, NetworkItemFlags, NetworkSlot,
+};
+use archipelago::resolver::{ItemNames, Resolver};
+
+fn game(items: &[(&str, i64)], locations: &[(&str, i64)]) -> GameData {
+    GameData {
+        item_name_to_id: items.iter().map(|(n, id)| (n.to_string(), *id)).collect(),
+        location_name_to_id: locations
+            .iter()
+            .map(|(n, id)| (n.to_string(), *id))
+            .collect(),
+        version: 0,
+        checksum: String::new(),
+    }
+}
+
+fn setup() -> (Resolver, HashMap<i64, NetworkSlot>) {
+    let data_package = DataPackageObject {
+        games: HashMap::from([
+            (
+                "Ours".to_string(),
+                game(&[("Sword", 1)], &[("Our Chest", 10)]),
+            ),
+            (
+                "Theirs".to_string(),
+                game(&[("Hookshot", 1)], &[("Their Chest", 10)]),
+            ),
+        ]),
+    };
+    let slot_info = serde_json::from_value(serde_json::json!({
+        "1": { "name": "Us", "game": "Ours", "type": 1, "group_members": [] },
+        "2": { "name": "Them", "game": "Theirs", "type": 1, "group_members": [] },
+    }))
+    .unwrap();
+
+    (Resolver::new(&data_package), slot_info)
+}
+
+fn item(player: i64) -> NetworkItem {
+    NetworkItem {
+        item: 1,
+        location: 10,
+        player,
+        flags: NetworkItemFlags::default(),
+    }
+}
+
+#[test]
+fn names_received_items_in_the_senders_game() {
+    let (resolver, slot_info) = setup();
+    assert_eq!(
+        resolver.received_item(&item(2), "Ours", &slot_info),
+        ItemNames {
+            item: Some("Sword"),
+            location: Some("Their Chest"),
+            player: Some("Them"),
+        }
+    );
+}
+
+#[test]
+fn names_scouted_items_in_the_receivers_game() {
+    let (resolver, slot_info) = setup();
+    assert_eq!(
+        resolver.scouted_item(&item(2), "Ours", &slot_info),
+        ItemNames {
+            item: Some("Hookshot"),
+            location: Some("Our Chest"),
+            player: Some("Them"),
+        }
+    );
+
+    let unknown = resolver.scouted_item(&item(9), "Ours", &slot_info);
+    assert_eq!((unknown.item, unknown.player), (None, None));
+    assert_eq!(unknown.location, Some("Our Chest"));
+}
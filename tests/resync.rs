@@ -0,0 +1,131 @@
+//! Tests for [`archipelago::client::Client::full_resync`].
+
+mod common;
+
+use std::collections::HashMap;
+
+use archipelago::client::ResyncSnapshot;
+use archipelago::fixtures;
+use archipelago::persist::{Json, PersistFormat};
+use archipelago::protocol::{
+    ClientMessage, ClientStatus, Hint, HintStatus, NetworkItemFlags, Retrieved, RoomUpdate,
+    ServerMessage,
+};
+use archipelago::testing::{MockConnection, MockRoom, MockServer};
+
+use common::{options, room, TIMEOUT};
+
+/// Answers a resync as the server does: the items, but only if there are
+/// any, then the Retrieved for the data storage read.
+async fn answer_resync(connection: &mut MockConnection, items: Vec<ServerMessage>) {
+    let mut synced = false;
+    loop {
+        for message in connection.expect_recv().await.unwrap() {
+            match message {
+                ClientMessage::Sync(_) => synced = true,
+                ClientMessage::Get(get) => {
+                    assert!(synced, "the Sync is sent first");
+                    let keys: HashMap<_, _> = get
+                        .keys
+                        .into_iter()
+                        .map(|key| (key, serde_json::Value::Null))
+                        .collect();
+                    let mut reply = items;
+                    reply.push(ServerMessage::Retrieved(Retrieved { keys }));
+                    connection.send(reply).await.unwrap();
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn completes_with_an_empty_inventory() {
+    let mut server = MockServer::in_memory(room());
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    let (snapshot, ()) = tokio::time::timeout(TIMEOUT, async {
+        tokio::join!(client.full_resync(), answer_resync(&mut connection, vec![]))
+    })
+    .await
+    .expect("the resync finishes without any ReceivedItems");
+
+    let snapshot = snapshot.unwrap();
+    assert!(snapshot.items.is_empty());
+    assert!(snapshot.hints.is_empty());
+    assert!(snapshot.client_status.is_none());
+}
+
+#[tokio::test]
+async fn collects_the_resent_items() {
+    let mut server = MockServer::in_memory(MockRoom::new());
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let items = fixtures::received_items();
+
+    let (snapshot, ()) = tokio::time::timeout(TIMEOUT, async {
+        tokio::join!(
+            client.full_resync(),
+            answer_resync(
+                &mut connection,
+                vec![ServerMessage::ReceivedItems(items.clone())]
+            )
+        )
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(snapshot.unwrap().items.len(), items.items.len());
+}
+
+/// Checks a snapshot reads back from `format` as it was written.
+fn check_snapshot<F: PersistFormat>(format: F) {
+    let mut update = RoomUpdate::default();
+    update.hint_points = Some(3);
+    let snapshot = ResyncSnapshot {
+        items: fixtures::received_items().items,
+        hints: vec![Hint {
+            receiving_player: fixtures::SLOT,
+            finding_player: fixtures::OTHER_SLOT,
+            location: fixtures::FIRST_LOCATION_ID,
+            item: fixtures::FIRST_ITEM_ID,
+            found: false,
+            entrance: String::new(),
+            item_flags: NetworkItemFlags::default(),
+            status: HintStatus::Priority,
+        }],
+        client_status: Some(ClientStatus::Playing),
+        room_updates: vec![update],
+    };
+
+    let bytes = format.serialize(&snapshot).unwrap();
+    let read: ResyncSnapshot = format.deserialize(&bytes).unwrap();
+    assert_eq!(read.items.len(), snapshot.items.len());
+    assert_eq!(read.hints.len(), 1);
+    assert_eq!(read.hints[0].location, fixtures::FIRST_LOCATION_ID);
+    assert_eq!(read.hints[0].status, HintStatus::Priority);
+    assert_eq!(read.client_status, snapshot.client_status);
+    assert_eq!(read.room_updates.len(), 1);
+    assert_eq!(read.room_updates[0].hint_points, Some(3));
+    assert_eq!(read.room_updates[0].checked_locations, None);
+}
+
+#[test]
+fn snapshots_round_trip_as_json() {
+    check_snapshot(Json);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn snapshots_round_trip_as_bincode() {
+    check_snapshot(archipelago::persist::Bincode);
+}
+
+#[cfg(feature = "messagepack")]
+#[test]
+fn snapshots_round_trip_as_messagepack() {
+    check_snapshot(archipelago::persist::MessagePack);
+}
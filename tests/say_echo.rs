@@ -0,0 +1,107 @@
+//! Tests for leaving the echoes of our own chat out of chat events.
+
+mod common;
+
+use archipelago::client::Client;
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, ServerMessage};
+use archipelago::testing::{MockConnection, MockServer};
+use futures::StreamExt;
+
+use common::{options, room, TIMEOUT};
+
+/// The Chat the server sends every client when a client on our slot says
+/// `text`.
+fn echo(text: &str) -> ServerMessage {
+    serde_json::from_value(serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "Chat",
+        "team": 0,
+        "slot": fixtures::SLOT,
+        "message": text,
+        "data": [{ "text": format!("Player1: {}", text) }],
+    }))
+    .unwrap()
+}
+
+/// The chat text of the next message, or None if the client left it out.
+async fn next_chat(client: &mut Client) -> Option<String> {
+    let message = tokio::time::timeout(TIMEOUT, client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(
+        matches!(message, ServerMessage::PrintJSON(_)),
+        "{message:?}"
+    );
+    client.chat_message(&message).map(|chat| chat.message)
+}
+
+async fn expect_say(connection: &mut MockConnection, text: &str) {
+    let messages = connection.expect_recv().await.unwrap();
+    assert!(
+        matches!(&messages[..], [ClientMessage::Say(say)] if say.text == text),
+        "{messages:?}"
+    );
+}
+
+#[tokio::test]
+async fn drops_only_our_own_echo() {
+    let mut server = MockServer::in_memory(room());
+    let mut ours = server.connect(options()).await.unwrap();
+    let mut ours_connection = server.accept().await.unwrap();
+    let mut other = server.connect(options()).await.unwrap();
+    let mut other_connection = server.accept().await.unwrap();
+    ours.set_suppress_say_echo(true);
+
+    // Both clients on the slot say the same thing, and another thing is said
+    // by the other client alone.
+    ours.say("hello").await.unwrap();
+    expect_say(&mut ours_connection, "hello").await;
+    other.say("hello").await.unwrap();
+    expect_say(&mut other_connection, "hello").await;
+    other.say("anyone there?").await.unwrap();
+    expect_say(&mut other_connection, "anyone there?").await;
+
+    for connection in [&mut ours_connection, &mut other_connection] {
+        connection
+            .send(vec![echo("hello"), echo("hello"), echo("anyone there?")])
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(next_chat(&mut ours).await, None);
+    assert_eq!(next_chat(&mut ours).await.as_deref(), Some("hello"));
+    assert_eq!(next_chat(&mut ours).await.as_deref(), Some("anyone there?"));
+
+    // Without suppression, every echo is chat.
+    assert_eq!(next_chat(&mut other).await.as_deref(), Some("hello"));
+    assert_eq!(next_chat(&mut other).await.as_deref(), Some("hello"));
+    assert_eq!(
+        next_chat(&mut other).await.as_deref(),
+        Some("anyone there?")
+    );
+}
+
+#[tokio::test]
+async fn keeps_chat_said_before_suppressing() {
+    let mut server = MockServer::in_memory(room());
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    client.say("before").await.unwrap();
+    expect_say(&mut connection, "before").await;
+    client.set_suppress_say_echo(true);
+    client.say("after").await.unwrap();
+    expect_say(&mut connection, "after").await;
+
+    connection
+        .send(vec![echo("before"), echo("after"), echo("after")])
+        .await
+        .unwrap();
+    assert_eq!(next_chat(&mut client).await.as_deref(), Some("before"));
+    assert_eq!(next_chat(&mut client).await, None);
+    // Said once, so only echoed once.
+    assert_eq!(next_chat(&mut client).await.as_deref(), Some("after"));
+}
@@ -0,0 +1,90 @@
+//! Tests for creating hints by scouting our own locations.
+
+mod common;
+
+use archipelago::client::ScoutHintError;
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, CreateAsHint, LocationInfo, NetworkItem, NetworkItemFlags, ServerMessage,
+};
+use archipelago::testing::{MockRoom, MockServer};
+use archipelago::Error;
+
+use common::{options, room, TIMEOUT};
+
+/// The first of our locations which hasn't been checked. The fixture room
+/// charges 5 points a hint, and we have 5.
+const LOCATION: i64 = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+
+#[tokio::test]
+async fn scouts_hints_we_can_afford() {
+    let mut server = MockServer::in_memory(room());
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    let reply = tokio::spawn(async move {
+        let scouts = connection.expect_recv().await.unwrap();
+        assert!(matches!(
+            &scouts[..],
+            [ClientMessage::LocationScouts(scouts)] if scouts.locations == [LOCATION]
+        ));
+        let item = NetworkItem {
+            item: fixtures::FIRST_ITEM_ID,
+            location: LOCATION,
+            player: fixtures::SLOT,
+            flags: NetworkItemFlags::default(),
+        };
+        connection
+            .send(vec![ServerMessage::LocationInfo(LocationInfo {
+                locations: vec![item],
+            })])
+            .await
+            .unwrap();
+        connection
+    });
+
+    let scouted = tokio::time::timeout(
+        TIMEOUT,
+        client.scout_hints(vec![LOCATION], CreateAsHint::Broadcast),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(scouted.locations.len(), 1);
+    reply.await.unwrap();
+}
+
+#[tokio::test]
+async fn refuses_hints_we_cannot_afford() {
+    let server = MockServer::in_memory(MockRoom::new());
+    let mut client = server.connect(options()).await.unwrap();
+
+    let error = client
+        .scout_hints(vec![LOCATION, LOCATION + 1], CreateAsHint::BroadcastNew)
+        .await
+        .expect_err("two hints cost 10 points");
+    assert!(matches!(
+        error,
+        Error::ScoutHint(ScoutHintError::NotEnoughHintPoints {
+            required: 10,
+            available: 5
+        })
+    ));
+}
+
+#[tokio::test]
+async fn refuses_hints_when_checks_earn_no_points() {
+    let mut room_info = fixtures::room_info();
+    room_info.location_check_points = 0;
+    let server = MockServer::in_memory(MockRoom::new().room_info(room_info));
+    let mut client = server.connect(options()).await.unwrap();
+
+    let error = client
+        .scout_hints(vec![LOCATION, LOCATION + 1], CreateAsHint::Broadcast)
+        .await
+        .expect_err("hints can't be afforded");
+    assert!(matches!(
+        error,
+        Error::ScoutHint(ScoutHintError::HintsDisabled)
+    ));
+}
@@ -0,0 +1,36 @@
+//! Checks the public API against an earlier release with
+//! [cargo-semver-checks](https://github.com/obi1kenobi/cargo-semver-checks),
+//! so changes which would require a major version bump are caught before
+//! release.
+//!
+//! This is skipped unless `ARCHIPELAGO_SEMVER_BASELINE` is set to the git
+//! revision of the release to compare against (for example `v0.2.0`), as it
+//! requires `cargo-semver-checks` to be installed and builds the crate twice.
+//! `cargo xtask semver-checks <rev>` runs the same check.
+
+use std::process::Command;
+
+#[test]
+fn no_breaking_changes_since_baseline() {
+    let baseline = match std::env::var("ARCHIPELAGO_SEMVER_BASELINE") {
+        Ok(baseline) => baseline,
+        Err(_) => {
+            eprintln!("ARCHIPELAGO_SEMVER_BASELINE not set, skipping semver checks");
+            return;
+        }
+    };
+
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["semver-checks", "check-release", "-p", "archipelago"])
+        .args(["--baseline-rev", &baseline])
+        .status()
+        .expect("failed to run cargo semver-checks, is it installed?");
+
+    assert!(
+        status.success(),
+        "breaking changes found since {}",
+        baseline
+    );
+}
@@ -0,0 +1,127 @@
+//! Tests for the methods which send each kind of client message.
+
+mod common;
+
+use archipelago::fixtures;
+use archipelago::protocol::{
+    ClientMessage, CreateAsHint, DataStorageOperation, LocationInfo, NetworkItem, NetworkItemFlags,
+    ServerMessage,
+};
+use archipelago::Error;
+
+use common::{connect, next_message, TIMEOUT};
+
+#[tokio::test]
+async fn sends_messages() {
+    let (mut client, mut server) = connect().await;
+
+    client.sync().await.unwrap();
+    assert!(matches!(server.recv().await[..], [ClientMessage::Sync(_)]));
+
+    client
+        .location_scouts(vec![1, 2], CreateAsHint::BroadcastNew)
+        .await
+        .unwrap();
+    match &server.recv().await[..] {
+        [ClientMessage::LocationScouts(scouts)] => {
+            assert_eq!(scouts.locations, [1, 2]);
+            assert_eq!(scouts.create_as_hint, 2);
+        }
+        messages => panic!("expected LocationScouts, got {:?}", messages),
+    }
+
+    client.get(vec!["key".to_string()]).await.unwrap();
+    assert!(matches!(
+        &server.recv().await[..],
+        [ClientMessage::Get(get)] if get.keys == ["key"]
+    ));
+
+    client
+        .set(
+            "counter",
+            serde_json::json!(0),
+            true,
+            vec![DataStorageOperation::Add(serde_json::json!(1))],
+        )
+        .await
+        .unwrap();
+    match &server.recv().await[..] {
+        [ClientMessage::Set(set)] => {
+            assert_eq!(set.key, "counter");
+            assert!(set.want_reply);
+            assert_eq!(set.operations.len(), 1);
+        }
+        messages => panic!("expected Set, got {:?}", messages),
+    }
+}
+
+#[tokio::test]
+async fn rejects_read_only_keys() {
+    let (mut client, _server) = connect().await;
+
+    let result = client
+        .set(
+            "_read_hints_0_1",
+            serde_json::Value::Null,
+            false,
+            Vec::new(),
+        )
+        .await;
+    assert!(matches!(result, Err(Error::InvalidSetKey(_))));
+}
+
+fn location_info(locations: &[i64]) -> ServerMessage {
+    ServerMessage::LocationInfo(LocationInfo {
+        locations: locations
+            .iter()
+            .map(|&location| NetworkItem {
+                item: fixtures::FIRST_ITEM_ID,
+                location,
+                player: fixtures::OTHER_SLOT,
+                flags: NetworkItemFlags::default(),
+            })
+            .collect(),
+    })
+}
+
+#[tokio::test]
+async fn scout_waits_for_its_reply() {
+    let (mut client, mut server) = connect().await;
+    let scouted = [fixtures::FIRST_LOCATION_ID, fixtures::FIRST_LOCATION_ID + 1];
+
+    let server = tokio::spawn(async move {
+        let scouts = server.recv().await;
+        assert!(matches!(scouts[..], [ClientMessage::LocationScouts(_)]));
+
+        // A reply to some earlier scout arrives first.
+        server
+            .send_messages(vec![
+                location_info(&[fixtures::FIRST_LOCATION_ID + 5]),
+                common::chat("between"),
+                location_info(&[scouted[1], scouted[0]]),
+            ])
+            .await;
+        server
+    });
+
+    let info = tokio::time::timeout(
+        TIMEOUT,
+        client.scout_locations(scouted.to_vec(), CreateAsHint::No),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(info.locations.len(), 2);
+    let _server = server.await.unwrap();
+
+    match next_message(&mut client).await {
+        ServerMessage::LocationInfo(info) => {
+            assert_eq!(info.locations[0].location, fixtures::FIRST_LOCATION_ID + 5)
+        }
+        message => panic!("expected the earlier LocationInfo, got {:?}", message),
+    }
+    assert_eq!(
+        common::chat_text(&next_message(&mut client).await),
+        "between"
+    );
+}
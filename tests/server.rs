@@ -0,0 +1,105 @@
+//! Tests for serving clients with [`archipelago::server`].
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::protocol::{ClientMessage, ConnectionRefusedError, PrintJSON, ServerMessage};
+use archipelago::server::{Server, ServerConfig, ServerHandler, ServerSession, Slot};
+use archipelago::Error;
+use futures::StreamExt;
+use tokio::net::TcpListener;
+
+const GAME: &str = "Server Quest";
+
+/// Echoes chat back to the sender and records location checks.
+#[derive(Default)]
+struct Echo {
+    checks: Mutex<Vec<(i64, Vec<i64>)>>,
+}
+
+impl ServerHandler for Echo {
+    async fn on_message(
+        &self,
+        session: &ServerSession,
+        message: ClientMessage,
+    ) -> archipelago::Result<()> {
+        match message {
+            ClientMessage::Say(say) => {
+                session.send(vec![ServerMessage::PrintJSON(PrintJSON::ServerChat {
+                    data: Vec::new(),
+                    message: format!("echo: {}", say.text),
+                })])
+            }
+            ClientMessage::LocationChecks(checks) => {
+                let slot = session.slot().slot;
+                self.checks.lock().unwrap().push((slot, checks.locations));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+async fn serve() -> (String, Server<Echo>) {
+    let config = ServerConfig::new("test seed")
+        .password("hunter2")
+        .slot(Slot::new(1, "Player1", GAME).locations(vec![100, 101, 102]))
+        .slot(Slot::new(2, "Player2", GAME));
+    let server = Server::new(config, Echo::default());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let serving = server.clone();
+    tokio::spawn(async move { serving.serve(listener).await });
+
+    (url, server)
+}
+
+fn options(name: &str) -> ConnectOptions {
+    ConnectOptions::new(name).game(GAME).password("hunter2")
+}
+
+#[tokio::test]
+async fn hands_messages_to_the_handler() {
+    let (url, server) = serve().await;
+
+    let anonymous = AnonymousClient::new(&url).await.unwrap();
+    assert!(anonymous.requires_password());
+    let mut client = anonymous.connect_with(options("Player1")).await.unwrap();
+    assert_eq!(client.get_connected().slot, 1);
+    assert_eq!(client.get_connected().missing_locations, [100, 101, 102]);
+    assert_eq!(client.get_connected().players.len(), 2);
+
+    client.check_locations(&[101]).await.unwrap();
+    client.say("hello").await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        message,
+        ServerMessage::PrintJSON(PrintJSON::ServerChat { message, .. }) if message == "echo: hello"
+    ));
+    assert_eq!(*server.handler().checks.lock().unwrap(), [(1, vec![101])]);
+}
+
+#[tokio::test]
+async fn refuses_unknown_slots() {
+    let (url, _server) = serve().await;
+
+    let error = AnonymousClient::new(&url)
+        .await
+        .unwrap()
+        .connect_with(options("Player3"))
+        .await
+        .err()
+        .expect("there is no Player3");
+    assert!(matches!(
+        &error,
+        Error::ConnectionRefused(errors)
+            if matches!(errors[..], [ConnectionRefusedError::InvalidSlot])
+    ));
+}
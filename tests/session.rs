@@ -0,0 +1,124 @@
+//! Tests for keeping a session's state up to date as messages arrive.
+
+mod common;
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, RoomUpdate, ServerMessage};
+use archipelago::room::CheckedBy;
+use archipelago::session::Session;
+use archipelago::testing::{MockRoom, MockServer};
+use futures::StreamExt;
+
+use common::{room, TIMEOUT};
+
+async fn next_update(session: &mut Session) -> archipelago::session::SessionUpdate {
+    tokio::time::timeout(TIMEOUT, session.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn tracks_items_and_checks() {
+    let mut server = MockServer::in_memory(MockRoom::new());
+    let client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let mut session = Session::new(client);
+    let reader = session.reader();
+
+    // The room sends our items after connecting.
+    let update = next_update(&mut session).await;
+    assert!(matches!(update.message, ServerMessage::ReceivedItems(_)));
+    assert!(update.room_delta.is_none());
+    assert_eq!(
+        reader.read().received_items().len(),
+        fixtures::received_items().items.len()
+    );
+
+    let location = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let delta = session.check_locations(&[location]).await.unwrap();
+    assert!(delta.is_empty(), "checks aren't optimistic by default");
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(matches!(
+        &sent[..],
+        [ClientMessage::LocationChecks(checks)] if checks.locations == [location]
+    ));
+
+    let mut room_update = RoomUpdate::default();
+    room_update.checked_locations = Some(vec![location]);
+    connection.room_update(room_update).await.unwrap();
+    let update = next_update(&mut session).await;
+    assert!(matches!(update.message, ServerMessage::RoomUpdate(_)));
+    assert_eq!(update.room_delta.unwrap().new_checks, [location]);
+
+    let state = session.state();
+    assert_eq!(state.room().checked_by(location), Some(CheckedBy::Us));
+    assert!(!state.room().missing_locations().contains(location));
+}
+
+#[tokio::test]
+async fn follows_the_clients_optimistic_checks() {
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .optimistic_checks(true),
+        )
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+    let mut session = Session::new(client);
+
+    let confirmed = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let rejected = confirmed + 1;
+    let delta = session
+        .check_locations(&[confirmed, rejected])
+        .await
+        .unwrap();
+    assert_eq!(delta.new_checks, [confirmed, rejected]);
+    assert_eq!(
+        session.state().room().checked_by(rejected),
+        Some(CheckedBy::Us)
+    );
+    connection.expect_recv().await.unwrap();
+
+    // The server only confirms one of them, so the client rolls the other
+    // back, and the room state with it.
+    let mut room_update = RoomUpdate::default();
+    room_update.checked_locations = Some(vec![confirmed]);
+    connection.room_update(room_update).await.unwrap();
+    let update = next_update(&mut session).await;
+    assert!(update.room_delta.unwrap().new_checks.is_empty());
+
+    let state = session.state();
+    assert_eq!(state.room().checked_by(confirmed), Some(CheckedBy::Us));
+    assert_eq!(state.room().checked_by(rejected), None);
+    assert!(state.room().missing_locations().contains(rejected));
+    assert!(!session
+        .client()
+        .get_connected()
+        .checked_locations
+        .contains(&rejected));
+}
+
+#[tokio::test]
+async fn ends_with_the_client() {
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let connection = server.accept().await.unwrap();
+    let mut session = Session::new(client);
+
+    connection.close().await.unwrap();
+    let next = tokio::time::timeout(TIMEOUT, session.next()).await.unwrap();
+    assert!(next.is_none(), "{next:?}");
+    assert!(session.into_client().session_ended().is_some());
+}
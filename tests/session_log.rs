@@ -0,0 +1,150 @@
+//! Tests for writing session logs and reading them back.
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::protocol::{NetworkItem, NetworkItemFlags, ReceivedItems, ServerMessage};
+use archipelago::session_log::{LogEntry, LogFormat, Rotation, SessionLog};
+use archipelago::testing::{MockRoom, MockServer};
+
+fn entry(time: f64, text: &str) -> LogEntry {
+    LogEntry {
+        time,
+        kind: "Chat".to_string(),
+        text: text.to_string(),
+        index: None,
+        item: None,
+    }
+}
+
+fn read_lines(path: &std::path::Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn writes_text_lines_with_utc_timestamps() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.log");
+    let mut log = SessionLog::open(&path, LogFormat::Text).unwrap();
+
+    log.write(entry(0.0, "the epoch")).unwrap();
+    log.write(entry(1_709_210_096.5, "a leap day")).unwrap();
+
+    assert_eq!(
+        read_lines(&path),
+        [
+            "1970-01-01T00:00:00.000Z [Chat] the epoch",
+            "2024-02-29T12:34:56.500Z [Chat] a leap day",
+        ]
+    );
+}
+
+#[test]
+fn appends_to_an_existing_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("logs").join("session.log");
+
+    let mut log = SessionLog::open(&path, LogFormat::Text).unwrap();
+    log.write(entry(0.0, "first session")).unwrap();
+    drop(log);
+    let mut log = SessionLog::open(&path, LogFormat::Text).unwrap();
+    log.write(entry(0.0, "second session")).unwrap();
+
+    let lines = read_lines(&path);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with("first session"));
+    assert!(lines[1].ends_with("second session"));
+}
+
+#[test]
+fn rotates_and_keeps_old_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.log");
+    // Each line is 39 bytes, so every line starts a new file.
+    let mut log = SessionLog::open(&path, LogFormat::Text)
+        .unwrap()
+        .with_rotation(Rotation::new(50).keep(2));
+
+    for i in 0..4 {
+        log.write(entry(0.0, &format!("line {}", i))).unwrap();
+    }
+
+    let rotated = |n: usize| dir.path().join(format!("session.log.{}", n));
+    assert!(read_lines(&path)[0].ends_with("line 3"));
+    assert!(read_lines(&rotated(1))[0].ends_with("line 2"));
+    assert!(read_lines(&rotated(2))[0].ends_with("line 1"));
+    assert!(!rotated(3).exists());
+}
+
+#[tokio::test]
+async fn records_messages_as_json_lines() {
+    let mut server = MockServer::in_memory(MockRoom::new().on_connect(vec![]));
+    let mut client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    // Receiving answers the GetDataPackage, so items can be named.
+    tokio::spawn(async move { connection.recv().await });
+    client.load_game_data(fixtures::GAME).await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    let mut log = SessionLog::open(&path, LogFormat::JsonLines).unwrap();
+
+    let chat: ServerMessage = serde_json::from_value(serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "Chat",
+        "team": 0,
+        "slot": fixtures::OTHER_SLOT,
+        "message": "hi",
+        "data": [
+            { "type": "player_id", "text": fixtures::OTHER_SLOT.to_string() },
+            { "text": ": hi" },
+        ],
+    }))
+    .unwrap();
+    let item = NetworkItem {
+        item: fixtures::FIRST_ITEM_ID + 3,
+        location: fixtures::FIRST_LOCATION_ID + 4,
+        player: fixtures::OTHER_SLOT,
+        flags: NetworkItemFlags::default(),
+    };
+    let received = ServerMessage::ReceivedItems(ReceivedItems {
+        index: 7,
+        items: vec![item.clone()],
+    });
+    log.record(&client, &chat).unwrap();
+    log.record(&client, &received).unwrap();
+    // Messages other than PrintJSON and ReceivedItems aren't logged.
+    let bounced = serde_json::from_value(serde_json::json!({ "cmd": "Bounced", "data": {} }));
+    log.record(&client, &bounced.unwrap()).unwrap();
+
+    let entries: Vec<serde_json::Value> = read_lines(&path)
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0]["kind"], "Chat");
+    assert_eq!(entries[0]["text"], "Player2: hi");
+    assert!(entries[0]["time"].as_f64().unwrap() > 0.0);
+    assert!(entries[0].get("index").is_none());
+    assert!(entries[0].get("item").is_none());
+
+    assert_eq!(entries[1]["kind"], "ItemReceived");
+    assert_eq!(
+        entries[1]["text"],
+        format!(
+            "Received {} from Player2 ({})",
+            fixtures::item_name(3),
+            fixtures::location_name(4)
+        )
+    );
+    assert_eq!(entries[1]["index"], 7);
+    assert_eq!(entries[1]["item"], serde_json::to_value(&item).unwrap());
+}
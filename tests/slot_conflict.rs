@@ -0,0 +1,85 @@
+//! Tests for detecting another client taking over our slot.
+
+mod common;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::{ConnectOptions, SlotConflictPolicy};
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::ClientTag;
+use futures::{FutureExt, SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::Message;
+
+use common::{connect, connect_with_options, next_message, TIMEOUT};
+
+fn join(slot: i64, tags: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "cmd": "PrintJSON",
+        "type": "Join",
+        "data": [{ "text": "Player joined" }],
+        "team": 0,
+        "slot": slot,
+        "tags": tags,
+    })
+}
+
+#[tokio::test]
+async fn reports_other_game_clients_joining_our_slot() {
+    let (mut client, mut server) = connect().await;
+    let mut taken = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::SlotTakenOver])),
+    );
+
+    // Our own Join, a tracker, another player and finally a second instance
+    // of the game.
+    server
+        .send(vec![
+            join(fixtures::SLOT, &[]),
+            join(fixtures::SLOT, &["Tracker"]),
+            join(fixtures::OTHER_SLOT, &[]),
+            join(fixtures::SLOT, &["AP"]),
+        ])
+        .await;
+    for _ in 0..4 {
+        next_message(&mut client).await;
+    }
+
+    let event = tokio::time::timeout(TIMEOUT, taken.next()).await.unwrap();
+    assert!(matches!(
+        event,
+        Some(Event::SlotTakenOver { tags }) if tags == [ClientTag::AP]
+    ));
+    assert!(taken.next().now_or_never().is_none());
+}
+
+#[tokio::test]
+async fn yields_the_slot() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .slot_conflict(SlotConflictPolicy::Yield);
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+
+    server
+        .send(vec![join(fixtures::SLOT, &[]), join(fixtures::SLOT, &[])])
+        .await;
+    for _ in 0..2 {
+        tokio::time::timeout(TIMEOUT, client.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    // The client closes the connection, and the stream ends once the close
+    // handshake completes.
+    let frame = tokio::time::timeout(TIMEOUT, server.socket().next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(frame, Message::Close(_)));
+    server.socket().flush().await.unwrap();
+
+    let end = tokio::time::timeout(TIMEOUT, client.recv()).await.unwrap();
+    assert!(end.is_none());
+}
@@ -0,0 +1,74 @@
+//! Tests for extracting the slot_data keys games share by convention.
+
+use std::collections::HashMap;
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, ClientTag};
+use archipelago::slot_data::CommonSlotData;
+use archipelago::testing::{MockRoom, MockServer};
+use serde_json::{json, Value};
+
+fn common(value: Value) -> CommonSlotData {
+    let slot_data: HashMap<String, Value> = serde_json::from_value(value).unwrap();
+    CommonSlotData::from_slot_data(&slot_data)
+}
+
+#[test]
+fn extracts_common_keys() {
+    let data = common(json!({
+        "death_link": true,
+        "goal": "defeat_ganon",
+        "seed_name": "12345",
+        "shuffle_keys": 2,
+    }));
+    assert_eq!(data.death_link, Some(true));
+    assert_eq!(data.goal, Some(json!("defeat_ganon")));
+    assert_eq!(data.seed, Some(json!("12345")));
+    assert_eq!(data.rest.len(), 1);
+    assert_eq!(data.rest["shuffle_keys"], 2);
+}
+
+#[test]
+fn reads_death_link_from_any_spelling() {
+    assert_eq!(common(json!({ "deathlink": 1 })).death_link, Some(true));
+    assert_eq!(common(json!({ "DeathLink": 0 })).death_link, Some(false));
+    assert_eq!(
+        common(json!({ "death_link": false })).death_link,
+        Some(false)
+    );
+    assert_eq!(common(json!({})).death_link, None);
+
+    // Values which aren't a bool or an integer are left for the game.
+    let data = common(json!({ "death_link": "on" }));
+    assert_eq!(data.death_link, None);
+    assert_eq!(data.rest["death_link"], "on");
+}
+
+#[tokio::test]
+async fn adds_the_death_link_tag_when_enabled() {
+    let mut connected = fixtures::connected();
+    connected
+        .slot_data
+        .insert("death_link".to_string(), json!(1));
+    let mut server = MockServer::in_memory(MockRoom::new().connected(connected).on_connect(vec![]));
+    let client = server
+        .connect(
+            ConnectOptions::new("Player1")
+                .game(fixtures::GAME)
+                .auto_death_link(true),
+        )
+        .await
+        .unwrap();
+    let mut connection = server.accept().await.unwrap();
+
+    assert_eq!(client.common_slot_data().death_link, Some(true));
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(
+        matches!(
+            &sent[..],
+            [ClientMessage::ConnectUpdate(update)] if update.tags.contains(&ClientTag::DeathLink)
+        ),
+        "{sent:?}"
+    );
+}
@@ -0,0 +1,46 @@
+//! Runs the flow of `examples/basic.rs` against the mock server.
+
+use std::time::Duration;
+
+use archipelago::fixtures;
+use archipelago::protocol::{PrintJSON, ServerMessage};
+use archipelago::smoke;
+use archipelago::testing::{MockRoom, MockServer};
+
+#[tokio::test]
+async fn runs_until_the_server_closes() {
+    let room = MockRoom::new().on_connect(vec![
+        ServerMessage::ReceivedItems(fixtures::received_items()),
+        ServerMessage::PrintJSON(PrintJSON::ServerChat {
+            data: Vec::new(),
+            message: "welcome".to_string(),
+        }),
+    ]);
+    let mut server = MockServer::listen(room).await.unwrap();
+    let url = server.url().to_string();
+
+    let server = tokio::spawn(async move {
+        let connection = server.accept().await.unwrap();
+        assert_eq!(connection.connect_packet().name, "Player1");
+        connection.close().await.unwrap();
+        server
+    });
+
+    let mut messages = Vec::new();
+    let options = smoke::text_client("Player1", fixtures::GAME);
+    let received = tokio::time::timeout(
+        Duration::from_secs(5),
+        smoke::run(url, options, |message| messages.push(message.clone())),
+    )
+    .await
+    .expect("the client should stop once the server closes")
+    .unwrap();
+    let _server = server.await.unwrap();
+
+    assert_eq!(received, 2);
+    assert!(matches!(messages[0], ServerMessage::ReceivedItems(_)));
+    assert!(matches!(
+        &messages[1],
+        ServerMessage::PrintJSON(PrintJSON::ServerChat { message, .. }) if message == "welcome"
+    ));
+}
@@ -0,0 +1,188 @@
+//! Tests for tracking the state of a session from its messages.
+
+use archipelago::fixtures;
+use archipelago::persist::{Json, PersistFormat};
+use archipelago::protocol::ServerMessage;
+use archipelago::room::CheckedBy;
+use archipelago::state::{StateTracker, TrackedState};
+use serde_json::json;
+
+fn message(value: serde_json::Value) -> ServerMessage {
+    serde_json::from_value(value).unwrap()
+}
+
+fn received_items(index: i64, items: &[i64]) -> ServerMessage {
+    let items: Vec<_> = items
+        .iter()
+        .map(|&item| {
+            json!({
+                "item": item,
+                "location": fixtures::FIRST_LOCATION_ID,
+                "player": fixtures::OTHER_SLOT,
+                "flags": 0,
+            })
+        })
+        .collect();
+    message(json!({ "cmd": "ReceivedItems", "index": index, "items": items }))
+}
+
+fn tracker() -> StateTracker {
+    StateTracker::new(&fixtures::room_info(), &fixtures::connected())
+}
+
+fn item_ids(tracker: &StateTracker) -> Vec<i64> {
+    let state = tracker.read();
+    state
+        .received_items()
+        .iter()
+        .map(|item| item.item)
+        .collect()
+}
+
+#[test]
+fn tracks_received_items_by_index() {
+    let tracker = tracker();
+
+    assert!(tracker.handle(&received_items(0, &[10, 11])).is_none());
+    tracker.handle(&received_items(2, &[12]));
+    assert_eq!(item_ids(&tracker), [10, 11, 12]);
+
+    // A full resend, such as after a Sync, replaces the list.
+    tracker.handle(&received_items(0, &[10, 11, 12, 13]));
+    assert_eq!(item_ids(&tracker), [10, 11, 12, 13]);
+
+    // Items resent from an index already seen replace those after it.
+    tracker.handle(&received_items(3, &[23]));
+    assert_eq!(item_ids(&tracker), [10, 11, 12, 23]);
+}
+
+#[test]
+fn mirrors_data_storage() {
+    let tracker = tracker();
+
+    tracker.handle(&message(json!({
+        "cmd": "Retrieved",
+        "keys": { "a": 1, "b": null },
+    })));
+    tracker.handle(&message(json!({
+        "cmd": "SetReply",
+        "key": "a",
+        "value": 2,
+        "original_value": 1,
+    })));
+
+    let state = tracker.read();
+    assert_eq!(state.storage()["a"], 2);
+    assert_eq!(state.storage()["b"], serde_json::Value::Null);
+    assert_eq!(state.storage().len(), 2);
+}
+
+#[test]
+fn reports_room_changes() {
+    let tracker = tracker();
+    let ours = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    let partners = ours + 1;
+    tracker.room_mut().record_sent_checks(&[ours]);
+
+    let delta = tracker
+        .handle(&message(json!({
+            "cmd": "RoomUpdate",
+            "checked_locations": [ours, partners],
+            "hint_points": 7,
+        })))
+        .expect("RoomUpdates are reported");
+    assert_eq!(delta.new_checks, [ours, partners]);
+    assert_eq!(delta.hint_points, Some(7));
+
+    let state = tracker.read();
+    assert_eq!(state.room().hint_points(), 7);
+    assert_eq!(state.room().checked_by(ours), Some(CheckedBy::Us));
+    assert_eq!(state.room().checked_by(partners), Some(CheckedBy::Partner));
+    assert_eq!(
+        state.room().checked_by(fixtures::FIRST_LOCATION_ID),
+        Some(CheckedBy::Unknown)
+    );
+    assert!(!state.room().missing_locations().contains(ours));
+}
+
+#[test]
+fn readers_see_updates_from_other_threads() {
+    let tracker = tracker();
+    let reader = tracker.reader();
+    let before = reader.snapshot();
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| tracker.handle(&received_items(0, &[10])));
+    });
+
+    assert_eq!(reader.read().received_items().len(), 1);
+    // Snapshots are copies, so don't change.
+    assert!(before.received_items().is_empty());
+}
+
+/// Checks a snapshot saved in `format` loads back the same state.
+fn check_snapshot<F: PersistFormat>(format: F) {
+    let tracker = tracker();
+    let ours = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    tracker.room_mut().record_sent_checks(&[ours]);
+    tracker.handle(&received_items(0, &[10, 11]));
+    tracker.handle(&message(
+        json!({ "cmd": "RoomUpdate", "checked_locations": [ours] }),
+    ));
+    tracker.handle(&message(json!({
+        "cmd": "Retrieved",
+        "keys": { "a": { "nested": [1, 2] }, "b": null },
+    })));
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("snapshots")
+        .join(format!("state.{}", F::EXTENSION));
+    let saved = tracker.read().clone();
+    saved.save(&path, &format).unwrap();
+    let loaded = TrackedState::load(&path, &format).unwrap();
+
+    assert_eq!(item_ids_of(&loaded), [10, 11]);
+    assert_eq!(loaded.storage(), saved.storage());
+    assert_eq!(
+        loaded.room().checked_locations(),
+        saved.room().checked_locations()
+    );
+    assert_eq!(
+        loaded.room().missing_locations(),
+        saved.room().missing_locations()
+    );
+    assert_eq!(loaded.room().checked_by(ours), Some(CheckedBy::Us));
+    assert_eq!(loaded.room().players().len(), saved.room().players().len());
+    assert_eq!(loaded.room().hint_points(), saved.room().hint_points());
+
+    std::fs::write(&path, b"\xff\xfe garbage").unwrap();
+    let err = TrackedState::load(&path, &format).expect_err("the snapshot is corrupt");
+    assert!(matches!(err, archipelago::Error::Corrupt { .. }), "{err:?}");
+}
+
+fn item_ids_of(state: &TrackedState) -> Vec<i64> {
+    state
+        .received_items()
+        .iter()
+        .map(|item| item.item)
+        .collect()
+}
+
+#[test]
+fn snapshots_round_trip_as_json() {
+    check_snapshot(Json);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn snapshots_round_trip_as_bincode() {
+    check_snapshot(archipelago::persist::Bincode);
+}
+
+#[cfg(feature = "messagepack")]
+#[test]
+fn snapshots_round_trip_as_messagepack() {
+    check_snapshot(archipelago::persist::MessagePack);
+}
@@ -0,0 +1,100 @@
+//! Tests for the typed data storage API.
+
+mod common;
+
+use std::collections::HashMap;
+
+use archipelago::protocol::{
+    ClientMessage, DataStorageOperation, Retrieved, ServerMessage, SetReply,
+};
+use futures::StreamExt;
+
+use common::{chat, connect, next_message, TIMEOUT};
+
+fn set_reply(key: &str, value: i64, original_value: i64) -> ServerMessage {
+    ServerMessage::SetReply(SetReply {
+        key: key.to_string(),
+        value: value.into(),
+        original_value: original_value.into(),
+    })
+}
+
+#[tokio::test]
+async fn get_and_set_pair_with_their_replies() {
+    let (mut client, mut server) = connect().await;
+
+    let server = tokio::spawn(async move {
+        let get = server.recv().await;
+        assert!(matches!(&get[..], [ClientMessage::Get(get)] if get.keys == ["counter", "unset"]));
+        server
+            .send_messages(vec![
+                chat("before"),
+                ServerMessage::Retrieved(Retrieved {
+                    keys: HashMap::from([
+                        ("counter".to_string(), 3.into()),
+                        ("unset".to_string(), serde_json::Value::Null),
+                    ]),
+                }),
+            ])
+            .await;
+
+        let set = server.recv().await;
+        assert!(matches!(&set[..], [ClientMessage::Set(set)] if set.want_reply));
+        server
+            .send_messages(vec![set_reply("other", 1, 0), set_reply("counter", 4, 3)])
+            .await;
+        server
+    });
+
+    let values: HashMap<String, Option<i64>> =
+        tokio::time::timeout(TIMEOUT, client.data_storage().get(&["counter", "unset"]))
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(values["counter"], Some(3));
+    assert_eq!(values["unset"], None);
+
+    let reply = tokio::time::timeout(
+        TIMEOUT,
+        client
+            .data_storage()
+            .set("counter", vec![DataStorageOperation::Add(1.into())]),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(reply.value, 4);
+    assert_eq!(reply.original_value, 3);
+    let _server = server.await.unwrap();
+
+    // Messages which arrived in the meantime are still returned.
+    assert!(matches!(
+        next_message(&mut client).await,
+        ServerMessage::PrintJSON(_)
+    ));
+    assert!(
+        matches!(next_message(&mut client).await, ServerMessage::SetReply(reply) if reply.key == "other")
+    );
+}
+
+#[tokio::test]
+async fn subscriptions_receive_changes_to_their_key() {
+    let (mut client, mut server) = connect().await;
+
+    let mut changes = client.data_storage().subscribe("counter").await.unwrap();
+    let sent = server.recv().await;
+    assert!(matches!(&sent[..], [ClientMessage::SetNotify(notify)] if notify.keys == ["counter"]));
+
+    server
+        .send_messages(vec![set_reply("other", 1, 0), set_reply("counter", 5, 4)])
+        .await;
+    next_message(&mut client).await;
+    next_message(&mut client).await;
+
+    let change = tokio::time::timeout(TIMEOUT, changes.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(change.key, "counter");
+    assert_eq!(change.value, 5);
+}
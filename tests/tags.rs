@@ -0,0 +1,84 @@
+//! Tests for keeping tags in sync across ConnectUpdates.
+
+mod common;
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, ClientTag, ConnectUpdate, ItemsHandlingFlags};
+
+use common::connect_with_options;
+
+#[tokio::test]
+async fn version_tag_survives_connect_updates() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .version_tag("MyGame 2.1.0");
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+    assert_eq!(client.tags(), ["MyGame 2.1.0"]);
+
+    client.set_death_link(true).await.unwrap();
+    let update = server.recv().await;
+    assert!(matches!(
+        &update[..],
+        [ClientMessage::ConnectUpdate(update)] if update.tags == ["MyGame 2.1.0", "DeathLink"]
+    ));
+
+    client.set_tags(["AP"]).await.unwrap();
+    let update = server.recv().await;
+    assert!(matches!(
+        &update[..],
+        [ClientMessage::ConnectUpdate(update)] if update.tags == ["AP", "MyGame 2.1.0"]
+    ));
+}
+
+#[tokio::test]
+async fn switches_to_tracker_mode() {
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .version_tag("MyGame 2.1.0");
+    let (mut client, mut server, _) = connect_with_options("ws://", options).await;
+
+    client
+        .update_connection(["Tracker"], ItemsHandlingFlags::NONE)
+        .await
+        .unwrap();
+    let update = server.recv().await;
+    assert!(matches!(
+        &update[..],
+        [ClientMessage::ConnectUpdate(update)]
+            if update.tags == ["Tracker", "MyGame 2.1.0"]
+                && update.items_handling == ItemsHandlingFlags::NONE
+    ));
+    assert_eq!(client.items_handling(), ItemsHandlingFlags::NONE);
+
+    // Later updates keep the new items handling.
+    client.set_death_link(true).await.unwrap();
+    let update = server.recv().await;
+    assert!(matches!(
+        &update[..],
+        [ClientMessage::ConnectUpdate(update)] if update.items_handling == ItemsHandlingFlags::NONE
+    ));
+}
+
+#[test]
+fn client_tags_are_plain_strings() {
+    let update: ConnectUpdate = serde_json::from_value(serde_json::json!({
+        "items_handling": 1,
+        "tags": ["AP", "DeathLink", "MyGame 2.1.0"],
+    }))
+    .unwrap();
+    assert_eq!(
+        update.tags,
+        [
+            ClientTag::AP,
+            ClientTag::DeathLink,
+            ClientTag::Other("MyGame 2.1.0".to_string())
+        ]
+    );
+
+    let value = serde_json::to_value(&update).unwrap();
+    assert_eq!(
+        value["tags"],
+        serde_json::json!(["AP", "DeathLink", "MyGame 2.1.0"])
+    );
+}
@@ -0,0 +1,50 @@
+//! Tests for counting the background tasks spawned by the crate.
+//!
+//! The count is global, so this file holds a single test to keep other tests
+//! in the same binary from moving it.
+
+mod common;
+
+use std::time::Duration;
+
+use archipelago::client::ConnectOptions;
+use archipelago::fixtures;
+use archipelago::tasks::task_count;
+use archipelago::testing::MockServer;
+
+use common::{room, TIMEOUT};
+
+async fn wait_for_count(count: usize) {
+    tokio::time::timeout(TIMEOUT, async {
+        while task_count() != count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("expected {count} tasks, found {}", task_count()));
+}
+
+#[tokio::test]
+async fn counts_running_tasks() {
+    let baseline = task_count();
+    let mut server = MockServer::in_memory(room());
+    let client = server
+        .connect(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+    let _game_connection = server.accept().await.unwrap();
+
+    let companion = client.spawn_companion_tracker().await.unwrap();
+    let _companion_connection = server.accept().await.unwrap();
+    assert_eq!(task_count(), baseline + 1);
+
+    let (handle, messages) = client.spawn();
+    assert_eq!(task_count(), baseline + 2);
+
+    companion.stop();
+    wait_for_count(baseline + 1).await;
+
+    handle.close().await.unwrap();
+    drop(messages);
+    wait_for_count(baseline).await;
+}
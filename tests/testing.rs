@@ -0,0 +1,77 @@
+//! Tests for the mock server in [`archipelago::testing`].
+
+mod common;
+
+use archipelago::fixtures;
+use archipelago::protocol::{ClientMessage, ConnectionRefusedError, NetworkItem, ServerMessage};
+use archipelago::testing::{MockRoom, MockServer};
+use archipelago::Error;
+
+use common::{next_message, options, TIMEOUT};
+
+#[tokio::test]
+async fn scripts_items_over_an_in_memory_connection() {
+    let mut server = MockServer::in_memory(MockRoom::new());
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+    assert_eq!(connection.connect_packet().name, "Player1");
+
+    let ServerMessage::ReceivedItems(received) = next_message(&mut client).await else {
+        panic!("expected the items sent on connect");
+    };
+    assert_eq!(received.index, 0);
+
+    let item = NetworkItem {
+        item: fixtures::FIRST_ITEM_ID,
+        location: fixtures::FIRST_LOCATION_ID + 20,
+        player: fixtures::OTHER_SLOT,
+        flags: Default::default(),
+    };
+    connection.send_items(vec![item]).await.unwrap();
+    let ServerMessage::ReceivedItems(received) = next_message(&mut client).await else {
+        panic!("expected the scripted items");
+    };
+    assert_eq!(received.index, fixtures::CHECKED_COUNT);
+
+    let location = fixtures::FIRST_LOCATION_ID + fixtures::CHECKED_COUNT;
+    client.check_locations(&[location]).await.unwrap();
+    let sent = connection.expect_recv().await.unwrap();
+    assert!(matches!(
+        &sent[..],
+        [ClientMessage::LocationChecks(checks)] if checks.locations == [location]
+    ));
+}
+
+#[tokio::test]
+async fn serves_the_data_package_on_localhost() {
+    let mut server = MockServer::listen(MockRoom::new()).await.unwrap();
+    let mut client = server.connect(options()).await.unwrap();
+    let mut connection = server.accept().await.unwrap();
+    assert!(server.url().starts_with("ws://127.0.0.1:"));
+
+    // Receiving answers the GetDataPackage.
+    tokio::spawn(async move { connection.recv().await });
+    tokio::time::timeout(TIMEOUT, client.load_game_data(fixtures::GAME))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(client.resolver().unwrap().has_game(fixtures::GAME));
+}
+
+#[tokio::test]
+async fn refuses_the_wrong_password() {
+    let server = MockServer::in_memory(MockRoom::new().password("hunter2"));
+
+    let error = server
+        .connect(options().password("hunter3"))
+        .await
+        .err()
+        .expect("the password is wrong");
+    assert!(matches!(
+        &error,
+        Error::ConnectionRefused(errors)
+            if matches!(errors[..], [ConnectionRefusedError::InvalidPassword])
+    ));
+
+    server.connect(options().password("hunter2")).await.unwrap();
+}
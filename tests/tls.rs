@@ -0,0 +1,38 @@
+//! Tests for choosing between `ws://` and `wss://` when connecting.
+
+mod common;
+
+use archipelago::client::AnonymousClient;
+use archipelago::diagnostics::ConnectStage;
+use archipelago::Error;
+
+use common::{chat, chat_text, connect_with_scheme, next_message};
+
+#[tokio::test]
+async fn address_without_scheme_falls_back_to_ws() {
+    let (mut client, mut server) = connect_with_scheme("").await;
+
+    server.send_messages(vec![chat("hello")]).await;
+    assert_eq!(chat_text(&next_message(&mut client).await), "hello");
+}
+
+#[tokio::test]
+async fn explicit_wss_does_not_fall_back() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // Accepts the connection and closes it without a TLS handshake.
+    tokio::spawn(async move {
+        let _ = listener.accept().await;
+    });
+
+    let error = AnonymousClient::new(format!("wss://127.0.0.1:{}", port))
+        .await
+        .err()
+        .expect("connecting over TLS to a plain server should fail");
+    let Error::Connect(error) = error else {
+        panic!("expected a connect error, got {:?}", error);
+    };
+    assert_eq!(error.stage, ConnectStage::Tls);
+    assert!(error.diagnostics.url.starts_with("wss://"));
+}
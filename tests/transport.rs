@@ -0,0 +1,117 @@
+//! Tests for connecting through a custom [`Connector`].
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use archipelago::client::{AnonymousClient, ConnectOptions};
+use archipelago::diagnostics::{ConnectDiagnostics, ConnectError, ConnectStage};
+use archipelago::fixtures;
+use archipelago::protocol::{AnonymousServerMessage, ClientMessage, PrintJSON, ServerMessage};
+use archipelago::transport::{Connection, Connector};
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use tokio::io::DuplexStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Connects over an in-memory pipe, handing the server's end to the test.
+#[derive(Default)]
+struct PipeConnector {
+    servers: Mutex<Vec<DuplexStream>>,
+}
+
+impl Connector for PipeConnector {
+    fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<Connection, ConnectError>> {
+        Box::pin(async move {
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            self.servers.lock().unwrap().push(server);
+
+            let diagnostics = ConnectDiagnostics {
+                url: url.to_string(),
+                ..Default::default()
+            };
+            let upgrade_started = Instant::now();
+            match tokio_tungstenite::client_async(url, client).await {
+                Ok((ws, _)) => Ok(Connection {
+                    transport: Box::new(ws),
+                    diagnostics,
+                    upgrade_started,
+                }),
+                Err(e) => Err(diagnostics.fail(ConnectStage::WebSocket, e)),
+            }
+        })
+    }
+}
+
+async fn send(socket: &mut WebSocketStream<DuplexStream>, message: impl serde::Serialize) {
+    let frame = serde_json::Value::Array(vec![serde_json::to_value(message).unwrap()]);
+    socket.send(Message::Text(frame.to_string())).await.unwrap();
+}
+
+async fn recv(socket: &mut WebSocketStream<DuplexStream>) -> Vec<ClientMessage> {
+    loop {
+        if let Message::Text(text) = socket.next().await.unwrap().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn connects_through_a_custom_connector() {
+    let connector = Arc::new(PipeConnector::default());
+
+    let server_connector = connector.clone();
+    let server = tokio::spawn(async move {
+        // The client only hands over its end once connect is called.
+        let stream = loop {
+            if let Some(stream) = server_connector.servers.lock().unwrap().pop() {
+                break stream;
+            }
+            tokio::task::yield_now().await;
+        };
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        send(
+            &mut socket,
+            AnonymousServerMessage::RoomInfo(fixtures::room_info()),
+        )
+        .await;
+        assert!(matches!(
+            recv(&mut socket).await[..],
+            [ClientMessage::Connect(_)]
+        ));
+        send(
+            &mut socket,
+            AnonymousServerMessage::Connected(fixtures::connected()),
+        )
+        .await;
+        send(
+            &mut socket,
+            ServerMessage::PrintJSON(PrintJSON::ServerChat {
+                data: Vec::new(),
+                message: "over the pipe".to_string(),
+            }),
+        )
+        .await;
+        socket
+    });
+
+    let mut client = AnonymousClient::connect_via(connector, "ws://pipe")
+        .await
+        .unwrap()
+        .connect_with(ConnectOptions::new("Player1").game(fixtures::GAME))
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        message,
+        ServerMessage::PrintJSON(PrintJSON::ServerChat { message, .. }) if message == "over the pipe"
+    ));
+
+    drop(server.await.unwrap());
+}
@@ -0,0 +1,205 @@
+//! Tests for finding and reconnecting to rooms on an Archipelago WebHost,
+//! against a local stand-in for the WebHost serving captured room pages.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use archipelago::bus::SubscribeOptions;
+use archipelago::client::ConnectOptions;
+use archipelago::events::{Event, EventFilter, EventType};
+use archipelago::fixtures;
+use archipelago::protocol::ServerMessage;
+use archipelago::reconnect::ReconnectPolicy;
+use archipelago::testing::MockServer;
+use archipelago::webhost::{WebHostError, WebHostRoom};
+use futures::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use common::{room, TIMEOUT};
+
+const ROOM_ID: &str = "4xXWqPSfQsa6Y0bnrT1fWg";
+const ROOM_PAGE: &str = include_str!("data/webhost/room.html");
+const ROOM_STATUS: &str = include_str!("data/webhost/room_status.json");
+
+/// A WebHost serving a single room, whose `last_port` can be changed to move
+/// the room. Every path requested is recorded.
+struct WebHost {
+    url: String,
+    port: Arc<Mutex<u16>>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl WebHost {
+    async fn serve(port: u16) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/room/{}", listener.local_addr().unwrap(), ROOM_ID);
+        let port = Arc::new(Mutex::new(port));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let (room_port, seen) = (port.clone(), requests.clone());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                seen.lock().unwrap().push(path.to_string());
+
+                let body = if path == format!("/room/{}", ROOM_ID) {
+                    Some(ROOM_PAGE.to_string())
+                } else if path == format!("/api/room_status/{}", ROOM_ID) {
+                    let port = *room_port.lock().unwrap();
+                    Some(ROOM_STATUS.replace("38281", &port.to_string()))
+                } else {
+                    None
+                };
+                let response = match body {
+                    Some(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    None => "HTTP/1.1 404 NOT FOUND\r\nContent-Length: 0\r\n\r\n".to_string(),
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        Self {
+            url,
+            port,
+            requests,
+        }
+    }
+
+    fn move_to(&self, port: u16) {
+        *self.port.lock().unwrap() = port;
+    }
+}
+
+fn port_of(server: &MockServer) -> u16 {
+    server.url().rsplit(':').next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn parses_room_urls() {
+    let room = WebHostRoom::parse("https://archipelago.gg/room/4xXWqPSfQsa6Y0bnrT1fWg").unwrap();
+    assert_eq!(room.host(), "archipelago.gg");
+    assert_eq!(room.room_id(), ROOM_ID);
+    assert_eq!(room.last_address(), None);
+
+    let room = WebHostRoom::parse("https://archipelago.gg/room/4xXWqPSfQsa6Y0bnrT1fWg/").unwrap();
+    assert_eq!(room.room_id(), ROOM_ID);
+
+    let room = WebHostRoom::parse("http://localhost:8080/room/4xXWqPSfQsa6Y0bnrT1fWg").unwrap();
+    assert_eq!(room.host(), "localhost");
+    assert_eq!(room.room_id(), ROOM_ID);
+}
+
+#[test]
+fn rejects_other_urls() {
+    for url in [
+        "archipelago.gg/room/4xXWqPSfQsa6Y0bnrT1fWg",
+        "ftp://archipelago.gg/room/4xXWqPSfQsa6Y0bnrT1fWg",
+        "https://archipelago.gg",
+        "https://archipelago.gg/",
+        "https://archipelago.gg/seed/4xXWqPSfQsa6Y0bnrT1fWg",
+        "https://archipelago.gg/room/",
+        "https://archipelago.gg/room/4xXWqPSfQsa6Y0bnrT1fWg/extra",
+        "https://archipelago.gg:port/room/4xXWqPSfQsa6Y0bnrT1fWg",
+        "https:///room/4xXWqPSfQsa6Y0bnrT1fWg",
+    ] {
+        assert!(
+            matches!(WebHostRoom::parse(url), Err(WebHostError::InvalidUrl(_))),
+            "{url} should be rejected"
+        );
+    }
+}
+
+#[tokio::test]
+async fn resolves_the_room_port() {
+    let webhost = WebHost::serve(38281).await;
+    let room = WebHostRoom::parse(&webhost.url).unwrap();
+
+    let address = tokio::time::timeout(TIMEOUT, room.resolve())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(address, "ws://127.0.0.1:38281");
+
+    // The room page is visited first, to wake the room up.
+    assert_eq!(
+        *webhost.requests.lock().unwrap(),
+        [
+            format!("/room/{}", ROOM_ID),
+            format!("/api/room_status/{}", ROOM_ID),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reports_missing_rooms() {
+    let webhost = WebHost::serve(38281).await;
+    let url = webhost.url.replace(ROOM_ID, "missing");
+    let room = WebHostRoom::parse(&url).unwrap();
+
+    let err = room.resolve().await.expect_err("room shouldn't resolve");
+    assert!(matches!(err, WebHostError::Status(404)), "{err:?}");
+}
+
+#[tokio::test]
+async fn reconnects_to_where_the_room_moved() {
+    let mut first = MockServer::listen(room()).await.unwrap();
+    let mut second = MockServer::listen(room()).await.unwrap();
+    let (old, new) = (first.url().to_string(), second.url().to_string());
+    let webhost = WebHost::serve(port_of(&first)).await;
+
+    let mut room = WebHostRoom::parse(&webhost.url).unwrap();
+    let (client, changed) = room.connect().await.unwrap();
+    assert_eq!(changed, None);
+    let options = ConnectOptions::new("Player1")
+        .game(fixtures::GAME)
+        .reconnect(ReconnectPolicy::new().initial_delay(Duration::from_millis(10)))
+        .webhost_room(room);
+    let mut client = client.connect_with(options).await.unwrap();
+    let mut subscription = client.subscribe(
+        SubscribeOptions::new().filter(EventFilter::new().types([EventType::AddressChanged])),
+    );
+
+    // The room shuts down and comes back up on another port.
+    first.accept().await.unwrap().close().await.unwrap();
+    webhost.move_to(port_of(&second));
+    let server = tokio::spawn(async move {
+        let mut connection = second.accept().await.unwrap();
+        connection
+            .send_items(fixtures::received_items().items)
+            .await
+            .unwrap();
+        connection
+    });
+
+    let message = tokio::time::timeout(TIMEOUT, client.recv()).await.unwrap();
+    assert!(
+        matches!(message, Some(Ok(ServerMessage::ReceivedItems(_)))),
+        "{message:?}"
+    );
+    let _connection = server.await.unwrap();
+
+    let event = tokio::time::timeout(TIMEOUT, subscription.next())
+        .await
+        .unwrap();
+    assert!(
+        matches!(&event, Some(Event::AddressChanged { old: o, new: n }) if *o == old && *n == new),
+        "{event:?}"
+    );
+}
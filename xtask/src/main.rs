@@ -0,0 +1,252 @@
+//! Maintenance tasks for the archipelago crate.
+//!
+//! Run with `cargo xtask <task>`.
+//!
+//! - `protocol-diff <network protocol.md>`: compares the packets and types
+//!   described in Archipelago's network protocol documentation against the
+//!   structs in `src/protocol.rs`, reporting anything missing on either side.
+//! - `semver-checks <git rev>`: runs `cargo semver-checks` against the
+//!   release at the given revision, failing on changes which would require a
+//!   major version bump.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+type Definitions = BTreeMap<String, BTreeSet<String>>;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+
+    match args.next().as_deref() {
+        Some("protocol-diff") => {
+            let Some(doc) = args.next() else {
+                eprintln!("usage: cargo xtask protocol-diff <network protocol.md>");
+                return ExitCode::FAILURE;
+            };
+            protocol_diff(Path::new(&doc))
+        }
+        Some("semver-checks") => {
+            let Some(baseline) = args.next() else {
+                eprintln!("usage: cargo xtask semver-checks <git rev>");
+                return ExitCode::FAILURE;
+            };
+            semver_checks(&baseline)
+        }
+        _ => {
+            eprintln!("usage: cargo xtask protocol-diff <network protocol.md>");
+            eprintln!("       cargo xtask semver-checks <git rev>");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn protocol_diff(doc: &Path) -> ExitCode {
+    let doc = match std::fs::read_to_string(doc) {
+        Ok(doc) => doc,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", doc.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let source_path = workspace_root().join("src").join("protocol.rs");
+    let source = match std::fs::read_to_string(&source_path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("failed to read {}: {}", source_path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let documented = parse_doc(&doc);
+    let implemented = parse_source(&source);
+
+    let mut problems = 0;
+
+    for (name, fields) in &documented {
+        let Some(ours) = implemented.get(name) else {
+            println!("missing type: {}", name);
+            problems += 1;
+            continue;
+        };
+
+        for field in fields.difference(ours) {
+            println!("missing field: {}.{}", name, field);
+            problems += 1;
+        }
+
+        for field in ours.difference(fields) {
+            println!("undocumented field: {}.{}", name, field);
+            problems += 1;
+        }
+    }
+
+    if problems == 0 {
+        println!("protocol.rs matches the documentation");
+        ExitCode::SUCCESS
+    } else {
+        println!("{} difference(s) found", problems);
+        ExitCode::FAILURE
+    }
+}
+
+fn semver_checks(baseline: &str) -> ExitCode {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = std::process::Command::new(cargo)
+        .current_dir(workspace_root())
+        .args(["semver-checks", "check-release", "-p", "archipelago"])
+        .args(["--baseline-rev", baseline])
+        .status();
+
+    match status {
+        Ok(status) if status.success() => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("failed to run cargo semver-checks, is it installed? {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask should live inside the workspace")
+        .to_path_buf()
+}
+
+/// Extracts packet definitions from the markdown tables following each `###`
+/// heading, as well as type definitions from python `class` blocks in the
+/// appendix.
+fn parse_doc(doc: &str) -> Definitions {
+    let mut definitions = Definitions::new();
+    let mut current: Option<String> = None;
+    let mut in_code = false;
+
+    for line in doc.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            current = None;
+            continue;
+        }
+
+        if in_code {
+            if let Some(class) = trimmed.strip_prefix("class ") {
+                let name = class
+                    .split(['(', ':'])
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                definitions.entry(name.clone()).or_default();
+                current = Some(name);
+            } else if let Some(name) = &current {
+                if line.starts_with(char::is_whitespace) {
+                    if let Some((field, _)) = trimmed.split_once(':') {
+                        if is_identifier(field) {
+                            definitions
+                                .entry(name.clone())
+                                .or_default()
+                                .insert(field.to_string());
+                        }
+                    }
+                } else if !trimmed.is_empty() {
+                    current = None;
+                }
+            }
+            continue;
+        }
+
+        if let Some(heading) = trimmed.strip_prefix("### ") {
+            let name = heading.trim().to_string();
+            current = is_identifier(&name).then_some(name);
+            continue;
+        }
+
+        if trimmed.starts_with("## ") {
+            current = None;
+            continue;
+        }
+
+        let Some(name) = &current else {
+            continue;
+        };
+
+        if let Some(row) = trimmed.strip_prefix('|') {
+            let field = row.split('|').next().unwrap_or_default().trim();
+            let field = field.trim_matches('`');
+            if is_identifier(field) && !field.eq_ignore_ascii_case("name") {
+                definitions
+                    .entry(name.clone())
+                    .or_default()
+                    .insert(field.to_string());
+            }
+        }
+    }
+
+    definitions
+}
+
+/// Extracts `pub struct` definitions and their wire field names from
+/// protocol.rs, honoring `#[serde(rename = "...")]`.
+fn parse_source(source: &str) -> Definitions {
+    let mut definitions = Definitions::new();
+    let mut current: Option<String> = None;
+    let mut rename: Option<String> = None;
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+
+        if let Some(rest) = trimmed.strip_prefix("pub struct ") {
+            let name = rest
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            definitions.entry(name.clone()).or_default();
+            current = rest.ends_with('{').then_some(name);
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("pub type ") {
+            if let Some((name, _)) = rest.split_once('=') {
+                definitions.entry(name.trim().to_string()).or_default();
+            }
+            continue;
+        }
+
+        let Some(name) = &current else {
+            continue;
+        };
+
+        if trimmed == "}" {
+            current = None;
+            continue;
+        }
+
+        if let Some(attr) = trimmed.strip_prefix("#[serde(rename = \"") {
+            rename = attr.split('"').next().map(str::to_string);
+            continue;
+        }
+
+        let field = trimmed.strip_prefix("pub ").unwrap_or(trimmed);
+        if let Some((field, _)) = field.split_once(':') {
+            let field = field.trim_start_matches("r#");
+            if is_identifier(field) {
+                let field = rename.take().unwrap_or_else(|| field.to_string());
+                definitions.entry(name.clone()).or_default().insert(field);
+            }
+        }
+    }
+
+    definitions
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+}
@@ -0,0 +1,84 @@
+//! Tests for comparing protocol.rs against the network protocol
+//! documentation, using an excerpt of the upstream document.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+        .join("network_protocol.md")
+}
+
+fn protocol_diff(doc: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_xtask"))
+        .arg("protocol-diff")
+        .arg(doc)
+        .output()
+        .unwrap()
+}
+
+/// Runs protocol-diff against the fixture after applying `edits` to it.
+fn protocol_diff_edited(edits: &[(&str, &str)]) -> Output {
+    let mut doc = std::fs::read_to_string(fixture()).unwrap();
+    for (from, to) in edits {
+        assert!(doc.contains(from), "the fixture should contain {from:?}");
+        doc = doc.replacen(from, to, 1);
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("network_protocol.md");
+    std::fs::write(&path, doc).unwrap();
+    protocol_diff(&path)
+}
+
+#[test]
+fn matches_the_documented_protocol() {
+    let output = protocol_diff(&fixture());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(stdout.trim(), "protocol.rs matches the documentation");
+}
+
+#[test]
+fn reports_differences_in_tables_and_classes() {
+    let output = protocol_diff_edited(&[
+        // A packet field we don't have.
+        (
+            "| text | str | Text to send to others. |\n",
+            "| text | str | Text to send to others. |\n| team | int | The team to send to. |\n",
+        ),
+        // A field we have which the documentation dropped.
+        ("| data | dict | The data in the [Bounce](#Bounce) package copied |\n", ""),
+        // A packet we don't have at all.
+        (
+            "## Appendix\n",
+            "### Shout\n\n| Name | Type | Notes |\n| ---- | ---- | ----- |\n| text | str | Text. |\n\n## Appendix\n",
+        ),
+        // A field of a python class we don't have.
+        ("    flags: int\n", "    flags: int\n    owner: int\n"),
+    ]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{stdout}");
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "undocumented field: Bounced.data",
+            "missing field: NetworkItem.owner",
+            "missing field: Say.team",
+            "missing type: Shout",
+            "4 difference(s) found",
+        ]
+    );
+}
+
+#[test]
+fn fails_without_the_document() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = protocol_diff(&dir.path().join("missing.md"));
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("failed to read"));
+}
@@ -0,0 +1,121 @@
+//! Tests for the semver-checks wrapper, run against a fake cargo which
+//! records how it was called.
+
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+const FAKE_CARGO: &str = r#"#!/bin/sh
+pwd > "$FAKE_CARGO_LOG"
+printf '%s\n' "$@" >> "$FAKE_CARGO_LOG"
+exit "$FAKE_CARGO_EXIT"
+"#;
+
+/// Held while writing the fake cargo and while running commands. Otherwise a
+/// command spawned by another test can inherit the fake cargo's handle while
+/// it is being written, and running it fails with "text file busy".
+static SPAWN: Mutex<()> = Mutex::new(());
+
+fn run(command: &mut Command) -> Output {
+    let _spawn = SPAWN.lock().unwrap_or_else(|e| e.into_inner());
+    command.output().unwrap()
+}
+
+struct FakeCargo {
+    dir: tempfile::TempDir,
+}
+
+impl FakeCargo {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cargo");
+        let _spawn = SPAWN.lock().unwrap_or_else(|e| e.into_inner());
+        std::fs::write(&path, FAKE_CARGO).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Self { dir }
+    }
+
+    fn log(&self) -> PathBuf {
+        self.dir.path().join("log")
+    }
+
+    fn semver_checks(&self, args: &[&str], exit: u8) -> Output {
+        run(Command::new(env!("CARGO_BIN_EXE_xtask"))
+            .arg("semver-checks")
+            .args(args)
+            .env("CARGO", self.dir.path().join("cargo"))
+            .env("FAKE_CARGO_LOG", self.log())
+            .env("FAKE_CARGO_EXIT", exit.to_string()))
+    }
+
+    /// The directory cargo ran in and the arguments it was given.
+    fn call(&self) -> (PathBuf, Vec<String>) {
+        let log = std::fs::read_to_string(self.log()).unwrap();
+        let mut lines = log.lines();
+        let dir = PathBuf::from(lines.next().unwrap());
+        (dir, lines.map(str::to_string).collect())
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .canonicalize()
+        .unwrap()
+}
+
+#[test]
+fn checks_the_crate_against_the_baseline() {
+    let cargo = FakeCargo::new();
+    let output = cargo.semver_checks(&["v0.1.0"], 0);
+    assert!(output.status.success());
+
+    let (dir, args) = cargo.call();
+    assert_eq!(dir.canonicalize().unwrap(), workspace_root());
+    assert_eq!(
+        args,
+        [
+            "semver-checks",
+            "check-release",
+            "-p",
+            "archipelago",
+            "--baseline-rev",
+            "v0.1.0"
+        ]
+    );
+}
+
+#[test]
+fn fails_when_the_check_fails() {
+    let cargo = FakeCargo::new();
+    let output = cargo.semver_checks(&["v0.1.0"], 1);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn fails_when_cargo_is_missing() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run(Command::new(env!("CARGO_BIN_EXE_xtask"))
+        .args(["semver-checks", "v0.1.0"])
+        .env("CARGO", dir.path().join("missing")));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is it installed?"), "{stderr}");
+}
+
+#[test]
+fn requires_a_baseline() {
+    let cargo = FakeCargo::new();
+    let output = cargo.semver_checks(&[], 0);
+    assert!(!output.status.success());
+    assert!(!cargo.log().exists(), "cargo shouldn't have run");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("usage: cargo xtask semver-checks"),
+        "{stderr}"
+    );
+}